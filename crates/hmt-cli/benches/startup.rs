@@ -0,0 +1,77 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Measures how long the CLI takes to start for commands that should not
+//! need configuration, the registry or a project.
+//!
+//! Each command runs against an empty home directory, and the benchmark
+//! fails when a median exceeds `HUMMANTA_STARTUP_BUDGET_MS` (default 100).
+
+use std::{
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+/// The number of runs each command is measured over.
+const RUNS: usize = 20;
+
+/// The commands measured, as passed to the binary.
+const COMMANDS: &[&[&str]] = &[&["--help"], &["cache", "--help"], &["cache", "status"]];
+
+fn main() {
+    let budget = std::env::var("HUMMANTA_STARTUP_BUDGET_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_millis(100));
+
+    let home = tempfile::tempdir().expect("Failed to create home directory");
+    let mut exceeded = false;
+    for args in COMMANDS {
+        let median = median(args, home.path());
+        let over = median > budget;
+        exceeded |= over;
+
+        let status = if over { "over budget" } else { "ok" };
+        println!("hummanta {:<16} {:>8.2?} {}", args.join(" "), median, status);
+    }
+
+    if exceeded {
+        eprintln!("Startup exceeded the budget of {budget:?}");
+        std::process::exit(1);
+    }
+}
+
+/// Runs the binary with `args` and returns the median wall time.
+fn median(args: &[&str], home: &std::path::Path) -> Duration {
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let status = Command::new(env!("CARGO_BIN_EXE_hummanta"))
+                .args(args)
+                .env("HOME", home)
+                .env("HUMMANTA_OFFLINE", "true")
+                .current_dir(home)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .expect("Failed to run hummanta");
+            assert!(status.success(), "hummanta {} failed", args.join(" "));
+            start.elapsed()
+        })
+        .collect();
+
+    times.sort();
+    times[RUNS / 2]
+}
@@ -12,22 +12,78 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Builds a project and its dependencies.
+//!
+//! Dependencies declared in `hummanta.toml` are built first, in topological
+//! order. The frontend, backend and linker of every project receive one
+//! `--dependency <name>=<dir>` flag per direct dependency, where `<dir>` is
+//! the dependency's output directory containing its `outputs.json`.
+//! Dependencies are built into `target/<triple>/deps` of the root project,
+//! never into their own directories, which may be shared or read-only.
+//!
+//! Every tool and plugin runs with the variables of the `[env]` table of the
+//! project being built, and changing them rebuilds its outputs. Tools also
+//! find the installed runtimes their package uses in
+//! `HUMMANTA_RUNTIME_<NAME>` variables, and updating a runtime rebuilds
+//! their outputs. The plugins
+//! declared in the config run after the phase they name, and their
+//! fingerprints are recorded in `outputs.json`.
+//!
+//! Outputs of the compile and emit steps are reused when the tool package,
+//! the hash of its binary, the inputs and the arguments are unchanged since
+//! the previous build. Outputs missing locally are downloaded from the
+//! `[remote_cache]` of the config if one is set, and with
+//! `mode = "read-write"` the outputs built locally are uploaded to it once
+//! the build succeeds.
+//!
+//! Every build ends by writing `target/<triple>/build-status.json`, with the
+//! outcome, durations, warning count, artifact hashes and package versions,
+//! for status reporters, and `target/<triple>/build.log`, with the commands
+//! run and their output, for `hummanta report`.
+
 use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    ffi::OsString,
     fs,
-    path::{Path, PathBuf},
-    sync::Arc,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{anyhow, bail, Context as _};
 use clap::Args;
 use once_cell::sync::OnceCell;
-use tracing::info;
-use walkdir::WalkDir;
+use tokio::sync::RwLock;
+
+use hmt_manifest::{
+    BuildStatus, CategoryMap, Durations, Entry, FrozenPackage, Output, OutputKind, OutputManifest,
+    ProjectManifest, Stage, Status, COVERAGE_CAPABILITY,
+};
+use hmt_registry::{
+    manager::Manager,
+    traits::{PackageKind, Query},
+};
+use hmt_utils::{checksum, event::warning, process::Process};
 
-use hmt_manifest::{ManifestFile, ProjectManifest};
-use hmt_registry::traits::Query;
+use crate::{
+    context::Context,
+    deps,
+    errors::{CliError, Result},
+    fingerprint::{self, BuildCache, Fingerprint, Tool, OUTPUTS_FILE},
+    flock::BuildLock,
+    graph::{Graph, GraphFormat},
+    jobs::{Job, Scheduler},
+    manifest,
+    plugin::{Phase, Pipeline, StepContext},
+    utils,
+};
+
+/// The file describing the last build, written next to `outputs.json`.
+pub const STATUS_FILE: &str = "build-status.json";
 
-use crate::{context::Context, errors::Result, utils};
+/// The file logging the tools the last build ran, written next to
+/// `outputs.json`.
+pub const LOG_FILE: &str = "build.log";
 
 /// Builds the entire workspace
 #[derive(Args, Debug)]
@@ -36,108 +92,503 @@ pub struct Command {
     #[arg(long)]
     target: Option<String>,
 
+    /// Comma separated list of features to enable
+    ///
+    /// The frontend receives one `--feature <name>` flag per enabled
+    /// feature. Dependencies are built with their default features.
+    #[arg(long, value_delimiter = ',')]
+    features: Vec<String>,
+
+    /// Do not enable the `default` feature
+    #[arg(long)]
+    no_default_features: bool,
+
+    /// Remap project paths and zero timestamps for reproducible outputs
+    ///
+    /// The frontend and backend receive `--remap-path-prefix <project>=.`,
+    /// and every tool runs with `SOURCE_DATE_EPOCH=0`.
+    #[arg(long)]
+    deterministic: bool,
+
+    /// Build twice in deterministic mode and fail if any output differs
+    ///
+    /// Both builds run every step, without reusing previous outputs.
+    #[arg(long)]
+    verify_determinism: bool,
+
+    /// Write the build graph to `target/<triple>/build-graph.<format>`
+    #[arg(long, value_name = "FORMAT")]
+    emit_graph: Option<GraphFormat>,
+
+    /// The maximum number of tool invocations running at once
+    ///
+    /// Defaults to the per-phase limits of the `[jobs]` config. Unless
+    /// `adaptive = false` is configured, fewer run while the system is under
+    /// CPU or memory pressure.
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Fail instead of waiting when another build of the project is running
+    ///
+    /// Only one build runs in a project at a time.
+    #[arg(long)]
+    no_wait: bool,
+
+    /// Reinstall broken toolchain and target packages without asking
+    ///
+    /// Packages whose binary is missing or not executable are reinstalled
+    /// from the registry before building. Otherwise the build asks first on
+    /// a terminal, and fails when there is no one to ask.
+    #[arg(long)]
+    auto_repair: bool,
+
+    /// Instrument the emitted code for coverage
+    ///
+    /// The backend receives `--coverage`, and must have the `coverage`
+    /// capability.
+    #[arg(long)]
+    pub(super) coverage: bool,
+
+    /// Pin the toolchain and target packages in `hummanta.lock`
+    ///
+    /// Projects with a lockfile always pin them, and `--locked` requires the
+    /// installed packages to match it. This creates the lockfile if the
+    /// project has none.
+    #[arg(long)]
+    lock_tools: bool,
+
     /// The resolved target platform, determined by CLI or manifest
     #[clap(skip)]
     resolved_target: OnceCell<String>,
+
+    /// The log of the tools this build ran
+    #[clap(skip)]
+    log: BuildLog,
+}
+
+/// The commands a build ran with their exit status and output, in the order
+/// they finished. Outputs reused from the cache run no command.
+#[derive(Debug, Default, Clone)]
+struct BuildLog(Arc<Mutex<String>>);
+
+impl BuildLog {
+    /// Appends a line
+    fn line(&self, line: &str) {
+        let mut log = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        log.push_str(line);
+        log.push('\n');
+    }
+
+    /// Appends a command with its exit status and output
+    fn command(&self, command: &str, output: &std::process::Output) {
+        let mut entry = format!("$ {command}\n{}\n", output.status);
+        for stream in [&output.stdout, &output.stderr] {
+            let text = String::from_utf8_lossy(stream);
+            if !text.trim().is_empty() {
+                entry.push_str(text.trim_end());
+                entry.push('\n');
+            }
+        }
+        self.line(&entry);
+    }
+
+    /// Writes the log to `path`
+    fn save(&self, path: &Path) -> std::io::Result<()> {
+        fs::write(path, self.0.lock().unwrap_or_else(|e| e.into_inner()).as_bytes())
+    }
+}
+
+/// A project being built, either the root project or one of its dependencies
+struct Unit {
+    /// The project root directory
+    dir: PathBuf,
+    /// The project manifest
+    manifest: ProjectManifest,
+    /// The target platform to build for
+    target: String,
+    /// The build output directory
+    target_dir: PathBuf,
+    /// The output directories of dependencies inside `target_dir`, whose
+    /// outputs are not the project's own
+    nested: Vec<PathBuf>,
+    /// The `--dependency` flags passed to every tool
+    dependencies: Vec<OsString>,
+    /// The enabled features
+    features: BTreeSet<String>,
+    /// The hashes of the dependency outputs, part of every fingerprint
+    dependency_hashes: Vec<String>,
+    /// The environment variables of the `[env]` table
+    env: Vec<(String, String)>,
+}
+
+impl Unit {
+    /// Prepares a project for building, creating its output directory
+    fn new(
+        dir: PathBuf,
+        manifest: ProjectManifest,
+        target: &str,
+        sources: &deps::Sources,
+        outputs: &deps::Outputs,
+        features: BTreeSet<String>,
+        env: Vec<(String, String)>,
+    ) -> Result<Self> {
+        let target_dir = output_dir(outputs, &dir)?.to_path_buf();
+        let nested = outputs
+            .values()
+            .filter(|path| path.starts_with(&target_dir) && **path != target_dir)
+            .cloned()
+            .collect();
+
+        if !target_dir.exists() {
+            fs::create_dir_all(&target_dir) //
+                .context("Failed to create target directory")?;
+        }
+
+        let mut dependencies = Vec::new();
+        let mut dependency_hashes = Vec::new();
+        for (name, dependency) in &manifest.dependencies {
+            let dep_dir = deps::dependency_dir(&dir, name, dependency, sources)?;
+            let dep_target_dir = output_dir(outputs, &dep_dir)?;
+            let mut flag = OsString::from(format!("{name}="));
+            flag.push(dep_target_dir);
+
+            dependencies.push("--dependency".into());
+            dependencies.push(flag);
+
+            // Dependencies are built first, so their outputs are final
+            let outputs = fs::read(dep_target_dir.join(OUTPUTS_FILE)).unwrap_or_default();
+            dependency_hashes.push(checksum::digest(&outputs));
+        }
+
+        Ok(Self {
+            dir,
+            manifest,
+            target: target.to_string(),
+            target_dir,
+            nested,
+            dependencies,
+            features,
+            dependency_hashes,
+            env,
+        })
+    }
+
+    /// Returns the name the project is reported under, its directory name
+    fn name(&self) -> String {
+        self.dir.file_name().unwrap_or_default().to_string_lossy().into_owned()
+    }
+}
+
+/// What the tool invocations of one build step share
+struct JobContext<'a> {
+    /// The project being built
+    unit: &'a Unit,
+    /// The tool invoked
+    tool: &'a Tool,
+    /// The kind of the outputs
+    kind: OutputKind,
+    /// The outputs of the previous build
+    cache: &'a BuildCache,
 }
 
 impl Command {
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let started = Instant::now();
         let manifest_path = ctx.manifest_path()?;
-        let manifest = ProjectManifest::load(manifest_path)?;
+        let manifest = manifest::load(&ctx, manifest_path).await?;
+        let target = self.target(&manifest)?;
+        let target_dir = ctx.project_dir()?.join("target").join(target);
+
+        let mut status = BuildStatus::new(target);
+        let result = self.run(ctx.clone(), manifest, &mut status).await;
 
+        // Describe the build even when it failed, for status reporters
+        status.durations.total_ms = millis(started);
+        status.warnings = ctx.warnings();
+        status.finished_at =
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        match &result {
+            Ok(()) => status.status = Status::Success,
+            Err(e) => status.error = Some(format!("{e:#}")),
+        }
+        self.log.line(status.error.as_deref().map_or("Build succeeded", |e| e));
+        let written = fs::create_dir_all(&target_dir)
+            .map_err(Into::into)
+            .and_then(|_| status.save(target_dir.join(STATUS_FILE)));
+        if let Err(e) = written {
+            ctx.reporter()
+                .warn(warning::BUILD_STATUS, format!("Failed to write {STATUS_FILE}: {e}"));
+        }
+        if let Err(e) = self.log.save(&target_dir.join(LOG_FILE)) {
+            ctx.reporter().warn(warning::BUILD_STATUS, format!("Failed to write {LOG_FILE}: {e}"));
+        }
+
+        result
+    }
+
+    /// Builds the dependencies and then the project, recording the build in
+    /// `status`
+    async fn run(
+        &self,
+        ctx: Arc<Context>,
+        manifest: ProjectManifest,
+        status: &mut BuildStatus,
+    ) -> Result<()> {
+        let project_dir = ctx.project_dir()?;
         let target = self.target(&manifest)?;
-        let target_dir = self.target_dir(ctx.clone(), target)?;
+        let pipeline = Pipeline::new(&ctx.config()?.plugins)?;
+        let _lock = BuildLock::acquire(&project_dir.join("target"), !self.no_wait).await?;
+
+        // Build dependencies first, each after its own dependencies
+        let sources = deps::fetch(&ctx, project_dir).await?;
+        let dependencies = deps::resolve(project_dir, &manifest, &sources, &ctx.downloads_dir())?;
 
-        // Execute the complete build pipeline
-        self.compile(ctx.clone(), &manifest, &target_dir).await?;
-        self.emit(ctx.clone(), &manifest, &target_dir).await?;
+        let mut languages: BTreeSet<String> =
+            dependencies.iter().map(|dep| dep.manifest.project.language.to_lowercase()).collect();
+        languages.insert(manifest.project.language.to_lowercase());
+        let mut tools = Vec::new();
+        let toolchains = ctx.toolchains().await?;
+        for language in &languages {
+            tools.extend(frozen(&*toolchains.read().await, language));
+        }
+        tools.extend(frozen(&*ctx.targets().await?.read().await, target));
+        status.tools(&tools);
+        deps::lock_tools(&ctx, project_dir, tools, self.lock_tools).await?;
 
-        info!("Build completed for target '{}'", target);
+        let outputs = deps::outputs(project_dir, &ctx.target_dir(target)?, &dependencies)?;
+        for dep in dependencies {
+            ctx.reporter().info(format!("Building dependency '{}'", dep.name));
+            let features = dep.manifest.resolve_features(&[], true)?;
+            let env = ctx.project_env(&dep.manifest)?;
+            let unit = Unit::new(dep.dir, dep.manifest, target, &sources, &outputs, features, env)?;
+            self.build(ctx.clone(), &unit, &pipeline, &mut status.durations).await?;
+        }
+
+        let features = manifest.resolve_features(&self.features, !self.no_default_features)?;
+        let env = ctx.project_env(&manifest)?;
+        let dir = project_dir.to_path_buf();
+        let unit = Unit::new(dir, manifest, target, &sources, &outputs, features, env)?;
+        let outputs = self.build(ctx.clone(), &unit, &pipeline, &mut status.durations).await?;
+        status.artifacts(&outputs);
+
+        if self.verify_determinism {
+            ctx.reporter().info("Rebuilding to verify determinism".into());
+            let rebuilt = self.build(ctx.clone(), &unit, &pipeline, &mut status.durations).await?;
+            verify(&outputs, &rebuilt)?;
+            ctx.reporter().info("Build is deterministic".into());
+        }
+
+        if let Some(remote) = ctx.remote_cache()? {
+            let uploaded = remote.upload(ctx.reporter().as_ref()).await;
+            if uploaded > 0 {
+                ctx.reporter().info(format!("Uploaded {uploaded} outputs to the remote cache"));
+            }
+        }
+
+        ctx.reporter().info(format!("Build completed for target '{}'", target));
         Ok(())
     }
 
     /// Resolve target with clear precedence: CLI arg > manifest > error
-    fn target(&self, manifest: &ProjectManifest) -> Result<&str> {
-        self.resolved_target.get_or_try_init(|| {
-            if let Some(cli_target) = &self.target {
-                if !cli_target.is_empty() {
-                    return Ok(cli_target.to_owned());
-                }
-                bail!("Empty target specified in command line");
-            }
+    pub(super) fn target(&self, manifest: &ProjectManifest) -> Result<&str> {
+        self.resolved_target
+            .get_or_try_init(|| utils::resolve_target(&self.target, manifest))
+            .map(|s| s.as_str())
+    }
 
-            if let Some(manifest_target) = &manifest.project.target {
-                if !manifest_target.is_empty() {
-                    return Ok(manifest_target.to_owned());
-                }
-                bail!("Empty target specified in manifest");
-            }
+    /// Whether outputs must be reproducible
+    fn deterministic(&self) -> bool {
+        self.deterministic || self.verify_determinism
+    }
 
-            bail!("No target specified. Either set 'target' in hummanta.toml or use --target flag")
-        }).map(|s| s.as_str())
+    /// The flags remapping the project directory out of emitted code
+    fn remap_flags(&self, unit: &Unit) -> Vec<OsString> {
+        if !self.deterministic() {
+            return Vec::new();
+        }
+
+        let mut prefix = unit.dir.clone().into_os_string();
+        prefix.push("=.");
+        vec!["--remap-path-prefix".into(), prefix]
     }
 
-    /// Prepares and validates the build output directory
-    fn target_dir(&self, ctx: Arc<Context>, target: &str) -> Result<PathBuf> {
-        let target_dir = ctx.project_dir()?.join("target").join(target);
+    /// The environment every tool of a project runs with
+    fn envs(&self, unit: &Unit) -> Vec<(String, String)> {
+        let mut envs = unit.env.clone();
+        if self.deterministic() {
+            envs.push(("SOURCE_DATE_EPOCH".into(), "0".into()));
+        }
+        envs
+    }
 
-        if !target_dir.exists() {
-            fs::create_dir_all(&target_dir) //
-                .context("Failed to create target directory")?;
+    /// The outputs of the previous build and the remote cache that may be
+    /// reused
+    fn cache(&self, ctx: &Context, unit: &Unit) -> Result<BuildCache> {
+        if self.verify_determinism {
+            return Ok(BuildCache::default());
+        }
+        Ok(BuildCache::load(&unit.target_dir).with_remote(ctx.remote_cache()?))
+    }
+
+    /// The scheduler running the invocations of a phase
+    fn scheduler(&self, ctx: &Context, phase: Phase) -> Result<Scheduler> {
+        let config = &ctx.config()?.jobs;
+        Ok(Scheduler::new(config.max(phase, self.jobs), config.adaptive))
+    }
+
+    /// Fingerprints an invocation of `tool` with the given inputs and
+    /// arguments, in the environment of the unit
+    fn fingerprint(
+        &self,
+        tool: &Tool,
+        unit: &Unit,
+        inputs: &[&PathBuf],
+        args: &[OsString],
+    ) -> Result<String> {
+        let mut fingerprint = Fingerprint::new(tool);
+        for input in inputs {
+            fingerprint = fingerprint.file(input)?;
+        }
+        for arg in args {
+            fingerprint = fingerprint.arg(arg);
+        }
+        for hash in &unit.dependency_hashes {
+            fingerprint = fingerprint.arg(hash);
+        }
+        for (key, value) in self.envs(unit) {
+            fingerprint = fingerprint.arg(format!("{key}={value}"));
+        }
+        Ok(fingerprint.finish())
+    }
+
+    /// Executes the complete build pipeline for a single project, adding the
+    /// time spent in each phase to `durations`
+    async fn build(
+        &self,
+        ctx: Arc<Context>,
+        unit: &Unit,
+        pipeline: &Pipeline,
+        durations: &mut Durations,
+    ) -> Result<OutputManifest> {
+        self.check(&ctx, unit).await?;
+
+        let envs = self.envs(unit);
+        let step = StepContext {
+            dir: &unit.dir,
+            target_dir: &unit.target_dir,
+            target: &unit.target,
+            envs: &envs,
+            reporter: ctx.reporter().as_ref(),
+        };
+
+        let cache = self.cache(&ctx, unit)?;
+        let mut outputs = OutputManifest::new(&unit.target);
+        outputs.features = unit.features.iter().cloned().collect();
+        let started = Instant::now();
+        self.compile(ctx.clone(), unit, &cache, &mut outputs).await?;
+        pipeline.run(Phase::Compile, &step, &mut outputs).await?;
+        durations.compile_ms += millis(started);
+
+        let started = Instant::now();
+        self.emit(ctx.clone(), unit, &cache, &mut outputs).await?;
+        pipeline.run(Phase::Emit, &step, &mut outputs).await?;
+        durations.emit_ms += millis(started);
+
+        let started = Instant::now();
+        self.link(ctx.clone(), unit, &mut outputs).await?;
+        pipeline.run(Phase::Link, &step, &mut outputs).await?;
+        durations.link_ms += millis(started);
+
+        // Record the emitted artifacts for downstream tooling
+        let path = unit.target_dir.join(OUTPUTS_FILE);
+        outputs.save(path).context("Failed to write outputs.json")?;
+
+        if let Some(format) = self.emit_graph {
+            let path = unit.target_dir.join("build-graph").with_extension(format.extension());
+            let graph = Graph::from_outputs(&outputs).render(format)?;
+            fs::write(&path, graph).context("Failed to write build graph")?;
+            ctx.reporter().info(format!("Wrote build graph to {}", path.display()));
         }
 
-        Ok(target_dir)
+        Ok(outputs)
     }
 
-    /// Compiles source code to intermediate representation (CLIF)
+    /// Checks the installed binaries a project builds with, reinstalling
+    /// broken packages before they are invoked
+    async fn check(&self, ctx: &Context, unit: &Unit) -> Result<()> {
+        let language = unit.manifest.project.language.to_lowercase();
+        let toolchains = ctx.toolchains().await?;
+        repair(ctx, &toolchains, &language, self.auto_repair).await?;
+
+        let targets = ctx.targets().await?;
+        repair(ctx, &targets, &unit.target, self.auto_repair).await
+    }
+
+    /// Compiles source code to intermediate representation (CLIF), running
+    /// the stages declared by the language's toolchain in order
     async fn compile(
         &self,
         ctx: Arc<Context>,
-        manifest: &ProjectManifest,
-        target_dir: &Path,
+        unit: &Unit,
+        cache: &BuildCache,
+        outputs: &mut OutputManifest,
     ) -> Result<()> {
         // Acquires the toolchain manager.
         let manager = ctx.toolchains().await?;
         let manager = manager.read().await;
 
-        let language = &manifest.project.language;
-        let extension = manifest.project.extension.as_str();
+        let language = &unit.manifest.project.language;
+        let extension = unit.manifest.project.extension.as_str();
+        let steps = pipeline(manager.get_category(&language.to_lowercase()), language, extension)?;
 
-        // Get the appropriate frontend compiler
-        let packages = manager.get_package(language, "frontend");
-        let package = packages
-            .first()
-            .ok_or_else(|| anyhow!("Frontend compiler for '{}' not found", language))?;
-        let compiler_path = &package.entry.path;
+        // Each stage consumes the files written by the previous one, starting
+        // with the source files of the project
+        let mut scheduler = self.scheduler(&ctx, Phase::Compile)?;
+        let mut inputs = utils::sources(&unit.dir, extension);
+        for (index, step) in steps.iter().enumerate() {
+            let kind =
+                if index + 1 == steps.len() { OutputKind::Ir } else { OutputKind::Intermediate };
+            let runtimes = manager.package_runtimes(language, &step.runtimes);
+            let tool = Tool::new(&step.tool, &step.name, &step.version)?.runtimes(runtimes);
+            let context = JobContext { unit, tool: &tool, kind, cache };
 
-        // Process all source files with the matching language extension
-        for entry in WalkDir::new(ctx.project_dir()?)
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|e| e.path().extension().is_some_and(|ext| ext == extension))
-        {
-            let input = entry.path();
-            let file_stem = input
-                .file_stem()
-                .ok_or_else(|| anyhow!("Source file has no valid name: {}", input.display()))?;
-            let output = target_dir.join(file_stem).with_extension("clif");
-
-            let cmd = utils::command(
-                compiler_path,
-                &[
-                    "--input",
-                    input.to_str().context("Invalid input path")?,
-                    "--output",
-                    output.to_str().context("Invalid output path")?,
-                ],
-            )
-            .await?;
+            let mut jobs = Vec::with_capacity(inputs.len());
+            let mut written = Vec::with_capacity(inputs.len());
+            for input in inputs {
+                // Outputs mirror the layout of the sources, so sources with
+                // the same name in different directories never collide
+                let relative = input
+                    .strip_prefix(&unit.target_dir)
+                    .or_else(|_| input.strip_prefix(&unit.dir))
+                    .map_err(|_| anyhow!("Source file outside the project: {}", input.display()))?;
+                let output = unit.target_dir.join(relative).with_extension(&step.output);
+                if let Some(parent) = output.parent() {
+                    fs::create_dir_all(parent)?;
+                }
 
-            if !cmd.status.success() {
-                let stderr = String::from_utf8_lossy(&cmd.stderr);
-                bail!("Compilation failed with status {}:\n{}", cmd.status, stderr.trim());
+                let mut args: Vec<OsString> = vec![
+                    "--input".into(),
+                    (&input).into(),
+                    "--output".into(),
+                    output.clone().into(),
+                ];
+                args.extend(unit.dependencies.iter().cloned());
+                for feature in &unit.features {
+                    args.push("--feature".into());
+                    args.push(feature.into());
+                }
+                args.extend(self.remap_flags(unit));
+
+                let fingerprint = self.fingerprint(&tool, unit, &[&input], &args)?;
+                jobs.push(self.job(&context, input, output.clone(), args, fingerprint));
+                written.push(output);
             }
+
+            let label = format!("Compiling {} ({})", unit.name(), step.output);
+            let mut progress = ctx.progress(&label, jobs.len());
+            let built = scheduler.run(jobs, |output| progress.inc(&stem(output))).await?;
+            outputs.outputs.extend(built);
+            inputs = written;
         }
 
         Ok(())
@@ -147,45 +598,482 @@ impl Command {
     async fn emit(
         &self,
         ctx: Arc<Context>,
-        manifest: &ProjectManifest,
-        target_dir: &PathBuf,
+        unit: &Unit,
+        cache: &BuildCache,
+        outputs: &mut OutputManifest,
     ) -> Result<()> {
         let manager = ctx.targets().await?;
         let manager = manager.read().await;
 
-        let target = self.target(manifest)?;
+        let target = &unit.target;
 
         // Get the appropriate backend compiler
         let packages = manager.get_package(target, "backend");
-        let package =
-            packages.first().ok_or(anyhow!("Backend compiler for '{}' not found", target))?;
-        let compiler_path = &package.entry.path;
-
-        // Process all intermediate .clif files
-        for entry in fs::read_dir(target_dir)?
-            .filter_map(Result::ok)
-            .filter(|e| e.path().extension().is_some_and(|ext| ext == "clif"))
-        {
-            let input = entry.path();
+        let package = packages.first().ok_or_else(|| CliError::UnsupportedTarget {
+            role: "Backend compiler",
+            target: target.clone(),
+        })?;
+        let runtimes = manager.package_runtimes(target, package.entry.runtimes.keys());
+        let tool = Tool::new(&package.entry.path, &package.name, &package.entry.version)?
+            .runtimes(runtimes);
+        if self.coverage && !package.entry.supports(COVERAGE_CAPABILITY) {
+            bail!("Backend '{}' for '{}' does not support coverage", package.name, target);
+        }
+
+        // Process all intermediate .clif files, in a stable order
+        let mut inputs = utils::sources(&unit.target_dir, "clif");
+        inputs.retain(|input| !unit.nested.iter().any(|dir| input.starts_with(dir)));
+
+        let context = JobContext { unit, tool: &tool, kind: OutputKind::Object, cache };
+        let mut jobs = Vec::with_capacity(inputs.len());
+        for input in inputs {
             let output = input.with_extension("o");
 
-            let cmd = utils::command(
-                compiler_path,
-                &[
-                    "--input",
-                    input.to_str().context("Invalid input path")?,
-                    "--output",
-                    output.to_str().context("Invalid output path")?,
-                ],
-            )
-            .await?;
+            let mut args: Vec<OsString> = vec![
+                "--input".into(),
+                input.clone().into(),
+                "--output".into(),
+                output.clone().into(),
+            ];
+            args.extend(unit.dependencies.iter().cloned());
+            args.extend(self.remap_flags(unit));
+            if self.coverage {
+                args.push("--coverage".into());
+            }
+
+            let fingerprint = self.fingerprint(&tool, unit, &[&input], &args)?;
+            jobs.push(self.job(&context, input, output, args, fingerprint));
+        }
+
+        let label = format!("Emitting {}", unit.name());
+        let mut progress = ctx.progress(&label, jobs.len());
+        let mut scheduler = self.scheduler(&ctx, Phase::Emit)?;
+        let built = scheduler.run(jobs, |output| progress.inc(&file_name(output))).await?;
+        outputs.outputs.extend(built);
+
+        Ok(())
+    }
+
+    /// Invokes the tool of a step to build `output` from `input`, unless the
+    /// output of the previous build has the same fingerprint
+    fn job(
+        &self,
+        context: &JobContext,
+        input: PathBuf,
+        output: PathBuf,
+        args: Vec<OsString>,
+        fingerprint: String,
+    ) -> Job<Output> {
+        if let Some(cached) = context.cache.get(&output, &fingerprint) {
+            return Box::pin(std::future::ready(Ok(cached)));
+        }
+
+        let (path, package) = (context.tool.path.clone(), context.tool.package.clone());
+        let (kind, envs) = (context.kind, self.envs(context.unit));
+        let runtime_envs = context.tool.envs();
+        let remote = context.cache.remote();
+        let log = self.log.clone();
+        Box::pin(async move {
+            let restored = match &remote {
+                Some(remote) => remote.get(&fingerprint, &output).await,
+                None => false,
+            };
+
+            if !restored {
+                let process = Process::new(&path).args(&args).envs(envs).envs(runtime_envs);
+                let command = process.display();
+                let cmd = process.output().await?;
+                log.command(&command, &cmd);
+
+                if !cmd.status.success() {
+                    let stderr = String::from_utf8_lossy(&cmd.stderr);
+                    bail!("Compilation failed with status {}:\n{}", cmd.status, stderr.trim());
+                }
+                if let Some(remote) = &remote {
+                    remote.record(&fingerprint, &output);
+                }
+            }
+
+            Ok(artifact(kind, output, input)?.tool(path).package(package).fingerprint(fingerprint))
+        })
+    }
+
+    /// Links the objects of every declared binary into its own executable
+    async fn link(
+        &self,
+        ctx: Arc<Context>,
+        unit: &Unit,
+        outputs: &mut OutputManifest,
+    ) -> Result<()> {
+        let manifest = &unit.manifest;
+        if manifest.bins.is_empty() {
+            return Ok(());
+        }
+
+        let manager = ctx.targets().await?;
+        let manager = manager.read().await;
+
+        let target = &unit.target;
+
+        // Get the appropriate linker
+        let packages = manager.get_package(target, "linker");
+        let package = packages.first().ok_or_else(|| CliError::UnsupportedTarget {
+            role: "Linker",
+            target: target.clone(),
+        })?;
+        let linker_path = &package.entry.path;
+        let linker_package = fingerprint::package(&package.name, &package.entry.version);
+        let linker_envs =
+            utils::runtime_envs(&manager.package_runtimes(target, package.entry.runtimes.keys()));
+
+        // Objects built from an entry point belong only to their own binary.
+        // Objects mirror the layout of the sources, so an entry point is
+        // matched by its path relative to the project root.
+        let mut mains = HashSet::new();
+        for bin in &manifest.bins {
+            if !is_file_name(Path::new(&bin.name)) {
+                bail!("Binary name '{}' must be a file name, without directories", bin.name);
+            }
+            if !is_relative(&bin.main) {
+                bail!("Main source of binary '{}' must be inside the project", bin.name);
+            }
+            if !unit.dir.join(&bin.main).is_file() {
+                bail!("Main source of binary '{}' not found: {}", bin.name, bin.main.display());
+            }
+            mains.insert(main_object(unit, &bin.main));
+        }
+
+        let objects: Vec<PathBuf> =
+            outputs.by_kind(OutputKind::Object).map(|o| o.path.clone()).collect();
+        let shared: Vec<&PathBuf> = objects.iter().filter(|o| !mains.contains(*o)).collect();
+
+        for bin in &manifest.bins {
+            let object = main_object(unit, &bin.main);
+            let main = objects
+                .iter()
+                .find(|o| **o == object)
+                .ok_or_else(|| anyhow!("No object emitted for binary '{}'", bin.name))?;
+            let output = unit.target_dir.join(&bin.name);
+            if output.is_dir() {
+                bail!("Binary '{}' clashes with the outputs of a source directory", bin.name);
+            }
+
+            let mut args: Vec<OsString> = Vec::new();
+            for input in std::iter::once(main).chain(shared.iter().copied()) {
+                args.push("--input".into());
+                args.push(input.into());
+            }
+            args.push("--output".into());
+            args.push(output.clone().into());
+            args.extend(unit.dependencies.iter().cloned());
+            args.extend(bin.flags.iter().map(OsString::from));
+
+            let process = Process::new(linker_path)
+                .args(&args)
+                .envs(self.envs(unit))
+                .envs(linker_envs.iter().cloned());
+            let command = process.display();
+            let cmd = process.output().await?;
+            self.log.command(&command, &cmd);
 
             if !cmd.status.success() {
                 let stderr = String::from_utf8_lossy(&cmd.stderr);
-                bail!("Compilation failed with status {}:\n{}", cmd.status, stderr.trim());
+                bail!(
+                    "Linking '{}' failed with status {}:\n{}",
+                    bin.name,
+                    cmd.status,
+                    stderr.trim()
+                );
             }
+
+            let inputs = shared.iter().map(|o| o.to_path_buf()).collect();
+            let output = artifact(OutputKind::Executable, output, main.clone())?;
+            outputs.push(
+                output.inputs(inputs).tool(linker_path.clone()).package(linker_package.clone()),
+            );
         }
 
         Ok(())
     }
 }
+
+/// Returns the output directory assigned to a project of the build
+fn output_dir<'a>(outputs: &'a deps::Outputs, dir: &Path) -> Result<&'a Path> {
+    outputs
+        .get(dir)
+        .map(PathBuf::as_path)
+        .ok_or_else(|| anyhow!("No output directory for {}", dir.display()))
+}
+
+/// Returns the object built from the entry point of a binary
+fn main_object(unit: &Unit, main: &Path) -> PathBuf {
+    unit.target_dir.join(main).with_extension("o")
+}
+
+/// Whether a path is a single file name, e.g. not `..` or `a/b`
+fn is_file_name(path: &Path) -> bool {
+    let mut components = path.components();
+    matches!((components.next(), components.next()), (Some(Component::Normal(_)), None))
+}
+
+/// Whether a path stays inside the directory it is relative to
+fn is_relative(path: &Path) -> bool {
+    path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Returns the milliseconds elapsed since `started`
+fn millis(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
+
+/// Fails if any output of the two builds differs
+fn verify(first: &OutputManifest, second: &OutputManifest) -> Result<()> {
+    let differing = differing(first, second);
+    if !differing.is_empty() {
+        let list: Vec<_> = differing.iter().map(|p| format!("  {}", p.display())).collect();
+        bail!("Build is not deterministic, outputs differ:\n{}", list.join("\n"));
+    }
+    Ok(())
+}
+
+/// Returns the outputs whose hashes differ, or that only one build emitted
+fn differing<'a>(first: &'a OutputManifest, second: &'a OutputManifest) -> Vec<&'a PathBuf> {
+    let hashes: HashMap<_, _> = second.outputs.iter().map(|o| (&o.path, &o.hash)).collect();
+    let mut differing: Vec<_> = first
+        .outputs
+        .iter()
+        .filter(|o| hashes.get(&o.path) != Some(&&o.hash))
+        .map(|o| &o.path)
+        .collect();
+
+    let paths: HashSet<_> = first.outputs.iter().map(|o| &o.path).collect();
+    differing.extend(second.outputs.iter().map(|o| &o.path).filter(|p| !paths.contains(p)));
+    differing
+}
+
+/// Returns the registry packages installed for a domain
+fn frozen<T: PackageKind>(manager: &Manager<T>, domain: &str) -> Vec<FrozenPackage> {
+    let mut packages = Vec::new();
+    for (category, entries) in manager.get_category(domain).into_iter().flatten() {
+        for (name, entry) in entries.iter().filter(|(_, entry)| entry.source.is_registry()) {
+            packages.extend(FrozenPackage::new(manager.kind(), domain, category, name, entry));
+        }
+    }
+    packages
+}
+
+/// Reinstalls the packages of a domain whose binary is missing or not
+/// executable, asking first unless `auto` is set
+async fn repair<T: PackageKind>(
+    ctx: &Context,
+    manager: &RwLock<Manager<T>>,
+    domain: &str,
+    auto: bool,
+) -> Result<()> {
+    let broken = manager.read().await.broken(domain);
+    if broken.is_empty() {
+        return Ok(());
+    }
+
+    for (category, name) in &broken {
+        ctx.reporter().warn(
+            warning::BROKEN_PACKAGE,
+            format!("The {} '{}' of '{}' is missing or not executable", category, name, domain),
+        );
+    }
+    if !auto && !utils::ask("Reinstall the broken packages? [y/N]").await? {
+        bail!("Broken {} of '{}', rerun with --auto-repair to reinstall them", T::kind(), domain);
+    }
+
+    let mut manager = manager.write().await;
+    for (category, name) in &broken {
+        ctx.reporter().info(format!("Reinstalling {}", name));
+        manager.repair(domain, category, name).await?;
+    }
+
+    Ok(())
+}
+
+/// A stage of the compile pipeline
+#[derive(Debug, PartialEq)]
+struct Step {
+    tool: PathBuf,
+    name: String,
+    version: String,
+    runtimes: Vec<String>,
+    input: String,
+    output: String,
+}
+
+/// Resolves the compile pipeline declared by the installed toolchain of a
+/// language. Toolchains declaring no stages compile with their frontend.
+fn pipeline(
+    categories: Option<&CategoryMap>,
+    language: &str,
+    extension: &str,
+) -> Result<Vec<Step>> {
+    let mut stages: Vec<(&Stage, &String, &Entry)> = categories
+        .into_iter()
+        .flat_map(|categories| categories.values())
+        .flat_map(|packages| packages.iter())
+        .filter_map(|(name, entry)| entry.stage.as_ref().map(|stage| (stage, name, entry)))
+        .collect();
+    stages.sort_by_key(|(stage, _, _)| stage.order);
+
+    let steps = if stages.is_empty() {
+        let (name, entry) = categories
+            .and_then(|categories| categories.get("frontend"))
+            .and_then(|packages| packages.iter().next())
+            .ok_or_else(|| CliError::UnsupportedLanguage {
+                role: "Frontend compiler",
+                language: language.to_string(),
+            })?;
+        vec![Step {
+            tool: entry.path.clone(),
+            name: name.clone(),
+            version: entry.version.clone(),
+            runtimes: entry.runtimes.keys().cloned().collect(),
+            input: extension.into(),
+            output: "clif".into(),
+        }]
+    } else {
+        stages
+            .into_iter()
+            .map(|(stage, name, entry)| Step {
+                tool: entry.path.clone(),
+                name: name.clone(),
+                version: entry.version.clone(),
+                runtimes: entry.runtimes.keys().cloned().collect(),
+                input: stage.input.clone(),
+                output: stage.output.clone(),
+            })
+            .collect()
+    };
+
+    // The stages must chain from the source extension to CLIF
+    let mut current = extension;
+    for step in &steps {
+        if step.input != current {
+            bail!(
+                "Stage '{}' of the '{}' pipeline reads '.{}' files, expected '.{}'",
+                step.tool.display(),
+                language,
+                step.input,
+                current
+            );
+        }
+        current = &step.output;
+    }
+    if current != "clif" {
+        bail!("The '{}' pipeline produces '.{}' files instead of '.clif'", language, current);
+    }
+
+    Ok(steps)
+}
+
+/// The file stem of an output, shown as compile progress
+fn stem(output: &Output) -> String {
+    output.path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// The file name of an output, shown as emit progress
+fn file_name(output: &Output) -> String {
+    output.path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Describes an emitted artifact, hashing its contents
+fn artifact(kind: OutputKind, path: PathBuf, source: PathBuf) -> Result<Output> {
+    let hash = checksum::digest_file(&path)
+        .context(format!("Missing build output: {}", path.display()))?;
+    Ok(Output::new(kind, path, source, hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(entries: &[(&str, &str)]) -> OutputManifest {
+        let mut outputs = OutputManifest::new("x86_64-unknown-linux-gnu");
+        for (path, hash) in entries {
+            let path = PathBuf::from(path);
+            outputs.push(Output::new(
+                OutputKind::Object,
+                path,
+                "main.clif".into(),
+                hash.to_string(),
+            ));
+        }
+        outputs
+    }
+
+    fn stage(order: u32, input: &str, output: &str) -> Entry {
+        let mut entry = Entry::new("v1.0.0".into(), None, format!("/bin/{input}").into());
+        entry.stage = Some(Stage { order, input: input.into(), output: output.into() });
+        entry
+    }
+
+    #[test]
+    fn test_binary_paths() {
+        assert!(is_file_name(Path::new("app")));
+        assert!(!is_file_name(Path::new("../app")));
+        assert!(!is_file_name(Path::new("bin/app")));
+        assert!(!is_file_name(Path::new("/app")));
+        assert!(!is_file_name(Path::new("..")));
+
+        assert!(is_relative(Path::new("src/main.sol")));
+        assert!(is_relative(Path::new("./main.sol")));
+        assert!(!is_relative(Path::new("../main.sol")));
+        assert!(!is_relative(Path::new("/src/main.sol")));
+    }
+
+    #[test]
+    fn test_pipeline_defaults_to_frontend() {
+        let mut categories = CategoryMap::new();
+        let frontend = Entry::new("v1.0.0".into(), None, "/bin/frontend".into());
+        categories.entry("frontend".into()).or_default().insert("solc".into(), frontend);
+
+        let steps = pipeline(Some(&categories), "solidity", "sol").unwrap();
+        assert_eq!(
+            steps,
+            [Step {
+                tool: "/bin/frontend".into(),
+                name: "solc".into(),
+                version: "v1.0.0".into(),
+                runtimes: Vec::new(),
+                input: "sol".into(),
+                output: "clif".into(),
+            }]
+        );
+        assert!(pipeline(None, "solidity", "sol").is_err());
+    }
+
+    #[test]
+    fn test_pipeline_stages() {
+        let mut categories = CategoryMap::new();
+        categories.entry("frontend".into()).or_default().insert("cc".into(), stage(1, "i", "clif"));
+        categories
+            .entry("preprocessor".into())
+            .or_default()
+            .insert("cpp".into(), stage(0, "c", "i"));
+
+        let steps = pipeline(Some(&categories), "c", "c").unwrap();
+        let tools: Vec<_> = steps.iter().map(|s| s.tool.to_str().unwrap()).collect();
+        assert_eq!(tools, ["/bin/c", "/bin/i"]);
+
+        // Stages must chain from the source extension to CLIF
+        assert!(pipeline(Some(&categories), "c", "h").is_err());
+        categories.get_mut("frontend").unwrap().insert("cc".into(), stage(1, "i", "ll"));
+        assert!(pipeline(Some(&categories), "c", "c").is_err());
+    }
+
+    #[test]
+    fn test_differing() {
+        let first = outputs(&[("main.o", "a"), ("lib.o", "b"), ("old.o", "c")]);
+        let second = outputs(&[("main.o", "a"), ("lib.o", "x"), ("new.o", "d")]);
+
+        let differing = differing(&first, &second);
+        assert_eq!(differing, [&PathBuf::from("lib.o"), &"old.o".into(), &"new.o".into()]);
+        assert!(verify(&first, &second).is_err());
+        assert!(verify(&first, &first).is_ok());
+    }
+}
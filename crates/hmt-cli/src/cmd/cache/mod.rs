@@ -0,0 +1,40 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod status;
+
+use std::sync::Arc;
+
+use crate::{context::Context, errors::Result};
+use clap::{Args, Subcommand};
+
+/// Inspect the registry metadata cache
+#[derive(Args, Debug)]
+pub struct Command {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    Status(status::Command),
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        match &self.command {
+            Commands::Status(cmd) => cmd.exec(ctx).await,
+        }
+    }
+}
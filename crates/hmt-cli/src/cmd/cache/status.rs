@@ -0,0 +1,57 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::Args;
+
+use crate::{context::Context, errors::Result};
+
+/// Shows the size and freshness of the registry metadata cache
+#[derive(Args, Debug)]
+pub struct Command {
+    /// List every cached URL
+    #[arg(long)]
+    verbose: bool,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let cache = ctx.metadata_cache()?;
+        let mut entries = cache.entries();
+        entries.sort_by(|a, b| a.url.cmp(&b.url));
+
+        let size: u64 = entries.iter().map(|e| e.size).sum();
+        let fresh = entries.iter().filter(|e| cache.is_fresh(e)).count();
+
+        println!("Location: {}", cache.dir().display());
+        println!(
+            "Entries:  {} ({} fresh, {} expired)",
+            entries.len(),
+            fresh,
+            entries.len() - fresh
+        );
+        println!("Size:     {} of {} bytes", size, cache.limit());
+        println!("TTL:      {}s", ctx.config()?.cache.ttl);
+
+        if self.verbose {
+            for entry in &entries {
+                let state = if cache.is_fresh(entry) { "fresh" } else { "expired" };
+                println!("  {} {} ({} bytes)", state, entry.url, entry.size);
+            }
+        }
+
+        Ok(())
+    }
+}
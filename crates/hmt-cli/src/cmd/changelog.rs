@@ -0,0 +1,126 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fmt::Write as _, sync::Arc};
+
+use clap::Args;
+use hmt_registry::{
+    manager::{Changelog, Manager},
+    traits::{PackageKind, PackageManager},
+};
+use tracing::info;
+
+use crate::{context::Context, errors::Result};
+
+/// Shows the release notes between the installed and latest versions of
+/// outdated toolchain and target packages
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The languages to show release notes for; all installed ones without any.
+    domains: Vec<String>,
+
+    /// Print the release notes as JSON, for automation.
+    #[arg(long)]
+    json: bool,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let mut changelogs = Vec::new();
+        changelogs.extend(self.collect(&*ctx.toolchains().await?.read().await).await?);
+        changelogs.extend(self.collect(&*ctx.targets().await?.read().await).await?);
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&changelogs)?);
+        } else if changelogs.is_empty() {
+            info!("All packages are up to date");
+        } else {
+            print!("{}", render(&changelogs));
+        }
+
+        Ok(())
+    }
+
+    /// Collects the release notes of the selected domains installed by a
+    /// manager.
+    async fn collect<T: PackageKind>(&self, manager: &Manager<T>) -> Result<Vec<Changelog>> {
+        let installed: Vec<String> =
+            manager.list().into_iter().flatten().map(|(d, _)| d.clone()).collect();
+
+        let mut changelogs = Vec::new();
+        for domain in
+            installed.iter().filter(|d| self.domains.is_empty() || self.domains.contains(d))
+        {
+            changelogs.extend(manager.changelog(domain).await?);
+        }
+        Ok(changelogs)
+    }
+}
+
+/// Renders the release notes grouped per package, newest release first.
+fn render(changelogs: &[Changelog]) -> String {
+    let mut out = String::new();
+    for changelog in changelogs {
+        let Changelog { name, category, from, to, .. } = changelog;
+        let _ = writeln!(out, "{name} ({category}) {from} → {to}");
+        for release in &changelog.releases {
+            let _ = writeln!(out, "\n  {}", release.version);
+            for line in release.changelog.iter().flat_map(|notes| notes.trim_end().lines()) {
+                let _ = writeln!(out, "    {line}");
+            }
+            match (&release.changelog, &release.changelog_url) {
+                (_, Some(url)) => {
+                    let _ = writeln!(out, "    See {url}");
+                }
+                (None, None) => out.push_str("    No release notes published\n"),
+                (Some(_), None) => {}
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use hmt_registry::manager::ReleaseNotes;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let notes = |version: &str, changelog: Option<&str>, url: Option<&str>| ReleaseNotes {
+            version: version.into(),
+            changelog: changelog.map(Into::into),
+            changelog_url: url.map(Into::into),
+        };
+        let changelog = Changelog {
+            category: "frontend".into(),
+            name: "solidity-frontend".into(),
+            from: "v1.0.0".into(),
+            to: "v1.2.0".into(),
+            releases: vec![
+                notes("v1.2.0", Some("- Added events\n- Fixed imports\n"), None),
+                notes("v1.1.0", None, Some("https://example.com/CHANGELOG.md")),
+                notes("v1.0.1", None, None),
+            ],
+        };
+
+        let out = render(&[changelog]);
+        assert!(out.starts_with("solidity-frontend (frontend) v1.0.0 → v1.2.0\n"));
+        assert!(out.contains("  v1.2.0\n    - Added events\n    - Fixed imports\n"));
+        assert!(out.contains("  v1.1.0\n    See https://example.com/CHANGELOG.md\n"));
+        assert!(out.contains("  v1.0.1\n    No release notes published\n"));
+    }
+}
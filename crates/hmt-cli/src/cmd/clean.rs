@@ -0,0 +1,82 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, sync::Arc};
+
+use anyhow::Context as _;
+use clap::Args;
+use hmt_registry::traits::PackageManager;
+
+use crate::{context::Context, errors::Result, fingerprint, flock::BuildLock};
+
+/// Removes the build outputs of the project
+///
+/// With `--stale`, only outputs produced by toolchain and target package
+/// versions that are no longer installed are removed, and the remaining
+/// outputs stay available for reuse by the next build.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Only remove outputs of package versions no longer installed
+    #[arg(long)]
+    stale: bool,
+
+    /// Only clean the outputs of this target platform
+    #[arg(long)]
+    target: Option<String>,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let root = ctx.project_dir()?.join("target");
+        if !root.exists() {
+            return Ok(());
+        }
+        let _lock = BuildLock::acquire(&root, true).await?;
+
+        let mut target_dirs = Vec::new();
+        for entry in fs::read_dir(&root)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let selected = self.target.as_ref().is_none_or(|target| name == target.as_str());
+            if entry.file_type()?.is_dir() && selected {
+                target_dirs.push(entry.path());
+            }
+        }
+
+        if !self.stale {
+            for dir in &target_dirs {
+                fs::remove_dir_all(dir).context(format!("Failed to remove {}", dir.display()))?;
+            }
+            ctx.reporter().info(format!("Removed {} build output directories", target_dirs.len()));
+            return Ok(());
+        }
+
+        let mut installed = fingerprint::installed(ctx.toolchains().await?.read().await.list());
+        installed.extend(fingerprint::installed(ctx.targets().await?.read().await.list()));
+
+        let mut removed = 0;
+        for dir in &target_dirs {
+            removed += fingerprint::remove_stale(dir, &installed)?.len();
+
+            // Dependencies are built into their own directories under `deps`
+            let Ok(deps) = fs::read_dir(dir.join("deps")) else { continue };
+            for dep in deps {
+                removed += fingerprint::remove_stale(&dep?.path(), &installed)?.len();
+            }
+        }
+        ctx.reporter().info(format!("Removed {removed} stale build outputs"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::Args;
+
+use crate::{completions::Completions, context::Context, errors::Result};
+
+/// Prints the registry domains of a kind, for shell completion
+///
+/// Only the local completions cache is read, which is refreshed after
+/// commands that access the registry, so completion never waits on the
+/// network. Prints nothing until the registry has been accessed once.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The kind of packages, e.g. `toolchains` or `targets`
+    kind: String,
+
+    /// Only print domains starting with this prefix
+    #[arg(default_value = "")]
+    prefix: String,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let completions = Completions::load(&ctx.completions_path()?);
+        for domain in completions.domains(&self.kind, &self.prefix) {
+            println!("{domain}");
+        }
+
+        Ok(())
+    }
+}
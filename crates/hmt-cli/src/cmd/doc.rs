@@ -0,0 +1,105 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, sync::Arc};
+
+use anyhow::{anyhow, bail, Context as _};
+use clap::Args;
+use tracing::info;
+
+use hmt_registry::traits::Query;
+use hmt_utils::process::Process;
+
+use crate::{
+    context::Context,
+    errors::{CliError, Result},
+    manifest, utils,
+};
+
+/// Generates documentation for the project sources
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Open the generated documentation in a browser
+    #[arg(long)]
+    open: bool,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let manifest_path = ctx.manifest_path()?;
+        let manifest = manifest::load(&ctx, manifest_path).await?;
+        let project_dir = ctx.project_dir()?;
+
+        // Acquires the toolchain manager.
+        let manager = ctx.toolchains().await?;
+        let manager = manager.read().await;
+
+        let language = &manifest.project.language;
+        let extension = manifest.project.extension.as_str();
+
+        // Get the appropriate documentation generator
+        let packages = manager.get_package(language, "doc-generator");
+        let package = packages.first().ok_or_else(|| CliError::UnsupportedLanguage {
+            role: "Documentation generator",
+            language: language.to_string(),
+        })?;
+        let generator_path = &package.entry.path;
+
+        let env = ctx.project_env(&manifest)?;
+        let runtime_envs =
+            utils::runtime_envs(&manager.package_runtimes(language, package.entry.runtimes.keys()));
+        let doc_dir = project_dir.join("target").join("doc");
+        fs::create_dir_all(&doc_dir).context("Failed to create doc directory")?;
+
+        // Process all source files with the matching language extension
+        let mut pages = Vec::new();
+        for input in utils::sources(project_dir, extension) {
+            let file_stem = input
+                .file_stem()
+                .ok_or_else(|| anyhow!("Source file has no valid name: {}", input.display()))?;
+            let output = doc_dir.join(file_stem).with_extension("html");
+
+            let cmd = Process::new(generator_path)
+                .envs(env.iter().cloned())
+                .envs(runtime_envs.iter().cloned())
+                .arg("--input")
+                .arg(&input)
+                .arg("--output")
+                .arg(&output)
+                .output()
+                .await?;
+
+            if !cmd.status.success() {
+                let stderr = String::from_utf8_lossy(&cmd.stderr);
+                bail!("Documentation failed with status {}:\n{}", cmd.status, stderr.trim());
+            }
+
+            pages.push(output);
+        }
+
+        info!("Documentation generated in {}", doc_dir.display());
+
+        if self.open {
+            // Prefer an index page if the generator produced one
+            let index = doc_dir.join("index.html");
+            let page = if index.exists() { Some(&index) } else { pages.first() };
+            match page {
+                Some(page) => utils::open(page).await?,
+                None => bail!("No documentation pages were generated"),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, path::PathBuf, sync::Arc};
+
+use anyhow::{bail, Context as _};
+use clap::Args;
+use tracing::{info, warn};
+
+use hmt_manifest::{FreezeManifest, ManifestFile};
+use hmt_utils::signature::{self, SIGNATURE_FILE_SUFFIX};
+
+use crate::{context::Context, errors::Result};
+
+/// Installs the exact packages listed by `toolchain freeze`
+///
+/// Packages are installed in the recorded order from their recorded
+/// artifacts, packages already installed with the same hash are skipped.
+/// Like `toolchain add --from-lock`, every artifact must still be served by
+/// the registry and pass the signature and provenance policy.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The freeze manifest to replay
+    manifest: PathBuf,
+
+    /// Hex-encoded public key the manifest signature must verify against,
+    /// required when the policy requires signatures
+    #[arg(long)]
+    public_key: Option<String>,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        if let Some(public_key) = &self.public_key {
+            let mut path = self.manifest.clone().into_os_string();
+            path.push(format!(".{SIGNATURE_FILE_SUFFIX}"));
+            let signature = fs::read_to_string(&path)
+                .context(format!("Failed to read signature: {}", PathBuf::from(&path).display()))?;
+            signature::verify(public_key, &fs::read(&self.manifest)?, signature.trim())?;
+        } else if ctx.config()?.policy.require_signatures {
+            bail!("The policy requires signatures, pass the manifest signer's --public-key");
+        } else {
+            warn!("No --public-key given, the manifest signature is not verified");
+        }
+
+        let manifest = FreezeManifest::load(&self.manifest)
+            .context(format!("Failed to read freeze manifest: {}", self.manifest.display()))?;
+
+        let toolchains = ctx.toolchains().await?;
+        let mut toolchains = toolchains.write().await;
+        let targets = ctx.targets().await?;
+        let mut targets = targets.write().await;
+
+        let mut progress = ctx.progress("Importing", manifest.packages.len());
+        for package in &manifest.packages {
+            progress.inc(&format!("{} {}", package.name, package.version));
+            let installed = if package.kind == toolchains.kind() {
+                toolchains.install_locked(package).await?
+            } else if package.kind == targets.kind() {
+                targets.install_locked(package).await?
+            } else {
+                bail!("Unknown package kind '{}' for {}", package.kind, package.name);
+            };
+
+            if installed {
+                info!("Installed {} {}", package.name, package.version);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context as _};
+use clap::Args;
+use hmt_fetcher::FetchContext;
+use hmt_utils::{checksum, temp::TempFile};
+use tracing::info;
+
+use crate::{context::Context, errors::Result};
+
+/// Downloads a file, verifying its checksum
+///
+/// Every scheme the registry can be served from is supported, and the
+/// configured mirrors are tried before the URL itself. Downloads verified
+/// with `--sha256` are kept in the download cache and not fetched again.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The URL to download, e.g. `https://...` or `file://...`
+    url: String,
+
+    /// The expected SHA-256 hash of the file
+    #[arg(long, value_name = "HASH")]
+    sha256: Option<String>,
+
+    /// The URL of a file holding the expected SHA-256 hash
+    #[arg(long, value_name = "URL", conflicts_with = "sha256")]
+    sha256_url: Option<String>,
+
+    /// Where to write the file, `-` for stdout; defaults to the last
+    /// segment of the URL in the current directory
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Download even if the file is in the download cache
+    #[arg(long)]
+    no_cache: bool,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => PathBuf::from(file_name(&self.url)?),
+        };
+
+        let data = match self.cached(&ctx) {
+            Some(data) => data,
+            None if ctx.offline() => bail!("{} is not cached, cannot fetch it offline", self.url),
+            None => self.download(&ctx).await?,
+        };
+
+        if output == Path::new("-") {
+            std::io::stdout().write_all(&data)?;
+            return Ok(());
+        }
+        fs::write(&output, &data).context(format!("Failed to write {}", output.display()))?;
+        info!("Fetched {} ({} bytes) to {}", self.url, data.len(), output.display());
+
+        Ok(())
+    }
+
+    /// Returns the file from the download cache, if verified before
+    fn cached(&self, ctx: &Context) -> Option<Vec<u8>> {
+        let hash = self.sha256.as_ref().filter(|_| !self.no_cache)?.to_lowercase();
+        let data = fs::read(ctx.downloads_dir().join(&hash)).ok()?;
+        (checksum::digest(&data) == hash).then_some(data)
+    }
+
+    /// Downloads and verifies the file, adding it to the download cache
+    async fn download(&self, ctx: &Context) -> Result<Vec<u8>> {
+        let mut context = FetchContext::new(&self.url);
+        let hash = self.sha256.as_ref().map(|hash| hash.to_lowercase());
+        if let Some(hash) = &hash {
+            context = context.checksum(hash);
+        }
+        if let Some(url) = &self.sha256_url {
+            context = context.checksum_url(url);
+        }
+
+        let name = file_name(&self.url).unwrap_or(&self.url);
+        let mut progress = ctx.progress(&format!("Fetching {name}"), 1);
+        let data = ctx.fetcher()?.fetch(&context).await?;
+        progress.inc(name);
+
+        if let Some(hash) = hash {
+            let file = TempFile::new_in(&ctx.downloads_dir())?;
+            fs::write(file.path(), &data)?;
+            file.persist(&ctx.downloads_dir().join(hash))?;
+        }
+
+        Ok(data)
+    }
+}
+
+/// Returns the last path segment of a URL, ignoring any query or fragment
+fn file_name(url: &str) -> Result<&str> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+    match path.rsplit_once('/') {
+        Some((_, name)) if !name.is_empty() => Ok(name),
+        _ => bail!("Cannot name the file of {url}, pass --output"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        fn name(url: &str) -> Option<&str> {
+            file_name(url).ok()
+        }
+
+        assert_eq!(name("https://example.com/dl/tool-v1.0.0.tar.gz"), Some("tool-v1.0.0.tar.gz"));
+        assert_eq!(name("https://example.com/dl/a.zip?token=x#top"), Some("a.zip"));
+        assert_eq!(name("file:///tmp/checksums.txt"), Some("checksums.txt"));
+        assert_eq!(name("https://example.com/"), None);
+        assert_eq!(name("https://example.com"), None);
+    }
+}
@@ -14,10 +14,12 @@
 
 use std::{
     collections::HashSet,
-    io::{self, Write},
+    fs,
+    io::{self, IsTerminal, Write},
     path::Path,
     str::FromStr,
     sync::Arc,
+    time::Duration,
 };
 
 use anyhow::Context as _;
@@ -26,13 +28,39 @@ use clap::Args;
 use hmt_detection::DetectResult;
 use hmt_manifest::{ManifestFile, PackageEntry, Project, ProjectManifest};
 use hmt_registry::traits::Query;
+use hmt_utils::process::Process;
 use tracing::{debug, info, warn};
 
-use crate::{context::Context, errors::Result, utils};
+use super::{prefetch, self_};
+use crate::{context::Context, deps::LOCKFILE, errors::Result, shell, utils};
+
+/// How long a detector may inspect the project before it is stopped.
+const DETECT_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Initializes the workspace
 #[derive(Args, Debug)]
-pub struct Command {}
+pub struct Command {
+    /// Create or append `.gitignore` entries for the target directory
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    gitignore: Option<bool>,
+
+    /// Add the lockfile to `.gitignore` instead of committing it
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    ignore_lockfile: Option<bool>,
+
+    /// Create a minimal `.editorconfig` if none exists
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    editorconfig: Option<bool>,
+
+    /// Download the detected toolchain in the background
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    prefetch: Option<bool>,
+
+    /// Add `~/.hummanta/bin` to PATH in shell configuration files, offered
+    /// when it is missing and the input is a terminal
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    setup_path: Option<bool>,
+}
 
 impl Command {
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
@@ -47,19 +75,60 @@ impl Command {
         let path = std::env::current_dir()?;
         let languages = self.detect(&detectors, &path).await?;
 
-        match languages.len() {
-            0 => warn!("No supported language detected in this directory"),
-            1 => self.write_config(languages[0].clone())?,
-            _ => {
-                // Multiple matches - let user choose
-                let selected = self.prompt_user_selection(&languages)?;
-                self.write_config(selected)?
+        let selected = match languages.len() {
+            0 => {
+                warn!("No supported language detected in this directory");
+                return Ok(());
+            }
+            1 => languages[0].clone(),
+            // Multiple matches - let user choose
+            _ => self.prompt_user_selection(&languages)?,
+        };
+
+        let extension = selected.1.clone();
+        self.write_config(selected)?;
+
+        // Generate auxiliary files, flags take precedence over config defaults
+        let defaults = &ctx.config()?.init;
+        if self.gitignore.unwrap_or(defaults.gitignore) {
+            let ignore_lockfile = self.ignore_lockfile.unwrap_or(defaults.ignore_lockfile);
+            self.write_gitignore(&path, ignore_lockfile)?;
+        }
+        if self.editorconfig.unwrap_or(defaults.editorconfig) {
+            self.write_editorconfig(&path, &extension)?;
+        }
+        if self.prefetch.unwrap_or(defaults.prefetch) && !ctx.offline() {
+            // The project is usable without it, so a failed spawn is not fatal
+            match prefetch::spawn(&ctx) {
+                Ok(()) => info!("Prefetching toolchain in the background"),
+                Err(e) => warn!("Failed to start prefetch: {e}"),
+            }
+        }
+        if self.setup_path(&ctx)? {
+            // The project is usable without it, so a failed setup is not fatal
+            if let Err(e) = self_::setup(&ctx, None) {
+                warn!("Failed to set up PATH: {e}");
             }
         }
 
         Ok(())
     }
 
+    /// Whether to set up PATH: as given by the flag, else after asking if
+    /// the bin directory is not on PATH yet
+    fn setup_path(&self, ctx: &Context) -> Result<bool> {
+        if let Some(setup) = self.setup_path {
+            return Ok(setup);
+        }
+        if shell::on_path(&ctx.bin_dir()) || !io::stdin().is_terminal() {
+            return Ok(false);
+        }
+
+        let prompt =
+            format!("Add {} to PATH in your shell configuration? [y/N]", ctx.bin_dir().display());
+        utils::confirm(&prompt)
+    }
+
     /// Execute all detectors and return all matching languages
     async fn detect(
         &self,
@@ -69,11 +138,12 @@ impl Command {
         let mut languages = HashSet::new();
 
         for detector in detectors {
-            let cmd = utils::command(
-                &detector.entry.path,
-                &["--path", path.to_str().context("Path contains invalid UTF-8")?],
-            )
-            .await?;
+            let cmd = Process::new(&detector.entry.path)
+                .arg("--path")
+                .arg(path)
+                .timeout(DETECT_TIMEOUT)
+                .output()
+                .await?;
 
             if !cmd.status.success() {
                 continue;
@@ -131,4 +201,87 @@ impl Command {
 
         Ok(())
     }
+
+    /// Create `.gitignore`, or append the entries it is missing
+    fn write_gitignore(&self, path: &Path, ignore_lockfile: bool) -> Result<()> {
+        let mut entries = vec!["/target"];
+        if ignore_lockfile {
+            entries.push(LOCKFILE);
+        }
+
+        let gitignore = path.join(".gitignore");
+        let existing = fs::read_to_string(&gitignore).unwrap_or_default();
+        let content = gitignore_content(&existing, &entries);
+
+        if content != existing {
+            fs::write(&gitignore, content).context("Failed to write .gitignore")?;
+            info!("Updated {}", gitignore.display());
+        }
+
+        Ok(())
+    }
+
+    /// Create a minimal `.editorconfig` unless one already exists
+    fn write_editorconfig(&self, path: &Path, extension: &str) -> Result<()> {
+        let editorconfig = path.join(".editorconfig");
+        if editorconfig.exists() {
+            debug!("{} already exists, skipping", editorconfig.display());
+            return Ok(());
+        }
+
+        let content = format!(
+            "root = true\n\n\
+             [*]\n\
+             charset = utf-8\n\
+             end_of_line = lf\n\
+             insert_final_newline = true\n\
+             trim_trailing_whitespace = true\n\n\
+             [*.{extension}]\n\
+             indent_style = space\n\
+             indent_size = 4\n"
+        );
+
+        fs::write(&editorconfig, content).context("Failed to write .editorconfig")?;
+        info!("Created {}", editorconfig.display());
+
+        Ok(())
+    }
+}
+
+/// Returns `existing` with every missing entry appended on its own line
+fn gitignore_content(existing: &str, entries: &[&str]) -> String {
+    let present: HashSet<&str> = existing.lines().map(str::trim).collect();
+
+    let mut content = existing.to_string();
+    for entry in entries.iter().filter(|e| !present.contains(*e)) {
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(entry);
+        content.push('\n');
+    }
+
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_content_new_file() {
+        assert_eq!(gitignore_content("", &["/target"]), "/target\n");
+    }
+
+    #[test]
+    fn test_gitignore_content_appends_missing() {
+        let content = gitignore_content("*.log", &["/target", LOCKFILE]);
+        assert_eq!(content, "*.log\n/target\nhummanta.lock\n");
+    }
+
+    #[test]
+    fn test_gitignore_content_keeps_existing() {
+        let existing = "/target\n";
+        assert_eq!(gitignore_content(existing, &["/target"]), existing);
+    }
 }
@@ -13,15 +13,39 @@
 // limitations under the License.
 
 mod build;
+mod cache;
+mod changelog;
+mod clean;
+mod completions;
+mod doc;
+mod env;
+mod fetch;
 mod init;
+mod prefetch;
+mod query;
+mod registry;
+mod repl;
+mod report;
+mod run;
+mod self_;
+mod serve;
 mod target;
+mod test;
 mod toolchain;
+mod vendor;
 
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
-use clap::{Parser, Subcommand};
+use clap::{builder::PossibleValuesParser, Parser, Subcommand};
+use hmt_utils::event::warning;
 
-use crate::{context::Context, errors::Result};
+use crate::{
+    context::Context,
+    errors::{ErrorFormat, Result},
+    progress::ProgressMode,
+};
+
+pub use self_::remove_stale;
 
 #[derive(Parser)]
 #[command(arg_required_else_help = true, disable_help_subcommand = false)]
@@ -32,23 +56,128 @@ pub struct Command {
     /// Override the registry URL.
     #[arg(long, global = true, env = "HUMMANTA_REGISTRY")]
     pub registry: Option<String>,
+
+    /// Run without accessing the network.
+    #[arg(long, global = true, env = "HUMMANTA_OFFLINE")]
+    pub offline: bool,
+
+    /// Require `hummanta.lock` to be up to date, and verify that the registry
+    /// still serves the manifests it was locked from.
+    #[arg(long, global = true, env = "HUMMANTA_LOCKED")]
+    pub locked: bool,
+
+    /// How progress is reported; `plain` avoids redrawn lines and colors.
+    #[arg(long, global = true, value_enum, default_value_t, env = "HUMMANTA_PROGRESS")]
+    pub progress: ProgressMode,
+
+    /// How a failure is reported; `json` prints a machine-readable report
+    /// with a stable error code.
+    #[arg(long, global = true, value_enum, default_value_t, env = "HUMMANTA_ERROR_FORMAT")]
+    pub error_format: ErrorFormat,
+
+    /// Silence the warnings with an id, e.g. `--allow stale-metadata`.
+    #[arg(
+        long,
+        global = true,
+        value_name = "ID",
+        value_delimiter = ',',
+        value_parser = PossibleValuesParser::new(warning::ALL)
+    )]
+    pub allow: Vec<String>,
+
+    /// Fail the command if it reported any warning that is not allowed.
+    #[arg(long, global = true, env = "HUMMANTA_DENY_WARNINGS")]
+    pub deny_warnings: bool,
+
+    /// The home directory packages are installed into, `~/.hummanta` by
+    /// default. Administrators point it at the system-wide installation to
+    /// change it.
+    #[arg(long, global = true, value_name = "DIR", env = "HUMMANTA_HOME")]
+    pub home: Option<PathBuf>,
+
+    /// The read-only system-wide installation whose packages are used unless
+    /// the home directory has them, `/opt/hummanta` by default if it exists.
+    #[arg(long, global = true, value_name = "DIR", env = "HUMMANTA_SYSTEM_HOME")]
+    pub system_home: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     Build(build::Command),
+    Cache(cache::Command),
+    Changelog(changelog::Command),
+    Clean(clean::Command),
+    Completions(completions::Command),
+    Doc(doc::Command),
+    Env(env::Command),
+    Fetch(fetch::Command),
     Init(init::Command),
+    Prefetch(prefetch::Command),
+    Query(query::Command),
+    Registry(registry::Command),
+    Repl(repl::Command),
+    Report(report::Command),
+    Run(run::Command),
+    #[command(name = "self")]
+    SelfCmd(self_::Command),
+    Serve(serve::Command),
     Target(target::Command),
+    Test(test::Command),
     Toolchain(toolchain::Command),
+    Vendor(vendor::Command),
 }
 
 impl Command {
+    /// Returns the name of the invoked subcommand.
+    pub fn name(&self) -> &'static str {
+        match &self.command {
+            Commands::Build(_) => "build",
+            Commands::Cache(_) => "cache",
+            Commands::Changelog(_) => "changelog",
+            Commands::Clean(_) => "clean",
+            Commands::Completions(_) => "completions",
+            Commands::Doc(_) => "doc",
+            Commands::Env(_) => "env",
+            Commands::Fetch(_) => "fetch",
+            Commands::Init(_) => "init",
+            Commands::Prefetch(_) => "prefetch",
+            Commands::Query(_) => "query",
+            Commands::Registry(_) => "registry",
+            Commands::Repl(_) => "repl",
+            Commands::Report(_) => "report",
+            Commands::Run(_) => "run",
+            Commands::SelfCmd(_) => "self",
+            Commands::Serve(_) => "serve",
+            Commands::Target(_) => "target",
+            Commands::Test(_) => "test",
+            Commands::Toolchain(_) => "toolchain",
+            Commands::Vendor(_) => "vendor",
+        }
+    }
+
     pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
         match &self.command {
             Commands::Build(cmd) => cmd.exec(ctx).await,
+            Commands::Cache(cmd) => cmd.exec(ctx).await,
+            Commands::Changelog(cmd) => cmd.exec(ctx).await,
+            Commands::Clean(cmd) => cmd.exec(ctx).await,
+            Commands::Completions(cmd) => cmd.exec(ctx).await,
+            Commands::Doc(cmd) => cmd.exec(ctx).await,
+            Commands::Env(cmd) => cmd.exec(ctx).await,
+            Commands::Fetch(cmd) => cmd.exec(ctx).await,
             Commands::Init(cmd) => cmd.exec(ctx).await,
+            Commands::Prefetch(cmd) => cmd.exec(ctx).await,
+            Commands::Query(cmd) => cmd.exec(ctx).await,
+            Commands::Registry(cmd) => cmd.exec(ctx).await,
+            Commands::Repl(cmd) => cmd.exec(ctx).await,
+            Commands::Report(cmd) => cmd.exec(ctx).await,
+            Commands::Run(cmd) => cmd.exec(ctx).await,
+            Commands::SelfCmd(cmd) => cmd.exec(ctx).await,
+            Commands::Serve(cmd) => cmd.exec(ctx).await,
             Commands::Target(cmd) => cmd.exec(ctx).await,
+            Commands::Test(cmd) => cmd.exec(ctx).await,
             Commands::Toolchain(cmd) => cmd.exec(ctx).await,
+            Commands::Vendor(cmd) => cmd.exec(ctx).await,
         }
     }
 }
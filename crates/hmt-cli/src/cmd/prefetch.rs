@@ -0,0 +1,85 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::Args;
+use tracing::{debug, info};
+
+use hmt_registry::traits::Query;
+use hmt_utils::process::Process;
+
+use crate::{context::Context, errors::Result, manifest};
+
+/// Downloads the toolchain and target the current project needs into the
+/// download cache, so the first build installs them without waiting on the
+/// network
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The target to prefetch, defaults to the one in hummanta.toml
+    #[arg(long)]
+    target: Option<String>,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        if ctx.offline() {
+            info!("Offline mode, nothing to prefetch");
+            return Ok(());
+        }
+
+        let manifest = manifest::load(&ctx, ctx.manifest_path()?).await?;
+        let language = manifest.project.language.to_lowercase();
+
+        // Only the download cache is written, never the installed packages,
+        // so a build or install running meanwhile is not disturbed
+        let toolchains = ctx.toolchains().await?;
+        let toolchains = toolchains.read().await;
+        if toolchains.get_package(&language, "frontend").is_empty() {
+            info!("Prefetching {} toolchain", language);
+            let count = toolchains.prefetch(&language).await?;
+            debug!("Downloaded {} artifacts of the {} toolchain", count, language);
+        } else {
+            debug!("{} toolchain is already installed", language);
+        }
+
+        let Some(target) = self.target.as_ref().or(manifest.project.target.as_ref()) else {
+            return Ok(());
+        };
+
+        let targets = ctx.targets().await?;
+        let targets = targets.read().await;
+        if targets.get_category(target).is_none() {
+            info!("Prefetching {} target", target);
+            let count = targets.prefetch(target).await?;
+            debug!("Downloaded {} artifacts of the {} target", count, target);
+        } else {
+            debug!("{} target is already installed", target);
+        }
+
+        Ok(())
+    }
+}
+
+/// Starts `hummanta prefetch` as a detached process, so it keeps running
+/// after the current command exits. The global options choosing where
+/// packages come from and go are passed on, so it warms the same caches.
+pub fn spawn(ctx: &Context) -> Result<()> {
+    Process::new(std::env::current_exe()?)
+        .arg("prefetch")
+        .args(ctx.forwarded_args()?)
+        .spawn_detached()?;
+
+    Ok(())
+}
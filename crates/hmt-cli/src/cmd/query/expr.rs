@@ -0,0 +1,386 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The expression language of `hummanta query`.
+//!
+//! ```text
+//! expr    = and ("or" and)*
+//! and     = unary ("and" unary)*
+//! unary   = "not" unary | "(" expr ")" | operand (op operand)?
+//! op      = "==" | "!=" | "<" | "<=" | ">" | ">=" | "~"
+//! operand = field | "string" | 'string' | word | true | false | null
+//! ```
+//!
+//! A lone operand is true unless it is `false`, `null` or empty. Ordering
+//! compares versions when both sides are versions, and text otherwise.
+//! `~` tests whether the left side contains the right side.
+
+use std::cmp::Ordering;
+
+use anyhow::{bail, Result};
+
+/// A value of a field or literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Str(String),
+    Bool(bool),
+    Null,
+}
+
+impl Value {
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Str(s) => !s.is_empty(),
+            Value::Bool(b) => *b,
+            Value::Null => false,
+        }
+    }
+
+    fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Str(a), Value::Str(b)) => Some(match (version(a), version(b)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            }),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+fn version(s: &str) -> Option<semver::Version> {
+    semver::Version::parse(s.strip_prefix('v').unwrap_or(s)).ok()
+}
+
+/// Rows an expression is evaluated against.
+pub trait Fields {
+    /// The names of all fields, to reject unknown ones when parsing.
+    const NAMES: &'static [&'static str];
+
+    /// Returns the value of a field listed in [`Fields::NAMES`].
+    fn field(&self, name: &str) -> Value;
+}
+
+/// A comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "~",
+        }
+    }
+}
+
+/// A side of a comparison.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Operand {
+    Field(String),
+    Literal(Value),
+}
+
+/// A parsed expression.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Op, Operand),
+    Test(Operand),
+}
+
+impl Expr {
+    /// Parses an expression, checking field names against `F`.
+    pub fn parse<F: Fields>(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0, fields: F::NAMES };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected `{}` in query", token.text());
+        }
+        Ok(expr)
+    }
+
+    /// Evaluates the expression against a row.
+    pub fn eval(&self, row: &impl Fields) -> bool {
+        match self {
+            Expr::Or(a, b) => a.eval(row) || b.eval(row),
+            Expr::And(a, b) => a.eval(row) && b.eval(row),
+            Expr::Not(e) => !e.eval(row),
+            Expr::Test(operand) => operand.value(row).truthy(),
+            Expr::Compare(a, op, b) => {
+                let (a, b) = (a.value(row), b.value(row));
+                match op {
+                    Op::Eq => a == b,
+                    Op::Ne => a != b,
+                    Op::Contains => match (a, b) {
+                        (Value::Str(a), Value::Str(b)) => a.contains(&b),
+                        _ => false,
+                    },
+                    Op::Lt => a.compare(&b) == Some(Ordering::Less),
+                    Op::Le => matches!(a.compare(&b), Some(Ordering::Less | Ordering::Equal)),
+                    Op::Gt => a.compare(&b) == Some(Ordering::Greater),
+                    Op::Ge => matches!(a.compare(&b), Some(Ordering::Greater | Ordering::Equal)),
+                }
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn value(&self, row: &impl Fields) -> Value {
+        match self {
+            Operand::Field(name) => row.field(name),
+            Operand::Literal(value) => value.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Word(word) => word.clone(),
+            Token::Str(s) => format!("{s:?}"),
+            Token::Op(op) => op.symbol().into(),
+            Token::Open => "(".into(),
+            Token::Close => ")".into(),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some(next) => s.push(next),
+                        None => bail!("Unterminated string in query"),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '=' | '!' | '<' | '>' | '~' => {
+                chars.next();
+                let eq = chars.next_if_eq(&'=').is_some();
+                let op = match (c, eq) {
+                    ('=', true) => Op::Eq,
+                    ('!', true) => Op::Ne,
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    ('~', false) => Op::Contains,
+                    _ => bail!("Unknown operator `{c}` in query"),
+                };
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(next) =
+                    chars.next_if(|c| !c.is_whitespace() && !"()\"'=!<>~".contains(*c))
+                {
+                    word.push(next);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    fields: &'static [&'static str],
+}
+
+impl Parser {
+    fn next_if(&mut self, token: &Token) -> bool {
+        let matched = self.tokens.get(self.pos) == Some(token);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.next_if(&Token::Word(keyword.into()))
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.next_if(&Token::Open) {
+            let expr = self.or()?;
+            if !self.next_if(&Token::Close) {
+                bail!("Missing `)` in query");
+            }
+            return Ok(expr);
+        }
+
+        let left = self.operand()?;
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => {
+                let op = *op;
+                self.pos += 1;
+                Ok(Expr::Compare(left, op, self.operand()?))
+            }
+            _ => Ok(Expr::Test(left)),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            bail!("Unexpected end of query");
+        };
+        self.pos += 1;
+
+        Ok(match token {
+            Token::Str(s) => Operand::Literal(Value::Str(s)),
+            Token::Word(word) => match word.as_str() {
+                "true" => Operand::Literal(Value::Bool(true)),
+                "false" => Operand::Literal(Value::Bool(false)),
+                "null" => Operand::Literal(Value::Null),
+                _ if self.fields.contains(&word.as_str()) => Operand::Field(word),
+                // Versions and numbers need no quotes
+                _ if word.trim_start_matches('v').starts_with(|c: char| c.is_ascii_digit()) => {
+                    Operand::Literal(Value::Str(word))
+                }
+                _ => bail!("Unknown field `{word}`, expected one of: {}", self.fields.join(", ")),
+            },
+            token => bail!("Unexpected `{}` in query", token.text()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Row {
+        name: &'static str,
+        version: &'static str,
+        latest: Option<&'static str>,
+    }
+
+    impl Fields for Row {
+        const NAMES: &'static [&'static str] = &["name", "version", "latest", "outdated"];
+
+        fn field(&self, name: &str) -> Value {
+            let text = |s: &str| Value::Str(s.into());
+            match name {
+                "name" => text(self.name),
+                "version" => text(self.version),
+                "latest" => self.latest.map_or(Value::Null, text),
+                _ => Value::Bool(self.latest.is_some_and(|l| l != self.version)),
+            }
+        }
+    }
+
+    const ROW: Row = Row { name: "solidity-frontend", version: "v0.9.0", latest: Some("v0.10.0") };
+
+    fn eval(query: &str, row: &Row) -> bool {
+        Expr::parse::<Row>(query).unwrap().eval(row)
+    }
+
+    #[test]
+    fn test_compare() {
+        assert!(eval(r#"name == "solidity-frontend""#, &ROW));
+        assert!(eval("name != 'other'", &ROW));
+        assert!(eval("name ~ 'frontend'", &ROW));
+        // Versions compare numerically, not as text
+        assert!(eval("version < latest", &ROW));
+        assert!(eval("version >= 0.9.0", &ROW));
+        assert!(!eval("latest < v0.9.1", &ROW));
+    }
+
+    #[test]
+    fn test_logic() {
+        assert!(eval("outdated and (name ~ 'solidity' or false)", &ROW));
+        assert!(eval("not name ~ 'backend'", &ROW));
+        assert!(!eval("outdated and not outdated", &ROW));
+
+        let current = Row { latest: None, ..ROW };
+        assert!(eval("latest == null and not outdated", &current));
+        assert!(!eval("version < latest", &current));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |query: &str| Expr::parse::<Row>(query).unwrap_err().to_string();
+        assert!(error("nmae == 'x'").contains("Unknown field `nmae`"));
+        assert!(error("name == 'x").contains("Unterminated string"));
+        assert!(error("(outdated").contains("Missing `)`"));
+        assert!(error("outdated outdated").contains("Unexpected `outdated`"));
+        assert!(error("name =").contains("Unknown operator"));
+        assert!(error("name ==").contains("Unexpected end"));
+        assert!(error("name == <").contains("Unexpected `<`"));
+    }
+}
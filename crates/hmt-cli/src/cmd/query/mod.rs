@@ -0,0 +1,144 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod expr;
+
+use std::sync::Arc;
+
+use clap::Args;
+use hmt_registry::{
+    manager::Manager,
+    traits::{PackageKind, PackageManager},
+};
+use serde::Serialize;
+
+use crate::{context::Context, errors::Result};
+
+use expr::{Expr, Fields, Value};
+
+/// Queries installed packages, printing the matching rows as JSON
+///
+/// Rows join the installed cache with cached registry metadata, and are
+/// filtered by an expression over their fields, e.g.
+/// `outdated`, `version < latest`, or
+/// `domain == "solidity" and category == "frontend"`.
+/// The registry is never accessed, so `latest` is null for packages whose
+/// metadata has not been fetched yet.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The expression rows must match; all rows are printed without one.
+    #[arg(verbatim_doc_comment)]
+    expr: Option<String>,
+}
+
+/// An installed package.
+#[derive(Debug, Serialize)]
+struct Row {
+    kind: &'static str,
+    domain: String,
+    category: String,
+    name: String,
+    version: String,
+    latest: Option<String>,
+    outdated: bool,
+    source: &'static str,
+    stage: Option<String>,
+    description: Option<String>,
+    path: String,
+}
+
+impl Fields for Row {
+    const NAMES: &'static [&'static str] = &[
+        "kind",
+        "domain",
+        "category",
+        "name",
+        "version",
+        "latest",
+        "outdated",
+        "source",
+        "stage",
+        "description",
+        "path",
+    ];
+
+    fn field(&self, name: &str) -> Value {
+        let text = |s: &str| Value::Str(s.to_string());
+        let optional = |s: &Option<String>| s.as_deref().map_or(Value::Null, text);
+        match name {
+            "kind" => text(self.kind),
+            "domain" => text(&self.domain),
+            "category" => text(&self.category),
+            "name" => text(&self.name),
+            "version" => text(&self.version),
+            "latest" => optional(&self.latest),
+            "outdated" => Value::Bool(self.outdated),
+            "source" => text(self.source),
+            "stage" => optional(&self.stage),
+            "description" => optional(&self.description),
+            "path" => text(&self.path),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let expr = self.expr.as_deref().map(Expr::parse::<Row>).transpose()?;
+
+        let mut rows = Vec::new();
+        rows.extend(installed(&*ctx.toolchains().await?.read().await));
+        rows.extend(installed(&*ctx.targets().await?.read().await));
+        rows.retain(|row| expr.as_ref().is_none_or(|expr| expr.eval(row)));
+
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        Ok(())
+    }
+}
+
+/// Returns a row for every package installed by a manager.
+fn installed<T: PackageKind>(manager: &Manager<T>) -> Vec<Row> {
+    let Some(domains) = manager.list() else {
+        return Vec::new();
+    };
+
+    let mut rows = Vec::new();
+    for (domain, categories) in domains {
+        let latest = manager.cached_latest(domain);
+        for (category, packages) in categories {
+            for (name, entry) in packages {
+                // Only registry packages have a latest release to compare with
+                let latest = match entry.source.is_registry() {
+                    true => latest.get(&(category.clone(), name.clone())).cloned(),
+                    false => None,
+                };
+                rows.push(Row {
+                    kind: T::kind(),
+                    domain: domain.clone(),
+                    category: category.clone(),
+                    name: name.clone(),
+                    version: entry.version.clone(),
+                    outdated: latest.as_ref().is_some_and(|latest| *latest != entry.version),
+                    latest,
+                    source: entry.source.as_str(),
+                    stage: entry.stage.as_ref().map(|s| format!("{}->{}", s.input, s.output)),
+                    description: entry.description.clone(),
+                    path: entry.path.display().to_string(),
+                });
+            }
+        }
+    }
+
+    rows
+}
@@ -0,0 +1,292 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context as _};
+use clap::Args;
+use serde::Serialize;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{info, warn};
+
+use hmt_manifest::{Finding, Severity, Submission, Verdict, PACKAGE_MANIFEST};
+
+use crate::{context::Context, errors::Result};
+
+/// The largest request body the lint server accepts.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// The largest request line and headers the lint server accepts.
+const MAX_HEADER_SIZE: u64 = 16 * 1024;
+
+/// How long the lint server waits for a request before closing the
+/// connection.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Validates package manifests submitted to a registry
+///
+/// Each directory holds a package's index.toml and the release manifests it
+/// references. Besides the offline checks, artifact URLs are checked for
+/// reachability unless `--offline` is given.
+///
+/// With `--serve`, verdicts are served over HTTP instead: `POST /lint` takes
+/// a JSON submission `{"package": "<index.toml>", "releases": {"<file>":
+/// "<release manifest>"}}` and answers with the JSON verdict. The server
+/// never fetches the artifact URLs of a submission.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Directories containing the manifests to lint
+    #[arg(default_value = "manifests")]
+    dirs: Vec<PathBuf>,
+
+    /// Print the verdicts as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Serve verdicts over HTTP on the given address
+    #[arg(long, value_name = "ADDR")]
+    serve: Option<SocketAddr>,
+}
+
+/// The verdict on a single directory.
+#[derive(Serialize)]
+struct Report<'a> {
+    path: &'a Path,
+    #[serde(flatten)]
+    verdict: Verdict,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        if let Some(addr) = self.serve {
+            return serve(ctx, addr).await;
+        }
+
+        let mut reports = Vec::with_capacity(self.dirs.len());
+        for dir in &self.dirs {
+            let verdict = check(&ctx, &load(dir)?, true).await;
+            reports.push(Report { path: dir, verdict });
+        }
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&reports)?);
+        } else {
+            for report in &reports {
+                for finding in &report.verdict.findings {
+                    let severity = match finding.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                    };
+                    println!(
+                        "{}: {severity}[{}]: {}",
+                        report.path.display(),
+                        finding.check,
+                        finding.message
+                    );
+                }
+            }
+        }
+
+        let invalid = reports.iter().filter(|r| !r.verdict.valid).count();
+        if invalid > 0 {
+            bail!("{invalid} of {} submissions failed linting", reports.len());
+        }
+        info!("All {} submissions passed linting", reports.len());
+
+        Ok(())
+    }
+}
+
+/// Reads the package manifest and the release manifests of a directory.
+fn load(dir: &Path) -> Result<Submission> {
+    let path = dir.join(PACKAGE_MANIFEST);
+    let package = fs::read_to_string(&path)
+        .context(format!("Failed to read package manifest: {}", path.display()))?;
+
+    let mut submission = Submission { package, ..Default::default() };
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name != PACKAGE_MANIFEST && name.ends_with(".toml") {
+            submission.releases.insert(name.to_string(), fs::read_to_string(&path)?);
+        }
+    }
+
+    Ok(submission)
+}
+
+/// Lints a submission, checking its artifact URLs are reachable if `probe`
+/// is set and network access is enabled.
+async fn check(ctx: &Context, submission: &Submission, probe: bool) -> Verdict {
+    let mut verdict = submission.lint();
+    if !probe || ctx.offline() {
+        return verdict;
+    }
+
+    let fetcher = ctx.remote_fetcher();
+    for url in submission.artifact_urls() {
+        // Malformed URLs were already reported
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            continue;
+        }
+        if let Err(e) = fetcher.head(&url).await {
+            verdict.push(Finding::error("unreachable", format!("{url}: {e}")));
+        }
+    }
+
+    verdict
+}
+
+/// Answers lint requests until the process is stopped.
+async fn serve(ctx: Arc<Context>, addr: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(addr).await.context(format!("Failed to bind {addr}"))?;
+    info!("Serving lint verdicts on http://{}/lint", listener.local_addr()?);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(&ctx, stream).await {
+                warn!("Failed to answer {peer}: {e}");
+            }
+        });
+    }
+}
+
+/// Answers a single HTTP request.
+async fn handle(ctx: &Context, stream: TcpStream) -> Result<()> {
+    let mut stream = BufReader::new(stream);
+
+    let request = tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await;
+    let request = request.unwrap_or_else(|_| Err(anyhow!("timed out reading the request")));
+    let (status, body) = match request {
+        Ok((method, path, _)) if path != "/lint" => {
+            (404, error(&format!("no route for {method} {path}")))
+        }
+        Ok((method, _, _)) if method != "POST" => (405, error("only POST is allowed")),
+        Ok((_, _, body)) => match serde_json::from_slice::<Submission>(&body) {
+            // Probing URLs named by clients would let them reach any host
+            // through the server
+            Ok(submission) => (200, serde_json::to_string(&check(ctx, &submission, false).await)?),
+            Err(e) => (400, error(&format!("invalid submission: {e}"))),
+        },
+        Err(e) => (400, error(&e.to_string())),
+    };
+
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Bad Request",
+    };
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.get_mut().write_all(response.as_bytes()).await?;
+
+    Ok(())
+}
+
+/// Renders an error message as a JSON body.
+fn error(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+/// Reads the method, path and body of an HTTP/1.1 request, failing if the
+/// request line and headers exceed [`MAX_HEADER_SIZE`] or the body exceeds
+/// [`MAX_BODY_SIZE`].
+async fn read_request<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> Result<(String, String, Vec<u8>)> {
+    let mut head = (&mut *reader).take(MAX_HEADER_SIZE);
+    let mut line = String::new();
+    head.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        bail!("malformed request line");
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        if head.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().context("invalid Content-Length")?;
+            }
+        }
+    }
+    if head.limit() == 0 {
+        bail!("request headers exceed {MAX_HEADER_SIZE} bytes");
+    }
+    if length > MAX_BODY_SIZE {
+        bail!("request body exceeds {MAX_BODY_SIZE} bytes");
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    Ok((method, path, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let request = b"POST /lint HTTP/1.1\r\nHost: localhost\r\ncontent-length: 4\r\n\r\n{}\r\n";
+        let (method, path, body) = read_request(&mut &request[..]).await.unwrap();
+        assert_eq!(method, "POST");
+        assert_eq!(path, "/lint");
+        assert_eq!(body, b"{}\r\n");
+
+        let oversized =
+            format!("POST /lint HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_SIZE + 1);
+        assert!(read_request(&mut oversized.as_bytes()).await.is_err());
+        assert!(read_request(&mut &b"\r\n"[..]).await.is_err());
+
+        let endless = format!("POST /lint HTTP/1.1\r\nX-Padding: {}", "a".repeat(1 << 20));
+        let err = read_request(&mut endless.as_bytes()).await.unwrap_err();
+        assert!(err.to_string().contains("headers exceed"));
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(PACKAGE_MANIFEST), "latest = \"v1.0.0\"").unwrap();
+        fs::write(dir.path().join("release-v1.0.0.toml"), "version = \"v1.0.0\"").unwrap();
+        fs::write(dir.path().join("index.toml.sig"), "abc").unwrap();
+
+        let submission = load(dir.path()).unwrap();
+        assert_eq!(submission.package, "latest = \"v1.0.0\"");
+        assert_eq!(submission.releases.keys().collect::<Vec<_>>(), ["release-v1.0.0.toml"]);
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, path::PathBuf, sync::Arc};
+
+use anyhow::{bail, Context as _};
+use clap::Args;
+use tracing::{info, warn};
+
+use hmt_fetcher::Webhook;
+use hmt_manifest::{ManifestFile, PackageManifest, ReleaseManifest, ReleaseNotification};
+use hmt_utils::signature::{SigningKey, SIGNATURE_FILE_SUFFIX};
+
+use crate::{context::Context, errors::Result};
+
+/// Promotes a released version from one channel to another
+///
+/// The package manifest in the manifests directory, the tree published to
+/// the registry, is rewritten and signed into `index.toml.sig`. Configured
+/// webhooks are then notified with a signed JSON payload.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The name of the package
+    package: String,
+
+    /// The version to promote
+    version: String,
+
+    /// The channel the version is currently on
+    #[arg(long)]
+    from: String,
+
+    /// The channel to promote the version to
+    #[arg(long)]
+    to: String,
+
+    /// Directory containing the package's index.toml and release manifests
+    #[arg(long, default_value = "manifests")]
+    manifests_dir: PathBuf,
+
+    /// Path to the hex-encoded Ed25519 signing key
+    #[arg(long, env = "HUMMANTA_SIGNING_KEY")]
+    key: PathBuf,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let key = SigningKey::load(&self.key)?;
+
+        let path = self.manifests_dir.join("index.toml");
+        let mut manifest = PackageManifest::load(&path)
+            .context(format!("Failed to read package manifest: {}", path.display()))?;
+
+        if manifest.package.name != self.package {
+            bail!(
+                "{} describes '{}', not '{}'",
+                path.display(),
+                manifest.package.name,
+                self.package
+            );
+        }
+
+        // Only maintainers may promote packages that list any
+        let fingerprint = key.fingerprint();
+        if !manifest.package.maintainers.is_empty()
+            && manifest.package.maintainer(&fingerprint).is_none()
+        {
+            bail!("Signing key {fingerprint} does not belong to a maintainer of {}", self.package);
+        }
+
+        manifest.promote(&self.version, &self.from, &self.to)?;
+        manifest.save(&path)?;
+
+        let signature = key.sign(&fs::read(&path)?);
+        let signature_path = path.with_extension(format!("toml.{SIGNATURE_FILE_SUFFIX}"));
+        fs::write(&signature_path, signature).context("Failed to write manifest signature")?;
+
+        info!("Promoted {} {} from {} to {}", self.package, self.version, self.from, self.to);
+
+        self.notify(&ctx, &manifest, &key).await
+    }
+
+    /// Notifies the configured webhooks of the promotion.
+    ///
+    /// The promotion is already published, so failed deliveries only warn.
+    async fn notify(
+        &self,
+        ctx: &Context,
+        manifest: &PackageManifest,
+        key: &SigningKey,
+    ) -> Result<()> {
+        let config = &ctx.config()?.webhooks;
+        if config.endpoints.is_empty() {
+            return Ok(());
+        }
+
+        let release = self.manifests_dir.join(&manifest.releases[&self.version]);
+        let release = ReleaseManifest::load(&release)
+            .context(format!("Failed to read release manifest: {}", release.display()))?;
+        let payload =
+            ReleaseNotification::new("promote", &self.package, &self.to, &release).to_json()?;
+
+        let webhook = Webhook::new(config.endpoints.clone()).retries(config.retries);
+        for (endpoint, e) in webhook.send(&payload, &key.sign(&payload)).await {
+            warn!("Failed to notify {endpoint}: {e}");
+        }
+
+        Ok(())
+    }
+}
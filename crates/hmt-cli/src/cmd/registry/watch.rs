@@ -0,0 +1,457 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    net::SocketAddr,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{bail, Context as _};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::Notify,
+};
+use tracing::{info, warn};
+
+use hmt_fetcher::{FetchContext, Fetcher};
+use hmt_manifest::{IndexManifest, PackageManifest, ReleaseManifest};
+use hmt_registry::{
+    manager::{Library, Target, Toolchain},
+    traits::PackageKind,
+};
+use hmt_utils::{bytes::FromSlice, checksum, temp::TempFile};
+
+use crate::{context::Context, errors::Result};
+
+/// The file the sync state is checkpointed to, inside the mirror.
+const STATE_FILE: &str = ".watch-state.json";
+
+/// The longest delay between polls while the registry keeps failing.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Mirrors the registry into a directory, syncing new releases as they are
+/// published
+///
+/// Every file is stored under its URL without the scheme, e.g.
+/// `https://hummanta.github.io/registry/index.toml` under
+/// `<DIR>/hummanta.github.io/registry/index.toml`. Clients use the mirror
+/// by mapping `"https://" = ["https://mirror.example.com/"]` under
+/// `[mirrors]` in their configuration.
+///
+/// The registry is polled every `--interval` seconds. With `--listen`,
+/// release webhooks, as sent by `registry promote`, trigger a sync right
+/// away. Synced manifests and releases are checkpointed in the mirror, so
+/// a restarted watch only fetches what changed. Failed polls are retried
+/// with exponential backoff.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The directory the mirror is written to
+    dir: PathBuf,
+
+    /// Seconds between polls of the registry
+    #[arg(long, default_value_t = 300)]
+    interval: u64,
+
+    /// Address to receive release webhooks on, e.g. 127.0.0.1:8080
+    #[arg(long)]
+    listen: Option<SocketAddr>,
+
+    /// Only mirror manifests, not release artifacts
+    #[arg(long)]
+    metadata_only: bool,
+
+    /// Sync once and exit
+    #[arg(long)]
+    once: bool,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        if ctx.offline() {
+            bail!("Cannot watch the registry with --offline");
+        }
+
+        // The mirror is synced from the origin, never from configured mirrors
+        let fetcher =
+            Fetcher::with_remote(ctx.remote_fetcher()).with_reporter(ctx.reporter().clone());
+        let mut mirror = Mirror::open(fetcher, &ctx.registry()?, &self.dir, !self.metadata_only)?;
+
+        if self.once {
+            let releases = mirror.sync().await?;
+            info!("Synced {} new releases into {}", releases, self.dir.display());
+            return Ok(());
+        }
+
+        let trigger = Arc::new(Notify::new());
+        if let Some(addr) = self.listen {
+            let listener =
+                TcpListener::bind(addr).await.context(format!("Failed to listen on {addr}"))?;
+            info!("Listening for release webhooks on {}", addr);
+            tokio::spawn(listen(listener, trigger.clone()));
+        }
+
+        let interval = Duration::from_secs(self.interval);
+        let mut failures = 0;
+        loop {
+            match mirror.sync().await {
+                Ok(releases) => {
+                    failures = 0;
+                    if releases > 0 {
+                        info!("Synced {} new releases", releases);
+                    }
+                }
+                Err(e) => {
+                    failures += 1;
+                    warn!("Failed to sync the registry: {e:#}");
+                }
+            }
+
+            let delay = backoff(interval, failures);
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = trigger.notified() => info!("Received a webhook, syncing"),
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the delay before the next poll: the interval, doubled for every
+/// consecutive failure up to [`MAX_BACKOFF`].
+fn backoff(interval: Duration, failures: u32) -> Duration {
+    if failures == 0 {
+        return interval;
+    }
+    interval.saturating_mul(1 << failures.min(16)).min(MAX_BACKOFF.max(interval))
+}
+
+/// Accepts webhook deliveries, triggering a sync for each.
+///
+/// Payloads are not inspected: a delivery only makes the next poll happen
+/// sooner, and the poll itself fetches from the registry.
+async fn listen(listener: TcpListener, trigger: Arc<Notify>) {
+    loop {
+        let Ok((mut socket, _)) = listener.accept().await else { continue };
+        let trigger = trigger.clone();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(&mut socket);
+            let mut line = String::new();
+            while reader.read_line(&mut line).await.is_ok_and(|n| n > 0) {
+                if line.trim().is_empty() {
+                    break;
+                }
+                line.clear();
+            }
+            let response = "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n";
+            let _ = socket.write_all(response.as_bytes()).await;
+            trigger.notify_one();
+        });
+    }
+}
+
+/// What has been mirrored, checkpointed after every change.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    /// The hash of every mirrored manifest, by URL.
+    manifests: BTreeMap<String, String>,
+    /// The URLs of the release manifests mirrored with their artifacts.
+    releases: BTreeSet<String>,
+}
+
+/// A local mirror of a registry.
+struct Mirror {
+    fetcher: Fetcher,
+    registry: String,
+    dir: PathBuf,
+    artifacts: bool,
+    state: State,
+}
+
+impl Mirror {
+    /// Opens the mirror in `dir`, resuming from its checkpoint.
+    fn open(fetcher: Fetcher, registry: &str, dir: &Path, artifacts: bool) -> Result<Self> {
+        fs::create_dir_all(dir).context(format!("Failed to create {}", dir.display()))?;
+
+        let state = match fs::read(dir.join(STATE_FILE)) {
+            Ok(data) => serde_json::from_slice(&data).context("Failed to read the watch state")?,
+            Err(_) => State::default(),
+        };
+        let registry = registry.trim_end_matches('/').to_string();
+        Ok(Self { fetcher, registry, dir: dir.to_path_buf(), artifacts, state })
+    }
+
+    /// Syncs the mirror with the registry, returning the number of
+    /// releases mirrored.
+    ///
+    /// Packages that fail to sync are skipped with a warning and retried by
+    /// the next sync.
+    async fn sync(&mut self) -> Result<usize> {
+        let url = self.resolve("index.toml");
+        let index = self.fetch(&url).await?;
+        let parsed = IndexManifest::from_slice(&index)?;
+
+        let mut releases = 0;
+        let kinds = [Toolchain::kind(), Target::kind(), Library::kind()];
+        for (section, key) in parsed.entries() {
+            let Some(path) = parsed.get(section, key) else { continue };
+            let url = self.resolve(path);
+            let data = self.fetch(&url).await?;
+            if kinds.contains(&section.as_str()) {
+                let domain = IndexManifest::from_slice(&data)?;
+                for (category, name) in domain.entries() {
+                    match self.sync_package(&domain, category, name).await {
+                        Ok(synced) => releases += synced,
+                        Err(e) => warn!("Failed to sync {}: {e:#}", name),
+                    }
+                }
+            }
+            self.store(&url, &data)?;
+        }
+        self.store(&url, &index)?;
+
+        Ok(releases)
+    }
+
+    /// Syncs the releases of a package, storing its manifest once all of
+    /// them are mirrored.
+    async fn sync_package(
+        &mut self,
+        index: &IndexManifest,
+        category: &str,
+        name: &str,
+    ) -> Result<usize> {
+        let Some(homepage) = index.get(category, name) else { return Ok(0) };
+        let homepage = homepage.trim_end_matches('/');
+
+        let url = format!("{homepage}/manifests/index.toml");
+        let data = self.fetch(&url).await?;
+        if self.state.manifests.get(&url) == Some(&checksum::digest(&data)) {
+            return Ok(0);
+        }
+
+        let package = PackageManifest::from_slice(&data)?;
+        let mut releases = 0;
+        for path in package.releases.values() {
+            let release_url = format!("{homepage}/manifests/{path}");
+            if self.state.releases.contains(&release_url) {
+                continue;
+            }
+
+            let release = self.fetch(&release_url).await?;
+            if self.artifacts {
+                for artifact in ReleaseManifest::from_slice(&release)?.artifacts.values() {
+                    let context = FetchContext::new(&artifact.url).checksum(&artifact.hash);
+                    let data = self.fetcher.fetch(&context).await?;
+                    self.write(&artifact.url, &data)?;
+
+                    if let Some(provenance) = &artifact.provenance {
+                        let data = self.fetch(provenance).await?;
+                        self.write(provenance, &data)?;
+                    }
+                }
+            }
+            self.store(&release_url, &release)?;
+            self.state.releases.insert(release_url);
+            self.checkpoint()?;
+            releases += 1;
+        }
+
+        self.store(&url, &data)?;
+        Ok(releases)
+    }
+
+    /// Resolves a path of the registry to its URL.
+    fn resolve(&self, path: &str) -> String {
+        match path.contains("://") {
+            true => path.to_string(),
+            false => format!("{}/{}", self.registry, path),
+        }
+    }
+
+    /// Fetches a file from the origin.
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        Ok(self.fetcher.fetch(&FetchContext::new(url)).await?)
+    }
+
+    /// Stores a manifest in the mirror if it changed, and checkpoints it.
+    fn store(&mut self, url: &str, data: &[u8]) -> Result<()> {
+        let hash = checksum::digest(data);
+        if self.state.manifests.get(url) == Some(&hash) {
+            return Ok(());
+        }
+
+        self.write(url, data)?;
+        self.state.manifests.insert(url.to_string(), hash);
+        self.checkpoint()
+    }
+
+    /// Writes a file into the mirror, at the path of its URL.
+    fn write(&self, url: &str, data: &[u8]) -> Result<()> {
+        let Some(path) = local_path(&self.dir, url) else {
+            bail!("Cannot mirror {url}: the URL has no valid path");
+        };
+        let parent = path.parent().unwrap_or(&self.dir);
+        fs::create_dir_all(parent)?;
+
+        let temp = TempFile::new_in(parent)?;
+        fs::write(temp.path(), data)?;
+        temp.persist(&path)?;
+        Ok(())
+    }
+
+    /// Saves the sync state, so an interrupted watch resumes from here.
+    fn checkpoint(&self) -> Result<()> {
+        let temp = TempFile::new_in(&self.dir)?;
+        fs::write(temp.path(), serde_json::to_vec_pretty(&self.state)?)?;
+        temp.persist(&self.dir.join(STATE_FILE))?;
+        Ok(())
+    }
+}
+
+/// Returns the path a URL is mirrored at: its host and path, without the
+/// scheme, below `dir`. URLs escaping `dir` have none.
+fn local_path(dir: &Path, url: &str) -> Option<PathBuf> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = rest.split(['?', '#']).next()?;
+
+    let mut path = dir.to_path_buf();
+    for component in Path::new(rest).components() {
+        match component {
+            Component::Normal(name) => path.push(name),
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+    (path != dir).then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use hmt_manifest::{Artifact, ManifestFile, Package, Release};
+
+    use super::*;
+
+    fn url(path: &Path) -> String {
+        format!("file://{}", path.display())
+    }
+
+    /// Publishes a release of a package into a registry on disk.
+    fn publish(root: &Path, version: &str) {
+        let package_dir = root.join("packages").join("solidity-frontend");
+        let manifests_dir = package_dir.join("manifests");
+        fs::create_dir_all(&manifests_dir).unwrap();
+
+        let archive = package_dir.join(format!("solidity-frontend-{version}.tar.gz"));
+        fs::write(&archive, version).unwrap();
+        let artifact = Artifact {
+            url: url(&archive),
+            hash: checksum::digest(version.as_bytes()),
+            signature: None,
+            size: None,
+            provenance: None,
+        };
+        let mut release = ReleaseManifest::new(Release::new(version.to_string()), HashMap::new());
+        release.add_artifact("x86_64-unknown-linux-gnu".to_string(), artifact);
+        release.save(manifests_dir.join(format!("release-{version}.toml"))).unwrap();
+
+        let index_path = manifests_dir.join("index.toml");
+        let mut manifest = match index_path.exists() {
+            true => PackageManifest::load(&index_path).unwrap(),
+            false => {
+                let package = Package {
+                    name: "solidity-frontend".to_string(),
+                    homepage: url(&package_dir),
+                    kind: "frontend".to_string(),
+                    ..Default::default()
+                };
+                PackageManifest::new(package, version.to_string())
+            }
+        };
+        manifest.add_release(version.to_string(), format!("release-{version}.toml"));
+        manifest.latest = version.to_string();
+        manifest.save(&index_path).unwrap();
+
+        let mut domain = IndexManifest::new();
+        domain.insert("frontend".into(), "solidity-frontend".into(), url(&package_dir));
+        fs::create_dir_all(root.join("toolchains")).unwrap();
+        domain.save(root.join("toolchains/solidity.toml")).unwrap();
+
+        let mut index = IndexManifest::new();
+        index.insert("toolchains".into(), "solidity".into(), "toolchains/solidity.toml".into());
+        index.save(root.join("index.toml")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sync() {
+        let registry = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        publish(registry.path(), "v1.0.0");
+
+        let open =
+            || Mirror::open(Fetcher::default(), &url(registry.path()), dir.path(), true).unwrap();
+        assert_eq!(open().sync().await.unwrap(), 1);
+
+        let mirrored = |path: &Path| local_path(dir.path(), &url(path)).unwrap();
+        let package_dir = registry.path().join("packages/solidity-frontend");
+        assert!(mirrored(&registry.path().join("index.toml")).exists());
+        assert!(mirrored(&package_dir.join("manifests/release-v1.0.0.toml")).exists());
+        assert_eq!(
+            fs::read(mirrored(&package_dir.join("solidity-frontend-v1.0.0.tar.gz"))).unwrap(),
+            b"v1.0.0"
+        );
+
+        // A restarted watch resumes from the checkpoint
+        let mut mirror = open();
+        assert_eq!(mirror.sync().await.unwrap(), 0);
+        publish(registry.path(), "v1.1.0");
+        assert_eq!(mirror.sync().await.unwrap(), 1);
+        assert!(mirrored(&package_dir.join("solidity-frontend-v1.1.0.tar.gz")).exists());
+    }
+
+    #[test]
+    fn test_local_path() {
+        let dir = Path::new("/mirror");
+        assert_eq!(
+            local_path(dir, "https://example.com/registry/index.toml?x=1"),
+            Some(PathBuf::from("/mirror/example.com/registry/index.toml"))
+        );
+        assert_eq!(
+            local_path(dir, "file:///srv/registry/index.toml"),
+            Some(PathBuf::from("/mirror/srv/registry/index.toml"))
+        );
+        assert_eq!(local_path(dir, "https://example.com/../etc/passwd"), None);
+        assert_eq!(local_path(dir, "https://"), None);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let interval = Duration::from_secs(60);
+        assert_eq!(backoff(interval, 0), interval);
+        assert_eq!(backoff(interval, 1), Duration::from_secs(120));
+        assert_eq!(backoff(interval, 3), Duration::from_secs(480));
+        assert_eq!(backoff(interval, 100), MAX_BACKOFF);
+        assert_eq!(backoff(Duration::from_secs(7200), 2), Duration::from_secs(7200));
+    }
+}
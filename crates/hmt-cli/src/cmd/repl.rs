@@ -0,0 +1,96 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{env, sync::Arc};
+
+use anyhow::bail;
+use clap::Args;
+use tracing::debug;
+
+use hmt_registry::traits::Query;
+use hmt_utils::process::Process;
+
+use crate::{
+    context::Context,
+    deps,
+    errors::{CliError, Result},
+    manifest, utils,
+};
+
+/// Starts an interactive interpreter for the project language
+///
+/// The project context is passed to the interpreter through the environment:
+/// `HUMMANTA_PROJECT_DIR`, `HUMMANTA_SEARCH_PATH` (the project and its
+/// dependencies, joined like `PATH`) and, when a target is known,
+/// `HUMMANTA_TARGET_DIR`. The runtimes the interpreter uses are exposed as
+/// `HUMMANTA_RUNTIME_<NAME>`.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The target platform whose build outputs are exposed
+    #[arg(long)]
+    target: Option<String>,
+
+    /// Arguments passed to the interpreter
+    #[arg(last = true)]
+    args: Vec<String>,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let manifest_path = ctx.manifest_path()?;
+        let manifest = manifest::load(&ctx, manifest_path).await?;
+        let project_dir = ctx.project_dir()?;
+
+        // Acquires the toolchain manager.
+        let manager = ctx.toolchains().await?;
+        let manager = manager.read().await;
+
+        // Get the appropriate interpreter
+        let language = &manifest.project.language;
+        let packages = manager.get_package(language, "repl");
+        let package = packages.first().ok_or_else(|| CliError::UnsupportedLanguage {
+            role: "REPL",
+            language: language.to_string(),
+        })?;
+
+        // Expose the project and all of its dependencies
+        let sources = deps::fetch(&ctx, project_dir).await?;
+        let mut search_path = vec![project_dir.to_path_buf()];
+        search_path.extend(
+            deps::resolve(project_dir, &manifest, &sources, &ctx.downloads_dir())?
+                .into_iter()
+                .map(|d| d.dir),
+        );
+
+        let runtimes = manager.package_runtimes(language, package.entry.runtimes.keys());
+        let mut process = Process::new(&package.entry.path)
+            .args(&self.args)
+            .envs(utils::runtime_envs(&runtimes))
+            .current_dir(project_dir)
+            .env("HUMMANTA_PROJECT_DIR", project_dir)
+            .env("HUMMANTA_SEARCH_PATH", env::join_paths(&search_path)?);
+
+        if let Ok(target) = utils::resolve_target(&self.target, &manifest) {
+            process = process.env("HUMMANTA_TARGET_DIR", ctx.target_dir(&target)?);
+        }
+
+        debug!("Starting {}", package.entry.path.display());
+        let status = process.status().await?;
+        if !status.success() {
+            bail!("REPL exited with status {}", status);
+        }
+
+        Ok(())
+    }
+}
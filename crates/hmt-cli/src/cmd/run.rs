@@ -0,0 +1,108 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{anyhow, bail, Context as _};
+use clap::Args;
+use tracing::info;
+
+use hmt_manifest::{OutputManifest, ProjectManifest};
+use hmt_registry::traits::Query;
+use hmt_utils::process::Process;
+
+use crate::{context::Context, errors::Result, manifest, utils};
+
+/// Runs a binary built by `build`
+///
+/// The runner or binary runs with the variables of the `[env]` table.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The name of the binary to run
+    #[arg(long)]
+    bin: Option<String>,
+
+    /// The target platform the binary was built for
+    #[arg(long)]
+    target: Option<String>,
+
+    /// Arguments passed to the binary
+    #[arg(last = true)]
+    args: Vec<String>,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let manifest_path = ctx.manifest_path()?;
+        let manifest = manifest::load(&ctx, manifest_path).await?;
+
+        let target = utils::resolve_target(&self.target, &manifest)?;
+        let name = self.bin(&manifest)?;
+
+        // Locate the executable from the last build's outputs
+        let outputs_path = ctx.target_dir(&target)?.join("outputs.json");
+        let outputs = OutputManifest::load(&outputs_path)
+            .context("No build outputs found. Please run `hummanta build` first.")?;
+        let executable = outputs
+            .executable(name)
+            .ok_or_else(|| anyhow!("Binary '{}' has not been built for '{}'", name, target))?;
+
+        let process = process(&ctx, &target, &executable.path).await?;
+
+        info!("Running {}", executable.path.display());
+        let env = ctx.project_env(&manifest)?;
+        let status = process.args(&self.args).envs(env).status().await?;
+        if !status.success() {
+            bail!("Binary '{}' exited with status {}", name, status);
+        }
+
+        Ok(())
+    }
+
+    /// Select the binary: `--bin` if given, else the only declared one
+    fn bin<'a>(&'a self, manifest: &'a ProjectManifest) -> Result<&'a str> {
+        if let Some(name) = &self.bin {
+            if manifest.get_bin(name).is_none() {
+                bail!("No binary named '{}' in hummanta.toml", name);
+            }
+            return Ok(name);
+        }
+
+        match manifest.bins.as_slice() {
+            [] => bail!("No binaries declared. Add a [[bin]] section to hummanta.toml"),
+            [bin] => Ok(&bin.name),
+            _ => bail!("Multiple binaries declared, use --bin to select one"),
+        }
+    }
+}
+
+/// Prepares the process running an executable, through the target runner
+/// if one is installed, otherwise directly. The runner finds the runtimes
+/// it uses in the environment.
+pub(super) async fn process(ctx: &Context, target: &str, executable: &Path) -> Result<Process> {
+    let manager = ctx.targets().await?;
+    let manager = manager.read().await;
+
+    Ok(match manager.get_package(target, "runner").first() {
+        Some(runner) => {
+            let runtimes = manager.package_runtimes(target, runner.entry.runtimes.keys());
+            Process::new(&runner.entry.path)
+                .envs(utils::runtime_envs(&runtimes))
+                .arg("--input")
+                .arg(executable)
+                .arg("--")
+        }
+        None => Process::new(executable),
+    })
+}
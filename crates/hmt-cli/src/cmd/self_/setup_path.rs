@@ -0,0 +1,110 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{env, fs, path::Path, sync::Arc};
+
+use anyhow::{anyhow, bail, Context as _};
+use clap::Args;
+
+use crate::{
+    context::Context,
+    errors::Result,
+    shell::{self, Shell},
+};
+
+/// Adds `~/.hummanta/bin` to PATH in shell configuration files
+///
+/// The running `hummanta` binary is linked into the directory, so the
+/// command is found wherever it was installed. The configuration files of every shell that exist
+/// are updated, and the one of the current shell is created if missing. Running it again changes
+/// nothing, and `hummanta self uninstall` removes the lines again.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The shell to set up, detected from `$SHELL` by default
+    #[arg(long, value_enum)]
+    shell: Option<Shell>,
+
+    /// Print the command to add to a configuration file instead
+    #[arg(long)]
+    print: bool,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        if self.print {
+            let shell = self.shell.or_else(Shell::current).unwrap_or(Shell::Sh);
+            println!("{}", shell.export(&ctx.bin_dir()));
+            return Ok(());
+        }
+
+        setup(&ctx, self.shell)
+    }
+}
+
+/// Links the binary into the bin directory, and adds the directory to PATH
+/// in the configuration files of `shell`, or of the current shell, and of
+/// every other shell configured.
+pub fn setup(ctx: &Context, shell: Option<Shell>) -> Result<()> {
+    let bin_dir = ctx.bin_dir();
+    if cfg!(windows) {
+        bail!("Setting up PATH is not supported on Windows, add {} manually", bin_dir.display());
+    }
+
+    let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not determine home directory"))?;
+    let files = shell::config_files(&home, shell.or_else(Shell::current));
+    if files.is_empty() {
+        bail!("No shell configuration found, pass --shell or add the output of --print manually");
+    }
+
+    fs::create_dir_all(&bin_dir)?;
+    if link(&bin_dir)? {
+        ctx.reporter().info(format!("Linked hummanta into {}", bin_dir.display()));
+    }
+    for (shell, path) in files {
+        if shell::add(&path, shell, &bin_dir)? {
+            ctx.reporter().info(format!(
+                "Added {} to PATH in {}",
+                bin_dir.display(),
+                path.display()
+            ));
+        }
+    }
+
+    if !shell::on_path(&bin_dir) {
+        ctx.reporter().info("Restart your shell to pick up the new PATH".into());
+    }
+    Ok(())
+}
+
+/// Links the running binary into `bin_dir`, returning whether the link
+/// changed. A `hummanta` installed there by other means is left alone.
+fn link(bin_dir: &Path) -> Result<bool> {
+    let exe = env::current_exe()
+        .and_then(|exe| exe.canonicalize())
+        .context("Failed to locate the hummanta binary")?;
+    let shim = bin_dir.join(format!("hummanta{}", env::consts::EXE_SUFFIX));
+    if shim.canonicalize().is_ok_and(|target| target == exe) {
+        return Ok(false);
+    }
+
+    match shim.symlink_metadata() {
+        Ok(metadata) if metadata.file_type().is_symlink() => fs::remove_file(&shim)?,
+        Ok(_) => return Ok(false),
+        Err(_) => {}
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&exe, &shim)
+        .context(format!("Failed to link {}", shim.display()))?;
+    Ok(true)
+}
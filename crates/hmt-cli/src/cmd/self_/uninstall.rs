@@ -0,0 +1,60 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, sync::Arc};
+
+use anyhow::{anyhow, Context as _};
+use clap::Args;
+use tracing::info;
+
+use crate::{context::Context, errors::Result, shell, utils};
+
+/// Removes the Hummanta home directory and the PATH setup
+///
+/// Every installed package, cache and the configuration are deleted, and the
+/// lines added by `hummanta self setup-path` are removed from the shell
+/// configuration files. The `hummanta` binary itself is left to the tool that
+/// installed it.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let home_dir = ctx.home_dir();
+        let prompt = format!("Remove {} and all installed packages? [y/N]", home_dir.display());
+        if !self.yes && !utils::confirm(&prompt)? {
+            info!("Uninstall cancelled");
+            return Ok(());
+        }
+
+        let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not determine home directory"))?;
+        for (_, path) in shell::config_files(&home, None) {
+            if shell::remove(&path)? {
+                ctx.reporter().info(format!("Removed the PATH setup from {}", path.display()));
+            }
+        }
+
+        if home_dir.exists() {
+            fs::remove_dir_all(&home_dir)
+                .context(format!("Failed to remove {}", home_dir.display()))?;
+            ctx.reporter().info(format!("Removed {}", home_dir.display()));
+        }
+
+        Ok(())
+    }
+}
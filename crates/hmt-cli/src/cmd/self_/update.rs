@@ -0,0 +1,178 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    env, fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, Context as _};
+use clap::Args;
+use hmt_fetcher::FetchContext;
+use hmt_manifest::{Artifact, PackageManifest, ReleaseManifest};
+use hmt_utils::{archive, bytes::FromSlice, host, signature, temp::TempDir};
+use semver::Version;
+use tracing::info;
+
+use crate::{context::Context, errors::Result};
+
+/// Where the manifests of the releases of the CLI are published.
+const MANIFESTS_URL: &str = "https://hummanta.github.io/hummanta/manifests";
+
+/// The hex-encoded Ed25519 public key the releases of the CLI are signed
+/// with, set by the release workflow when building.
+const RELEASE_KEY: Option<&str> = option_env!("HUMMANTA_RELEASE_KEY");
+
+/// Updates the `hummanta` binary to the latest release
+///
+/// The CLI is published like a registry package, with its manifests under
+/// `https://hummanta.github.io/hummanta/manifests`. The build installed is
+/// picked by the capabilities of the host: Linux hosts without glibc, such
+/// as Alpine containers, get the fully static musl build, and other hosts
+/// prefer the variant of the running binary.
+///
+/// Only newer releases are installed unless `--version` asks for another,
+/// and only builds signed with the release key compiled into the running
+/// binary.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// Install this version instead of the latest, e.g. v0.12.0
+    #[arg(long)]
+    version: Option<String>,
+
+    /// Only report whether another release is available
+    #[arg(long)]
+    check: bool,
+
+    /// Where the release manifests are read from
+    #[arg(long, default_value = MANIFESTS_URL, env = "HUMMANTA_UPDATE_URL", hide = true)]
+    manifests_url: String,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        if ctx.offline() {
+            bail!("Cannot update hummanta with --offline");
+        }
+
+        let fetcher = ctx.fetcher()?;
+        let base = self.manifests_url.trim_end_matches('/');
+        let data =
+            fetcher.fetch(&FetchContext::new(&format!("{base}/index.toml")).metadata()).await?;
+        let package = PackageManifest::from_slice(&data)?;
+
+        let current = Version::parse(env!("CARGO_PKG_VERSION"))?;
+        let version = self.version.as_ref().unwrap_or(&package.latest);
+        let up_to_date = match (&self.version, parse(version)) {
+            (Some(_), Some(version)) => version == current,
+            (None, Some(latest)) => latest <= current,
+            (Some(_), None) => false,
+            (None, None) => bail!("hummanta has an invalid latest release {version}"),
+        };
+        if up_to_date {
+            info!("hummanta v{current} is up to date");
+            return Ok(());
+        }
+
+        let file = package
+            .releases
+            .get(version)
+            .ok_or_else(|| anyhow!("hummanta has no release {version}"))?;
+        let data = fetcher.fetch(&FetchContext::new(&format!("{base}/{file}")).metadata()).await?;
+        let release = ReleaseManifest::from_slice(&data)?;
+
+        let targets = host::targets();
+        let (target, artifact) = release.find_artifact(&targets).ok_or_else(|| {
+            anyhow!("hummanta {version} has no build for {}", targets.join(" or "))
+        })?;
+
+        if self.check {
+            info!("hummanta {version} is available for {target}, run `hummanta self update`");
+            return Ok(());
+        }
+
+        verify(artifact).context(format!("Refusing to install hummanta {version}"))?;
+        let context = FetchContext::new(&artifact.url).checksum(&artifact.hash);
+        let data = fetcher.fetch(&context).await?;
+        let exe = env::current_exe()
+            .and_then(|exe| exe.canonicalize())
+            .context("Failed to locate the hummanta binary")?;
+        replace(&exe, data).await?;
+
+        info!("Updated hummanta from v{current} to {version} ({target})");
+        Ok(())
+    }
+}
+
+/// Parses a version of the CLI, with or without its `v` prefix.
+fn parse(version: &str) -> Option<Version> {
+    Version::parse(version.trim_start_matches('v')).ok()
+}
+
+/// Verifies that the artifact was signed with the release key. The
+/// signature covers its hash, which the download is checked against.
+fn verify(artifact: &Artifact) -> Result<()> {
+    let Some(key) = RELEASE_KEY.filter(|key| !key.is_empty()) else {
+        bail!("This build of hummanta has no release key, reinstall it to update");
+    };
+    let signature = artifact.signature.as_deref().ok_or_else(|| anyhow!("It is not signed"))?;
+    signature::verify(key, artifact.hash.as_bytes(), signature)
+        .context("It is not signed with the hummanta release key")?;
+    Ok(())
+}
+
+/// Removes the binary a previous update on Windows moved aside, which could
+/// not be deleted while it was running.
+pub fn remove_stale() {
+    if cfg!(windows) {
+        if let Ok(exe) = env::current_exe() {
+            let _ = fs::remove_file(exe.with_extension("old"));
+        }
+    }
+}
+
+/// Replaces the binary at `exe` with the one in a release archive. The
+/// archive is unpacked next to it, so the replacement is a rename.
+async fn replace(exe: &Path, data: Vec<u8>) -> Result<()> {
+    let dir = exe.parent().ok_or_else(|| anyhow!("{} has no parent", exe.display()))?;
+    let binary = format!("hummanta{}", env::consts::EXE_SUFFIX);
+
+    let staging = TempDir::new_in(dir).context(format!("Failed to write to {}", dir.display()))?;
+    archive::unpack_paths_async(
+        Cursor::new(data),
+        staging.path().to_path_buf(),
+        vec![PathBuf::from(&binary)],
+    )
+    .await
+    .context("Failed to unpack the release")?;
+
+    // Windows cannot replace a running binary, but can move it aside. It is
+    // moved back if the new one cannot take its place, and removed by the
+    // next run otherwise.
+    let old = exe.with_extension("old");
+    if cfg!(windows) {
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).context(format!("Failed to move {} aside", exe.display()))?;
+    }
+    if let Err(e) = fs::rename(staging.path().join(&binary), exe) {
+        if cfg!(windows) {
+            let _ = fs::rename(&old, exe);
+        }
+        return Err(e).context(format!("Failed to replace {}", exe.display()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,526 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Context as _};
+use clap::Args;
+use hmt_error::ErrorReport;
+use hmt_utils::process::Process;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, UnboundedSender},
+};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use crate::{context::Context, errors::Result};
+
+/// The modification times of the files of a project.
+type Snapshot = BTreeMap<PathBuf, SystemTime>;
+
+/// The longest line an editor may send, in bytes.
+const MAX_LINE: u64 = 64 * 1024;
+
+/// The methods starting the request line of HTTP, which no editor sends.
+const HTTP_METHODS: [&str; 9] =
+    ["GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH"];
+
+/// Watches projects for editors, building them whenever their files change
+///
+/// Editors connect to `--listen` and exchange lines of JSON. The first
+/// line must be `{"request": "authenticate", "token": "<token>"}`, with the
+/// token the daemon wrote to `serve.token` in the Hummanta home directory
+/// for this run; it is readable by the current user only and removed when
+/// the daemon stops. Sessions sending anything else first, a line longer
+/// than 64 KiB, or anything that looks like HTTP are closed, so neither
+/// other users nor web pages can drive builds.
+///
+/// A request `{"request": "watch", "root": "<dir>"}` then registers the
+/// project in `<dir>` and `{"request": "unwatch", "root": "<dir>"}` removes
+/// it again; both are answered with `{"event": "watching"|"unwatched",
+/// "root": "<dir>"}`.
+///
+/// Every watched project is built once registered, and again whenever a
+/// file outside `target` and hidden directories changes. After each build,
+/// every editor watching the project receives `{"event": "diagnostics",
+/// "root": "<dir>", "success": <bool>, "diagnostics": [...]}`, each with a
+/// `severity`, the `code` of the error or the id of the warning, and a
+/// `message`. Editors registering a project that was already built receive
+/// its last diagnostics right away.
+///
+/// Builds run `hummanta build` in the project, exactly as on the command
+/// line. They share the fingerprints and outputs of the target directory
+/// with command line builds, and take turns with them through its build
+/// lock, so neither invalidates nor overwrites the work of the other.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The address editors connect to
+    #[arg(long, default_value = "127.0.0.1:7171")]
+    listen: SocketAddr,
+
+    /// Milliseconds between checks of the watched projects for changes
+    #[arg(long, default_value_t = 500)]
+    interval: u64,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let listener = TcpListener::bind(self.listen)
+            .await
+            .context(format!("Failed to bind {}", self.listen))?;
+        info!("Serving editors on {}", listener.local_addr()?);
+
+        let token_file = ctx.home_dir().join("serve.token");
+        let token = token();
+        write_token(&token_file, &token)
+            .context(format!("Failed to write {}", token_file.display()))?;
+
+        let daemon = Arc::new(Daemon {
+            exe: std::env::current_exe().context("Failed to locate the hummanta binary")?,
+            args: ctx.forwarded_args()?,
+            token,
+            projects: Mutex::default(),
+        });
+        tokio::spawn(daemon.clone().poll(Duration::from_millis(self.interval)));
+
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, peer) = accepted?;
+                    let daemon = daemon.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = daemon.session(stream).await {
+                            warn!("Session with {peer} failed: {e}");
+                        }
+                    });
+                }
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+
+        let _ = fs::remove_file(&token_file);
+        Ok(())
+    }
+}
+
+/// A request of an editor.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "request", rename_all = "snake_case")]
+enum Request {
+    Authenticate { token: String },
+    Watch { root: PathBuf },
+    Unwatch { root: PathBuf },
+}
+
+/// A message pushed to editors.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Push {
+    Watching { root: PathBuf },
+    Unwatched { root: PathBuf },
+    Diagnostics { root: PathBuf, success: bool, diagnostics: Vec<Diagnostic> },
+    Error { message: String },
+}
+
+impl Push {
+    /// Encodes the message as a line of JSON.
+    fn line(&self) -> String {
+        let mut line = serde_json::to_string(self).unwrap_or_default();
+        line.push('\n');
+        line
+    }
+}
+
+/// How severe a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem reported by a build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Diagnostic {
+    severity: Severity,
+    /// The code of the error, or the id of the warning.
+    code: String,
+    message: String,
+}
+
+/// A watched project.
+#[derive(Default)]
+struct Project {
+    files: Snapshot,
+    /// The sessions receiving the diagnostics of the project.
+    editors: Vec<UnboundedSender<String>>,
+    /// The diagnostics of the last build, as pushed.
+    last: Option<String>,
+    building: bool,
+    /// Whether files changed since the last build started.
+    dirty: bool,
+}
+
+/// The projects watched for all sessions.
+struct Daemon {
+    exe: PathBuf,
+    /// The global options passed on to builds, so they share the state of
+    /// builds run from the command line.
+    args: Vec<OsString>,
+    /// The secret editors authenticate with.
+    token: String,
+    projects: Mutex<HashMap<PathBuf, Project>>,
+}
+
+impl Daemon {
+    fn projects(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Project>> {
+        self.projects.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Answers the requests of an editor until it disconnects.
+    async fn session(&self, stream: TcpStream) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        tokio::spawn(async move {
+            while let Some(line) = rx.recv().await {
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut reader = BufReader::new(reader);
+        let result = async {
+            match read_line(&mut reader).await? {
+                Some(line) if self.authenticates(&line) => {}
+                _ => bail!("Session did not authenticate"),
+            }
+
+            while let Some(line) = read_line(&mut reader).await? {
+                let push = match serde_json::from_str::<Request>(&line) {
+                    Ok(Request::Authenticate { .. }) => continue,
+                    Ok(Request::Watch { root }) => match self.watch(&root, &tx) {
+                        Ok(root) => Push::Watching { root },
+                        Err(e) => Push::Error { message: format!("{e:#}") },
+                    },
+                    Ok(Request::Unwatch { root }) => {
+                        let root = root.canonicalize().unwrap_or(root);
+                        self.unwatch(&tx, Some(&root));
+                        Push::Unwatched { root }
+                    }
+                    Err(e) => Push::Error { message: format!("Invalid request: {e}") },
+                };
+                let _ = tx.send(push.line());
+            }
+            Ok(())
+        }
+        .await;
+
+        self.unwatch(&tx, None);
+        result
+    }
+
+    /// Whether the line is an authentication request with the token.
+    fn authenticates(&self, line: &str) -> bool {
+        matches!(
+            serde_json::from_str::<Request>(line),
+            Ok(Request::Authenticate { token }) if token == self.token
+        )
+    }
+
+    /// Registers an editor for a project, returning its canonical root.
+    /// Its last diagnostics are pushed right away.
+    fn watch(&self, root: &Path, tx: &UnboundedSender<String>) -> Result<PathBuf> {
+        let root = root.canonicalize().context(format!("Failed to watch {}", root.display()))?;
+        if !root.join("hummanta.toml").is_file() {
+            bail!("No hummanta.toml in {}", root.display());
+        }
+
+        let mut projects = self.projects();
+        let project = projects.entry(root.clone()).or_insert_with(|| {
+            info!("Watching {}", root.display());
+            Project { files: snapshot(&root), dirty: true, ..Default::default() }
+        });
+        if !project.editors.iter().any(|editor| editor.same_channel(tx)) {
+            project.editors.push(tx.clone());
+        }
+        if let Some(last) = &project.last {
+            let _ = tx.send(last.clone());
+        }
+
+        Ok(root)
+    }
+
+    /// Unregisters an editor from a project, or from all projects, and
+    /// stops watching projects no editor is left for.
+    fn unwatch(&self, tx: &UnboundedSender<String>, root: Option<&Path>) {
+        self.projects().retain(|dir, project| {
+            if root.is_none_or(|root| root == dir) {
+                project.editors.retain(|editor| !editor.same_channel(tx));
+            }
+            let watched = !project.editors.is_empty();
+            if !watched {
+                info!("Stopped watching {}", dir.display());
+            }
+            watched
+        });
+    }
+
+    /// Checks the watched projects for changes every `interval`, and
+    /// builds those that changed.
+    async fn poll(self: Arc<Self>, interval: Duration) {
+        loop {
+            let roots: Vec<PathBuf> = self.projects().keys().cloned().collect();
+            for root in roots {
+                let dir = root.clone();
+                let Ok(files) = tokio::task::spawn_blocking(move || snapshot(&dir)).await else {
+                    continue;
+                };
+
+                let mut projects = self.projects();
+                let Some(project) = projects.get_mut(&root) else { continue };
+                if project.files != files {
+                    project.files = files;
+                    project.dirty = true;
+                }
+                if project.dirty && !project.building {
+                    project.dirty = false;
+                    project.building = true;
+                    tokio::spawn(self.clone().build(root));
+                }
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Builds a project and pushes the diagnostics to its editors.
+    async fn build(self: Arc<Self>, root: PathBuf) {
+        info!("Building {}", root.display());
+        let output = Process::new(&self.exe)
+            .args(["build", "--progress", "json", "--error-format", "json"])
+            .args(&self.args)
+            .current_dir(&root)
+            .output()
+            .await;
+
+        let push = match output {
+            Ok(output) => Push::Diagnostics {
+                root: root.clone(),
+                success: output.status.success(),
+                diagnostics: diagnostics(&String::from_utf8_lossy(&output.stderr)),
+            },
+            Err(e) => Push::Error { message: format!("Failed to build {}: {e}", root.display()) },
+        };
+
+        let line = push.line();
+        if let Some(project) = self.projects().get_mut(&root) {
+            project.building = false;
+            project.editors.retain(|editor| editor.send(line.clone()).is_ok());
+            project.last = Some(line);
+        }
+    }
+}
+
+/// Reads a line sent by an editor, without its line break. Fails on lines
+/// longer than [`MAX_LINE`] and on lines that look like HTTP.
+async fn read_line(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<Option<String>> {
+    let mut line = Vec::new();
+    let read = reader.take(MAX_LINE + 1).read_until(b'\n', &mut line).await?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    } else if read as u64 > MAX_LINE {
+        bail!("Request longer than {MAX_LINE} bytes");
+    }
+
+    let line =
+        String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if is_http(&line) {
+        bail!("Refusing an HTTP request");
+    }
+    Ok(Some(line))
+}
+
+/// Whether a line is an HTTP request line or header, as sent by browsers
+/// and other clients that must not reach the daemon.
+fn is_http(line: &str) -> bool {
+    let line = line.trim_end_matches('\r');
+    let request = line
+        .split_once(' ')
+        .is_some_and(|(method, rest)| HTTP_METHODS.contains(&method) && rest.contains("HTTP/"));
+    let header = line.split_once(':').is_some_and(|(name, _)| {
+        ["host", "origin"].contains(&name.trim().to_ascii_lowercase().as_str())
+    });
+    request || header || line.starts_with("HTTP/")
+}
+
+/// Generates a secret token for editors to authenticate with.
+fn token() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).expect("the system random number generator is available");
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Writes the token to a file only the current user can read.
+fn write_token(path: &Path, token: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let _ = fs::remove_file(path);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    io::Write::write_all(&mut options.open(path)?, token.as_bytes())
+}
+
+/// Collects the warnings and the error of a build from the lines of JSON
+/// it wrote to stderr.
+fn diagnostics(stderr: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for line in stderr.lines() {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else { continue };
+        if value.get("event").is_some() {
+            if value["event"] == "warning" {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    code: value["id"].as_str().unwrap_or_default().to_string(),
+                    message: value["message"].as_str().unwrap_or_default().to_string(),
+                });
+            }
+        } else if let Ok(report) = serde_json::from_value::<ErrorReport>(value) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                code: report.code,
+                message: report.message,
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Takes the modification times of the files of a project, leaving out the
+/// build outputs and hidden directories such as `.git`.
+fn snapshot(root: &Path) -> Snapshot {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0 ||
+                !(entry.file_type().is_dir() &&
+                    (entry.file_name() == "target" ||
+                        entry.file_name().to_string_lossy().starts_with('.')))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((entry.into_path(), modified))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_request() {
+        let request = serde_json::from_str::<Request>(r#"{"request":"watch","root":"/p"}"#);
+        assert_eq!(request.unwrap(), Request::Watch { root: PathBuf::from("/p") });
+        assert!(serde_json::from_str::<Request>(r#"{"request":"build"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_line() {
+        let mut reader = BufReader::new(&b"{\"request\":\"watch\"}\n"[..]);
+        assert_eq!(read_line(&mut reader).await.unwrap().unwrap(), r#"{"request":"watch"}"#);
+        assert!(read_line(&mut reader).await.unwrap().is_none());
+
+        let long = vec![b'a'; MAX_LINE as usize + 1];
+        assert!(read_line(&mut BufReader::new(long.as_slice())).await.is_err());
+
+        let http = b"GET /watch HTTP/1.1\r\nHost: localhost\r\n";
+        assert!(read_line(&mut BufReader::new(&http[..])).await.is_err());
+    }
+
+    #[test]
+    fn test_is_http() {
+        assert!(is_http("POST / HTTP/1.1\r"));
+        assert!(is_http("Origin: https://example.com"));
+        assert!(is_http("host: 127.0.0.1:7171"));
+        assert!(!is_http(r#"{"request":"watch","root":"/p"}"#));
+        assert!(!is_http(r#"{"request":"authenticate","token":"GET HTTP/"}"#));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let stderr = concat!(
+            r#"{"event":"info","message":"Locking solidity 0.8.0"}"#,
+            "\n",
+            r#"{"event":"warning","id":"stale-metadata","message":"Using expired metadata"}"#,
+            "\n",
+            "not json\n",
+            r#"{"code":"cli.target-not-specified","category":"usage","message":"No target","causes":["missing"]}"#,
+            "\n",
+        );
+
+        assert_eq!(
+            diagnostics(stderr),
+            [
+                Diagnostic {
+                    severity: Severity::Warning,
+                    code: "stale-metadata".into(),
+                    message: "Using expired metadata".into(),
+                },
+                Diagnostic {
+                    severity: Severity::Error,
+                    code: "cli.target-not-specified".into(),
+                    message: "No target".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        for path in ["hummanta.toml", "src/main.sol", "target/evm/outputs.json", ".git/HEAD"] {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), "").unwrap();
+        }
+
+        let files: Vec<PathBuf> = snapshot(root).into_keys().collect();
+        assert_eq!(files, [root.join("hummanta.toml"), root.join("src/main.sol")]);
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::Args;
+use hmt_utils::event::warning;
+use tracing::info;
+
+use crate::{context::Context, errors::Result};
+
+/// Installs the backend, linker, sysroot and runner of a target platform.
+///
+/// The packages are resolved from the bundle the registry publishes for
+/// the target, and every role the registry lacks a package for is reported.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The target triple, e.g. `x86_64-unknown-linux-gnu`.
+    triple: String,
+
+    /// Print the installed and missing packages as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        // Acquires the target manager.
+        let manager = ctx.targets().await?;
+        let mut manager = manager.write().await;
+
+        let report = manager.add_bundle(&self.triple).await?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+
+        for package in &report.installed {
+            info!("Installed {} {} as {}", package.name, package.version, package.role);
+        }
+        for missing in &report.missing {
+            ctx.reporter().warn(
+                warning::INCOMPLETE_TARGET,
+                format!("No {} installed for {}: {}", missing.role, self.triple, missing.reason),
+            );
+        }
+        info!("Successfully installed {} target", self.triple);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, sync::Arc};
+
+use anyhow::{anyhow, bail, Context as _};
+use clap::Args;
+use tracing::info;
+
+use hmt_manifest::{Binary, OutputManifest};
+
+use super::{build, run};
+use crate::{
+    context::Context,
+    coverage::{Coverage, COVERAGE_FILE_VAR, RAW_EXTENSION, REPORT_FILE},
+    errors::Result,
+    fingerprint::OUTPUTS_FILE,
+    manifest,
+};
+
+/// Builds the project and runs its tests
+///
+/// Every `[[bin]]` with `test = true` is a test. Tests run through the target
+/// runner if one is installed, with the variables of the `[env]` table, and
+/// fail when they exit with a non-zero status.
+///
+/// With `--coverage` the backend instruments the emitted code, and every test
+/// writes its raw coverage as an LCOV tracefile to the path in
+/// `HUMMANTA_COVERAGE_FILE`, below `target/coverage`. The raw files are merged
+/// into `target/coverage/lcov.info`.
+#[derive(Args, Debug)]
+#[group(skip)]
+pub struct Command {
+    /// Only run the tests with these names
+    #[arg(long = "test", value_name = "NAME")]
+    tests: Vec<String>,
+
+    #[command(flatten)]
+    build: build::Command,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        self.build.exec(ctx.clone()).await?;
+
+        let manifest = manifest::load(&ctx, ctx.manifest_path()?).await?;
+        let target = self.build.target(&manifest)?;
+        let tests = self.tests(&manifest.bins)?;
+        if tests.is_empty() {
+            info!("No tests declared. Add `test = true` to a [[bin]] section of hummanta.toml");
+            return Ok(());
+        }
+
+        let outputs = OutputManifest::load(ctx.target_dir(target)?.join(OUTPUTS_FILE))
+            .context("Failed to read build outputs")?;
+
+        let coverage_dir = ctx.project_dir()?.join("target").join("coverage");
+        if self.build.coverage {
+            if coverage_dir.exists() {
+                fs::remove_dir_all(&coverage_dir).context("Failed to clear coverage directory")?;
+            }
+            fs::create_dir_all(&coverage_dir).context("Failed to create coverage directory")?;
+        }
+
+        let env = ctx.project_env(&manifest)?;
+        let mut failed = Vec::new();
+        for test in &tests {
+            let executable = outputs.executable(&test.name).ok_or_else(|| {
+                anyhow!("Test '{}' has not been built for '{}'", test.name, target)
+            })?;
+
+            let mut process = run::process(&ctx, target, &executable.path).await?.envs(env.clone());
+            if self.build.coverage {
+                let raw = coverage_dir.join(format!("{}.{RAW_EXTENSION}", test.name));
+                process = process.env(COVERAGE_FILE_VAR, raw);
+            }
+
+            info!("Running test {}", test.name);
+            if !process.status().await?.success() {
+                failed.push(test.name.as_str());
+            }
+        }
+
+        if self.build.coverage {
+            let coverage = Coverage::merge(&coverage_dir)?;
+            let report = coverage_dir.join(REPORT_FILE);
+            fs::write(&report, coverage.to_lcov()).context("Failed to write coverage report")?;
+
+            let (hit, total) = coverage.summary();
+            let percent = if total == 0 { 0.0 } else { hit as f64 * 100.0 / total as f64 };
+            ctx.reporter().info(format!(
+                "Line coverage {percent:.1}% ({hit}/{total}), written to {}",
+                report.display()
+            ));
+        }
+
+        if !failed.is_empty() {
+            bail!("{} of {} tests failed: {}", failed.len(), tests.len(), failed.join(", "));
+        }
+
+        ctx.reporter().info(format!("{} tests passed", tests.len()));
+        Ok(())
+    }
+
+    /// Selects the tests to run: those given with `--test`, else all of them
+    fn tests<'a>(&self, bins: &'a [Binary]) -> Result<Vec<&'a Binary>> {
+        let tests: Vec<&Binary> = bins.iter().filter(|bin| bin.test).collect();
+        if self.tests.is_empty() {
+            return Ok(tests);
+        }
+
+        self.tests
+            .iter()
+            .map(|name| {
+                tests
+                    .iter()
+                    .find(|test| test.name == *name)
+                    .copied()
+                    .ok_or_else(|| anyhow!("No test named '{}' in hummanta.toml", name))
+            })
+            .collect()
+    }
+}
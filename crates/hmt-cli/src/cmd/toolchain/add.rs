@@ -12,19 +12,56 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
+use anyhow::{bail, Context as _};
 use clap::Args;
-use hmt_registry::traits::PackageManager;
+use hmt_manifest::{LockManifest, ManifestFile};
 use tracing::info;
 
-use crate::{context::Context, errors::Result};
+use crate::{context::Context, deps::LOCKFILE, errors::Result};
 
 /// Installs the specified language's toolchain.
+///
+/// With `--path`, a single package is installed from a local archive named
+/// `<name>-v<version>.tar.gz` instead, without accessing the registry.
+///
+/// With `--from-lock`, the exact toolchain packages pinned in the project's
+/// `hummanta.lock` are installed, and the install fails if the registry no
+/// longer serves them.
+///
+/// With `--components`, packages whose releases declare components install
+/// only the named ones, e.g. `--components core` to skip documentation.
 #[derive(Args, Debug)]
 pub struct Command {
     /// The language to install the toolchain for.
-    language: String,
+    #[arg(required_unless_present_any = ["path", "from_lock"])]
+    language: Option<String>,
+
+    /// Install the toolchain packages pinned in `hummanta.lock`.
+    #[arg(long, conflicts_with_all = ["language", "path"])]
+    from_lock: bool,
+
+    /// Install a package from a local archive.
+    #[arg(long, conflicts_with = "language", requires_all = ["domain", "category"])]
+    path: Option<PathBuf>,
+
+    /// The language the local package is installed for.
+    #[arg(long, requires = "path")]
+    domain: Option<String>,
+
+    /// The category of the local package, e.g. `frontend`.
+    #[arg(long, requires = "path")]
+    category: Option<String>,
+
+    /// Install the local package even though the policy requires signatures
+    /// or provenance, which local archives cannot have.
+    #[arg(long, requires = "path")]
+    allow_unverified: bool,
+
+    /// The release components to install, separated by commas.
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["path", "from_lock"])]
+    components: Vec<String>,
 }
 
 impl Command {
@@ -33,8 +70,39 @@ impl Command {
         let manager = ctx.toolchains().await?;
         let mut manager = manager.write().await;
 
-        manager.add(&self.language).await?;
-        info!("Successfully installed {} toolchains", self.language);
+        if let (Some(path), Some(domain), Some(category)) =
+            (&self.path, &self.domain, &self.category)
+        {
+            let name = manager.install_local(domain, category, path, self.allow_unverified).await?;
+            info!("Successfully installed {} from {}", name, path.display());
+            return Ok(());
+        }
+
+        if self.from_lock {
+            let lock_path = ctx.project_dir()?.join(LOCKFILE);
+            let lock = LockManifest::load(&lock_path).context(
+                "No hummanta.lock found. Please run `hummanta build --lock-tools` first.",
+            )?;
+
+            let packages: Vec<_> =
+                lock.tools.iter().filter(|package| package.kind == manager.kind()).collect();
+            if packages.is_empty() {
+                bail!("hummanta.lock pins no toolchain packages");
+            }
+            let mut progress = ctx.progress("Installing", packages.len());
+            for package in packages {
+                if manager.install_locked_tool(package).await? {
+                    info!("Installed {} {}", package.name, package.version);
+                }
+                progress.inc(&package.name);
+            }
+            info!("Successfully installed the locked toolchains");
+            return Ok(());
+        }
+
+        let language = self.language.as_deref().unwrap_or_default();
+        manager.add_components(language, &self.components).await?;
+        info!("Successfully installed {} toolchains", language);
 
         Ok(())
     }
@@ -0,0 +1,80 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, path::PathBuf, sync::Arc};
+
+use anyhow::{bail, Context as _};
+use clap::Args;
+use tracing::info;
+
+use hmt_manifest::{FreezeManifest, InstalledManifest, ManifestFile};
+use hmt_registry::{
+    manager::Manager,
+    traits::{PackageKind, PackageManager},
+};
+use hmt_utils::signature::{SigningKey, SIGNATURE_FILE_SUFFIX};
+
+use crate::{context::Context, errors::Result};
+
+/// Writes every installed package to a manifest for `env import`
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The file to write the manifest to
+    #[arg(long, short, default_value = "hummanta-freeze.toml")]
+    output: PathBuf,
+
+    /// Path to the hex-encoded Ed25519 key signing the manifest
+    #[arg(long, env = "HUMMANTA_SIGNING_KEY")]
+    key: Option<PathBuf>,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let key = self.key.as_deref().map(SigningKey::load).transpose()?;
+
+        let mut installed = InstalledManifest::new();
+        collect(&mut installed, &*ctx.toolchains().await?.read().await);
+        collect(&mut installed, &*ctx.targets().await?.read().await);
+
+        let (manifest, missing) = FreezeManifest::from_installed(&installed);
+        if !missing.is_empty() {
+            bail!(
+                "Cannot freeze packages installed without artifact records, reinstall them: {}",
+                missing.join(", ")
+            );
+        }
+
+        manifest.save(&self.output).context("Failed to write freeze manifest")?;
+        if let Some(key) = key {
+            let signature = key.sign(&fs::read(&self.output)?);
+            let mut path = self.output.clone().into_os_string();
+            path.push(format!(".{SIGNATURE_FILE_SUFFIX}"));
+            fs::write(path, signature).context("Failed to write freeze manifest signature")?;
+        }
+
+        info!("Froze {} packages into {}", manifest.packages.len(), self.output.display());
+        Ok(())
+    }
+}
+
+/// Adds the packages installed by a manager.
+fn collect<T: PackageKind>(installed: &mut InstalledManifest, manager: &Manager<T>) {
+    for (domain, categories) in manager.list().into_iter().flatten() {
+        for (category, packages) in categories {
+            for (name, entry) in packages {
+                installed.insert(manager.kind(), domain, category, name, entry.clone());
+            }
+        }
+    }
+}
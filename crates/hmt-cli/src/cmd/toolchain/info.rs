@@ -0,0 +1,61 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::Args;
+use hmt_registry::traits::RemoteMetadata;
+use hmt_utils::event::warning;
+
+use crate::{context::Context, errors::Result};
+
+/// Displays registry information about a language's toolchain packages
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The language to show the toolchain information for.
+    language: String,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        // Acquires the toolchain manager.
+        let manager = ctx.toolchains().await?;
+        let manager = manager.read().await;
+
+        let index = manager.fetch_index(&self.language).await?;
+
+        println!("{}", self.language);
+        for (category, name) in index.entries() {
+            let Ok(package) = manager.fetch_package(&index, category, name).await else {
+                ctx.reporter()
+                    .warn(warning::FETCH_FAILED, format!("{name} failed to fetch, skipping"));
+                continue;
+            };
+
+            println!("  {name} {} ({category})", package.latest);
+            if let Some(desc) = &package.package.description {
+                println!("  {desc}");
+            }
+            for maintainer in &package.package.maintainers {
+                match &maintainer.contact {
+                    Some(contact) => println!("  maintainer: {} <{contact}>", maintainer.name),
+                    None => println!("  maintainer: {}", maintainer.name),
+                }
+                println!("    key: {}", maintainer.fingerprint);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{path::PathBuf, sync::Arc};
+
+use clap::Args;
+use tracing::info;
+
+use crate::{context::Context, errors::Result};
+
+/// Links a toolchain package to a locally built binary.
+///
+/// The binary is used in place rather than copied, so `hummanta build`
+/// picks up every rebuild. Linked packages are skipped by `update` and
+/// shown as linked by `list`.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The language the package belongs to.
+    domain: String,
+
+    /// The category of the package, e.g. `frontend`.
+    category: String,
+
+    /// The name of the package.
+    name: String,
+
+    /// The binary to link, e.g. `target/debug/solidity-frontend`.
+    #[arg(long)]
+    path: PathBuf,
+
+    /// Link the binary even though the policy requires signatures or
+    /// provenance, which local builds cannot have.
+    #[arg(long)]
+    allow_unverified: bool,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        // Acquires the toolchain manager.
+        let manager = ctx.toolchains().await?;
+        let mut manager = manager.write().await;
+
+        manager.link(
+            &self.domain,
+            &self.category,
+            &self.name,
+            &self.path,
+            self.allow_unverified,
+        )?;
+        info!("Linked {} to {}", self.name, self.path.display());
+
+        Ok(())
+    }
+}
@@ -13,9 +13,15 @@
 // limitations under the License.
 
 mod add;
+mod freeze;
+mod info;
+mod link_dev;
 mod list;
 mod remove;
 mod show;
+mod update;
+mod upgrade_impact;
+mod which;
 
 use std::sync::Arc;
 
@@ -34,7 +40,13 @@ enum Commands {
     Add(add::Command),
     Remove(remove::Command),
     Show(show::Command),
+    Update(update::Command),
+    Info(info::Command),
+    Freeze(freeze::Command),
     List(list::Command),
+    LinkDev(link_dev::Command),
+    Which(which::Command),
+    UpgradeImpact(upgrade_impact::Command),
 }
 
 impl Command {
@@ -43,7 +55,13 @@ impl Command {
             Commands::Add(cmd) => cmd.exec(ctx).await,
             Commands::Remove(cmd) => cmd.exec(ctx).await,
             Commands::Show(cmd) => cmd.exec(ctx).await,
+            Commands::Update(cmd) => cmd.exec(ctx).await,
+            Commands::Info(cmd) => cmd.exec(ctx).await,
+            Commands::Freeze(cmd) => cmd.exec(ctx).await,
             Commands::List(cmd) => cmd.exec(ctx).await,
+            Commands::LinkDev(cmd) => cmd.exec(ctx).await,
+            Commands::Which(cmd) => cmd.exec(ctx).await,
+            Commands::UpgradeImpact(cmd) => cmd.exec(ctx).await,
         }
     }
 }
@@ -0,0 +1,203 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use clap::{Args, ValueEnum};
+use hmt_registry::manager::Update;
+use tracing::info;
+
+use crate::{context::Context, errors::Result};
+
+const GREEN: &str = "32";
+const RED: &str = "31";
+const YELLOW: &str = "33";
+
+/// Updates the specified language's toolchain to the latest releases
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The language to update the toolchain for.
+    language: String,
+
+    /// Only show what would change, without installing anything.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// How the changes are reported.
+    #[arg(long, value_enum, default_value_t)]
+    output: OutputFormat,
+}
+
+/// The formats changes can be reported in.
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum OutputFormat {
+    /// An aligned table, colorized on interactive terminals
+    #[default]
+    Table,
+    /// JSON, for automation
+    Json,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        // Acquires the toolchain manager.
+        let manager = ctx.toolchains().await?;
+        let mut manager = manager.write().await;
+
+        let updates = if self.dry_run {
+            manager.updates(&self.language).await?
+        } else {
+            manager.update(&self.language).await?
+        };
+
+        match self.output {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&updates)?),
+            OutputFormat::Table if updates.is_empty() => {
+                info!("{} toolchain is up to date", self.language)
+            }
+            OutputFormat::Table => print!("{}", table(&updates, ctx.color())),
+        }
+
+        if !self.dry_run && !updates.is_empty() {
+            info!("Successfully updated {} toolchain", self.language);
+        }
+
+        Ok(())
+    }
+}
+
+/// A table cell, with the color it is shown in.
+type Cell = (String, Option<&'static str>);
+
+/// Renders one row per update, with columns aligned on the plain text.
+fn table(updates: &[Update], color: bool) -> String {
+    let header = ["PACKAGE", "CATEGORY", "VERSION", "SIZE", "TARGETS"];
+    let mut rows: Vec<Vec<Cell>> = vec![header.iter().map(|h| (h.to_string(), None)).collect()];
+    rows.extend(updates.iter().map(row));
+
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| rows.iter().map(|row| row[i].0.chars().count()).max().unwrap_or(0))
+        .collect();
+
+    let mut out = String::new();
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|((text, code), width)| {
+                let padded = format!("{text:<width$}");
+                match code {
+                    Some(code) if color => format!("\x1b[{code}m{padded}\x1b[0m"),
+                    _ => padded,
+                }
+            })
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Describes a single update as table cells.
+fn row(update: &Update) -> Vec<Cell> {
+    let diff = &update.diff;
+    let version = format!("{} → {}", diff.from.as_deref().unwrap_or("(new)"), diff.to);
+
+    let size = match diff.size_delta {
+        Some(delta) if delta > 0 => (format_size(delta), Some(YELLOW)),
+        Some(delta) if delta < 0 => (format_size(delta), Some(GREEN)),
+        Some(delta) => (format_size(delta), None),
+        None => ("?".to_string(), None),
+    };
+
+    let targets: Vec<String> = diff
+        .added_targets
+        .iter()
+        .map(|t| format!("+{t}"))
+        .chain(diff.removed_targets.iter().map(|t| format!("-{t}")))
+        .collect();
+    let targets = match (diff.added_targets.is_empty(), diff.removed_targets.is_empty()) {
+        (true, true) => ("-".to_string(), None),
+        (_, false) => (targets.join(", "), Some(RED)),
+        (false, true) => (targets.join(", "), Some(GREEN)),
+    };
+
+    vec![
+        (update.name.clone(), None),
+        (update.category.clone(), None),
+        (version, Some(GREEN)),
+        size,
+        targets,
+    ]
+}
+
+/// Formats a signed byte count with a binary unit, e.g. `+1.5 MiB`.
+fn format_size(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    let bytes = delta.unsigned_abs() as f64;
+    match bytes {
+        b if b >= 1024.0 * 1024.0 => format!("{sign}{:.1} MiB", b / (1024.0 * 1024.0)),
+        b if b >= 1024.0 => format!("{sign}{:.1} KiB", b / 1024.0),
+        b => format!("{sign}{b} B"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmt_manifest::{Entry, ReleaseDiff};
+
+    fn update(name: &str, from: Option<&str>, size_delta: Option<i64>) -> Update {
+        Update {
+            category: "frontend".to_string(),
+            name: name.to_string(),
+            diff: ReleaseDiff {
+                from: from.map(str::to_string),
+                to: "v1.1.0".to_string(),
+                size_delta,
+                added_targets: vec!["aarch64-apple-darwin".to_string()],
+                removed_targets: Vec::new(),
+            },
+            entry: Entry::new("v1.1.0".to_string(), None, Default::default()),
+        }
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "+0 B");
+        assert_eq!(format_size(-512), "-512 B");
+        assert_eq!(format_size(1536), "+1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "+3.0 MiB");
+    }
+
+    #[test]
+    fn test_table() {
+        let updates =
+            [update("solidity-frontend", Some("v1.0.0"), Some(2048)), update("solc", None, None)];
+
+        let rendered = table(&updates, false);
+        let lines: Vec<_> = rendered.lines().collect();
+        assert_eq!(lines[0], "PACKAGE            CATEGORY  VERSION          SIZE      TARGETS");
+        assert_eq!(
+            lines[1],
+            "solidity-frontend  frontend  v1.0.0 → v1.1.0  +2.0 KiB  +aarch64-apple-darwin"
+        );
+        assert_eq!(
+            lines[2],
+            "solc               frontend  (new) → v1.1.0   ?         +aarch64-apple-darwin"
+        );
+        assert!(!rendered.contains('\x1b'));
+        assert!(table(&updates, true).contains("\x1b[32m"));
+    }
+}
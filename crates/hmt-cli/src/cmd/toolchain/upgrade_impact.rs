@@ -0,0 +1,266 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::anyhow;
+use clap::Args;
+use hmt_manifest::{LockManifest, ManifestFile};
+use hmt_registry::{manager::Toolchain, traits::PackageKind};
+use hmt_utils::event::warning;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::{context::Context, deps::LOCKFILE, errors::Result, manifest};
+
+/// How deep below a root projects are searched for.
+const MAX_DEPTH: usize = 6;
+
+/// Directories never holding projects of their own.
+const SKIPPED_DIRS: [&str; 3] = ["target", "vendor", "node_modules"];
+
+/// Reports which projects would need relocking if a language's toolchain
+/// were upgraded to a version.
+///
+/// Projects are searched for below the `[projects] roots` of the config, or
+/// the given `--root` directories. A project is affected when its
+/// `hummanta.lock` pins a toolchain package of the language at another
+/// version, or when it uses the language without a lockfile yet.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The toolchain and its proposed version, as `<language>@<version>`.
+    spec: String,
+
+    /// Only consider the toolchain package with this name.
+    #[arg(long)]
+    package: Option<String>,
+
+    /// A directory to search for projects, instead of the configured roots.
+    #[arg(long = "root", value_name = "DIR")]
+    roots: Vec<PathBuf>,
+
+    /// Print the affected projects as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+/// How an upgrade affects a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    /// The lockfile pins other versions and must be updated.
+    Relock,
+    /// The lockfile already pins the proposed version.
+    Current,
+    /// The project uses the language, but has no lockfile yet.
+    Unlocked,
+}
+
+/// A toolchain package pinned by a lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Pin {
+    name: String,
+    version: String,
+}
+
+/// The impact of an upgrade on a project.
+#[derive(Debug, Serialize)]
+struct Impact {
+    project: PathBuf,
+    status: Status,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pinned: Vec<Pin>,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let (domain, version) = self
+            .spec
+            .split_once('@')
+            .filter(|(domain, version)| !domain.is_empty() && !version.is_empty())
+            .ok_or_else(|| anyhow!("Expected <language>@<version>, got '{}'", self.spec))?;
+        let domain = domain.to_lowercase();
+
+        let roots = match self.roots.is_empty() {
+            true => ctx.config()?.projects.roots.iter().map(|root| expand(root)).collect(),
+            false => self.roots.clone(),
+        };
+        if roots.is_empty() {
+            return Err(anyhow!(
+                "No project roots configured, set `[projects] roots` or pass --root"
+            ));
+        }
+
+        let mut impacts = Vec::new();
+        for dir in roots.iter().flat_map(|root| find_projects(root)) {
+            let manifest = match manifest::load(&ctx, &dir.join("hummanta.toml")).await {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    ctx.reporter().warn(
+                        warning::PROJECT_SKIPPED,
+                        format!("Skipping {}: {e:#}", dir.display()),
+                    );
+                    continue;
+                }
+            };
+            let lock = LockManifest::load(dir.join(LOCKFILE)).ok();
+            let language = manifest.project.language.to_lowercase();
+            let assessed =
+                assess(lock.as_ref(), &language, &domain, version, self.package.as_deref());
+            if let Some((status, pinned)) = assessed {
+                impacts.push(Impact { project: dir, status, pinned });
+            }
+        }
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&impacts)?);
+            return Ok(());
+        }
+
+        let relock = impacts.iter().filter(|impact| impact.status == Status::Relock).count();
+        for impact in &impacts {
+            let pinned: Vec<_> =
+                impact.pinned.iter().map(|pin| format!("{} {}", pin.name, pin.version)).collect();
+            match impact.status {
+                Status::Relock => {
+                    println!("relock    {} ({})", impact.project.display(), pinned.join(", "))
+                }
+                Status::Current => println!("current   {}", impact.project.display()),
+                Status::Unlocked => println!("unlocked  {}", impact.project.display()),
+            }
+        }
+        println!(
+            "{relock} of {} {domain} projects would need relocking for {version}",
+            impacts.len()
+        );
+
+        Ok(())
+    }
+}
+
+/// Finds the project directories below a root, skipping hidden, output
+/// and vendored directories.
+fn find_projects(root: &Path) -> Vec<PathBuf> {
+    let mut projects: Vec<PathBuf> = WalkDir::new(root)
+        .max_depth(MAX_DEPTH)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0
+                || !entry.file_type().is_dir()
+                || !(name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && entry.file_name() == "hummanta.toml")
+        .filter_map(|entry| entry.path().parent().map(Path::to_path_buf))
+        .collect();
+    projects.sort();
+    projects
+}
+
+/// Assesses the impact of upgrading the toolchain of `domain` to `version`
+/// on a project of `language`, or `None` if the project does not use it.
+fn assess(
+    lock: Option<&LockManifest>,
+    language: &str,
+    domain: &str,
+    version: &str,
+    package: Option<&str>,
+) -> Option<(Status, Vec<Pin>)> {
+    let pinned: Vec<Pin> = lock
+        .into_iter()
+        .flat_map(|lock| &lock.tools)
+        .filter(|tool| tool.kind == Toolchain::kind() && tool.domain.eq_ignore_ascii_case(domain))
+        .filter(|tool| package.is_none_or(|package| tool.name == package))
+        .map(|tool| Pin { name: tool.name.clone(), version: tool.version.clone() })
+        .collect();
+
+    if pinned.is_empty() {
+        // Dependencies of other languages lock their toolchains too, so only
+        // the project language tells about projects without pins
+        return (lock.is_none() && language == domain).then_some((Status::Unlocked, pinned));
+    }
+
+    let outdated: Vec<Pin> = pinned.iter().filter(|pin| pin.version != version).cloned().collect();
+    match outdated.is_empty() {
+        true => Some((Status::Current, pinned)),
+        false => Some((Status::Relock, outdated)),
+    }
+}
+
+/// Expands a leading `~` to the home directory.
+fn expand(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use hmt_manifest::LockedTool;
+
+    use super::*;
+
+    fn lock(tools: &[(&str, &str, &str)]) -> LockManifest {
+        let mut lock = LockManifest::new();
+        lock.tools = tools
+            .iter()
+            .map(|(domain, name, version)| LockedTool {
+                kind: "toolchains".into(),
+                domain: domain.to_string(),
+                category: "frontend".into(),
+                name: name.to_string(),
+                version: version.to_string(),
+                hashes: Default::default(),
+            })
+            .collect();
+        lock
+    }
+
+    #[test]
+    fn test_assess() {
+        let lock = lock(&[("solidity", "frontend", "v1.0.0"), ("solidity", "fmt", "v2.0.0")]);
+        let (status, pinned) = assess(Some(&lock), "solidity", "solidity", "v2.0.0", None).unwrap();
+        assert_eq!(status, Status::Relock);
+        assert_eq!(pinned, [Pin { name: "frontend".into(), version: "v1.0.0".into() }]);
+
+        let assessed = assess(Some(&lock), "solidity", "solidity", "v2.0.0", Some("fmt"));
+        assert_eq!(assessed.unwrap().0, Status::Current);
+
+        assert_eq!(
+            assess(None, "solidity", "solidity", "v2.0.0", None).unwrap().0,
+            Status::Unlocked
+        );
+        assert!(assess(None, "move", "solidity", "v2.0.0", None).is_none());
+        assert!(assess(Some(&lock), "solidity", "move", "v2.0.0", None).is_none());
+    }
+
+    #[test]
+    fn test_find_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        for project in ["a", "b/c", "a/target/d", ".git/e", "b/vendor/f"] {
+            fs::create_dir_all(dir.path().join(project)).unwrap();
+            fs::write(dir.path().join(project).join("hummanta.toml"), "").unwrap();
+        }
+
+        let projects = find_projects(dir.path());
+        assert_eq!(projects, [dir.path().join("a"), dir.path().join("b/c")]);
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use clap::Args;
+
+use crate::{context::Context, errors::Result};
+
+/// Shows which package installed a binary of a language's toolchain.
+///
+/// Binaries shipped by more than one package resolve to the package that
+/// kept the name when the clash was resolved at install time.
+#[derive(Args, Debug)]
+pub struct Command {
+    /// The language of the toolchain.
+    language: String,
+
+    /// The name of the binary, e.g. `fmt`.
+    binary: String,
+}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        // Acquires the toolchain manager.
+        let manager = ctx.toolchains().await?;
+        let manager = manager.read().await;
+
+        let (category, name, path) =
+            manager.which(&self.language, &self.binary).ok_or_else(|| {
+                anyhow!("No {} toolchain package installed {}", self.language, self.binary)
+            })?;
+        println!("{}", path.display());
+        println!("  installed by {name} ({category})");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, path::PathBuf, sync::Arc};
+
+use anyhow::Context as _;
+use clap::Args;
+
+use hmt_manifest::{LockManifest, ManifestFile};
+
+use crate::{
+    context::Context,
+    deps::{self, LOCKFILE},
+    errors::Result,
+    utils,
+};
+
+/// Copies registry dependencies into `vendor/` for offline builds
+#[derive(Args, Debug)]
+pub struct Command {}
+
+impl Command {
+    pub async fn exec(&self, ctx: Arc<Context>) -> Result<()> {
+        let project_dir = ctx.project_dir()?;
+
+        let sources = deps::fetch(&ctx, project_dir).await?;
+        if sources.is_empty() {
+            ctx.reporter().info("No registry dependencies to vendor".into());
+            return Ok(());
+        }
+
+        let lock_path = project_dir.join(LOCKFILE);
+        let mut lock = LockManifest::load(&lock_path)?;
+
+        for (name, source) in &sources {
+            let path = PathBuf::from("vendor").join(name);
+            let dest = project_dir.join(&path);
+
+            // Already vendored copies are resolved to themselves
+            if dest.canonicalize().is_ok_and(|dest| &dest != source) {
+                fs::remove_dir_all(&dest)?;
+            }
+            if !dest.exists() {
+                utils::copy_dir(source, &dest)
+                    .context(format!("Failed to vendor dependency '{name}'"))?;
+            }
+
+            let mut package =
+                lock.get(name).cloned().context(format!("'{name}' is missing from {LOCKFILE}"))?;
+            package.path = Some(path);
+            lock.insert(package);
+
+            ctx.reporter().info(format!("Vendored {} into {}", name, dest.display()));
+        }
+
+        lock.save(&lock_path).context("Failed to write hummanta.lock")?;
+        Ok(())
+    }
+}
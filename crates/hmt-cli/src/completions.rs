@@ -0,0 +1,88 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use hmt_manifest::IndexManifest;
+use hmt_utils::temp::TempFile;
+
+use crate::errors::Result;
+
+/// The name of the completions cache in the metadata cache directory.
+pub const COMPLETIONS_FILE: &str = "completions.toml";
+
+/// The domains available in the registry, by kind.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Completions(BTreeMap<String, BTreeSet<String>>);
+
+impl Completions {
+    /// Lists the domains of every kind in a registry index.
+    pub fn from_index(index: &IndexManifest) -> Self {
+        let mut domains = BTreeMap::new();
+        for kind in index.sections() {
+            let keys = index.keys(kind).map(|(domain, _)| domain.clone()).collect();
+            domains.insert(kind.clone(), keys);
+        }
+
+        Self(domains)
+    }
+
+    /// Loads the completions, empty if the file is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path).ok().and_then(|s| toml::from_str(&s).ok()).unwrap_or_default()
+    }
+
+    /// Saves the completions, replacing the file atomically.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = TempFile::new_in(path.parent().unwrap_or(Path::new(".")))?;
+        fs::write(file.path(), toml::to_string(self)?)?;
+        file.persist(path)?;
+
+        Ok(())
+    }
+
+    /// Returns the domains of a kind starting with `prefix`.
+    pub fn domains<'a>(&'a self, kind: &str, prefix: &'a str) -> impl Iterator<Item = &'a String> {
+        self.0.get(kind).into_iter().flatten().filter(move |d| d.starts_with(prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completions() {
+        let mut index = IndexManifest::new();
+        index.insert("toolchains".into(), "solidity".into(), "toolchains/solidity.toml".into());
+        index.insert("toolchains".into(), "move".into(), "toolchains/move.toml".into());
+        index.insert("targets".into(), "evm".into(), "targets/evm.toml".into());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("completions.toml");
+        Completions::from_index(&index).save(&path).unwrap();
+
+        let completions = Completions::load(&path);
+        assert_eq!(completions.domains("toolchains", "").collect::<Vec<_>>(), ["move", "solidity"]);
+        assert_eq!(completions.domains("toolchains", "so").collect::<Vec<_>>(), ["solidity"]);
+        assert_eq!(completions.domains("libraries", "").count(), 0);
+        assert_eq!(Completions::load(&dir.path().join("missing")), Completions::default());
+    }
+}
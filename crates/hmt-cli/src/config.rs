@@ -12,11 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
+use hmt_registry::{storage::StorageKind, Policy};
 use serde::{Deserialize, Serialize};
 
-use crate::errors::Result;
+use crate::{
+    errors::Result,
+    jobs,
+    plugin::{Phase, Plugin},
+};
 
 const DEFAULT_REGISTRY: &str = "https://hummanta.github.io/registry";
 
@@ -28,11 +33,234 @@ pub struct Config {
     /// the environment variable `HUMMANTA_REGISTRY`,
     /// or left as the default.
     pub registry: String,
+
+    /// Defaults for `hummanta init`.
+    #[serde(default)]
+    pub init: InitConfig,
+
+    /// Restrictions on which packages may be installed.
+    #[serde(default)]
+    pub policy: Policy,
+
+    /// The backend storing the installed package cache.
+    #[serde(default)]
+    pub storage: StorageKind,
+
+    /// Endpoints notified after `registry promote`.
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+
+    /// Limits of the registry metadata cache.
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    /// Limits of concurrent tool invocations during builds.
+    #[serde(default)]
+    pub jobs: JobsConfig,
+
+    /// A build cache shared with other machines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_cache: Option<RemoteCacheConfig>,
+
+    /// Which host variables projects may read.
+    #[serde(default)]
+    pub env: EnvConfig,
+
+    /// Where the projects of this machine live.
+    #[serde(default)]
+    pub projects: ProjectsConfig,
+
+    /// Which warnings are shown, and whether they fail commands.
+    #[serde(default)]
+    pub warnings: WarningsConfig,
+
+    /// Mirrors tried before URLs starting with a prefix, e.g.
+    /// `"https://github.com/" = ["https://mirror.example.com/github/"]`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub mirrors: BTreeMap<String, Vec<String>>,
+
+    /// Build steps contributed by plugin binaries.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<Plugin>,
 }
 
 impl Default for Config {
     fn default() -> Self {
-        Self { registry: DEFAULT_REGISTRY.to_string() }
+        Self {
+            registry: DEFAULT_REGISTRY.to_string(),
+            init: InitConfig::default(),
+            policy: Policy::default(),
+            storage: StorageKind::default(),
+            webhooks: WebhookConfig::default(),
+            cache: CacheConfig::default(),
+            jobs: JobsConfig::default(),
+            remote_cache: None,
+            env: EnvConfig::default(),
+            projects: ProjectsConfig::default(),
+            warnings: WarningsConfig::default(),
+            mirrors: BTreeMap::new(),
+            plugins: Vec::new(),
+        }
+    }
+}
+
+/// Controls which auxiliary files `hummanta init` generates.
+///
+/// Each option can be overridden by the matching `init` flag.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InitConfig {
+    /// Create or append a `.gitignore` ignoring the target directory.
+    pub gitignore: bool,
+
+    /// Also ignore the lockfile instead of committing it.
+    pub ignore_lockfile: bool,
+
+    /// Create a minimal `.editorconfig` if none exists.
+    pub editorconfig: bool,
+
+    /// Download the detected toolchain in the background.
+    pub prefetch: bool,
+}
+
+impl Default for InitConfig {
+    fn default() -> Self {
+        Self { gitignore: true, ignore_lockfile: false, editorconfig: true, prefetch: false }
+    }
+}
+
+/// Webhooks receiving a signed JSON payload when a release changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// The URLs to POST to.
+    pub endpoints: Vec<String>,
+
+    /// How often a failed delivery is retried.
+    pub retries: u32,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self { endpoints: Vec::new(), retries: 3 }
+    }
+}
+
+/// Controls the environment of toolchain processes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvConfig {
+    /// The host variables the `[env]` table of a project may expand, e.g.
+    /// `["HOME"]`. None by default, so projects cannot read secrets.
+    pub expand: Vec<String>,
+}
+
+/// Locates the projects of this machine.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectsConfig {
+    /// Directories searched for projects by commands looking across them,
+    /// e.g. `["~/code"]`.
+    pub roots: Vec<PathBuf>,
+}
+
+/// Controls the warnings of commands.
+///
+/// Both options add to the matching `--allow` and `--deny-warnings` flags.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WarningsConfig {
+    /// The ids of warnings never shown, e.g. `["stale-metadata"]`.
+    pub allow: Vec<String>,
+
+    /// Fail commands reporting any warning that is not allowed.
+    pub deny: bool,
+}
+
+/// Controls the registry metadata cache.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Seconds fetched metadata is used without asking the registry.
+    pub ttl: u64,
+
+    /// Upper bound of the total cache size, in bytes.
+    pub max_size: u64,
+
+    /// Seconds spent refreshing expiring metadata after a command.
+    pub refresh_timeout: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { ttl: 60 * 60, max_size: 64 * 1024 * 1024, refresh_timeout: 2 }
+    }
+}
+
+/// A remote build cache, e.g.
+/// ```toml
+/// [remote_cache]
+/// url = "https://cache.example.com/hummanta"
+/// mode = "read-write"
+/// token_env = "HUMMANTA_CACHE_TOKEN"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteCacheConfig {
+    /// The base URL entries are stored below.
+    pub url: String,
+
+    /// Whether builds only download entries, or also upload their outputs.
+    #[serde(default)]
+    pub mode: RemoteCacheMode,
+
+    /// The variable holding the bearer token sent with every request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+}
+
+/// How builds use the remote cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemoteCacheMode {
+    /// Download entries only, e.g. on developer machines.
+    #[default]
+    Read,
+    /// Download entries and upload the outputs of successful builds.
+    ReadWrite,
+}
+
+/// Limits of concurrent tool invocations in the build phases.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobsConfig {
+    /// The maximum number of compile invocations, twice the number of CPUs
+    /// if unset.
+    pub compile: Option<usize>,
+
+    /// The maximum number of emit invocations, the number of CPUs if unset.
+    pub emit: Option<usize>,
+
+    /// Adjust the number of invocations to the CPU and memory pressure.
+    pub adaptive: bool,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self { compile: None, emit: None, adaptive: true }
+    }
+}
+
+impl JobsConfig {
+    /// Returns the maximum number of invocations of a phase, capped by
+    /// `jobs` if given.
+    pub fn max(&self, phase: Phase, jobs: Option<usize>) -> usize {
+        let cpus = jobs::cpus();
+        let max = match phase {
+            Phase::Compile => self.compile.unwrap_or(cpus * 2),
+            Phase::Emit => self.emit.unwrap_or(cpus),
+            Phase::Link => 1,
+        };
+        jobs.map_or(max, |jobs| max.min(jobs)).max(1)
     }
 }
 
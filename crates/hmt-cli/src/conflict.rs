@@ -0,0 +1,82 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Asking the user how to install files that clash between packages.
+
+use std::io::{self, IsTerminal};
+
+use async_trait::async_trait;
+use hmt_manifest::Resolution;
+use hmt_registry::conflict::{Conflict, ConflictResolver};
+
+/// Asks on the terminal how to install a file another package installed,
+/// skipping the file when there is no one to ask.
+///
+/// The question goes to stderr, keeping stdout to the output of commands,
+/// and the answer is read on a blocking thread.
+pub struct Prompt;
+
+#[async_trait]
+impl ConflictResolver for Prompt {
+    async fn resolve(&self, conflict: &Conflict) -> Resolution {
+        if !io::stdin().is_terminal() {
+            return Resolution::Skip;
+        }
+
+        eprintln!(
+            "{} of {} is already installed by {}. [s]kip, [r]ename to {}, or [o]verwrite? [S/r/o]",
+            conflict.file,
+            conflict.package,
+            conflict.owner,
+            conflict.renamed()
+        );
+        let answer = tokio::task::spawn_blocking(|| {
+            let mut input = String::new();
+            io::stdin().read_line(&mut input).map(|_| input)
+        })
+        .await;
+        match answer {
+            Ok(Ok(input)) => parse(&input, conflict),
+            _ => Resolution::Skip,
+        }
+    }
+}
+
+/// Parses the answer to the prompt, skipping on anything unexpected.
+fn parse(answer: &str, conflict: &Conflict) -> Resolution {
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "r" | "rename" => Resolution::Rename(conflict.renamed()),
+        "o" | "overwrite" => Resolution::Replace,
+        _ => Resolution::Skip,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let conflict = Conflict {
+            domain: "solidity".into(),
+            file: "fmt".into(),
+            package: "foo".into(),
+            owner: "bar".into(),
+        };
+        assert_eq!(parse("r\n", &conflict), Resolution::Rename("fmt-foo".into()));
+        assert_eq!(parse(" Overwrite ", &conflict), Resolution::Replace);
+        assert_eq!(parse("\n", &conflict), Resolution::Skip);
+        assert_eq!(parse("what", &conflict), Resolution::Skip);
+    }
+}
@@ -13,28 +13,62 @@
 // limitations under the License.
 
 use std::{
+    ffi::OsString,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, OnceLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context as _, Ok};
 use tokio::sync::{OnceCell, RwLock};
 use tracing::debug;
 
+use hmt_fetcher::{Fetcher, RemoteFetcher};
+use hmt_manifest::ProjectManifest;
 use hmt_registry::{
-    manager::{TargetManager, ToolchainManager},
+    cache::MetadataCache,
+    manager::{LibraryManager, Manager, TargetManager, ToolchainManager},
+    storage::{self, Storage},
+    traits::PackageKind,
     RegistryClient,
 };
+use hmt_utils::{checksum, event::Reporter, temp};
 
-use crate::{config::Config, errors::Result, utils};
+use crate::{
+    cmd::Command,
+    completions::{Completions, COMPLETIONS_FILE},
+    config::Config,
+    conflict::Prompt,
+    errors::{CliError, Result},
+    progress::{Progress, ProgressMode},
+    remote_cache::RemoteCache,
+    reporter, utils,
+};
+
+/// The header used to attach the per-invocation trace ID to registry requests.
+const TRACE_ID_HEADER: &str = "X-Hummanta-Trace-Id";
+
+/// The system-wide installation used when none is given.
+const SYSTEM_HOME: &str = "/opt/hummanta";
+
+/// How old temporary files must be before they are considered left behind
+/// by a crashed run.
+const TEMP_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
 
 /// Holds the state of the application.
 pub struct Context {
-    /// The configuration for the application.
-    pub config: Config,
+    /// The path to the Hummanta home directory, created on first write.
+    home_dir: PathBuf,
 
-    /// The path to the configuration.
-    pub config_path: PathBuf,
+    /// The read-only system-wide installation layered under the home
+    /// directory, if any.
+    system_dir: Option<PathBuf>,
+
+    /// Set once the home directory exists and has been swept.
+    home_ready: OnceLock<()>,
+
+    /// Lazily loaded configuration for the application.
+    config: OnceLock<Config>,
 
     /// Overridden registry URL
     registry: Option<String>,
@@ -45,60 +79,367 @@ pub struct Context {
     /// Lazily initialized toolchain manager
     toolchain_manager: OnceCell<Arc<RwLock<ToolchainManager>>>,
 
-    /// The path to the project manifest.
-    manifest_path: Option<PathBuf>,
+    /// Lazily initialized library manager
+    library_manager: OnceCell<Arc<RwLock<LibraryManager>>>,
+
+    /// Lazily connected remote build cache, if configured.
+    remote_cache: OnceLock<Option<Arc<RemoteCache>>>,
+
+    /// Lazily discovered path to the project manifest.
+    manifest_path: OnceLock<Option<PathBuf>>,
+
+    /// The name of the invoked command, reported in the user agent.
+    command: String,
+
+    /// Unique ID of this invocation, attached to requests and logs.
+    trace_id: String,
+
+    /// Whether network access is disabled.
+    offline: bool,
+
+    /// Whether the lockfile must be used as is.
+    locked: bool,
+
+    /// How progress is reported, never `Auto`.
+    progress: ProgressMode,
+
+    /// Receives the events of registry operations and builds.
+    reporter: Arc<dyn Reporter>,
+
+    /// Filters and counts the warnings passed to `reporter`.
+    warnings: Arc<reporter::Warnings>,
+
+    /// Whether `--deny-warnings` is given.
+    deny_warnings: bool,
 }
 
 impl Context {
-    /// Creates a new context with loaded configuration
-    pub fn new(registry: &Option<String>) -> Result<Self> {
-        let home_dir = dirs::home_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?
-            .join(".hummanta");
-
-        // Ensure home directory exists
-        if !home_dir.exists() {
-            std::fs::create_dir_all(&home_dir)
-                .context("Failed to create Hummanta home directory")?;
-        }
-
-        let config_path = home_dir.join("config.toml");
-        let config = Config::load(&config_path)?;
-        let manifest_path = utils::find("hummanta.toml").ok();
+    /// Creates a new context for the command.
+    ///
+    /// Configuration and the project manifest are only read when a command
+    /// asks for them, so commands that need neither start without touching
+    /// the filesystem.
+    pub fn new(cmd: &Command) -> Result<Self> {
+        let home_dir = match &cmd.home {
+            Some(home) => home.clone(),
+            None => dirs::home_dir()
+                .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?
+                .join(".hummanta"),
+        };
+        let system_dir = cmd
+            .system_home
+            .clone()
+            .or_else(|| Path::new(SYSTEM_HOME).is_dir().then(|| PathBuf::from(SYSTEM_HOME)))
+            .filter(|dir| *dir != home_dir);
 
+        let progress = cmd.progress.resolve();
+        let warnings = Arc::new(reporter::Warnings::new(reporter::reporter(progress), &cmd.allow));
         let context = Self {
-            config,
-            config_path,
-            registry: registry.clone(),
+            home_dir,
+            system_dir,
+            home_ready: OnceLock::new(),
+            config: OnceLock::new(),
+            registry: cmd.registry.clone(),
             target_manager: OnceCell::new(),
             toolchain_manager: OnceCell::new(),
-            manifest_path,
+            library_manager: OnceCell::new(),
+            remote_cache: OnceLock::new(),
+            manifest_path: OnceLock::new(),
+            command: cmd.name().to_string(),
+            trace_id: trace_id(),
+            offline: cmd.offline,
+            locked: cmd.locked,
+            progress,
+            reporter: warnings.clone(),
+            warnings,
+            deny_warnings: cmd.deny_warnings,
         };
-        debug!("Registry: {}", context.registry());
+        debug!("Trace ID: {}", context.trace_id);
 
         Ok(context)
     }
 
     /// Gets the path to the Hummanta home directory.
     pub fn home_dir(&self) -> PathBuf {
-        self.config_path.parent().unwrap().to_path_buf()
+        self.home_dir.clone()
+    }
+
+    /// Gets the read-only system-wide installation, if any.
+    pub fn system_dir(&self) -> Option<&Path> {
+        self.system_dir.as_deref()
+    }
+
+    /// Gets the directory of executables put on `PATH` by
+    /// `hummanta self setup-path`.
+    pub fn bin_dir(&self) -> PathBuf {
+        self.home_dir.join("bin")
+    }
+
+    /// Gets the configuration, loading it on first use.
+    pub fn config(&self) -> Result<&Config> {
+        if let Some(config) = self.config.get() {
+            return Ok(config);
+        }
+
+        let config = Config::load(&self.home_dir.join("config.toml"))?;
+        self.warnings.allow(&config.warnings.allow);
+        Ok(self.config.get_or_init(|| config))
     }
 
     /// Computes the final registry URL based on the priority:
     /// CLI > Environment > Config > Default.
-    fn registry(&self) -> String {
-        self.registry
-            .clone()
-            .or_else(|| std::env::var("HUMMANTA_REGISTRY").ok())
-            .unwrap_or_else(|| self.config.registry.clone())
+    pub fn registry(&self) -> Result<String> {
+        match self.registry.clone().or_else(|| std::env::var("HUMMANTA_REGISTRY").ok()) {
+            Some(registry) => Ok(registry),
+            None => Ok(self.config()?.registry.clone()),
+        }
+    }
+
+    /// Whether network access is disabled for this invocation.
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Whether the lockfile must be used as is for this invocation.
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Returns the global options choosing where packages come from and go,
+    /// for `hummanta` processes started on behalf of this one, so they use
+    /// the same registry, installations and caches.
+    pub fn forwarded_args(&self) -> Result<Vec<OsString>> {
+        let mut args: Vec<OsString> = vec![
+            "--registry".into(),
+            self.registry()?.into(),
+            "--home".into(),
+            self.home_dir.clone().into(),
+        ];
+        if let Some(dir) = &self.system_dir {
+            args.extend(["--system-home".into(), dir.into()]);
+        }
+        if self.offline {
+            args.push("--offline".into());
+        }
+        if self.locked {
+            args.push("--locked".into());
+        }
+        Ok(args)
+    }
+
+    /// Gets the reporter showing events to the user.
+    pub fn reporter(&self) -> &Arc<dyn Reporter> {
+        &self.reporter
+    }
+
+    /// Gets the number of warnings reported so far, without allowed ones.
+    pub fn warnings(&self) -> usize {
+        self.warnings.count()
+    }
+
+    /// Fails if warnings were reported while `--deny-warnings` or the
+    /// configuration denies them.
+    pub fn check_warnings(&self) -> Result<()> {
+        let count = self.warnings();
+        let deny = self.deny_warnings || self.config().is_ok_and(|config| config.warnings.deny);
+        if deny && count > 0 {
+            return Err(CliError::DeniedWarnings { count }.into());
+        }
+        Ok(())
+    }
+
+    /// Starts reporting the progress of a task over `total` items.
+    pub fn progress(&self, label: &str, total: usize) -> Progress {
+        Progress::new(self.reporter.clone(), label, total)
+    }
+
+    /// Whether output may be colorized, which plain progress mode disables.
+    pub fn color(&self) -> bool {
+        self.progress == ProgressMode::Live
+    }
+
+    /// Gets the unique ID of this invocation.
+    pub fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    /// Builds the user agent: CLI version, OS, architecture and command.
+    fn user_agent(&self) -> String {
+        format!(
+            "hummanta/{} ({}; {}) {}",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            self.command
+        )
+    }
+
+    /// Creates an HTTP fetcher that identifies this invocation.
+    pub fn remote_fetcher(&self) -> RemoteFetcher {
+        RemoteFetcher::new().user_agent(&self.user_agent()).header(TRACE_ID_HEADER, &self.trace_id)
+    }
+
+    /// Creates a registry client that identifies this invocation.
+    fn registry_client(&self) -> Result<RegistryClient> {
+        let registry = self.registry()?;
+        debug!("Registry: {}", registry);
+
+        let fetcher = self.fetcher()?;
+        Ok(RegistryClient::with_fetcher(&registry, fetcher).with_cache(self.metadata_cache()?))
+    }
+
+    /// Creates a fetcher for all supported schemes, using the configured
+    /// mirrors.
+    pub fn fetcher(&self) -> Result<Fetcher> {
+        Ok(Fetcher::with_remote(self.remote_fetcher())
+            .with_reporter(self.reporter.clone())
+            .with_mirrors(self.config()?.mirrors.clone()))
+    }
+
+    /// Resolves the environment variables a project sets for its toolchain
+    /// processes, expanding only the host variables the config allows.
+    pub fn project_env(&self, manifest: &ProjectManifest) -> Result<Vec<(String, String)>> {
+        let allowed = &self.config()?.env.expand;
+        Ok(manifest.resolve_env(allowed, |var| std::env::var(var).ok())?)
+    }
+
+    /// Gets the cache of downloads verified by `hummanta fetch`, and of the
+    /// artifacts `hummanta prefetch` downloads, keyed by their SHA-256 hash.
+    pub fn downloads_dir(&self) -> PathBuf {
+        self.home_dir.join("cache").join("downloads")
+    }
+
+    /// Gets the remote build cache, unless none is configured or network
+    /// access is disabled.
+    pub fn remote_cache(&self) -> Result<Option<Arc<RemoteCache>>> {
+        if let Some(cache) = self.remote_cache.get() {
+            return Ok(cache.clone());
+        }
+
+        let cache = match &self.config()?.remote_cache {
+            Some(config) if !self.offline => {
+                Some(Arc::new(RemoteCache::new(self.remote_fetcher(), config)?))
+            }
+            _ => None,
+        };
+        Ok(self.remote_cache.get_or_init(|| cache).clone())
+    }
+
+    /// Gets the registry metadata cache, separate per registry.
+    pub fn metadata_cache(&self) -> Result<MetadataCache> {
+        let config = &self.config()?.cache;
+
+        Ok(MetadataCache::new(self.metadata_dir()?)
+            .temp_dir(self.temp_dir())
+            .ttl(Duration::from_secs(config.ttl))
+            .max_size(config.max_size))
+    }
+
+    /// Gets the path to the completions cache of the current registry.
+    pub fn completions_path(&self) -> Result<PathBuf> {
+        Ok(self.metadata_dir()?.join(COMPLETIONS_FILE))
+    }
+
+    /// Gets the metadata cache directory of the current registry.
+    fn metadata_dir(&self) -> Result<PathBuf> {
+        let registry = checksum::digest(self.registry()?.as_bytes());
+        Ok(self.home_dir.join("cache").join("metadata").join(&registry[..16]))
+    }
+
+    /// Refreshes expiring registry metadata, bounded by the configured timeout.
+    ///
+    /// Skipped when the command never reached the registry.
+    pub async fn refresh_metadata(&self) {
+        let used = self.target_manager.initialized()
+            || self.toolchain_manager.initialized()
+            || self.library_manager.initialized();
+        if self.offline || !used {
+            return;
+        }
+
+        let Some((config, client)) = self.config().ok().zip(self.registry_client().ok()) else {
+            return;
+        };
+        let timeout = Duration::from_secs(config.cache.refresh_timeout);
+        let refresh = async {
+            client.refresh_metadata().await;
+            self.update_completions(&client).await;
+        };
+        if tokio::time::timeout(timeout, refresh).await.is_err() {
+            debug!("Metadata refresh timed out");
+        }
+    }
+
+    /// Rewrites the completions cache from the registry index.
+    async fn update_completions(&self, client: &RegistryClient) {
+        let Some(path) = self.completions_path().ok() else {
+            return;
+        };
+        let saved = match client.index().await {
+            Result::Ok(index) => Completions::from_index(&index).save(&path),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = saved {
+            debug!("Failed to update completions: {}", e);
+        }
+    }
+
+    /// Gets the directory temporary files are written to before being
+    /// moved into the home directory.
+    fn temp_dir(&self) -> PathBuf {
+        self.home_dir.join("tmp")
+    }
+
+    /// Creates the home directory if it does not exist yet, and removes
+    /// temporary files left behind by crashed runs.
+    fn prepare_home(&self) -> Result<()> {
+        if self.home_ready.get().is_some() {
+            return Ok(());
+        }
+
+        if !self.home_dir.exists() {
+            std::fs::create_dir_all(&self.home_dir)
+                .context("Failed to create Hummanta home directory")?;
+        }
+        match temp::sweep(&self.temp_dir(), TEMP_MAX_AGE) {
+            Result::Ok(0) => {}
+            Result::Ok(removed) => debug!("Removed {} leftover temporary files", removed),
+            Err(e) => debug!("Failed to sweep temporary files: {}", e),
+        }
+
+        self.home_ready.get_or_init(|| ());
+        Ok(())
+    }
+
+    /// Opens the configured installed cache storage.
+    fn storage(&self) -> Result<Box<dyn Storage>> {
+        self.prepare_home()?;
+        Ok(storage::open(self.config()?.storage, &self.home_dir)?)
+    }
+
+    /// Layers a manager over the system-wide installation, if any. Its
+    /// storage is only read, so it is opened as whatever kind it has.
+    fn with_system<T: PackageKind>(&self, manager: Manager<T>) -> Result<Manager<T>> {
+        let Some(root) = &self.system_dir else {
+            return Ok(manager);
+        };
+        debug!("System installation: {}", root.display());
+        let storage = storage::open_read_only(storage::detect(root), root)?;
+        Ok(manager.with_system(root.clone(), storage)?)
     }
 
     /// Gets the target manager, initializing it if necessary
     pub async fn targets(&self) -> Result<Arc<RwLock<TargetManager>>> {
         self.target_manager
             .get_or_try_init(|| async {
-                let registry = RegistryClient::new(&self.registry());
-                Ok(Arc::new(RwLock::new(TargetManager::new(registry, self.home_dir()))))
+                let registry = self.registry_client()?;
+                let manager = TargetManager::new(registry, self.home_dir())
+                    .with_policy(self.config()?.policy.clone())
+                    .with_resolver(Arc::new(Prompt))
+                    .with_downloads(self.downloads_dir())
+                    .with_storage(self.storage()?)?;
+                let manager = self.with_system(manager)?;
+                Ok(Arc::new(RwLock::new(manager)))
             })
             .await
             .cloned()
@@ -108,8 +449,30 @@ impl Context {
     pub async fn toolchains(&self) -> Result<Arc<RwLock<ToolchainManager>>> {
         self.toolchain_manager
             .get_or_try_init(|| async {
-                let registry = RegistryClient::new(&self.registry());
-                Ok(Arc::new(RwLock::new(ToolchainManager::new(registry, self.home_dir()))))
+                let registry = self.registry_client()?;
+                let manager = ToolchainManager::new(registry, self.home_dir())
+                    .with_policy(self.config()?.policy.clone())
+                    .with_resolver(Arc::new(Prompt))
+                    .with_downloads(self.downloads_dir())
+                    .with_storage(self.storage()?)?;
+                let manager = self.with_system(manager)?;
+                Ok(Arc::new(RwLock::new(manager)))
+            })
+            .await
+            .cloned()
+    }
+
+    /// Gets the library manager, initializing it if necessary
+    pub async fn libraries(&self) -> Result<Arc<RwLock<LibraryManager>>> {
+        self.library_manager
+            .get_or_try_init(|| async {
+                let registry = self.registry_client()?;
+                let manager = LibraryManager::new(registry, self.home_dir())
+                    .with_policy(self.config()?.policy.clone())
+                    .with_resolver(Arc::new(Prompt))
+                    .with_storage(self.storage()?)?;
+                let manager = self.with_system(manager)?;
+                Ok(Arc::new(RwLock::new(manager)))
             })
             .await
             .cloned()
@@ -117,9 +480,8 @@ impl Context {
 
     /// Gets the path to the Hummanta project manifest.
     pub fn manifest_path(&self) -> Result<&PathBuf> {
-        self.manifest_path.as_ref().ok_or_else(|| {
-            anyhow::anyhow!("Could not find 'hummanta.toml'. Please run `hummanta init` first.")
-        })
+        let path = self.manifest_path.get_or_init(|| utils::find("hummanta.toml").ok());
+        path.as_ref().ok_or_else(|| CliError::ManifestNotFound.into())
     }
 
     /// Determine project directory from manifest path
@@ -128,4 +490,15 @@ impl Context {
             anyhow::anyhow!("Could not determine project directory from manifest path")
         })
     }
+
+    /// Gets the build output directory for the given target.
+    pub fn target_dir(&self, target: &str) -> Result<PathBuf> {
+        Ok(self.project_dir()?.join("target").join(target))
+    }
+}
+
+/// Generates a trace ID from the current time and process ID.
+fn trace_id() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    format!("{:x}-{:x}", nanos, std::process::id())
 }
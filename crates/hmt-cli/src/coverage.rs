@@ -0,0 +1,155 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Line coverage of test runs.
+//!
+//! Binaries instrumented by a backend write their raw coverage as an LCOV
+//! tracefile to the path in `HUMMANTA_COVERAGE_FILE`, one per run, below
+//! `target/coverage`. The tracefiles are merged into one report by summing
+//! the hit counts of every line.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context as _};
+
+use crate::errors::Result;
+
+/// The variable naming the file an instrumented binary writes its coverage to.
+pub const COVERAGE_FILE_VAR: &str = "HUMMANTA_COVERAGE_FILE";
+
+/// The extension of raw coverage files.
+pub const RAW_EXTENSION: &str = "lcov";
+
+/// The name of the merged report.
+pub const REPORT_FILE: &str = "lcov.info";
+
+/// The hit counts of the lines of every source file.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+    files: BTreeMap<PathBuf, BTreeMap<u32, u64>>,
+}
+
+impl Coverage {
+    /// Merges the raw coverage files in `dir`.
+    pub fn merge(dir: &Path) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == RAW_EXTENSION))
+            .collect();
+        paths.sort();
+
+        let mut coverage = Coverage::default();
+        for path in paths {
+            let content = fs::read_to_string(&path)?;
+            coverage
+                .add(&content)
+                .with_context(|| format!("Invalid coverage file {}", path.display()))?;
+        }
+        Ok(coverage)
+    }
+
+    /// Adds the hit counts of an LCOV tracefile. Records other than lines
+    /// are ignored.
+    pub fn add(&mut self, tracefile: &str) -> Result<()> {
+        let mut lines = None;
+        for (number, record) in tracefile.lines().map(str::trim).enumerate() {
+            let number = number + 1;
+            if let Some(file) = record.strip_prefix("SF:") {
+                lines = Some(self.files.entry(PathBuf::from(file)).or_default());
+            } else if let Some(data) = record.strip_prefix("DA:") {
+                let Some(lines) = lines.as_mut() else {
+                    bail!("Line {number}: line data outside of a source file");
+                };
+                let mut fields = data.split(',');
+                let (Some(Ok(line)), Some(Ok(hits))) =
+                    (fields.next().map(str::parse::<u32>), fields.next().map(str::parse::<u64>))
+                else {
+                    bail!("Line {number}: invalid line data '{data}'");
+                };
+                *lines.entry(line).or_default() += hits;
+            } else if record == "end_of_record" {
+                lines = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of lines hit and the number of instrumented lines.
+    pub fn summary(&self) -> (usize, usize) {
+        self.files
+            .values()
+            .flat_map(BTreeMap::values)
+            .fold((0, 0), |(hit, total), hits| (hit + usize::from(*hits > 0), total + 1))
+    }
+
+    /// Renders the coverage as an LCOV tracefile.
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        for (file, lines) in &self.files {
+            let _ = writeln!(lcov, "SF:{}", file.display());
+            for (line, hits) in lines {
+                let _ = writeln!(lcov, "DA:{line},{hits}");
+            }
+            let hit = lines.values().filter(|hits| **hits > 0).count();
+            let _ = writeln!(lcov, "LH:{hit}\nLF:{}\nend_of_record", lines.len());
+        }
+        lcov
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("a.lcov"),
+            "TN:\nSF:src/main.sol\nFN:1,main\nDA:1,1\nDA:2,0\nend_of_record\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("b.lcov"),
+            "SF:src/main.sol\nDA:2,3,abc\nDA:5,0\nend_of_record\nSF:src/lib.sol\nDA:1,2\nend_of_record\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join(REPORT_FILE), "SF:ignored.sol\nDA:1,1\n").unwrap();
+
+        let coverage = Coverage::merge(dir.path()).unwrap();
+        assert_eq!(coverage.summary(), (3, 4));
+        assert_eq!(
+            coverage.to_lcov(),
+            "SF:src/lib.sol\nDA:1,2\nLH:1\nLF:1\nend_of_record\n\
+             SF:src/main.sol\nDA:1,1\nDA:2,3\nDA:5,0\nLH:2\nLF:3\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn test_add_invalid() {
+        let mut coverage = Coverage::default();
+        let error = coverage.add("DA:1,1\n").unwrap_err();
+        assert!(error.to_string().contains("outside of a source file"));
+        assert!(coverage.add("SF:a.sol\nDA:one,1\n").is_err());
+        assert_eq!(coverage.summary(), (0, 0));
+    }
+}
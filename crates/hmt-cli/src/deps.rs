@@ -0,0 +1,425 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context as _};
+
+use hmt_manifest::{
+    Dependency, FrozenPackage, LockManifest, LockedTool, ManifestFile, ProjectManifest,
+};
+use hmt_registry::{
+    manager::{self, Toolchain},
+    traits::PackageKind,
+};
+use hmt_utils::{checksum, event::warning, path};
+
+use crate::{context::Context, errors::Result, manifest};
+
+/// The name of the lockfile written next to `hummanta.toml`.
+pub const LOCKFILE: &str = "hummanta.lock";
+
+/// Source directories of registry dependencies, keyed by name.
+pub type Sources = HashMap<String, PathBuf>;
+
+/// Build output directories of the projects of a build, keyed by project
+/// directory.
+pub type Outputs = HashMap<PathBuf, PathBuf>;
+
+/// A dependency resolved to a project on disk.
+#[derive(Debug)]
+pub struct Resolved {
+    /// The name the dependency was declared under.
+    pub name: String,
+    /// The canonical project directory.
+    pub dir: PathBuf,
+    /// The project manifest of the dependency.
+    pub manifest: ProjectManifest,
+}
+
+/// Fetches the registry dependencies of the project in `dir`, transitively.
+///
+/// Versions pinned in the project's `hummanta.lock` are reused while they
+/// still satisfy the declared requirement; everything else is resolved to
+/// the newest matching release and pinned. Vendored copies are preferred
+/// over the cache, and in offline mode nothing is downloaded.
+///
+/// With `--locked`, every registry dependency must already be pinned, and
+/// the registry must still serve the release manifests recorded at lock
+/// time, so a registry modified after locking is detected.
+pub async fn fetch(ctx: &Context, dir: &Path) -> Result<Sources> {
+    let lock_path = dir.join(LOCKFILE);
+    let locked =
+        if lock_path.exists() { LockManifest::load(&lock_path)? } else { LockManifest::new() };
+
+    let root = dir.canonicalize().context("Failed to resolve project directory")?;
+    let mut lock = LockManifest { tools: locked.tools.clone(), ..LockManifest::new() };
+    let mut sources = Sources::new();
+    let mut visited = HashSet::new();
+    let mut queue = vec![root.clone()];
+
+    while let Some(dir) = queue.pop() {
+        if !visited.insert(dir.clone()) {
+            continue;
+        }
+
+        let manifest = manifest::load(ctx, &dir.join("hummanta.toml")).await?;
+        for (name, dependency) in &manifest.dependencies {
+            let Some(req) = &dependency.version else {
+                queue.push(dependency_dir(&dir, name, dependency, &sources)?);
+                continue;
+            };
+
+            // A library is shared by every project depending on it
+            if let Some(package) = lock.get(name) {
+                if !manager::matches(&package.version, req)? {
+                    bail!(
+                        "Conflicting requirements for '{}': {} does not match {}",
+                        name,
+                        package.version,
+                        req
+                    );
+                }
+                continue;
+            }
+
+            let libraries = ctx.libraries().await?;
+            let libraries = libraries.read().await;
+
+            let package = match locked.get(name) {
+                Some(package) if manager::matches(&package.version, req)? => package.clone(),
+                _ if ctx.locked() => {
+                    bail!("'{}' {} is not locked, but --locked was given", name, req)
+                }
+                _ if ctx.offline() => bail!("Cannot resolve '{}' {} in offline mode", name, req),
+                _ => {
+                    let package = libraries.resolve(name, req).await?;
+                    ctx.reporter().info(format!("Locking {} {}", name, package.version));
+                    package
+                }
+            };
+
+            if ctx.locked() && !ctx.offline() {
+                if package.manifest.is_none() {
+                    ctx.reporter().warn(
+                        warning::UNVERIFIED_LOCK,
+                        format!("'{}' was locked without a manifest digest, skipping checks", name),
+                    );
+                }
+                libraries.verify(&package).await?;
+            }
+
+            let vendored = package.path.as_ref().map(|path| root.join(path));
+            let source = match vendored {
+                Some(path) if path.join("hummanta.toml").is_file() => path.canonicalize()?,
+                _ if ctx.offline() => libraries.cached_source(&package)?.ok_or_else(|| {
+                    anyhow!("'{}' {} is neither vendored nor cached", name, package.version)
+                })?,
+                _ => libraries.fetch_source(&package).await?,
+            };
+            sources.insert(name.clone(), source.clone());
+            lock.insert(package);
+            queue.push(source);
+        }
+    }
+
+    // Rewrite the lockfile only when the pinned set changed,
+    // and never create one for projects without registry dependencies
+    let changed = lock != locked;
+    if changed && ctx.locked() {
+        bail!("hummanta.lock needs to be updated, but --locked was given");
+    }
+    if changed && (lock_path.exists() || !lock.packages.is_empty()) {
+        lock.save(&lock_path).context("Failed to write hummanta.lock")?;
+    }
+
+    Ok(sources)
+}
+
+/// Pins the toolchain and target packages a build of the project in `dir`
+/// uses in its `hummanta.lock`, for `toolchain add --from-lock`.
+///
+/// Every package is pinned to its installed version, with the artifact
+/// hashes of all targets of the release, so a lockfile written on one
+/// platform installs and verifies on every other. Pins matching the
+/// installed packages are kept without accessing the registry.
+///
+/// Projects without a lockfile only get one if `create` is set, so a build
+/// never creates one unasked. With `--locked`, the lockfile must exist and
+/// the installed packages must match the pinned ones.
+pub async fn lock_tools(
+    ctx: &Context,
+    dir: &Path,
+    tools: Vec<FrozenPackage>,
+    create: bool,
+) -> Result<()> {
+    let lock_path = dir.join(LOCKFILE);
+    let mut lock = match lock_path.exists() {
+        true => LockManifest::load(&lock_path)?,
+        false if ctx.locked() => bail!(
+            "hummanta.lock does not exist, but --locked was given; \
+             run `hummanta build --lock-tools` without --locked to create it"
+        ),
+        false if create => LockManifest::new(),
+        false => return Ok(()),
+    };
+
+    let locked = lock.tools.clone();
+    let mut pinned = Vec::with_capacity(tools.len());
+    for tool in &tools {
+        match locked.iter().find(|locked| locked.pins(tool, target_triple::TARGET)) {
+            Some(locked) => pinned.push(locked.clone()),
+            None if ctx.locked() => bail!(
+                "{} {} is not pinned in hummanta.lock, but --locked was given; \
+                 run `hummanta toolchain add --from-lock` to install the locked toolchains",
+                tool.name,
+                tool.version
+            ),
+            None => {
+                // Without the registry, only the installed artifact is known
+                let hashes = release_hashes(ctx, tool).await.unwrap_or_else(|e| {
+                    ctx.reporter().warn(
+                        warning::FETCH_FAILED,
+                        format!(
+                            "Locking {} {} for this platform only: {e}",
+                            tool.name, tool.version
+                        ),
+                    );
+                    BTreeMap::from([(target_triple::TARGET.to_string(), tool.hash.clone())])
+                });
+                pinned.push(LockedTool::new(tool, hashes));
+            }
+        }
+    }
+    lock.set_tools(pinned);
+    if lock.tools == locked {
+        return Ok(());
+    }
+    if ctx.locked() {
+        bail!(
+            "The installed toolchains differ from hummanta.lock, but --locked was given; \
+             run `hummanta toolchain add --from-lock` to install the locked ones"
+        );
+    }
+    lock.save(&lock_path).context("Failed to write hummanta.lock")?;
+
+    Ok(())
+}
+
+/// Returns the artifact hashes of every target of the release an installed
+/// package came from.
+async fn release_hashes(ctx: &Context, tool: &FrozenPackage) -> Result<BTreeMap<String, String>> {
+    let FrozenPackage { domain, category, name, version, .. } = tool;
+    if tool.kind == Toolchain::kind() {
+        let toolchains = ctx.toolchains().await?;
+        let toolchains = toolchains.read().await;
+        return Ok(toolchains.release_hashes(domain, category, name, version).await?);
+    }
+
+    let targets = ctx.targets().await?;
+    let targets = targets.read().await;
+    Ok(targets.release_hashes(domain, category, name, version).await?)
+}
+
+/// Resolves the transitive dependencies of the project in `dir`.
+///
+/// The result is in topological order: every project appears after all
+/// of its own dependencies. The root project itself is not included.
+/// Base manifests named by URL are read from the download cache `cache`.
+pub fn resolve(
+    dir: &Path,
+    manifest: &ProjectManifest,
+    sources: &Sources,
+    cache: &Path,
+) -> Result<Vec<Resolved>> {
+    let root = dir.canonicalize().context("Failed to resolve project directory")?;
+
+    let mut resolver =
+        Resolver { sources, cache, stack: vec![root.clone()], done: HashSet::new(), order: vec![] };
+    resolver.visit(&root, manifest)?;
+
+    Ok(resolver.order)
+}
+
+/// Assigns the project in `dir` and its resolved dependencies their build
+/// output directories.
+///
+/// The project builds into `target_dir`, and every dependency into
+/// `target_dir/deps/<name>-<hash>`, where the hash of its directory tells
+/// apart dependencies declared under the same name. Dependencies are never
+/// built in place, as registry libraries live in a cache shared between
+/// projects, or in a read-only system installation.
+pub fn outputs(dir: &Path, target_dir: &Path, dependencies: &[Resolved]) -> Result<Outputs> {
+    let mut outputs = Outputs::from([(dir.to_path_buf(), target_dir.to_path_buf())]);
+    for dep in dependencies {
+        if !path::is_file_name(Path::new(&dep.name)) {
+            bail!("Dependency name '{}' is not a valid file name", dep.name);
+        }
+        let hash = checksum::digest(dep.dir.to_string_lossy().as_bytes());
+        let output = target_dir.join("deps").join(format!("{}-{}", dep.name, &hash[..16]));
+        outputs.insert(dep.dir.clone(), output);
+    }
+
+    Ok(outputs)
+}
+
+/// Returns the canonical directory of a dependency declared by the project in `dir`.
+pub fn dependency_dir(
+    dir: &Path,
+    name: &str,
+    dependency: &Dependency,
+    sources: &Sources,
+) -> Result<PathBuf> {
+    let path = match (&dependency.path, &dependency.version) {
+        (Some(path), None) => dir.join(path),
+        (None, Some(_)) => {
+            return sources
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("Dependency '{}' has not been fetched", name));
+        }
+        _ => bail!("Dependency '{}' must specify exactly one of 'path' or 'version'", name),
+    };
+
+    if !path.join("hummanta.toml").is_file() {
+        bail!("Dependency '{}' is not a Hummanta project: {}", name, path.display());
+    }
+
+    path.canonicalize().context(format!("Failed to resolve dependency '{name}'"))
+}
+
+/// Depth-first traversal state.
+struct Resolver<'a> {
+    /// Source directories of registry dependencies.
+    sources: &'a Sources,
+    /// The download cache holding base manifests named by URL.
+    cache: &'a Path,
+    /// Projects on the current path, used to detect cycles.
+    stack: Vec<PathBuf>,
+    /// Projects already resolved.
+    done: HashSet<PathBuf>,
+    /// Resolved projects in topological order.
+    order: Vec<Resolved>,
+}
+
+impl Resolver<'_> {
+    fn visit(&mut self, dir: &Path, manifest: &ProjectManifest) -> Result<()> {
+        for (name, dependency) in &manifest.dependencies {
+            let dep_dir = dependency_dir(dir, name, dependency, self.sources)?;
+
+            if self.stack.contains(&dep_dir) {
+                bail!("Cyclic dependency detected on '{}' ({})", name, dep_dir.display());
+            }
+            if self.done.contains(&dep_dir) {
+                continue;
+            }
+
+            let dep_manifest = manifest::load_cached(self.cache, &dep_dir.join("hummanta.toml"))
+                .context(format!("Failed to load manifest of dependency '{name}'"))?;
+
+            self.stack.push(dep_dir.clone());
+            self.visit(&dep_dir, &dep_manifest)?;
+            self.stack.pop();
+
+            self.done.insert(dep_dir.clone());
+            self.order.push(Resolved { name: name.clone(), dir: dep_dir, manifest: dep_manifest });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn project(root: &Path, name: &str, deps: &[&str]) {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut manifest =
+            String::from("language = \"sol\"\nextension = \"sol\"\n\n[dependencies]\n");
+        for dep in deps {
+            manifest.push_str(&format!("{dep} = {{ path = \"../{dep}\" }}\n"));
+        }
+        fs::write(dir.join("hummanta.toml"), manifest).unwrap();
+    }
+
+    fn load(root: &Path, name: &str) -> ProjectManifest {
+        ProjectManifest::load(root.join(name).join("hummanta.toml")).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_topological_order() {
+        let root = tempdir().unwrap();
+        project(root.path(), "app", &["net", "core"]);
+        project(root.path(), "net", &["core"]);
+        project(root.path(), "core", &[]);
+
+        let app = load(root.path(), "app");
+        let order = resolve(&root.path().join("app"), &app, &Sources::new(), root.path()).unwrap();
+        let names: Vec<_> = order.iter().map(|d| d.name.as_str()).collect();
+
+        assert_eq!(names, vec!["core", "net"]);
+    }
+
+    #[test]
+    fn test_resolve_cycle() {
+        let root = tempdir().unwrap();
+        project(root.path(), "app", &["lib"]);
+        project(root.path(), "lib", &["app"]);
+
+        let app = load(root.path(), "app");
+        let result = resolve(&root.path().join("app"), &app, &Sources::new(), root.path());
+
+        assert!(result.unwrap_err().to_string().contains("Cyclic dependency"));
+    }
+
+    #[test]
+    fn test_outputs() {
+        let root = tempdir().unwrap();
+        project(root.path(), "app", &["core"]);
+        project(root.path(), "core", &[]);
+
+        let dir = root.path().join("app");
+        let order = resolve(&dir, &load(root.path(), "app"), &Sources::new(), root.path()).unwrap();
+        let target_dir = dir.join("target").join("wasm32");
+        let outputs = outputs(&dir, &target_dir, &order).unwrap();
+
+        assert_eq!(outputs[&dir], target_dir);
+        let core = &outputs[&root.path().join("core").canonicalize().unwrap()];
+        assert!(core.starts_with(target_dir.join("deps")));
+        assert!(core.file_name().unwrap().to_string_lossy().starts_with("core-"));
+
+        // Dependency names become directory names, so they cannot be paths
+        let escaping = Resolved { name: "../core".into(), ..order.into_iter().next().unwrap() };
+        assert!(super::outputs(&dir, &target_dir, &[escaping]).is_err());
+    }
+
+    #[test]
+    fn test_resolve_missing() {
+        let root = tempdir().unwrap();
+        project(root.path(), "app", &["missing"]);
+
+        let app = load(root.path(), "app");
+        assert!(resolve(&root.path().join("app"), &app, &Sources::new(), root.path()).is_err());
+    }
+}
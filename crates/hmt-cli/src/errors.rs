@@ -12,4 +12,144 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::error::Error as StdError;
+
+use clap::ValueEnum;
+use hmt_error::{Category, Code, Diagnostic, ErrorReport, Help};
+use hmt_fetcher::errors::FetchError;
+use hmt_manifest::ManifestError;
+use hmt_registry::error::RegistryError;
+use hmt_utils::disk::InsufficientSpace;
+use thiserror::Error;
+
 pub use anyhow::Result;
+
+/// Failures of the command line with known remediation.
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("Could not find 'hummanta.toml'. Please run `hummanta init` first.")]
+    ManifestNotFound,
+
+    #[error("No target specified. Either set 'target' in hummanta.toml or use --target flag")]
+    TargetNotSpecified,
+
+    #[error("{role} for '{target}' not found")]
+    UnsupportedTarget { role: &'static str, target: String },
+
+    #[error("{role} for '{language}' not found")]
+    UnsupportedLanguage { role: &'static str, language: String },
+
+    #[error("{count} warning(s) reported while warnings are denied")]
+    DeniedWarnings { count: usize },
+}
+
+impl Diagnostic for CliError {
+    fn code(&self) -> Code {
+        match self {
+            CliError::ManifestNotFound => Code::new("cli.manifest-not-found", Category::NotFound),
+            CliError::TargetNotSpecified => Code::new("cli.target-not-specified", Category::Usage),
+            CliError::UnsupportedTarget { .. } => {
+                Code::new("cli.unsupported-target", Category::NotFound)
+            }
+            CliError::UnsupportedLanguage { .. } => {
+                Code::new("cli.unsupported-language", Category::NotFound)
+            }
+            CliError::DeniedWarnings { .. } => Code::new("cli.denied-warnings", Category::Denied),
+        }
+    }
+
+    fn help(&self) -> Option<Help> {
+        let help = match self {
+            CliError::ManifestNotFound => {
+                Help::new("commands that build a project run inside its directory")
+                    .step("change into the project directory")
+                    .step("run `hummanta init` to create a new project")
+                    .page("missing-manifest")
+            }
+            CliError::TargetNotSpecified => Help::new("builds need a target platform")
+                .step("set `target` under [project] in hummanta.toml")
+                .step("or pass --target, e.g. `--target evm`")
+                .page("target"),
+            CliError::UnsupportedTarget { target, .. } => {
+                Help::new(format!("no installed target package supports '{target}'"))
+                    .step(format!("install it with `hummanta target add {target}`"))
+                    .step("check the spelling of the target")
+                    .page("unsupported-target")
+            }
+            CliError::UnsupportedLanguage { language, .. } => {
+                Help::new(format!("no installed toolchain supports '{language}'"))
+                    .step(format!("install it with `hummanta toolchain add {language}`"))
+                    .page("unsupported-language")
+            }
+            CliError::DeniedWarnings { .. } => {
+                Help::new("--deny-warnings or `warnings.deny` fails commands reporting warnings")
+                    .step("fix the cause of each warning")
+                    .step("or allow its id with `--allow <id>` or `warnings.allow`")
+                    .page("warnings")
+            }
+        };
+        Some(help)
+    }
+}
+
+/// How a failed command reports its error on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ErrorFormat {
+    /// The message, followed by a `help:` block when known.
+    #[default]
+    Text,
+    /// An [`ErrorReport`] as a single line of JSON.
+    Json,
+}
+
+/// Returns the diagnostic of an error in a chain, if its type is known.
+fn diagnose<'a>(cause: &'a (dyn StdError + 'static)) -> Option<&'a dyn Diagnostic> {
+    if let Some(e) = cause.downcast_ref::<CliError>() {
+        Some(e)
+    } else if let Some(e) = cause.downcast_ref::<RegistryError>() {
+        Some(e)
+    } else if let Some(e) = cause.downcast_ref::<FetchError>() {
+        Some(e)
+    } else if let Some(e) = cause.downcast_ref::<ManifestError>() {
+        Some(e)
+    } else if let Some(e) = cause.downcast_ref::<InsufficientSpace>() {
+        Some(e)
+    } else {
+        None
+    }
+}
+
+/// Captures a failed command's error with the code and help of the
+/// innermost known error in its chain.
+pub fn report(err: &anyhow::Error) -> ErrorReport {
+    ErrorReport::new(err.chain(), diagnose)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let err = anyhow::Error::from(CliError::UnsupportedTarget {
+            role: "Linker",
+            target: "evm".to_string(),
+        });
+        let report = self::report(&err);
+        assert_eq!(report.code, "cli.unsupported-target");
+        assert_eq!(report.exit_code(), 66);
+        assert!(report.help.unwrap().to_string().contains("hummanta target add evm"));
+
+        let err: anyhow::Error =
+            RegistryError::FetchError(FetchError::HashMismatch("abc".into())).into();
+        let err = err.context("Failed to install solidity-frontend");
+        let report = self::report(&err);
+        assert_eq!(report.code, "fetch.hash-mismatch");
+        assert_eq!(report.message, "Failed to install solidity-frontend");
+        assert_eq!(report.causes.len(), 2);
+
+        let report = self::report(&anyhow::anyhow!("unknown"));
+        assert_eq!((report.code.as_str(), report.exit_code()), ("unknown", 1));
+        assert!(report.help.is_none());
+    }
+}
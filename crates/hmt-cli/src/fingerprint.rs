@@ -0,0 +1,298 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fingerprints of tool invocations, and the cache of build outputs keyed
+//! by them.
+//!
+//! A fingerprint covers the package and version of the tool, the hash of
+//! its binary, the versions of the runtimes it uses, the contents of the
+//! inputs and the arguments. Updating or relinking a toolchain therefore
+//! changes the fingerprint of everything it built, and the outputs are
+//! rebuilt instead of reused.
+
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context as _;
+use hmt_manifest::{DomainMap, Output, OutputManifest, PackageEntry};
+use hmt_utils::checksum;
+
+use crate::{errors::Result, remote_cache::RemoteCache, utils};
+
+/// The file every build records its outputs in.
+pub const OUTPUTS_FILE: &str = "outputs.json";
+
+/// A tool invoked by a build step.
+pub struct Tool {
+    /// The path of the tool binary.
+    pub path: PathBuf,
+    /// The package providing the tool, as `<name>@<version>`.
+    pub package: String,
+    /// The SHA-256 hash of the tool binary.
+    hash: String,
+    /// The installed runtimes the tool uses.
+    runtimes: Vec<PackageEntry>,
+}
+
+impl Tool {
+    /// Identifies the tool at `path`, hashing its binary.
+    pub fn new(path: &Path, name: &str, version: &str) -> Result<Self> {
+        let data = fs::read(path).context(format!("Failed to read tool {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            package: package(name, version),
+            hash: checksum::digest(&data),
+            runtimes: Vec::new(),
+        })
+    }
+
+    /// Sets the installed runtimes the tool uses.
+    pub fn runtimes(mut self, runtimes: Vec<PackageEntry>) -> Self {
+        self.runtimes = runtimes;
+        self
+    }
+
+    /// Returns the variables exposing the runtimes of the tool to its
+    /// invocations.
+    pub fn envs(&self) -> Vec<(String, PathBuf)> {
+        utils::runtime_envs(&self.runtimes)
+    }
+}
+
+/// Formats the package of a tool as recorded in outputs.
+pub fn package(name: &str, version: &str) -> String {
+    format!("{name}@{version}")
+}
+
+/// Computes the fingerprint of a tool invocation.
+pub struct Fingerprint(Vec<u8>);
+
+impl Fingerprint {
+    /// Starts a fingerprint of an invocation of `tool`.
+    pub fn new(tool: &Tool) -> Self {
+        let fingerprint = Self(Vec::new()).part(tool.package.as_bytes()).part(tool.hash.as_bytes());
+        tool.runtimes.iter().fold(fingerprint, |fingerprint, runtime| {
+            fingerprint.arg(package(&runtime.name, &runtime.entry.version))
+        })
+    }
+
+    /// Adds the contents of an input file.
+    pub fn file(self, path: &Path) -> Result<Self> {
+        let data = fs::read(path).context(format!("Failed to read {}", path.display()))?;
+        Ok(self.part(checksum::digest(&data).as_bytes()))
+    }
+
+    /// Adds an argument or other value the output depends on.
+    pub fn arg(self, arg: impl AsRef<OsStr>) -> Self {
+        self.part(arg.as_ref().as_encoded_bytes())
+    }
+
+    /// Returns the fingerprint.
+    pub fn finish(self) -> String {
+        checksum::digest(&self.0)
+    }
+
+    /// Appends a length-prefixed part, so parts never run into each other.
+    fn part(mut self, bytes: &[u8]) -> Self {
+        self.0.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        self.0.extend_from_slice(bytes);
+        self
+    }
+}
+
+/// The outputs of the previous build of a target directory, reused when
+/// an invocation has the same fingerprint, backed by an optional remote
+/// cache.
+#[derive(Default)]
+pub struct BuildCache {
+    outputs: HashMap<PathBuf, Output>,
+    remote: Option<Arc<RemoteCache>>,
+}
+
+impl BuildCache {
+    /// Loads the outputs recorded in a target directory; the cache is empty
+    /// if none were recorded.
+    pub fn load(target_dir: &Path) -> Self {
+        let outputs = OutputManifest::load(target_dir.join(OUTPUTS_FILE)).unwrap_or_default();
+        let outputs = outputs.outputs.into_iter().map(|o| (o.path.clone(), o)).collect();
+        Self { outputs, remote: None }
+    }
+
+    /// Falls back to `remote` for outputs missing locally.
+    pub fn with_remote(mut self, remote: Option<Arc<RemoteCache>>) -> Self {
+        self.remote = remote;
+        self
+    }
+
+    /// Returns the remote cache, if any.
+    pub fn remote(&self) -> Option<Arc<RemoteCache>> {
+        self.remote.clone()
+    }
+
+    /// Returns the previous output at `path` if it was built with the same
+    /// fingerprint and is unchanged since.
+    pub fn get(&self, path: &Path, fingerprint: &str) -> Option<Output> {
+        let output = self.outputs.get(path)?;
+        if output.fingerprint.as_deref() != Some(fingerprint) {
+            return None;
+        }
+
+        let hash = checksum::digest_file(path).ok()?;
+        (hash == output.hash).then(|| output.clone())
+    }
+}
+
+/// Returns the packages installed in `domains`, as `<name>@<version>`.
+pub fn installed(domains: Option<&DomainMap>) -> HashSet<String> {
+    domains
+        .into_iter()
+        .flatten()
+        .flat_map(|(_, categories)| categories.values())
+        .flat_map(|packages| packages.iter())
+        .map(|(name, entry)| package(name, &entry.version))
+        .collect()
+}
+
+/// Removes the outputs of a target directory produced by packages not in
+/// `installed`, returning the removed paths.
+///
+/// Outputs recorded without their package are kept.
+pub fn remove_stale(target_dir: &Path, installed: &HashSet<String>) -> Result<Vec<PathBuf>> {
+    let path = target_dir.join(OUTPUTS_FILE);
+    let Ok(mut outputs) = OutputManifest::load(&path) else {
+        return Ok(Vec::new());
+    };
+
+    let (stale, kept): (Vec<_>, Vec<_>) = outputs
+        .outputs
+        .into_iter()
+        .partition(|o| o.package.as_ref().is_some_and(|p| !installed.contains(p)));
+    outputs.outputs = kept;
+
+    for output in &stale {
+        match fs::remove_file(&output.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).context(format!("Failed to remove {}", output.path.display()));
+            }
+            _ => {}
+        }
+    }
+    if !stale.is_empty() {
+        outputs.save(&path).context(format!("Failed to write {}", path.display()))?;
+    }
+
+    Ok(stale.into_iter().map(|o| o.path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use hmt_manifest::{Entry, OutputKind};
+
+    use super::*;
+
+    fn tool(dir: &Path, version: &str, contents: &str) -> Tool {
+        let path = dir.join("frontend");
+        fs::write(&path, contents).unwrap();
+        Tool::new(&path, "solidity-frontend", version).unwrap()
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("main.sol");
+        fs::write(&input, "contract A {}").unwrap();
+
+        let fingerprint = |tool: &Tool| {
+            Fingerprint::new(tool).file(&input).unwrap().arg("--feature").arg("net").finish()
+        };
+        let base = fingerprint(&tool(dir.path(), "v1.0.0", "binary"));
+        assert_eq!(fingerprint(&tool(dir.path(), "v1.0.0", "binary")), base);
+
+        // Updating or relinking the tool invalidates its outputs
+        assert_ne!(fingerprint(&tool(dir.path(), "v1.1.0", "binary")), base);
+        assert_ne!(fingerprint(&tool(dir.path(), "v1.0.0", "rebuilt")), base);
+
+        let tool = tool(dir.path(), "v1.0.0", "binary");
+        fs::write(&input, "contract B {}").unwrap();
+        assert_ne!(fingerprint(&tool), base);
+        let split = |a: &str, b: &str| Fingerprint::new(&tool).arg(a).arg(b).finish();
+        assert_ne!(split("ab", "c"), split("a", "bc"));
+
+        // So does updating a runtime it uses
+        let base = fingerprint(&tool);
+        let runtime = |version: &str| {
+            let entry = Entry::new(version.into(), None, dir.path().join("llvm-runtime"));
+            vec![PackageEntry::new("llvm-runtime".into(), entry)]
+        };
+        let tool = tool.runtimes(runtime("v17.0.0"));
+        let with_runtime = fingerprint(&tool);
+        assert_ne!(with_runtime, base);
+        assert_ne!(fingerprint(&tool.runtimes(runtime("v17.0.1"))), with_runtime);
+    }
+
+    #[test]
+    fn test_build_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.clif");
+        fs::write(&path, "clif").unwrap();
+
+        let mut outputs = OutputManifest::new("x86_64-unknown-linux-gnu");
+        let hash = checksum::digest(b"clif");
+        let output = Output::new(OutputKind::Ir, path.clone(), "main.sol".into(), hash);
+        outputs.push(output.fingerprint("abc".into()));
+        outputs.save(dir.path().join(OUTPUTS_FILE)).unwrap();
+
+        let cache = BuildCache::load(dir.path());
+        assert!(cache.get(&path, "abc").is_some());
+        assert!(cache.get(&path, "def").is_none());
+
+        // Outputs changed since the build are rebuilt
+        fs::write(&path, "edited").unwrap();
+        assert!(cache.get(&path, "abc").is_none());
+        assert!(BuildCache::load(&dir.path().join("missing")).get(&path, "abc").is_none());
+    }
+
+    #[test]
+    fn test_remove_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut outputs = OutputManifest::new("x86_64-unknown-linux-gnu");
+        let files =
+            [("old.o", Some("backend@v1")), ("new.o", Some("backend@v2")), ("plugin.o", None)];
+        for (file, package) in files {
+            let path = dir.path().join(file);
+            fs::write(&path, file).unwrap();
+            let output = Output::new(OutputKind::Object, path, "main.clif".into(), "".into());
+            outputs.push(match package {
+                Some(package) => output.package(package.into()),
+                None => output,
+            });
+        }
+        outputs.save(dir.path().join(OUTPUTS_FILE)).unwrap();
+
+        let installed = HashSet::from(["backend@v2".to_string()]);
+        let removed = remove_stale(dir.path(), &installed).unwrap();
+        assert_eq!(removed, [dir.path().join("old.o")]);
+        assert!(!dir.path().join("old.o").exists());
+        assert!(dir.path().join("new.o").exists());
+
+        let outputs = OutputManifest::load(dir.path().join(OUTPUTS_FILE)).unwrap();
+        assert_eq!(outputs.outputs.len(), 2);
+        assert!(remove_stale(dir.path(), &installed).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::{bail, Context as _};
+use tracing::info;
+
+use crate::errors::Result;
+
+/// The lock file guarding the target directory of a project.
+const LOCK_FILE: &str = ".build-lock";
+
+/// An exclusive lock on the target directory of a project, held for the
+/// duration of a build.
+///
+/// The lock is released when dropped, or by the OS when the process exits,
+/// so a crashed build never leaves the project locked. The lock file holds
+/// the PID of the build holding it, for reporting only.
+pub struct BuildLock {
+    _file: File,
+}
+
+impl BuildLock {
+    /// Locks the target directory, waiting for another build holding it to
+    /// finish, or failing right away if `wait` is false.
+    pub async fn acquire(target_dir: &Path, wait: bool) -> Result<Self> {
+        fs::create_dir_all(target_dir).context("Failed to create target directory")?;
+        let path = target_dir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .context(format!("Failed to open build lock {}", path.display()))?;
+
+        let mut file = match file.try_lock() {
+            Ok(()) => file,
+            Err(TryLockError::WouldBlock) => {
+                let holder = holder(&path);
+                if !wait {
+                    bail!("Another build is running{holder} in {}", target_dir.display());
+                }
+
+                info!("Waiting for another build{holder} to finish");
+                tokio::task::spawn_blocking(move || file.lock().map(|_| file))
+                    .await?
+                    .context("Failed to lock the target directory")?
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).context("Failed to lock the target directory");
+            }
+        };
+
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+/// Describes the build holding the lock, e.g. " (pid 42)", if known.
+fn holder(path: &Path) -> String {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok())
+        .map(|pid| format!(" (pid {pid})"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_build_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let target_dir = dir.path().join("target");
+
+        let lock = BuildLock::acquire(&target_dir, false).await.unwrap();
+        let pid = fs::read_to_string(target_dir.join(LOCK_FILE)).unwrap();
+        assert_eq!(pid, std::process::id().to_string());
+
+        let err = BuildLock::acquire(&target_dir, false).await.err().unwrap();
+        assert!(err.to_string().contains(&format!("(pid {pid})")));
+
+        drop(lock);
+        assert!(BuildLock::acquire(&target_dir, false).await.is_ok());
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use hmt_manifest::{OutputKind, OutputManifest};
+
+/// The formats a build graph can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz DOT
+    Dot,
+    /// JSON, for tooling
+    Json,
+}
+
+impl GraphFormat {
+    /// The file extension of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            GraphFormat::Dot => "dot",
+            GraphFormat::Json => "json",
+        }
+    }
+}
+
+/// The kind of a node in the build graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Source,
+    Intermediate,
+    Ir,
+    Object,
+    Executable,
+    Tool,
+}
+
+impl From<OutputKind> for NodeKind {
+    fn from(kind: OutputKind) -> Self {
+        match kind {
+            OutputKind::Intermediate => NodeKind::Intermediate,
+            OutputKind::Ir => NodeKind::Ir,
+            OutputKind::Object => NodeKind::Object,
+            OutputKind::Executable => NodeKind::Executable,
+        }
+    }
+}
+
+/// A file taking part in the build.
+#[derive(Debug, Serialize)]
+pub struct Node {
+    pub kind: NodeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// An input relation: `from` was used to produce `to`.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+}
+
+/// The dependency graph of a build, from sources through intermediate
+/// artifacts to executables, including the tools that produced them.
+#[derive(Debug, Default, Serialize)]
+pub struct Graph {
+    /// Nodes keyed by path.
+    pub nodes: BTreeMap<String, Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// Builds the graph from the outputs recorded by a build.
+    pub fn from_outputs(outputs: &OutputManifest) -> Self {
+        let mut graph = Graph::default();
+
+        // Register outputs first, so their inputs are not taken for sources
+        for output in &outputs.outputs {
+            let node = Node { kind: output.kind.into(), hash: Some(output.hash.clone()) };
+            graph.nodes.insert(id(&output.path), node);
+        }
+
+        for output in &outputs.outputs {
+            let to = id(&output.path);
+            for input in std::iter::once(&output.source).chain(&output.inputs) {
+                graph.node(input, NodeKind::Source);
+                graph.edge(id(input), to.clone());
+            }
+            if let Some(tool) = &output.tool {
+                graph.node(tool, NodeKind::Tool);
+                graph.edge(id(tool), to.clone());
+            }
+        }
+
+        graph
+    }
+
+    /// Renders the graph in the given format.
+    pub fn render(&self, format: GraphFormat) -> serde_json::Result<String> {
+        match format {
+            GraphFormat::Dot => Ok(self.to_dot()),
+            GraphFormat::Json => serde_json::to_string_pretty(self),
+        }
+    }
+
+    /// Renders the graph as Graphviz DOT.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph build {\n    rankdir=LR;\n");
+        for (id, node) in &self.nodes {
+            let shape = match node.kind {
+                NodeKind::Tool => "component",
+                NodeKind::Source => "note",
+                NodeKind::Executable => "doubleoctagon",
+                NodeKind::Intermediate | NodeKind::Ir | NodeKind::Object => "box",
+            };
+            let label =
+                Path::new(id).file_name().map_or(id.clone(), |n| n.to_string_lossy().into());
+            let _ = writeln!(dot, "    {:?} [label={:?}, shape={}];", id, label, shape);
+        }
+        for edge in &self.edges {
+            let style = match self.nodes.get(&edge.from).map(|n| n.kind) {
+                Some(NodeKind::Tool) => " [style=dashed]",
+                _ => "",
+            };
+            let _ = writeln!(dot, "    {:?} -> {:?}{};", edge.from, edge.to, style);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Adds a node unless one with the same path exists.
+    fn node(&mut self, path: &Path, kind: NodeKind) {
+        self.nodes.entry(id(path)).or_insert(Node { kind, hash: None });
+    }
+
+    /// Adds an edge unless it exists.
+    fn edge(&mut self, from: String, to: String) {
+        let edge = Edge { from, to };
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+}
+
+/// The node ID of a path.
+fn id(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmt_manifest::Output;
+
+    fn outputs() -> OutputManifest {
+        let mut outputs = OutputManifest::new("x86_64-unknown-linux-gnu");
+        outputs.push(
+            Output::new(OutputKind::Ir, "main.clif".into(), "main.sol".into(), "a".into())
+                .tool("frontend".into()),
+        );
+        outputs.push(
+            Output::new(OutputKind::Ir, "lib.clif".into(), "lib.sol".into(), "b".into())
+                .tool("frontend".into()),
+        );
+        outputs.push(
+            Output::new(OutputKind::Object, "main.o".into(), "main.clif".into(), "c".into())
+                .tool("backend".into()),
+        );
+        outputs.push(
+            Output::new(OutputKind::Object, "lib.o".into(), "lib.clif".into(), "d".into())
+                .tool("backend".into()),
+        );
+        outputs.push(
+            Output::new(OutputKind::Executable, "app".into(), "main.o".into(), "e".into())
+                .inputs(vec!["lib.o".into()])
+                .tool("linker".into()),
+        );
+        outputs
+    }
+
+    #[test]
+    fn test_from_outputs() {
+        let graph = Graph::from_outputs(&outputs());
+
+        assert_eq!(graph.nodes["main.sol"].kind, NodeKind::Source);
+        assert_eq!(graph.nodes["main.clif"].kind, NodeKind::Ir);
+        assert_eq!(graph.nodes["app"].kind, NodeKind::Executable);
+        assert_eq!(graph.nodes["linker"].kind, NodeKind::Tool);
+        assert_eq!(graph.nodes["app"].hash.as_deref(), Some("e"));
+
+        let inputs: Vec<_> =
+            graph.edges.iter().filter(|e| e.to == "app").map(|e| e.from.as_str()).collect();
+        assert_eq!(inputs, ["main.o", "lib.o", "linker"]);
+
+        // Tools used for several outputs appear once
+        let frontends = graph.edges.iter().filter(|e| e.from == "frontend").count();
+        assert_eq!(frontends, 2);
+        assert_eq!(graph.nodes.len(), 10);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = Graph::from_outputs(&outputs()).to_dot();
+        assert!(dot.starts_with("digraph build {"));
+        assert!(dot.contains(r#""main.o" -> "app";"#));
+        assert!(dot.contains(r#""linker" -> "app" [style=dashed];"#));
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Concurrent execution of tool invocations within a build phase.
+//!
+//! Every phase runs at most its configured number of invocations at once.
+//! In adaptive mode the limit starts at the number of CPUs and follows the
+//! pressure stall information of the system: it shrinks while tasks wait
+//! for CPU or memory, and grows back up to the maximum while they do not.
+//! IO-heavy phases thereby run more invocations than CPU-heavy ones.
+//! Systems without pressure information run at the maximum.
+
+use std::{
+    fs,
+    future::Future,
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use tokio::task::JoinSet;
+
+use crate::errors::Result;
+
+/// A tool invocation, resolving to its output.
+pub type Job<T> = Pin<Box<dyn Future<Output = Result<T>> + Send>>;
+
+/// How often the pressure is sampled at most.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The share of time some task waited for CPU above which the limit shrinks.
+const CPU_HIGH: f64 = 0.5;
+/// The share of time some task waited for CPU below which the limit grows.
+const CPU_LOW: f64 = 0.2;
+/// The share of time some task waited for memory above which the limit halves.
+const MEMORY_HIGH: f64 = 0.1;
+/// The share of time some task waited for memory below which the limit grows.
+const MEMORY_LOW: f64 = 0.02;
+
+/// Returns the number of CPUs available to the process.
+pub fn cpus() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Runs the invocations of a phase, at most `limit` at a time.
+pub struct Scheduler {
+    max: usize,
+    limit: usize,
+    sampler: Option<Sampler>,
+}
+
+impl Scheduler {
+    /// Creates a scheduler running at most `max` invocations at once.
+    pub fn new(max: usize, adaptive: bool) -> Self {
+        let sampler = adaptive.then(Sampler::new).flatten();
+        let limit = match sampler {
+            Some(_) => max.min(cpus()),
+            None => max,
+        };
+        Self { max, limit, sampler }
+    }
+
+    /// Runs the jobs, calling `done` as each finishes, and returns their
+    /// outputs in the order of the jobs. The remaining jobs are cancelled
+    /// on the first failure.
+    pub async fn run<T: Send + 'static>(
+        &mut self,
+        jobs: Vec<Job<T>>,
+        mut done: impl FnMut(&T),
+    ) -> Result<Vec<T>> {
+        let mut results: Vec<Option<T>> = jobs.iter().map(|_| None).collect();
+        let mut pending = jobs.into_iter().enumerate();
+        let mut running = JoinSet::new();
+
+        loop {
+            let limit = self.limit();
+            while running.len() < limit {
+                let Some((index, job)) = pending.next() else { break };
+                running.spawn(async move { (index, job.await) });
+            }
+
+            let Some(finished) = running.join_next().await else { break };
+            let (index, output) = finished.map_err(|e| anyhow!("Build job failed: {e}"))?;
+            let output = output?;
+            done(&output);
+            results[index] = Some(output);
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Returns the current limit, adjusting it to the pressure since the
+    /// previous sample.
+    fn limit(&mut self) -> usize {
+        if let Some(pressure) = self.sampler.as_mut().and_then(Sampler::sample) {
+            self.limit = adjust(self.limit, self.max, pressure);
+        }
+        self.limit
+    }
+}
+
+/// The share of time some task stalled on a resource.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Pressure {
+    cpu: f64,
+    memory: f64,
+}
+
+/// Returns the next limit under the given pressure: halved while memory
+/// is short, decremented while CPUs are saturated, and incremented while
+/// neither is.
+fn adjust(limit: usize, max: usize, pressure: Pressure) -> usize {
+    if pressure.memory > MEMORY_HIGH {
+        (limit / 2).max(1)
+    } else if pressure.cpu > CPU_HIGH {
+        limit.saturating_sub(1).max(1)
+    } else if pressure.cpu < CPU_LOW && pressure.memory < MEMORY_LOW {
+        (limit + 1).min(max)
+    } else {
+        limit
+    }
+}
+
+/// Samples the pressure stall information of the system.
+struct Sampler {
+    at: Instant,
+    cpu: u64,
+    memory: u64,
+}
+
+impl Sampler {
+    /// Starts sampling, or returns `None` if the system does not report
+    /// pressure.
+    fn new() -> Option<Self> {
+        Some(Self { at: Instant::now(), cpu: stall("cpu")?, memory: stall("memory")? })
+    }
+
+    /// Returns the pressure since the previous sample, or `None` if the
+    /// previous sample is too recent.
+    fn sample(&mut self) -> Option<Pressure> {
+        let elapsed = self.at.elapsed();
+        if elapsed < SAMPLE_INTERVAL {
+            return None;
+        }
+
+        let (cpu, memory) = (stall("cpu")?, stall("memory")?);
+        let micros = elapsed.as_micros() as f64;
+        let pressure = Pressure {
+            cpu: cpu.saturating_sub(self.cpu) as f64 / micros,
+            memory: memory.saturating_sub(self.memory) as f64 / micros,
+        };
+        *self = Self { at: Instant::now(), cpu, memory };
+        Some(pressure)
+    }
+}
+
+/// Reads the total microseconds some task stalled on a resource.
+fn stall(resource: &str) -> Option<u64> {
+    parse_stall(&fs::read_to_string(format!("/proc/pressure/{resource}")).ok()?)
+}
+
+/// Parses the `total` of the `some` line of a pressure file, e.g.
+/// `some avg10=0.00 avg60=0.00 avg300=0.00 total=12345`.
+fn parse_stall(content: &str) -> Option<u64> {
+    let line = content.lines().find(|line| line.starts_with("some "))?;
+    line.split_whitespace().find_map(|field| field.strip_prefix("total="))?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::JobsConfig, plugin::Phase};
+
+    use super::*;
+
+    fn pressure(cpu: f64, memory: f64) -> Pressure {
+        Pressure { cpu, memory }
+    }
+
+    #[test]
+    fn test_adjust() {
+        assert_eq!(adjust(8, 16, pressure(0.0, 0.0)), 9);
+        assert_eq!(adjust(16, 16, pressure(0.0, 0.0)), 16);
+        assert_eq!(adjust(8, 16, pressure(0.3, 0.0)), 8);
+        assert_eq!(adjust(8, 16, pressure(0.8, 0.0)), 7);
+        assert_eq!(adjust(8, 16, pressure(0.0, 0.5)), 4);
+        assert_eq!(adjust(1, 16, pressure(0.9, 0.5)), 1);
+    }
+
+    #[test]
+    fn test_parse_stall() {
+        let content = "some avg10=1.50 avg60=0.20 avg300=0.00 total=123456\n\
+                       full avg10=0.00 avg60=0.00 avg300=0.00 total=42\n";
+        assert_eq!(parse_stall(content), Some(123456));
+        assert_eq!(parse_stall("full avg10=0.00 total=42"), None);
+        assert_eq!(parse_stall(""), None);
+    }
+
+    #[test]
+    fn test_max() {
+        let config = JobsConfig { compile: Some(8), emit: Some(2), adaptive: true };
+        assert_eq!(config.max(Phase::Compile, None), 8);
+        assert_eq!(config.max(Phase::Compile, Some(4)), 4);
+        assert_eq!(config.max(Phase::Emit, Some(4)), 2);
+        assert_eq!(config.max(Phase::Emit, Some(0)), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let jobs: Vec<Job<usize>> = (0..10usize)
+            .map(|i| -> Job<usize> {
+                Box::pin(async move {
+                    tokio::time::sleep(Duration::from_millis(10 - i as u64)).await;
+                    Ok(i)
+                })
+            })
+            .collect();
+
+        let mut finished = 0;
+        let outputs = Scheduler::new(3, false).run(jobs, |_| finished += 1).await.unwrap();
+        assert_eq!(outputs, (0..10).collect::<Vec<_>>());
+        assert_eq!(finished, 10);
+
+        let failing: Vec<Job<usize>> =
+            vec![Box::pin(async { Ok(1) }), Box::pin(async { Err(anyhow!("boom")) })];
+        let error = Scheduler::new(1, false).run(failing, |_| {}).await.unwrap_err();
+        assert_eq!(error.to_string(), "boom");
+    }
+}
@@ -13,9 +13,23 @@
 // limitations under the License.
 
 mod cmd;
+mod completions;
 mod config;
+mod conflict;
 mod context;
+mod coverage;
+mod deps;
 mod errors;
+mod fingerprint;
+mod flock;
+mod graph;
+mod jobs;
+mod manifest;
+mod plugin;
+mod progress;
+mod remote_cache;
+mod reporter;
+mod shell;
 mod utils;
 
 use std::sync::Arc;
@@ -23,23 +37,45 @@ use std::sync::Arc;
 use clap::Parser;
 use cmd::Command;
 use context::Context;
-use errors::Result;
+use errors::{ErrorFormat, Result};
+use progress::ProgressMode;
 use tracing::error;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cmd = Command::parse();
+
     tracing_subscriber::fmt()
         .without_time() // Removes the timestamp
         .with_target(false) // remove the target (hummanta)
+        .with_ansi(cmd.progress.resolve() == ProgressMode::Live)
+        .with_writer(std::io::stderr) // keep stdout for the output of commands
         .init();
 
-    let cmd = Command::parse();
-    let ctx = Context::new(&cmd.registry)?;
+    cmd::remove_stale();
+    let ctx = Arc::new(Context::new(&cmd)?);
 
-    if let Err(err) = cmd.exec(Arc::new(ctx)).await {
-        error!("{}", err);
-        std::process::exit(1);
+    let result = match cmd.exec(ctx.clone()).await {
+        Ok(()) => ctx.check_warnings(),
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        let report = errors::report(&err);
+        match cmd.error_format {
+            ErrorFormat::Text => {
+                error!("{}", err);
+                if let Some(help) = &report.help {
+                    eprintln!("{help}");
+                }
+                error!("Trace ID: {}", ctx.trace_id());
+            }
+            ErrorFormat::Json => eprintln!("{}", serde_json::to_string(&report)?),
+        }
+        std::process::exit(report.exit_code());
     }
 
+    // Keep registry metadata warm for the next command
+    ctx.refresh_metadata().await;
+
     Ok(())
 }
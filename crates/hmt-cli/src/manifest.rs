@@ -0,0 +1,131 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loading of project manifests, merged over the base they extend.
+//!
+//! A base named by path is read relative to the extending manifest. A base
+//! named by URL must be pinned to its SHA-256; it is downloaded once into
+//! the download cache and read from there afterwards, offline included.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context as _};
+use hmt_fetcher::FetchContext;
+use hmt_manifest::{Extends, ProjectManifest};
+use hmt_utils::{checksum, temp::TempFile};
+
+use crate::{context::Context, errors::Result};
+
+/// Loads a project manifest, downloading the base it extends if needed.
+pub async fn load(ctx: &Context, path: &Path) -> Result<ProjectManifest> {
+    let source = read(path)?;
+    if let Some(Extends::Pinned { url, sha256 }) = ProjectManifest::extends_of(&source)? {
+        fetch_base(ctx, &url, &sha256.to_lowercase()).await?;
+    }
+
+    load_cached(&ctx.downloads_dir(), path)
+}
+
+/// Loads a project manifest whose base, if named by URL, is already in
+/// the download cache `cache`.
+pub fn load_cached(cache: &Path, path: &Path) -> Result<ProjectManifest> {
+    let source = read(path)?;
+    let base = match ProjectManifest::extends_of(&source)? {
+        None => return Ok(source.parse()?),
+        Some(Extends::Plain(base)) if base.contains("://") => {
+            bail!("The base manifest {base} must be pinned: extends = {{ url = \"{base}\", sha256 = \"...\" }}")
+        }
+        Some(Extends::Plain(base)) => read(&path.parent().unwrap_or(Path::new(".")).join(base))?,
+        Some(Extends::Pinned { url, sha256 }) => {
+            let sha256 = sha256.to_lowercase();
+            let data = fs::read(cache.join(&sha256))
+                .with_context(|| format!("The base manifest {url} is not downloaded"))?;
+            if checksum::digest(&data) != sha256 {
+                bail!("The cached base manifest {url} does not match its hash {sha256}");
+            }
+            String::from_utf8(data)
+                .with_context(|| format!("The base manifest {url} is not UTF-8"))?
+        }
+    };
+
+    ProjectManifest::with_base(&source, &base)
+        .with_context(|| format!("Failed to merge {} over its base", path.display()))
+}
+
+/// Downloads a pinned base manifest into the download cache, unless it is
+/// already there.
+async fn fetch_base(ctx: &Context, url: &str, sha256: &str) -> Result<()> {
+    let path: PathBuf = ctx.downloads_dir().join(sha256);
+    if path.exists() {
+        return Ok(());
+    }
+    if ctx.offline() {
+        bail!("The base manifest {url} is not downloaded and network access is disabled");
+    }
+
+    let data = ctx.fetcher()?.fetch(&FetchContext::new(url).checksum(sha256)).await?;
+    fs::create_dir_all(ctx.downloads_dir())?;
+    let file = TempFile::new_in(&ctx.downloads_dir())?;
+    fs::write(file.path(), &data)?;
+    file.persist(&path)?;
+    Ok(())
+}
+
+/// Reads a manifest source.
+fn read(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_load_cached() {
+        let dir = tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        fs::create_dir_all(dir.path().join("app")).unwrap();
+        fs::create_dir_all(&cache).unwrap();
+
+        let base = "language = \"solidity\"\nextension = \"sol\"\n";
+        fs::write(dir.path().join("base.toml"), base).unwrap();
+        let path = dir.path().join("app").join("hummanta.toml");
+
+        fs::write(&path, "extends = \"../base.toml\"\nextension = \"yul\"\n").unwrap();
+        let manifest = load_cached(&cache, &path).unwrap();
+        assert_eq!(manifest.project.language, "solidity");
+        assert_eq!(manifest.project.extension, "yul");
+
+        fs::write(&path, "extends = \"https://example.com/base.toml\"\n").unwrap();
+        assert!(load_cached(&cache, &path).unwrap_err().to_string().contains("must be pinned"));
+
+        let hash = checksum::digest(base.as_bytes());
+        let pinned = format!(
+            "extends = {{ url = \"https://example.com/base.toml\", sha256 = \"{hash}\" }}\n"
+        );
+        fs::write(&path, &pinned).unwrap();
+        assert!(load_cached(&cache, &path).unwrap_err().to_string().contains("not downloaded"));
+
+        fs::write(cache.join(&hash), base).unwrap();
+        assert_eq!(load_cached(&cache, &path).unwrap().project.extension, "sol");
+
+        fs::write(cache.join(&hash), "tampered").unwrap();
+        assert!(load_cached(&cache, &path).unwrap_err().to_string().contains("does not match"));
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context as _};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use hmt_manifest::OutputManifest;
+use hmt_utils::{checksum, event::Reporter, process::Process};
+
+use crate::errors::Result;
+
+/// The built-in phases of a build, which steps are ordered against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// Sources compiled to CLIF.
+    Compile,
+    /// CLIF compiled to objects.
+    Emit,
+    /// Objects linked to executables.
+    Link,
+}
+
+/// The project a step runs for.
+pub struct StepContext<'a> {
+    /// The project root directory.
+    pub dir: &'a Path,
+    /// The build output directory.
+    pub target_dir: &'a Path,
+    /// The target platform being built for.
+    pub target: &'a str,
+    /// The environment every tool runs with.
+    pub envs: &'a [(String, String)],
+    /// Receives the events of the build.
+    pub reporter: &'a dyn Reporter,
+}
+
+/// A processor running after one of the built-in phases of a build, e.g. an
+/// optimizer rewriting the CLIF between compile and emit.
+#[async_trait]
+pub trait BuildStep: Send + Sync {
+    /// The unique name of the step.
+    fn name(&self) -> &str;
+
+    /// The built-in phase the step runs after.
+    fn phase(&self) -> Phase;
+
+    /// The steps that must run before this one.
+    fn after(&self) -> &[String] {
+        &[]
+    }
+
+    /// A digest of everything determining the step's behavior, recorded in
+    /// `outputs.json` so changed steps can be told apart.
+    fn fingerprint(&self) -> Result<String>;
+
+    /// Processes the outputs emitted so far.
+    async fn run(&self, ctx: &StepContext<'_>, outputs: &mut OutputManifest) -> Result<()>;
+}
+
+/// A build step implemented by an external binary, declared in the config:
+/// ```toml
+/// [[plugins]]
+/// name = "clif-opt"
+/// path = "/usr/local/bin/clif-opt"
+/// phase = "compile"
+/// args = ["-O2"]
+/// ```
+///
+/// The binary is invoked as `<path> --project-dir <dir> --target-dir <dir>
+/// --target <triple>` followed by `args`, and rewrites the outputs in the target directory in
+/// place. Their hashes are refreshed once it exits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plugin {
+    /// The unique name of the step.
+    pub name: String,
+
+    /// The path of the plugin binary.
+    pub path: PathBuf,
+
+    /// The built-in phase the plugin runs after.
+    pub phase: Phase,
+
+    /// The plugins that must run before this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+
+    /// Extra arguments passed to the binary.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+#[async_trait]
+impl BuildStep for Plugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    fn after(&self) -> &[String] {
+        &self.after
+    }
+
+    fn fingerprint(&self) -> Result<String> {
+        let mut data = fs::read(&self.path).context(format!(
+            "Failed to read plugin '{}' at {}",
+            self.name,
+            self.path.display()
+        ))?;
+        for arg in &self.args {
+            data.push(0);
+            data.extend_from_slice(arg.as_bytes());
+        }
+        Ok(checksum::digest(&data))
+    }
+
+    async fn run(&self, ctx: &StepContext<'_>, outputs: &mut OutputManifest) -> Result<()> {
+        let mut args: Vec<OsString> = vec![
+            "--project-dir".into(),
+            ctx.dir.into(),
+            "--target-dir".into(),
+            ctx.target_dir.into(),
+            "--target".into(),
+            ctx.target.into(),
+        ];
+        args.extend(self.args.iter().map(OsString::from));
+
+        let cmd = Process::new(&self.path)
+            .args(&args)
+            .envs(ctx.envs.iter().cloned())
+            .stream(true)
+            .output()
+            .await?;
+        if !cmd.status.success() {
+            let stderr = String::from_utf8_lossy(&cmd.stderr);
+            bail!("Plugin '{}' failed with status {}:\n{}", self.name, cmd.status, stderr.trim());
+        }
+
+        // The plugin may have rewritten any output
+        for output in &mut outputs.outputs {
+            let data = fs::read(&output.path)
+                .context(format!("Missing build output: {}", output.path.display()))?;
+            output.hash = checksum::digest(&data);
+        }
+
+        Ok(())
+    }
+}
+
+/// The registry of build steps.
+#[derive(Default)]
+pub struct Pipeline {
+    steps: Vec<Box<dyn BuildStep>>,
+}
+
+impl Pipeline {
+    /// Creates a pipeline running the given plugins, checking their ordering
+    /// constraints can be satisfied.
+    pub fn new(plugins: &[Plugin]) -> Result<Self> {
+        let mut pipeline = Self::default();
+        for plugin in plugins {
+            pipeline.register(Box::new(plugin.clone()))?;
+        }
+
+        for phase in [Phase::Compile, Phase::Emit, Phase::Link] {
+            pipeline.steps(phase)?;
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Adds a step to the pipeline.
+    pub fn register(&mut self, step: Box<dyn BuildStep>) -> Result<()> {
+        if self.steps.iter().any(|s| s.name() == step.name()) {
+            bail!("Build step '{}' is registered twice", step.name());
+        }
+        self.steps.push(step);
+        Ok(())
+    }
+
+    /// Returns the steps running after `phase`, in execution order.
+    ///
+    /// A step runs after the steps named in its `after` list. Steps of
+    /// earlier phases always run first, so naming one is allowed, while
+    /// naming a step of a later phase is an error.
+    pub fn steps(&self, phase: Phase) -> Result<Vec<&dyn BuildStep>> {
+        let phases: HashMap<&str, Phase> =
+            self.steps.iter().map(|step| (step.name(), step.phase())).collect();
+
+        let mut pending: Vec<&dyn BuildStep> =
+            self.steps.iter().filter(|step| step.phase() == phase).map(|step| &**step).collect();
+        for step in &pending {
+            for dep in step.after() {
+                match phases.get(dep.as_str()) {
+                    None => bail!("Build step '{}' runs after unknown step '{}'", step.name(), dep),
+                    Some(other) if *other > phase => bail!(
+                        "Build step '{}' cannot run after '{}', which runs in a later phase",
+                        step.name(),
+                        dep
+                    ),
+                    Some(_) => {}
+                }
+            }
+        }
+
+        let mut ordered: Vec<&dyn BuildStep> = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let ready = pending
+                .iter()
+                .position(|step| {
+                    step.after().iter().all(|dep| {
+                        phases[dep.as_str()] < phase || ordered.iter().any(|s| s.name() == dep)
+                    })
+                })
+                .ok_or_else(|| {
+                    let names: Vec<_> = pending.iter().map(|step| step.name()).collect();
+                    anyhow!("Build steps {} have cyclic ordering constraints", names.join(", "))
+                })?;
+            ordered.push(pending.remove(ready));
+        }
+
+        Ok(ordered)
+    }
+
+    /// Runs the steps registered after `phase`, recording their fingerprints.
+    pub async fn run(
+        &self,
+        phase: Phase,
+        ctx: &StepContext<'_>,
+        outputs: &mut OutputManifest,
+    ) -> Result<()> {
+        for step in self.steps(phase)? {
+            ctx.reporter.info(format!("Running build step '{}'", step.name()));
+            let fingerprint = step.fingerprint()?;
+            step.run(ctx, outputs).await?;
+            outputs.steps.insert(step.name().to_string(), fingerprint);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(name: &str, phase: Phase, after: &[&str]) -> Plugin {
+        Plugin {
+            name: name.to_string(),
+            path: PathBuf::from(name),
+            phase,
+            after: after.iter().map(|s| s.to_string()).collect(),
+            args: Vec::new(),
+        }
+    }
+
+    fn names(steps: Vec<&dyn BuildStep>) -> Vec<&str> {
+        steps.into_iter().map(|step| step.name()).collect()
+    }
+
+    #[test]
+    fn test_pipeline_order() {
+        let pipeline = Pipeline::new(&[
+            plugin("strip", Phase::Compile, &["opt", "lint"]),
+            plugin("opt", Phase::Compile, &[]),
+            plugin("lint", Phase::Compile, &["opt"]),
+            plugin("sign", Phase::Link, &["lint"]),
+        ])
+        .unwrap();
+
+        assert_eq!(names(pipeline.steps(Phase::Compile).unwrap()), ["opt", "lint", "strip"]);
+        assert!(pipeline.steps(Phase::Emit).unwrap().is_empty());
+        assert_eq!(names(pipeline.steps(Phase::Link).unwrap()), ["sign"]);
+    }
+
+    #[test]
+    fn test_pipeline_invalid_constraints() {
+        let cyclic = [plugin("a", Phase::Emit, &["b"]), plugin("b", Phase::Emit, &["a"])];
+        assert!(Pipeline::new(&cyclic).is_err());
+
+        let later = [plugin("a", Phase::Compile, &["b"]), plugin("b", Phase::Link, &[])];
+        assert!(Pipeline::new(&later).is_err());
+
+        assert!(Pipeline::new(&[plugin("a", Phase::Emit, &["missing"])]).is_err());
+        assert!(
+            Pipeline::new(&[plugin("a", Phase::Emit, &[]), plugin("a", Phase::Link, &[])]).is_err()
+        );
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    env,
+    io::{self, IsTerminal},
+    sync::Arc,
+};
+
+use clap::ValueEnum;
+use hmt_utils::event::{Event, Reporter};
+
+/// How progress is reported on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ProgressMode {
+    /// Live on an interactive terminal, plain otherwise
+    #[default]
+    Auto,
+    /// A single status line redrawn in place
+    Live,
+    /// Periodic plain text lines, for screen readers and logs
+    Plain,
+    /// Every event as a line of JSON, for tools driving the CLI
+    Json,
+}
+
+impl ProgressMode {
+    /// Resolves `Auto` against the environment. Plain output is used when
+    /// stderr is not a terminal or the terminal declares itself `dumb`.
+    pub fn resolve(self) -> Self {
+        match self {
+            ProgressMode::Auto => {
+                let dumb = env::var("TERM").is_ok_and(|term| term == "dumb");
+                if io::stderr().is_terminal() && !dumb {
+                    ProgressMode::Live
+                } else {
+                    ProgressMode::Plain
+                }
+            }
+            mode => mode,
+        }
+    }
+}
+
+/// Reports the progress of a task over a known number of items.
+pub struct Progress {
+    reporter: Arc<dyn Reporter>,
+    label: String,
+    total: usize,
+    done: usize,
+}
+
+impl Progress {
+    /// Starts reporting a task of `total` items.
+    pub fn new(reporter: Arc<dyn Reporter>, label: &str, total: usize) -> Self {
+        Self { reporter, label: label.to_string(), total, done: 0 }
+    }
+
+    /// Marks the next item, named `item`, as done.
+    pub fn inc(&mut self, item: &str) {
+        self.done += 1;
+        self.reporter.report(Event::Progress {
+            label: self.label.clone(),
+            item: item.to_string(),
+            done: self.done,
+            total: self.total,
+        });
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if self.done > 0 {
+            self.reporter.report(Event::Finished { label: self.label.clone() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_keeps_explicit_mode() {
+        assert_eq!(ProgressMode::Plain.resolve(), ProgressMode::Plain);
+        assert_eq!(ProgressMode::Live.resolve(), ProgressMode::Live);
+        assert_eq!(ProgressMode::Json.resolve(), ProgressMode::Json);
+        assert_ne!(ProgressMode::Auto.resolve(), ProgressMode::Auto);
+    }
+}
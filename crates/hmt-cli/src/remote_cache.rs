@@ -0,0 +1,215 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A build cache shared between machines over HTTP.
+//!
+//! Entries hold the contents of a build output, keyed by the fingerprint of
+//! the invocation that built it: `GET <url>/<fingerprint>` downloads an
+//! entry and `PUT <url>/<fingerprint>` uploads one. Any HTTP server or
+//! S3-compatible bucket accepting these requests can serve as the cache.
+//!
+//! Fingerprints cover the paths of the inputs and outputs, so machines
+//! share entries when they build the project at the same path, as CI
+//! runners do. A missing or unreachable cache never fails a build, the
+//! output is built locally instead.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::bail;
+use hmt_fetcher::RemoteFetcher;
+use hmt_utils::{
+    event::{warning, Reporter},
+    temp::TempFile,
+};
+use tokio::task::JoinSet;
+use tracing::debug;
+
+use crate::{
+    config::{RemoteCacheConfig, RemoteCacheMode},
+    errors::Result,
+};
+
+/// A remote build cache.
+pub struct RemoteCache {
+    fetcher: RemoteFetcher,
+    /// The base URL, without a trailing slash.
+    url: String,
+    /// Whether outputs are uploaded.
+    writable: bool,
+    /// The outputs built locally, uploaded by [`RemoteCache::upload`].
+    pending: Mutex<Vec<(String, PathBuf)>>,
+}
+
+impl RemoteCache {
+    /// Connects to the cache configured in `config` with `fetcher`.
+    pub fn new(fetcher: RemoteFetcher, config: &RemoteCacheConfig) -> Result<Self> {
+        let fetcher = match &config.token_env {
+            Some(var) => match std::env::var(var) {
+                Ok(token) => fetcher.header("Authorization", &format!("Bearer {token}")),
+                Err(_) => bail!("The remote cache token variable {var} is not set"),
+            },
+            None => fetcher,
+        };
+
+        Ok(Self {
+            fetcher,
+            url: config.url.trim_end_matches('/').to_string(),
+            writable: config.mode == RemoteCacheMode::ReadWrite,
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Downloads the entry of `key` to `path`, returning whether it was found.
+    pub async fn get(&self, key: &str, path: &Path) -> bool {
+        let data = match self.fetcher.get(&self.entry_url(key)).await {
+            Ok(data) => data,
+            Err(e) => {
+                debug!("Remote cache miss for {}: {}", path.display(), e);
+                return false;
+            }
+        };
+
+        match write(path, &data) {
+            Ok(()) => true,
+            Err(e) => {
+                debug!("Failed to write {} from the remote cache: {}", path.display(), e);
+                false
+            }
+        }
+    }
+
+    /// Records an output built locally, to be uploaded once the build
+    /// succeeds. Does nothing unless the cache is writable.
+    pub fn record(&self, key: &str, path: &Path) {
+        if self.writable {
+            self.pending.lock().unwrap().push((key.to_string(), path.to_path_buf()));
+        }
+    }
+
+    /// Uploads the recorded outputs concurrently, warning about those that
+    /// failed. Returns the number of uploaded entries.
+    pub async fn upload(self: &Arc<Self>, reporter: &dyn Reporter) -> usize {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        let mut uploads = JoinSet::new();
+        for (key, path) in pending {
+            let cache = self.clone();
+            uploads.spawn(async move {
+                let data = fs::read(&path)?;
+                cache.fetcher.put(&cache.entry_url(&key), data).await?;
+                anyhow::Ok(())
+            });
+        }
+
+        let mut uploaded = 0;
+        while let Some(result) = uploads.join_next().await {
+            match result.map_err(anyhow::Error::from).and_then(|upload| upload) {
+                Ok(()) => uploaded += 1,
+                Err(e) => reporter.warn(
+                    warning::REMOTE_CACHE,
+                    format!("Failed to upload to the remote cache: {e}"),
+                ),
+            }
+        }
+        uploaded
+    }
+
+    /// Returns the URL of the entry of `key`.
+    fn entry_url(&self, key: &str) -> String {
+        format!("{}/{}", self.url, key)
+    }
+}
+
+/// Writes a downloaded entry, replacing the file atomically.
+fn write(path: &Path, data: &[u8]) -> Result<()> {
+    let file = TempFile::new_in(path.parent().unwrap_or(Path::new(".")))?;
+    fs::write(file.path(), data)?;
+    file.persist(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use hmt_e2e::{MockServer, Response};
+    use hmt_utils::event::LogReporter;
+
+    use super::*;
+
+    /// Serves GET and PUT requests from memory.
+    async fn server() -> MockServer {
+        let entries = Mutex::new(HashMap::<String, Vec<u8>>::new());
+        MockServer::start(move |request| {
+            let mut entries = entries.lock().unwrap();
+            match request.method.as_str() {
+                "PUT" => {
+                    entries.insert(request.path.clone(), request.body.clone());
+                    Response::ok("")
+                }
+                _ => match entries.get(&request.path) {
+                    Some(data) => Response::ok(data.clone()),
+                    None => Response::status("404 Not Found"),
+                },
+            }
+        })
+        .await
+    }
+
+    fn config(url: &str, mode: RemoteCacheMode) -> RemoteCacheConfig {
+        RemoteCacheConfig { url: format!("{url}/"), mode, token_env: None }
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip() {
+        let server = server().await;
+        let url = server.url("/cache");
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("main.o");
+        fs::write(&output, "object").unwrap();
+
+        // Read-only caches never upload
+        let cache = Arc::new(
+            RemoteCache::new(RemoteFetcher::new(), &config(&url, RemoteCacheMode::Read)).unwrap(),
+        );
+        cache.record("abc", &output);
+        assert_eq!(cache.upload(&LogReporter).await, 0);
+
+        let cache = Arc::new(
+            RemoteCache::new(RemoteFetcher::new(), &config(&url, RemoteCacheMode::ReadWrite))
+                .unwrap(),
+        );
+        assert!(!cache.get("abc", &output).await);
+        cache.record("abc", &output);
+        assert_eq!(cache.upload(&LogReporter).await, 1);
+
+        let restored = dir.path().join("restored.o");
+        assert!(cache.get("abc", &restored).await);
+        assert_eq!(fs::read_to_string(restored).unwrap(), "object");
+    }
+
+    #[test]
+    fn test_missing_token() {
+        let config = RemoteCacheConfig {
+            url: "https://cache.example.com".into(),
+            mode: RemoteCacheMode::Read,
+            token_env: Some("HUMMANTA_TEST_MISSING_CACHE_TOKEN".into()),
+        };
+        assert!(RemoteCache::new(RemoteFetcher::new(), &config).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use hmt_utils::event::{Event, Reporter};
+use tracing::{debug, info, warn};
+
+use crate::progress::ProgressMode;
+
+/// How often plain mode reports progress, besides the first and last item.
+const PLAIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Creates the reporter for a resolved progress mode.
+pub fn reporter(mode: ProgressMode) -> Arc<dyn Reporter> {
+    match mode {
+        ProgressMode::Json => Arc::new(JsonReporter),
+        mode => Arc::new(CliReporter::new(mode)),
+    }
+}
+
+/// Shows events as log lines, and progress as a live status line or
+/// periodic plain lines.
+pub struct CliReporter {
+    mode: ProgressMode,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// When plain mode last reported progress.
+    reported: Option<Instant>,
+    /// Whether a live status line is shown and must be ended before
+    /// anything else is written.
+    live: bool,
+}
+
+impl CliReporter {
+    pub fn new(mode: ProgressMode) -> Self {
+        Self { mode, state: Mutex::default() }
+    }
+
+    /// Returns the line to show for a progress event, if it is due.
+    fn progress_line(
+        &self,
+        state: &mut State,
+        label: &str,
+        item: &str,
+        done: usize,
+        total: usize,
+    ) -> Option<String> {
+        let line = format!("{label} [{done}/{total}] {item}");
+        if self.mode == ProgressMode::Live {
+            return Some(line);
+        }
+
+        // Plain mode never redraws, so only report now and then
+        let due = state.reported.is_none_or(|at| at.elapsed() >= PLAIN_INTERVAL);
+        if !due && done < total {
+            return None;
+        }
+        state.reported = Some(Instant::now());
+        Some(line)
+    }
+}
+
+impl Reporter for CliReporter {
+    fn report(&self, event: Event) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut stderr = io::stderr().lock();
+
+        // Leave the live status line behind, so following output starts on
+        // a line of its own
+        if state.live && !matches!(event, Event::Progress { .. } | Event::Fetched { .. }) {
+            let _ = writeln!(stderr);
+            state.live = false;
+        }
+
+        match event {
+            Event::Info { message } => info!("{message}"),
+            Event::Warning { id, message } => warn!("{message} [{id}]"),
+            Event::Progress { label, item, done, total } => {
+                let Some(line) = self.progress_line(&mut state, &label, &item, done, total) else {
+                    return;
+                };
+                let _ = match self.mode {
+                    ProgressMode::Live => write!(stderr, "\r\x1b[2K{line}"),
+                    _ => writeln!(stderr, "{line}"),
+                };
+                let _ = stderr.flush();
+                state.live = self.mode == ProgressMode::Live;
+            }
+            Event::Finished { .. } => state.reported = None,
+            Event::Fetched { url, size } => debug!("Fetched {url} ({size} bytes)"),
+        }
+    }
+}
+
+/// Drops the allowed warnings on their way to another reporter, and counts
+/// the others.
+pub struct Warnings {
+    inner: Arc<dyn Reporter>,
+    allowed: RwLock<BTreeSet<String>>,
+    count: AtomicUsize,
+}
+
+impl Warnings {
+    pub fn new(inner: Arc<dyn Reporter>, allowed: &[String]) -> Self {
+        Self {
+            inner,
+            allowed: RwLock::new(allowed.iter().cloned().collect()),
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Allows more warnings, e.g. those of the configuration once read.
+    pub fn allow(&self, ids: &[String]) {
+        self.allowed.write().unwrap_or_else(|e| e.into_inner()).extend(ids.iter().cloned());
+    }
+
+    /// Gets the number of warnings passed on so far.
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+impl Reporter for Warnings {
+    fn report(&self, event: Event) {
+        if let Event::Warning { id, .. } = &event {
+            if self.allowed.read().unwrap_or_else(|e| e.into_inner()).contains(id) {
+                return;
+            }
+            self.count.fetch_add(1, Ordering::Relaxed);
+        }
+        self.inner.report(event);
+    }
+}
+
+/// Writes every event as a line of JSON on stderr, for tools driving the
+/// CLI.
+pub struct JsonReporter;
+
+impl Reporter for JsonReporter {
+    fn report(&self, event: Event) {
+        if let Ok(line) = serde_json::to_string(&event) {
+            eprintln!("{line}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hmt_utils::event::warning;
+
+    use super::*;
+
+    #[test]
+    fn test_plain_reports_periodically() {
+        let reporter = CliReporter::new(ProgressMode::Plain);
+        let mut state = State::default();
+
+        let line = reporter.progress_line(&mut state, "Compiling", "a.sol", 1, 3);
+        assert_eq!(line.as_deref(), Some("Compiling [1/3] a.sol"));
+        assert_eq!(reporter.progress_line(&mut state, "Compiling", "b.sol", 2, 3), None);
+        let line = reporter.progress_line(&mut state, "Compiling", "c.sol", 3, 3);
+        assert_eq!(line.as_deref(), Some("Compiling [3/3] c.sol"));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let reporter = Warnings::new(Arc::new(JsonReporter), &["mirror-failed".into()]);
+        reporter.info("building".into());
+        reporter.warn(warning::FETCH_FAILED, "skipped".into());
+        reporter.warn(warning::MIRROR_FAILED, "retrying".into());
+        assert_eq!(reporter.count(), 1);
+
+        reporter.allow(&[warning::FETCH_FAILED.into()]);
+        reporter.warn(warning::FETCH_FAILED, "skipped".into());
+        assert_eq!(reporter.count(), 1);
+    }
+
+    #[test]
+    fn test_live_reports_every_item() {
+        let reporter = CliReporter::new(ProgressMode::Live);
+        let mut state = State::default();
+        assert!(reporter.progress_line(&mut state, "Emitting", "a.clif", 1, 2).is_some());
+        assert!(reporter.progress_line(&mut state, "Emitting", "b.clif", 2, 2).is_some());
+    }
+}
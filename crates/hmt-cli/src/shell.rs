@@ -0,0 +1,279 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Setup of `PATH` in the configuration files of shells.
+//!
+//! The export is written between marker comments, so adding it again
+//! replaces it instead of duplicating it, and it can be removed without
+//! touching the rest of the file.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+use anyhow::{anyhow, bail, Context as _};
+use clap::ValueEnum;
+
+use crate::errors::Result;
+
+/// The comment starting the block written to configuration files.
+const BEGIN_MARKER: &str = "# >>> hummanta >>>";
+
+/// The comment ending the block written to configuration files.
+const END_MARKER: &str = "# <<< hummanta <<<";
+
+/// A shell whose configuration files can put a directory on `PATH`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// Every supported shell.
+    const ALL: [Shell; 4] = [Shell::Sh, Shell::Bash, Shell::Zsh, Shell::Fish];
+
+    /// Returns the login shell of the user, from `$SHELL`.
+    pub fn current() -> Option<Self> {
+        let shell = PathBuf::from(std::env::var_os("SHELL")?);
+        match shell.file_name()?.to_str()? {
+            "sh" | "dash" | "ksh" => Some(Shell::Sh),
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+
+    /// Returns the command putting `dir` in front of `PATH`.
+    pub fn export(&self, dir: &Path) -> String {
+        match self {
+            Shell::Fish => format!("fish_add_path \"{}\"", dir.display()),
+            _ => format!("export PATH=\"{}:$PATH\"", dir.display()),
+        }
+    }
+
+    /// Returns the configuration file of the shell, relative to the home
+    /// directory. Terminals on macOS start login shells, which read
+    /// `.bash_profile` instead of `.bashrc`.
+    fn file(&self) -> &'static str {
+        match self {
+            Shell::Sh => ".profile",
+            Shell::Bash if cfg!(target_os = "macos") => ".bash_profile",
+            Shell::Bash => ".bashrc",
+            Shell::Zsh => ".zshrc",
+            Shell::Fish => ".config/fish/config.fish",
+        }
+    }
+}
+
+/// Returns the configuration files to update below `home`: those of every
+/// shell that exist, and the one of the `current` shell even if missing.
+pub fn config_files(home: &Path, current: Option<Shell>) -> Vec<(Shell, PathBuf)> {
+    Shell::ALL
+        .into_iter()
+        .map(|shell| (shell, home.join(shell.file())))
+        .filter(|(shell, path)| path.exists() || current == Some(*shell))
+        .collect()
+}
+
+/// Whether `dir` is on `PATH`.
+pub fn on_path(dir: &Path) -> bool {
+    std::env::var_os("PATH").is_some_and(|path| std::env::split_paths(&path).any(|p| p == dir))
+}
+
+/// Writes the block putting `dir` on `PATH` to a configuration file,
+/// returning whether the file changed.
+pub fn add(path: &Path, shell: Shell, dir: &Path) -> Result<bool> {
+    let existing = read(path)?.unwrap_or_default();
+    let mut content = without_block(path, &existing)?;
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&format!("{BEGIN_MARKER}\n{}\n{END_MARKER}\n", shell.export(dir)));
+
+    write(path, &existing, &content)
+}
+
+/// Removes the block from a configuration file, returning whether the file
+/// changed.
+pub fn remove(path: &Path) -> Result<bool> {
+    let Some(existing) = read(path)? else {
+        return Ok(false);
+    };
+    write(path, &existing, &without_block(path, &existing)?)
+}
+
+/// Reads a configuration file, or `None` if it does not exist.
+fn read(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).context(format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Removes the lines between the markers, and the markers themselves.
+///
+/// Fails if a begin marker has no end marker after it, rather than removing
+/// the rest of the file.
+fn without_block(path: &Path, content: &str) -> Result<String> {
+    let mut kept = String::with_capacity(content.len());
+    let mut inside = false;
+    for line in content.split_inclusive('\n') {
+        match line.trim_end() {
+            BEGIN_MARKER => inside = true,
+            END_MARKER if inside => inside = false,
+            _ if !inside => kept.push_str(line),
+            _ => {}
+        }
+    }
+
+    if inside {
+        bail!(
+            "{} has a `{BEGIN_MARKER}` line without a `{END_MARKER}` line after it, \
+             remove the block by hand",
+            path.display()
+        );
+    }
+    Ok(kept)
+}
+
+/// Writes `content` to `path` unless it equals `existing`.
+///
+/// The content is written to a temporary file renamed over the original,
+/// so an interrupted write never truncates it. Symlinks, as used by dotfile
+/// managers, are followed, and the permissions of the original are kept.
+fn write(path: &Path, existing: &str, content: &str) -> Result<bool> {
+    if content == existing {
+        return Ok(false);
+    }
+
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = path.parent().ok_or_else(|| anyhow!("{} has no parent", path.display()))?;
+    fs::create_dir_all(dir)?;
+
+    let write = || -> io::Result<()> {
+        let mut file = tempfile::NamedTempFile::new_in(dir)?;
+        io::Write::write_all(&mut file, content.as_bytes())?;
+        // Temporary files are private, new configuration files are not
+        match fs::metadata(&path) {
+            Ok(metadata) => file.as_file().set_permissions(metadata.permissions())?,
+            #[cfg(unix)]
+            Err(_) => file.as_file().set_permissions(fs::Permissions::from_mode(0o644))?,
+            #[cfg(not(unix))]
+            Err(_) => {}
+        }
+        file.persist(&path)?;
+        Ok(())
+    };
+    write().context(format!("Failed to write {}", path.display()))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".bashrc");
+        fs::write(&path, "alias ll='ls -l'").unwrap();
+        let bin = Path::new("/home/user/.hummanta/bin");
+
+        assert!(add(&path, Shell::Bash, bin).unwrap());
+        assert!(!add(&path, Shell::Bash, bin).unwrap());
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            "alias ll='ls -l'\n# >>> hummanta >>>\n\
+             export PATH=\"/home/user/.hummanta/bin:$PATH\"\n# <<< hummanta <<<\n"
+        );
+
+        // A moved directory replaces the block
+        assert!(add(&path, Shell::Bash, Path::new("/opt/hummanta/bin")).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap().matches(BEGIN_MARKER).count(), 1);
+
+        assert!(remove(&path).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "alias ll='ls -l'\n");
+        assert!(!remove(&path).unwrap());
+        assert!(!remove(&dir.path().join("missing")).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_keeps_symlinks_and_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("dotfiles-zshrc");
+        fs::write(&target, "alias ll='ls -l'\n").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o640)).unwrap();
+        let path = dir.path().join(".zshrc");
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+
+        assert!(add(&path, Shell::Zsh, Path::new("/bin")).unwrap());
+        assert!(fs::symlink_metadata(&path).unwrap().file_type().is_symlink());
+        assert!(fs::read_to_string(&target).unwrap().contains(BEGIN_MARKER));
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o640);
+
+        let new = dir.path().join(".profile");
+        assert!(add(&new, Shell::Sh, Path::new("/bin")).unwrap());
+        assert_eq!(fs::metadata(&new).unwrap().permissions().mode() & 0o777, 0o644);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        // A directory cannot be read as a file
+        assert!(add(dir.path(), Shell::Sh, Path::new("/bin")).is_err());
+        assert!(remove(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_unterminated_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".zshrc");
+        let content = "# >>> hummanta >>>\nexport PATH=\"/bin:$PATH\"\nalias ll='ls -l'\n";
+        fs::write(&path, content).unwrap();
+
+        assert!(add(&path, Shell::Zsh, Path::new("/bin")).is_err());
+        assert!(remove(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_config_files() {
+        let home = tempfile::tempdir().unwrap();
+        fs::write(home.path().join(Shell::Bash.file()), "").unwrap();
+        fs::write(home.path().join(".profile"), "").unwrap();
+
+        let files = config_files(home.path(), Some(Shell::Zsh));
+        assert_eq!(
+            files,
+            [
+                (Shell::Sh, home.path().join(".profile")),
+                (Shell::Bash, home.path().join(Shell::Bash.file())),
+                (Shell::Zsh, home.path().join(".zshrc")),
+            ]
+        );
+        assert_eq!(Shell::Fish.export(Path::new("/bin")), "fish_add_path \"/bin\"");
+    }
+}
@@ -13,18 +13,17 @@
 // limitations under the License.
 
 use std::{
-    ffi::{OsStr, OsString},
+    io::IsTerminal,
     path::{Path, PathBuf},
-    process::Output,
 };
 
-use anyhow::{anyhow, Context as _};
-use tokio::process::Command;
+use anyhow::{anyhow, bail, Context as _};
+use walkdir::WalkDir;
 
-use hmt_manifest::CategoryMap;
-use tracing::info;
+use hmt_manifest::{CategoryMap, PackageEntry, ProjectManifest, Source};
+use hmt_utils::process::Process;
 
-use crate::errors::Result;
+use crate::errors::{CliError, Result};
 
 pub fn confirm(prompt: &str) -> Result<bool> {
     println!("{prompt}");
@@ -35,11 +34,33 @@ pub fn confirm(prompt: &str) -> Result<bool> {
     Ok(input.trim().eq_ignore_ascii_case("y"))
 }
 
+/// Asks a yes or no question on stderr, keeping stdout to the output of
+/// commands, and reads the answer on a blocking thread. Answers no without
+/// asking when stdin is not a terminal, so scripts never wait on input.
+pub async fn ask(prompt: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        return Ok(false);
+    }
+
+    eprintln!("{prompt}");
+    let input = tokio::task::spawn_blocking(|| {
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input).map(|_| input)
+    })
+    .await??;
+
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
+
 pub fn print_domain_packages(domain: &str, categories: &CategoryMap) {
     println!("{domain}");
     for packages in categories.values() {
         for (name, entry) in packages {
-            println!("  {name} {}", entry.version);
+            match entry.source {
+                Source::Registry => println!("  {name} {}", entry.version),
+                Source::Local => println!("  {name} {} (local)", entry.version),
+                Source::Linked => println!("  {name} (linked to {})", entry.path.display()),
+            }
             if let Some(desc) = &entry.description {
                 println!("  {desc}");
             }
@@ -47,20 +68,23 @@ pub fn print_domain_packages(domain: &str, categories: &CategoryMap) {
     }
 }
 
-/// Executes a system command asynchronously and returns its complete output
-pub async fn command<S, I, T>(program: S, args: I) -> Result<Output>
-where
-    S: AsRef<OsStr>,
-    I: IntoIterator<Item = T>,
-    T: AsRef<OsStr>,
-{
-    // Convert arguments to OsString for display purposes
-    let args_vec: Vec<OsString> = args.into_iter().map(|a| a.as_ref().to_os_string()).collect();
-    let prog = program.as_ref().to_string_lossy();
-    let args_str = args_vec.iter().map(|a| a.to_string_lossy()).collect::<Vec<_>>().join(" ");
-    info!("Executing {prog} {args_str}");
-
-    Command::new(program.as_ref()).args(&args_vec).output().await.context("Command execute failed!")
+/// Opens a file with the platform's default application
+pub async fn open(path: &Path) -> Result<()> {
+    let process = if cfg!(target_os = "macos") {
+        Process::new("open")
+    } else if cfg!(windows) {
+        Process::new("cmd").args(["/C", "start", ""])
+    } else {
+        Process::new("xdg-open")
+    };
+
+    let status = process.arg(path).status().await?;
+
+    if !status.success() {
+        bail!("Failed to open {}: exited with {}", path.display(), status);
+    }
+
+    Ok(())
 }
 
 /// Searches for `filename` in current directory
@@ -89,3 +113,77 @@ pub fn find<P: AsRef<Path>>(filename: P) -> Result<PathBuf> {
     // If the loop finishes, the file was not found in the hierarchy.
     Err(anyhow!("Not found {}", filename.as_ref().display()))
 }
+
+/// Resolve target with clear precedence: CLI arg > manifest > error
+pub fn resolve_target(cli_target: &Option<String>, manifest: &ProjectManifest) -> Result<String> {
+    if let Some(cli_target) = cli_target {
+        if !cli_target.is_empty() {
+            return Ok(cli_target.to_owned());
+        }
+        bail!("Empty target specified in command line");
+    }
+
+    if let Some(manifest_target) = &manifest.project.target {
+        if !manifest_target.is_empty() {
+            return Ok(manifest_target.to_owned());
+        }
+        bail!("Empty target specified in manifest");
+    }
+
+    Err(CliError::TargetNotSpecified.into())
+}
+
+/// Returns the variables exposing shared runtimes to a tool: one
+/// `HUMMANTA_RUNTIME_<NAME>` per runtime, set to its installation path, e.g.
+/// `HUMMANTA_RUNTIME_LLVM_RUNTIME` for `llvm-runtime`
+pub fn runtime_envs(runtimes: &[PackageEntry]) -> Vec<(String, PathBuf)> {
+    runtimes
+        .iter()
+        .map(|runtime| {
+            let name: String = runtime
+                .name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                .collect();
+            (format!("HUMMANTA_RUNTIME_{name}"), runtime.entry.path.clone())
+        })
+        .collect()
+}
+
+/// Collects the source files with the given extension under a project
+/// directory, skipping build outputs and nested projects
+pub fn sources(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !is_excluded(e.path()))
+        .filter_map(Result::ok)
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == extension))
+        .map(|e| e.into_path())
+        .collect()
+}
+
+/// Whether a path is a build output directory or a nested project
+fn is_excluded(path: &Path) -> bool {
+    path.is_dir() && (path.ends_with("target") || path.join("hummanta.toml").is_file())
+}
+
+/// Recursively copies the directory `src` to `dest`, skipping build outputs
+pub fn copy_dir(src: &Path, dest: &Path) -> Result<()> {
+    for entry in WalkDir::new(src)
+        .into_iter()
+        .filter_entry(|e| !(e.depth() == 1 && e.file_name() == "target"))
+    {
+        let entry = entry?;
+        let path = dest.join(entry.path().strip_prefix(src)?);
+
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&path)?;
+        } else {
+            std::fs::copy(entry.path(), &path)
+                .context(format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+
+    Ok(())
+}
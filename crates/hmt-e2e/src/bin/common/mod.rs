@@ -0,0 +1,110 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Argument handling shared by the stub binaries.
+//!
+//! Stubs only depend on `std`, keeping the archives the fixture registry
+//! packs them into small.
+//!
+//! Every tool is invoked with `--input <path>` (repeated for the linker)
+//! and `--output <path>`, followed by flags a stub has no use for, such as
+//! `--dependency` and `--feature`. Detectors are invoked with `--path`.
+
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, Error, ErrorKind},
+    path::PathBuf,
+};
+
+/// The arguments of a stub invocation.
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    /// The `--input` files, in order.
+    pub inputs: Vec<PathBuf>,
+    /// The `--output` file.
+    pub output: Option<PathBuf>,
+    /// The `--path` to detect.
+    pub path: Option<PathBuf>,
+    /// The arguments after `--`.
+    pub rest: Vec<OsString>,
+}
+
+impl Args {
+    /// Parses the arguments of the current process.
+    pub fn from_env() -> Self {
+        Self::parse(std::env::args_os().skip(1))
+    }
+
+    /// Parses arguments, ignoring unknown flags.
+    pub fn parse(args: impl IntoIterator<Item = OsString>) -> Self {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.to_str() {
+                Some("--input") => parsed.inputs.extend(args.next().map(PathBuf::from)),
+                Some("--output") => parsed.output = args.next().map(PathBuf::from),
+                Some("--path") => parsed.path = args.next().map(PathBuf::from),
+                Some("--") => {
+                    parsed.rest = args.collect();
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        parsed
+    }
+}
+
+/// Runs a tool transforming its inputs into its output: the output is
+/// `<prefix>` followed by the concatenated inputs, so tests can trace every
+/// artifact back through the pipeline. Tools given the `stub-runtime`
+/// runtime prepend the contents of its `prefix` file as well.
+#[allow(dead_code)]
+pub fn transform(prefix: &str) -> io::Result<()> {
+    let args = Args::from_env();
+    let output =
+        args.output.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Missing --output"))?;
+
+    let mut data = match std::env::var_os("HUMMANTA_RUNTIME_STUB_RUNTIME") {
+        Some(dir) => fs::read(PathBuf::from(dir).join("prefix"))?,
+        None => Vec::new(),
+    };
+    data.extend(prefix.as_bytes());
+    for input in &args.inputs {
+        data.extend(fs::read(input)?);
+    }
+    fs::write(&output, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let args = Args::parse(
+            ["--input", "a.clif", "--dependency", "lib=dir", "--input", "b.clif"]
+                .into_iter()
+                .chain(["--output", "app", "--", "--flag"])
+                .map(OsString::from),
+        );
+
+        assert_eq!(args.inputs, [PathBuf::from("a.clif"), PathBuf::from("b.clif")]);
+        assert_eq!(args.output, Some(PathBuf::from("app")));
+        assert_eq!(args.path, None);
+        assert_eq!(args.rest, [OsString::from("--flag")]);
+    }
+}
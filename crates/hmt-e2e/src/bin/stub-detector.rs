@@ -0,0 +1,36 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detects the `stub` language in directories holding `.stub` files,
+//! printing the detection result as JSON.
+
+mod common;
+
+use common::Args;
+
+fn main() {
+    let found = Args::from_env()
+        .path
+        .and_then(|path| std::fs::read_dir(path).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .any(|entry| entry.path().extension().is_some_and(|ext| ext == "stub"));
+
+    if found {
+        println!(r#"{{"pass":true,"language":"stub","extension":"stub"}}"#);
+    } else {
+        println!(r#"{{"pass":false}}"#);
+    }
+}
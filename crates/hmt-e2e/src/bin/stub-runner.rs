@@ -0,0 +1,34 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs a linked stub executable by printing its contents, followed by the
+//! arguments passed to it.
+
+use std::io::{Error, ErrorKind, Result};
+
+use common::Args;
+
+mod common;
+
+fn main() -> Result<()> {
+    let args = Args::from_env();
+    let input =
+        args.inputs.first().ok_or(Error::new(ErrorKind::InvalidInput, "Missing --input"))?;
+    let data = std::fs::read_to_string(input)?;
+
+    let rest: Vec<_> = args.rest.iter().map(|arg| arg.to_string_lossy()).collect();
+    println!("{data} {}", rest.join(" "));
+
+    Ok(())
+}
@@ -0,0 +1,142 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    env,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use anyhow::{bail, Context as _, Result};
+use tempfile::TempDir;
+
+use crate::FixtureRegistry;
+
+/// Runs the `hummanta` binary in an isolated environment: a fresh home
+/// directory, a project directory and a fixture registry, all removed when
+/// the harness is dropped.
+pub struct Harness {
+    root: TempDir,
+    bin: PathBuf,
+    registry: FixtureRegistry,
+}
+
+impl Harness {
+    /// Creates a harness with an empty registry, running the binary found
+    /// by [`Harness::binary`].
+    pub fn new() -> Result<Self> {
+        let root = tempfile::tempdir()?;
+        fs::create_dir_all(root.path().join("home"))?;
+        fs::create_dir_all(root.path().join("project"))?;
+        let registry = FixtureRegistry::new(&root.path().join("registry"))?;
+
+        Ok(Self { bin: Self::binary("hummanta"), root, registry })
+    }
+
+    /// Returns the path of a binary built into the same target directory as
+    /// the running test, or `HUMMANTA_BIN` for `hummanta` when set.
+    pub fn binary(name: &str) -> PathBuf {
+        if let Some(bin) = env::var_os("HUMMANTA_BIN").filter(|_| name == "hummanta") {
+            return PathBuf::from(bin);
+        }
+
+        // Tests run from `target/<profile>/deps`, binaries live one level up
+        let exe = env::current_exe().unwrap_or_default();
+        let mut dir = exe.parent().map(Path::to_path_buf).unwrap_or_default();
+        if dir.ends_with("deps") {
+            dir.pop();
+        }
+        dir.join(format!("{name}{}", env::consts::EXE_SUFFIX))
+    }
+
+    /// Returns the fixture registry the CLI installs from.
+    pub fn registry(&mut self) -> &mut FixtureRegistry {
+        &mut self.registry
+    }
+
+    /// Publishes the stub binary `stub-<category>` as a package.
+    pub async fn publish_stub(&mut self, kind: &str, domain: &str, category: &str) -> Result<()> {
+        let name = format!("stub-{category}");
+        let binary = Self::binary(&name);
+        self.registry.publish(kind, domain, category, &name, &binary).await
+    }
+
+    /// Returns the home directory the CLI installs packages into.
+    pub fn home_dir(&self) -> PathBuf {
+        self.root.path().join("home").join(".hummanta")
+    }
+
+    /// Returns the directory the CLI runs in.
+    pub fn project_dir(&self) -> PathBuf {
+        self.root.path().join("project")
+    }
+
+    /// Writes a file relative to the project directory.
+    pub fn write(&self, path: &str, contents: &str) -> Result<()> {
+        let path = self.project_dir().join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents).context(format!("Failed to write {}", path.display()))
+    }
+
+    /// Runs the CLI in the project directory, returning its output whatever
+    /// its exit status.
+    pub fn run<I, S>(&self, args: I) -> Result<Output>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        if !self.bin.is_file() {
+            bail!("{} not found, build it with `cargo build -p hmt-cli`", self.bin.display());
+        }
+
+        let home = self.root.path().join("home");
+        Command::new(&self.bin)
+            .args(args)
+            .current_dir(self.project_dir())
+            .env("HOME", &home)
+            .env("USERPROFILE", &home)
+            .env("HUMMANTA_REGISTRY", self.registry.url())
+            .env("HUMMANTA_PROGRESS", "plain")
+            .env_remove("HUMMANTA_OFFLINE")
+            .env_remove("HUMMANTA_LOCKED")
+            .output()
+            .context(format!("Failed to run {}", self.bin.display()))
+    }
+
+    /// Runs the CLI, failing with its error output unless it succeeds, and
+    /// returns its standard output.
+    pub fn hummanta<I, S>(&self, args: I) -> Result<String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let args: Vec<_> = args.into_iter().map(|a| a.as_ref().to_os_string()).collect();
+        let output = self.run(&args)?;
+        if !output.status.success() {
+            bail!(
+                "hummanta {} failed with {}:\n{}{}",
+                args.iter().map(|a| a.to_string_lossy()).collect::<Vec<_>>().join(" "),
+                output.status,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
@@ -0,0 +1,31 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! End-to-end test harness for the whole pipeline.
+//!
+//! A [`FixtureRegistry`] publishes stub toolchain and target binaries, tiny
+//! programs honoring the tool contract built from `src/bin`, and a
+//! [`Harness`] drives the `hummanta` binary against it in an isolated home
+//! directory. Tests assert on the files the CLI leaves behind.
+//!
+//! A [`MockServer`] answers HTTP requests from a handler, for the unit tests
+//! of the crates talking to registries, caches and webhooks.
+
+mod harness;
+mod registry;
+mod server;
+
+pub use harness::Harness;
+pub use registry::{FixtureRegistry, VERSION};
+pub use server::{MockServer, Request, Response};
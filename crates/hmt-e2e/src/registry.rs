@@ -0,0 +1,173 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context as _, Result};
+use hmt_manifest::{
+    Artifact, IndexManifest, ManifestFile, Package, PackageManifest, Release, ReleaseManifest,
+    RUNTIME_CATEGORY,
+};
+use hmt_utils::{archive, checksum};
+
+/// The version every fixture package is published at.
+pub const VERSION: &str = "v0.1.0";
+
+/// A registry on the local filesystem, served through `file://` URLs.
+///
+/// Its layout matches a hosted registry: a top-level `index.toml` lists the
+/// domains of each kind, each domain index lists its packages, and every
+/// package has its own manifests and archives.
+pub struct FixtureRegistry {
+    root: PathBuf,
+    index: IndexManifest,
+}
+
+impl FixtureRegistry {
+    /// Creates an empty registry in `root`.
+    pub fn new(root: &Path) -> Result<Self> {
+        fs::create_dir_all(root)?;
+        let registry = Self { root: root.to_path_buf(), index: IndexManifest::new() };
+        registry.index.save(root.join("index.toml"))?;
+
+        Ok(registry)
+    }
+
+    /// Returns the URL the CLI reaches the registry at.
+    pub fn url(&self) -> String {
+        url(&self.root)
+    }
+
+    /// Publishes `binary` as the package `name` of a domain, for the
+    /// current platform.
+    pub async fn publish(
+        &mut self,
+        kind: &str,
+        domain: &str,
+        category: &str,
+        name: &str,
+        binary: &Path,
+    ) -> Result<()> {
+        // Packages unpack to a binary named after the package
+        let staging = tempfile::tempdir()?;
+        let staged = staging.path().join(name);
+        fs::copy(binary, &staged)
+            .context(format!("Stub binary not found: {}", binary.display()))?;
+        let archive = self.archive_path(name);
+        archive::archive_file(&staged, &archive).await?;
+
+        self.publish_archive(kind, domain, category, name, &archive)
+    }
+
+    /// Publishes the files of `dir` as the shared runtime `name` of a
+    /// domain, for the current platform.
+    pub async fn publish_runtime(
+        &mut self,
+        kind: &str,
+        domain: &str,
+        name: &str,
+        dir: &Path,
+    ) -> Result<()> {
+        let archive = self.archive_path(name);
+        archive::archive_dir(dir, &archive).await?;
+
+        self.publish_archive(kind, domain, RUNTIME_CATEGORY, name, &archive)
+    }
+
+    /// Makes the published package `name` use the runtime `runtime` at
+    /// versions matching `req`.
+    pub fn require_runtime(&self, name: &str, runtime: &str, req: &str) -> Result<()> {
+        let path = self.manifests_dir(name).join("index.toml");
+        let mut manifest = PackageManifest::load(&path)?;
+        manifest.package.runtimes.insert(runtime.to_string(), req.to_string());
+        manifest.save(&path)?;
+
+        Ok(())
+    }
+
+    /// Returns the path of the archive of a package.
+    fn archive_path(&self, name: &str) -> PathBuf {
+        let file_name = format!("{name}-{VERSION}-{}.tar.gz", target_triple::TARGET);
+        self.root.join("packages").join(name).join(file_name)
+    }
+
+    /// Returns the directory of the manifests of a package.
+    fn manifests_dir(&self, name: &str) -> PathBuf {
+        self.root.join("packages").join(name).join("manifests")
+    }
+
+    /// Publishes the archive of the package `name` of a domain, writing its
+    /// manifests and listing it in the indexes.
+    fn publish_archive(
+        &mut self,
+        kind: &str,
+        domain: &str,
+        category: &str,
+        name: &str,
+        archive: &Path,
+    ) -> Result<()> {
+        let package_dir = self.root.join("packages").join(name);
+        let manifests_dir = self.manifests_dir(name);
+        fs::create_dir_all(&manifests_dir)?;
+
+        let data = fs::read(archive)?;
+        let artifact = Artifact {
+            url: url(archive),
+            hash: checksum::digest(&data),
+            signature: None,
+            size: Some(data.len() as u64),
+            provenance: None,
+        };
+        let mut release = ReleaseManifest::new(Release::new(VERSION.to_string()), HashMap::new());
+        release.add_artifact(target_triple::TARGET.to_string(), artifact);
+        let release_file = format!("release-{VERSION}.toml");
+        release.save(manifests_dir.join(&release_file))?;
+
+        let package = Package {
+            name: name.to_string(),
+            homepage: url(&package_dir),
+            kind: category.to_string(),
+            targets: vec![target_triple::TARGET.to_string()],
+            ..Default::default()
+        };
+        let mut manifest = PackageManifest::new(package, VERSION.to_string());
+        manifest.add_release(VERSION.to_string(), release_file);
+        manifest.save(manifests_dir.join("index.toml"))?;
+
+        // List the package in its domain, and the domain in the registry
+        let domain_file = format!("{kind}/{domain}.toml");
+        let domain_path = self.root.join(&domain_file);
+        let mut domain_index = match domain_path.exists() {
+            true => IndexManifest::load(&domain_path)?,
+            false => IndexManifest::new(),
+        };
+        domain_index.insert(category.to_string(), name.to_string(), url(&package_dir));
+        fs::create_dir_all(self.root.join(kind))?;
+        domain_index.save(&domain_path)?;
+
+        self.index.insert(kind.to_string(), domain.to_string(), domain_file);
+        self.index.save(self.root.join("index.toml"))?;
+
+        Ok(())
+    }
+}
+
+/// Returns the `file://` URL of a path.
+fn url(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
@@ -0,0 +1,166 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+/// A request received by a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// The request method, e.g. `GET`.
+    pub method: String,
+    /// The request path, e.g. `/cache/abc`.
+    pub path: String,
+    /// The request line and headers, lowercased, one per line.
+    pub head: String,
+    /// The request body.
+    pub body: Vec<u8>,
+}
+
+/// A response sent by a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    /// Creates a `200 OK` response with a body.
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self { status: "200 OK".to_string(), headers: Vec::new(), body: body.into() }
+    }
+
+    /// Creates an empty response with a status, e.g. `404 Not Found`.
+    pub fn status(status: &str) -> Self {
+        Self { status: status.to_string(), ..Self::ok(Vec::new()) }
+    }
+
+    /// Adds a header to the response.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Serializes the response, leaving out the body for `HEAD` requests.
+    fn to_bytes(&self, head_only: bool) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {}\r\n", self.status);
+        for (name, value) in &self.headers {
+            response.push_str(&format!("{name}: {value}\r\n"));
+        }
+        response.push_str(&format!("Content-Length: {}\r\n\r\n", self.body.len()));
+
+        let mut response = response.into_bytes();
+        if !head_only {
+            response.extend_from_slice(&self.body);
+        }
+        response
+    }
+}
+
+/// The handler answering the requests of a [`MockServer`].
+type Handler = dyn Fn(&Request) -> Response + Send + Sync;
+
+/// An HTTP/1.1 server on a local port answering every request with a
+/// handler, for tests of HTTP clients. Requests are recorded in the order
+/// they arrived.
+pub struct MockServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    /// Starts a server answering every request with `handler`. It runs
+    /// until the test's runtime shuts down.
+    pub async fn start(handler: impl Fn(&Request) -> Response + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind");
+        let addr = listener.local_addr().expect("Failed to get local address");
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve(socket, handler.clone(), recorded.clone()));
+            }
+        });
+
+        Self { addr, requests }
+    }
+
+    /// Starts a server answering every request with `body`.
+    pub async fn body(body: impl Into<Vec<u8>>) -> Self {
+        let response = Response::ok(body);
+        Self::start(move |_| response.clone()).await
+    }
+
+    /// Returns the URL of `path` on the server, e.g. `/cache`.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    /// Returns the requests received so far.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Answers the requests of one connection until the client closes it.
+async fn serve(socket: TcpStream, handler: Arc<Handler>, requests: Arc<Mutex<Vec<Request>>>) {
+    let mut reader = BufReader::new(socket);
+    while let Some(request) = read_request(&mut reader).await {
+        let response = handler(&request).to_bytes(request.method == "HEAD");
+        requests.lock().unwrap_or_else(|e| e.into_inner()).push(request);
+        if reader.get_mut().write_all(&response).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads a request, or `None` once the connection is closed.
+async fn read_request(reader: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut head = line.to_lowercase();
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).await.ok()?;
+        if header.trim().is_empty() {
+            break;
+        }
+        let header = header.to_lowercase();
+        if let Some(value) = header.strip_prefix("content-length:") {
+            length = value.trim().parse().ok()?;
+        }
+        head.push_str(&header);
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await.ok()?;
+    Some(Request { method, path, head, body })
+}
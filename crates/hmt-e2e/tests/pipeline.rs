@@ -0,0 +1,301 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Drives the whole pipeline, init → toolchain add → target add → build →
+//! run, against stub tools.
+//!
+//! Tests running the CLI are ignored by default, since they need the
+//! `hummanta` binary built first; run them with `just e2e`.
+
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+
+use hmt_e2e::{Harness, VERSION};
+use hmt_manifest::{Binary, ManifestFile, ProjectManifest};
+use hmt_utils::event::{Event, Reporter};
+
+/// The language of the stub toolchain.
+const LANGUAGE: &str = "stub";
+
+/// The target of the stub backend, linker and runner.
+const TARGET: &str = "stub-vm";
+
+/// The runtime the stub frontend uses in the runtime tests.
+const RUNTIME: &str = "stub-runtime";
+
+async fn harness() -> Harness {
+    let mut harness = Harness::new().unwrap();
+    for category in ["detector", "frontend"] {
+        harness.publish_stub("toolchains", LANGUAGE, category).await.unwrap();
+    }
+    for category in ["backend", "linker", "runner"] {
+        harness.publish_stub("targets", TARGET, category).await.unwrap();
+    }
+    harness
+}
+
+/// Publishes a runtime whose `prefix` file the stub frontend prepends to
+/// its outputs, and makes the frontend use it.
+async fn publish_runtime(harness: &mut Harness) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("prefix"), "rt:").unwrap();
+
+    let registry = harness.registry();
+    registry.publish_runtime("toolchains", LANGUAGE, RUNTIME, dir.path()).await.unwrap();
+    registry.require_runtime("stub-frontend", RUNTIME, "^0.1").unwrap();
+}
+
+/// Records the events of an operation.
+#[derive(Default)]
+struct Recorder(Mutex<Vec<Event>>);
+
+impl Reporter for Recorder {
+    fn report(&self, event: Event) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+#[tokio::test]
+async fn test_fixture_registry_installs() {
+    use hmt_registry::{manager::ToolchainManager, traits::PackageManager, RegistryClient};
+
+    let mut harness = harness().await;
+    let client = RegistryClient::new(&harness.registry().url());
+    let recorder = Arc::new(Recorder::default());
+    let mut manager =
+        ToolchainManager::new(client, harness.home_dir()).with_reporter(recorder.clone());
+    manager.add(LANGUAGE).await.unwrap();
+
+    let dir = harness.home_dir().join("toolchains").join(LANGUAGE);
+    assert!(hmt_utils::path::is_executable(&dir.join("stub-detector")));
+    assert!(hmt_utils::path::is_executable(&dir.join("stub-frontend")));
+
+    let events = recorder.0.lock().unwrap();
+    let progress: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Progress { label, done, total, .. } => Some((label.as_str(), *done, *total)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(progress, [("Installing stub", 1, 2), ("Installing stub", 2, 2)]);
+    assert_eq!(events.last(), Some(&Event::Finished { label: "Installing stub".into() }));
+}
+
+#[tokio::test]
+async fn test_fixture_registry_installs_runtimes() {
+    use hmt_registry::{
+        manager::ToolchainManager,
+        traits::{PackageManager, Query},
+        RegistryClient,
+    };
+
+    let mut harness = harness().await;
+    publish_runtime(&mut harness).await;
+    let client = RegistryClient::new(&harness.registry().url());
+    let mut manager = ToolchainManager::new(client, harness.home_dir());
+    manager.add(LANGUAGE).await.unwrap();
+
+    let runtime = manager.runtime(LANGUAGE, RUNTIME).unwrap();
+    assert_eq!(runtime.version, VERSION);
+    assert_eq!(runtime.path, harness.home_dir().join("runtimes").join(LANGUAGE).join(RUNTIME));
+    assert_eq!(fs::read_to_string(runtime.path.join("prefix")).unwrap(), "rt:");
+
+    // Only the frontend uses the runtime
+    let frontend = manager.get_package(LANGUAGE, "frontend");
+    let runtimes = manager.package_runtimes(LANGUAGE, frontend[0].entry.runtimes.keys());
+    assert_eq!(runtimes.len(), 1);
+    assert_eq!(runtimes[0].entry.path, runtime.path);
+    let detector = manager.get_package(LANGUAGE, "detector");
+    assert!(manager.package_runtimes(LANGUAGE, detector[0].entry.runtimes.keys()).is_empty());
+}
+
+#[tokio::test]
+async fn test_fixture_registry_installs_locked_tools() {
+    use hmt_manifest::{FrozenPackage, LockedTool};
+    use hmt_registry::{manager::ToolchainManager, RegistryClient};
+
+    let mut harness = harness().await;
+    let client = RegistryClient::new(&harness.registry().url());
+    let mut manager = ToolchainManager::new(client, harness.home_dir());
+
+    let mut hashes =
+        manager.release_hashes(LANGUAGE, "frontend", "stub-frontend", VERSION).await.unwrap();
+    assert_eq!(hashes.keys().collect::<Vec<_>>(), [target_triple::TARGET]);
+
+    // A lockfile written on another platform pins its artifact as well
+    hashes.insert("riscv64gc-unknown-none-elf".into(), "0".repeat(64));
+    let package = FrozenPackage {
+        kind: "toolchains".into(),
+        domain: LANGUAGE.into(),
+        category: "frontend".into(),
+        name: "stub-frontend".into(),
+        version: VERSION.into(),
+        description: None,
+        url: String::new(),
+        hash: String::new(),
+        stage: None,
+    };
+    let tool = LockedTool::new(&package, hashes.clone());
+    assert!(manager.install_locked_tool(&tool).await.unwrap());
+    assert!(!manager.install_locked_tool(&tool).await.unwrap());
+
+    // Only the artifact of the current platform is installed
+    hashes.remove(target_triple::TARGET);
+    let foreign = LockedTool::new(&package, hashes);
+    let client = RegistryClient::new(&harness.registry().url());
+    let mut other = ToolchainManager::new(client, harness.home_dir().join("other"));
+    assert!(other.install_locked_tool(&foreign).await.is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires the hummanta binary, run with `just e2e`"]
+async fn test_pipeline() {
+    let harness = harness().await;
+    harness.write("main.stub", "hello").unwrap();
+
+    harness.hummanta(["toolchain", "add", LANGUAGE]).unwrap();
+    harness
+        .hummanta(["init", "--prefetch=false", "--gitignore=false", "--editorconfig=false"])
+        .unwrap();
+
+    let manifest_path = harness.project_dir().join("hummanta.toml");
+    let mut manifest = ProjectManifest::load(&manifest_path).unwrap();
+    assert_eq!(manifest.project.language, LANGUAGE);
+    manifest.project.target = Some(TARGET.to_string());
+    manifest.bins.push(Binary {
+        name: "app".into(),
+        main: "main.stub".into(),
+        flags: Vec::new(),
+        test: false,
+    });
+    manifest.save(&manifest_path).unwrap();
+
+    harness.hummanta(["target", "add", TARGET]).unwrap();
+    harness.hummanta(["build"]).unwrap();
+
+    let target_dir = harness.project_dir().join("target").join(TARGET);
+    assert_eq!(fs::read_to_string(target_dir.join("main.clif")).unwrap(), "clif:hello");
+    assert_eq!(fs::read_to_string(target_dir.join("main.o")).unwrap(), "obj:clif:hello");
+    assert_eq!(fs::read_to_string(target_dir.join("app")).unwrap(), "exe:obj:clif:hello");
+    assert!(target_dir.join("outputs.json").is_file());
+    let log = fs::read_to_string(target_dir.join("build.log")).unwrap();
+    assert!(log.ends_with("Build succeeded\n"));
+
+    // Nothing is pinned without a lockfile, so --locked refuses to build
+    assert!(!harness.project_dir().join("hummanta.lock").exists());
+    let output = harness.run(["build", "--locked"]).unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("hummanta.lock does not exist"));
+
+    let stdout = harness.hummanta(["run", "--", "world"]).unwrap();
+    assert_eq!(stdout.trim(), "exe:obj:clif:hello world");
+}
+
+#[tokio::test]
+#[ignore = "requires the hummanta binary, run with `just e2e`"]
+async fn test_pipeline_bins_with_same_file_name() {
+    let harness = harness().await;
+    harness.write("a/main.stub", "a").unwrap();
+    harness.write("b/main.stub", "b").unwrap();
+    let mut manifest =
+        format!("language = \"{LANGUAGE}\"\nextension = \"stub\"\ntarget = \"{TARGET}\"\n");
+    for name in ["a", "b"] {
+        manifest += &format!("\n[[bin]]\nname = \"app-{name}\"\nmain = \"{name}/main.stub\"\n");
+    }
+    harness.write("hummanta.toml", &manifest).unwrap();
+
+    harness.hummanta(["toolchain", "add", LANGUAGE]).unwrap();
+    harness.hummanta(["target", "add", TARGET]).unwrap();
+    harness.hummanta(["build"]).unwrap();
+
+    // Each binary links only the object of its own entry point
+    let target_dir = harness.project_dir().join("target").join(TARGET);
+    assert_eq!(fs::read_to_string(target_dir.join("a/main.o")).unwrap(), "obj:clif:a");
+    assert_eq!(fs::read_to_string(target_dir.join("app-a")).unwrap(), "exe:obj:clif:a");
+    assert_eq!(fs::read_to_string(target_dir.join("app-b")).unwrap(), "exe:obj:clif:b");
+}
+
+#[tokio::test]
+#[ignore = "requires the hummanta binary, run with `just e2e`"]
+async fn test_pipeline_dependency_outputs() {
+    let harness = harness().await;
+    let project =
+        format!("language = \"{LANGUAGE}\"\nextension = \"stub\"\ntarget = \"{TARGET}\"\n");
+    harness.write("lib/util.stub", "util").unwrap();
+    harness.write("lib/hummanta.toml", &project).unwrap();
+    harness.write("main.stub", "hello").unwrap();
+    let manifest = format!(
+        "{project}\n[[bin]]\nname = \"app\"\nmain = \"main.stub\"\n\n[dependencies]\nlib = {{ path = \"lib\" }}\n"
+    );
+    harness.write("hummanta.toml", &manifest).unwrap();
+
+    harness.hummanta(["toolchain", "add", LANGUAGE]).unwrap();
+    harness.hummanta(["target", "add", TARGET]).unwrap();
+    harness.hummanta(["build"]).unwrap();
+
+    // The dependency is built under the root project, never in place
+    assert!(!harness.project_dir().join("lib").join("target").exists());
+    let target_dir = harness.project_dir().join("target").join(TARGET);
+    let deps: Vec<_> =
+        fs::read_dir(target_dir.join("deps")).unwrap().map(|e| e.unwrap().path()).collect();
+    assert_eq!(deps.len(), 1);
+    assert!(deps[0].file_name().unwrap().to_string_lossy().starts_with("lib-"));
+    assert_eq!(fs::read_to_string(deps[0].join("util.o")).unwrap(), "obj:clif:util");
+    assert!(deps[0].join("outputs.json").is_file());
+
+    // Its outputs are not emitted again as the root project's own
+    let outputs = fs::read_to_string(target_dir.join("outputs.json")).unwrap();
+    assert!(!outputs.contains("util"));
+    assert_eq!(fs::read_to_string(target_dir.join("app")).unwrap(), "exe:obj:clif:hello");
+}
+
+#[tokio::test]
+#[ignore = "requires the hummanta binary, run with `just e2e`"]
+async fn test_build_without_toolchain_fails() {
+    let harness = harness().await;
+    harness.write("main.stub", "hello").unwrap();
+    harness
+        .write(
+            "hummanta.toml",
+            "[project]\nlanguage = \"stub\"\nextension = \"stub\"\ntarget = \"stub-vm\"\n",
+        )
+        .unwrap();
+
+    let output = harness.run(["build"]).unwrap();
+    assert!(!output.status.success());
+    assert!(!harness.project_dir().join("target").join(TARGET).join("main.clif").exists());
+}
+
+#[tokio::test]
+#[ignore = "requires the hummanta binary, run with `just e2e`"]
+async fn test_pipeline_runtimes() {
+    let mut harness = harness().await;
+    publish_runtime(&mut harness).await;
+    harness.write("main.stub", "hello").unwrap();
+
+    harness.hummanta(["toolchain", "add", LANGUAGE]).unwrap();
+    harness
+        .hummanta(["init", "--prefetch=false", "--gitignore=false", "--editorconfig=false"])
+        .unwrap();
+    harness.hummanta(["target", "add", TARGET]).unwrap();
+    harness.hummanta(["build", "--target", TARGET]).unwrap();
+
+    // The frontend found the runtime through the environment
+    let target_dir = harness.project_dir().join("target").join(TARGET);
+    assert_eq!(fs::read_to_string(target_dir.join("main.clif")).unwrap(), "rt:clif:hello");
+    assert_eq!(fs::read_to_string(target_dir.join("main.o")).unwrap(), "obj:rt:clif:hello");
+}
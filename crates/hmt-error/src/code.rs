@@ -0,0 +1,83 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The class of a failure, which determines the exit code.
+///
+/// Exit codes follow `sysexits.h`, so callers can tell failures worth
+/// retrying apart from ones that need the input fixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Category {
+    /// The command was used incorrectly.
+    Usage,
+    /// A manifest or other input is malformed.
+    Data,
+    /// A file, package or release does not exist.
+    NotFound,
+    /// A remote service is unreachable.
+    Unavailable,
+    /// Downloaded or built data does not match its recorded hash.
+    Integrity,
+    /// A policy or verification rejected the operation.
+    Denied,
+    /// The configuration is invalid.
+    Config,
+    /// Reading or writing a local file failed.
+    Io,
+    /// Anything else.
+    Internal,
+}
+
+impl Category {
+    /// Returns the exit code of the command line for the category.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Category::Usage => 64,
+            Category::Data | Category::Integrity => 65,
+            Category::NotFound => 66,
+            Category::Unavailable => 69,
+            Category::Internal => 70,
+            Category::Io => 74,
+            Category::Denied => 77,
+            Category::Config => 78,
+        }
+    }
+}
+
+/// A stable identifier of a failure, e.g. `fetch.hash-mismatch`.
+///
+/// Names are namespaced by the crate that raises them and never change
+/// meaning once published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Code {
+    pub name: &'static str,
+    pub category: Category,
+}
+
+impl Code {
+    /// Creates a code.
+    pub const fn new(name: &'static str, category: Category) -> Self {
+        Self { name, category }
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
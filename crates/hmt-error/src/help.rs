@@ -0,0 +1,90 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Remediation guidance for known failures, rendered below the error
+//! message as a `help:` block:
+//!
+//! ```text
+//! help: the downloaded file does not match its published checksum
+//!   - run the command again, the download may have been corrupted
+//!   - remove the cached download under `~/.hummanta/cache`
+//!   see: https://hummanta.github.io/docs/errors/hash-mismatch
+//! ```
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The root of the error documentation.
+pub const DOCS_URL: &str = "https://hummanta.github.io/docs/errors";
+
+/// Remediation guidance for a failure.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Help {
+    /// What the failure means, in plain words.
+    pub summary: String,
+    /// The steps that usually fix the failure, in the order to try them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<String>,
+    /// The documentation page of the failure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+}
+
+impl Help {
+    /// Creates guidance with the given summary and no steps.
+    pub fn new(summary: impl Into<String>) -> Self {
+        Self { summary: summary.into(), steps: Vec::new(), docs: None }
+    }
+
+    /// Adds a remediation step.
+    pub fn step(mut self, step: impl Into<String>) -> Self {
+        self.steps.push(step.into());
+        self
+    }
+
+    /// Links the documentation page of the failure, relative to [`DOCS_URL`].
+    pub fn page(mut self, page: &str) -> Self {
+        self.docs = Some(format!("{DOCS_URL}/{page}"));
+        self
+    }
+}
+
+impl fmt::Display for Help {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "help: {}", self.summary)?;
+        for step in &self.steps {
+            write!(f, "\n  - {step}")?;
+        }
+        if let Some(docs) = &self.docs {
+            write!(f, "\n  see: {docs}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_help_display() {
+        let help = Help::new("something failed").step("try again").step("ask").page("failure");
+        assert_eq!(
+            help.to_string(),
+            "help: something failed\n  - try again\n  - ask\n  see: https://hummanta.github.io/docs/errors/failure"
+        );
+        assert_eq!(Help::new("bare").to_string(), "help: bare");
+    }
+}
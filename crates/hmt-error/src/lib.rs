@@ -0,0 +1,39 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The error foundation shared by all crates.
+//!
+//! Error types implement [`Diagnostic`] to give each failure a stable
+//! [`Code`], which scripts match on and which maps to the exit code of the
+//! command line, and optionally remediation [`Help`]. An error and its
+//! chain of sources are captured in a serializable [`ErrorReport`].
+
+mod code;
+mod help;
+mod report;
+
+pub use code::{Category, Code};
+pub use help::{Help, DOCS_URL};
+pub use report::ErrorReport;
+
+/// An error with a stable code, and guidance on how it is usually fixed.
+pub trait Diagnostic {
+    /// Returns the code identifying the failure.
+    fn code(&self) -> Code;
+
+    /// Returns the remediation guidance, or `None` if there is none.
+    fn help(&self) -> Option<Help> {
+        None
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Category, Diagnostic, Help};
+
+/// The code of failures no [`Diagnostic`] describes.
+const UNKNOWN: &str = "unknown";
+
+/// A serializable record of an error and its chain of sources, as printed
+/// by JSON output modes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    /// The code of the innermost known error, or `unknown`.
+    pub code: String,
+    /// The category of the code.
+    pub category: Category,
+    /// The message of the outermost error.
+    pub message: String,
+    /// The messages of the sources, outermost first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub causes: Vec<String>,
+    /// How the failure is usually fixed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help: Option<Help>,
+}
+
+impl ErrorReport {
+    /// Captures an error chain, outermost first, taking the code and help
+    /// from the innermost error `diagnose` recognizes, which names the root
+    /// cause most precisely.
+    pub fn new<'a, I, F>(chain: I, diagnose: F) -> Self
+    where
+        I: IntoIterator<Item = &'a (dyn Error + 'static)>,
+        F: Fn(&'a (dyn Error + 'static)) -> Option<&'a dyn Diagnostic>,
+    {
+        let chain: Vec<_> = chain.into_iter().collect();
+        let diagnostic = chain.iter().rev().find_map(|cause| diagnose(*cause));
+
+        let mut messages = chain.iter().map(|cause| cause.to_string());
+        Self {
+            code: diagnostic.map_or(UNKNOWN, |d| d.code().name).to_string(),
+            category: diagnostic.map_or(Category::Internal, |d| d.code().category),
+            message: messages.next().unwrap_or_default(),
+            causes: messages.collect(),
+            help: diagnostic.and_then(Diagnostic::help),
+        }
+    }
+
+    /// Captures an error and its sources.
+    pub fn from_error<'a, F>(err: &'a (dyn Error + 'static), diagnose: F) -> Self
+    where
+        F: Fn(&'a (dyn Error + 'static)) -> Option<&'a dyn Diagnostic>,
+    {
+        Self::new(std::iter::successors(Some(err), |&e| e.source()), diagnose)
+    }
+
+    /// Returns the exit code of the command line for the failure.
+    pub fn exit_code(&self) -> i32 {
+        if self.code == UNKNOWN {
+            1
+        } else {
+            self.category.exit_code()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use thiserror::Error;
+
+    use super::*;
+    use crate::Code;
+
+    #[derive(Debug, Error)]
+    #[error("checksum mismatch")]
+    struct Mismatch;
+
+    impl Diagnostic for Mismatch {
+        fn code(&self) -> Code {
+            Code::new("test.mismatch", Category::Integrity)
+        }
+
+        fn help(&self) -> Option<Help> {
+            Some(Help::new("retry"))
+        }
+    }
+
+    #[derive(Debug, Error)]
+    #[error("install failed")]
+    struct Install(#[source] Mismatch);
+
+    fn diagnose<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a dyn Diagnostic> {
+        err.downcast_ref::<Mismatch>().map(|e| e as &dyn Diagnostic)
+    }
+
+    #[test]
+    fn test_error_report() {
+        let err = Install(Mismatch);
+        let report = ErrorReport::from_error(&err, diagnose);
+        assert_eq!(report.code, "test.mismatch");
+        assert_eq!(report.category, Category::Integrity);
+        assert_eq!(report.message, "install failed");
+        assert_eq!(report.causes, vec!["checksum mismatch"]);
+        assert_eq!(report.exit_code(), 65);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["category"], "integrity");
+        assert_eq!(json["help"]["summary"], "retry");
+
+        let unknown = ErrorReport::from_error(&std::fmt::Error, diagnose);
+        assert_eq!(unknown.code, "unknown");
+        assert_eq!(unknown.exit_code(), 1);
+    }
+}
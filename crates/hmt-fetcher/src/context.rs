@@ -14,6 +14,7 @@
 
 /// FetchContext is used to store context information related to fetch
 /// operations, including the URL, checksum, and its corresponding checksum URL.
+#[derive(Debug, Clone)]
 pub struct FetchContext {
     /// The URL to fetch data from.
     pub url: String,
@@ -21,12 +22,15 @@ pub struct FetchContext {
     pub checksum: Option<String>,
     /// The optional URL where the checksum can be fetched from.
     pub checksum_url: Option<String>,
+    /// Whether the data is registry metadata, which is requested
+    /// compressed. Artifacts are always fetched as they are stored.
+    pub metadata: bool,
 }
 
 impl FetchContext {
     /// Creates new instance with the specified URL.
     pub fn new(url: &str) -> Self {
-        Self { url: url.to_string(), checksum: None, checksum_url: None }
+        Self { url: url.to_string(), checksum: None, checksum_url: None, metadata: false }
     }
 
     /// Sets the checksum.
@@ -35,6 +39,17 @@ impl FetchContext {
         self
     }
 
+    /// Returns the same fetch from another URL, e.g. a mirror.
+    pub fn at(&self, url: &str) -> Self {
+        Self { url: url.to_string(), ..self.clone() }
+    }
+
+    /// Marks the data as registry metadata.
+    pub fn metadata(mut self) -> Self {
+        self.metadata = true;
+        self
+    }
+
     /// Sets the checksum url.
     pub fn checksum_url(mut self, checksum_url: &str) -> Self {
         self.checksum_url = Some(checksum_url.to_string());
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use hmt_error::{Category, Code, Diagnostic, Help};
 use thiserror::Error;
 
 /// Result type alias for fetcher operations
@@ -37,4 +38,47 @@ pub enum FetchError {
 
     #[error("Invalid path components: {0}")]
     InvalidPath(String),
+
+    #[error("Failed to decode response: {0}")]
+    DecodeError(String),
+}
+
+impl Diagnostic for FetchError {
+    fn code(&self) -> Code {
+        match self {
+            FetchError::InvalidUrl(_) => Code::new("fetch.invalid-url", Category::Usage),
+            FetchError::FileError(_) => Code::new("fetch.io", Category::Io),
+            FetchError::NetworkError(_) => Code::new("fetch.network", Category::Unavailable),
+            FetchError::HashMismatch(_) => Code::new("fetch.hash-mismatch", Category::Integrity),
+            FetchError::UnsupportedScheme(_) => {
+                Code::new("fetch.unsupported-scheme", Category::Usage)
+            }
+            FetchError::InvalidPath(_) => Code::new("fetch.invalid-path", Category::Usage),
+            FetchError::DecodeError(_) => Code::new("fetch.decode", Category::Data),
+        }
+    }
+
+    fn help(&self) -> Option<Help> {
+        let help = match self {
+            FetchError::HashMismatch(_) => {
+                Help::new("the downloaded file does not match its published checksum")
+                    .step("run the command again, the download may have been corrupted")
+                    .step("remove the cached download under `~/.hummanta/cache`")
+                    .step("if it persists, the release was changed after publishing; report it to the package maintainer")
+                    .page("hash-mismatch")
+            }
+            FetchError::NetworkError(_) => Help::new("the download server could not be reached")
+                .step("check your network connection and proxy settings")
+                .step("configure a mirror under [mirrors] in ~/.hummanta/config.toml")
+                .step("run with --offline to use installed packages only")
+                .page("network"),
+            FetchError::UnsupportedScheme(_) | FetchError::InvalidUrl(_) => {
+                Help::new("only http, https and file URLs can be fetched")
+                    .step("check the URL in the manifest or on the command line")
+                    .page("invalid-url")
+            }
+            _ => return None,
+        };
+        Some(help)
+    }
 }
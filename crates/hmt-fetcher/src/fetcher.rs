@@ -12,25 +12,65 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use hmt_utils::event::{warning, Event, LogReporter, Reporter};
 
 use crate::{
     context::FetchContext,
     errors::{FetchError, FetchResult},
     local::LocalFetcher,
     remote::RemoteFetcher,
+    stream::FetchStream,
     traits,
 };
 
 /// Manages multiple fetchers and routes requests based on URL scheme
 pub struct Fetcher {
     fetchers: HashMap<String, Arc<dyn traits::Fetcher + Send + Sync>>,
+    reporter: Arc<dyn Reporter>,
+    /// Alternative URL prefixes tried before each URL prefix, in order.
+    mirrors: BTreeMap<String, Vec<String>>,
 }
 
 impl Fetcher {
     /// Creates a new instance with default fetchers registered
     pub fn new() -> Self {
-        Self { fetchers: HashMap::new() }
+        Self { fetchers: HashMap::new(), reporter: LogReporter::shared(), mirrors: BTreeMap::new() }
+    }
+
+    /// Sets the reporter receiving the events of fetches.
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Sets the mirrors of URL prefixes. A URL starting with a mirrored
+    /// prefix is fetched from each mirror in turn, and from the URL itself
+    /// only once every mirror failed.
+    pub fn with_mirrors(mut self, mirrors: BTreeMap<String, Vec<String>>) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    /// Returns the reporter receiving the events of fetches, shared with
+    /// the components built on this fetcher.
+    pub fn reporter(&self) -> &Arc<dyn Reporter> {
+        &self.reporter
+    }
+
+    /// Creates a new instance with the given remote fetcher and the default
+    /// local fetcher registered.
+    pub fn with_remote(remote: RemoteFetcher) -> Self {
+        let mut fetcher = Self::new();
+
+        fetcher.register(Arc::new(remote));
+        fetcher.register(Arc::new(LocalFetcher));
+
+        fetcher
     }
 
     /// Registers a new fetcher implementation
@@ -40,14 +80,95 @@ impl Fetcher {
         }
     }
 
-    /// Fetches content from any supported source
+    /// Fetches content from any supported source, trying the mirrors of
+    /// the URL first
     pub async fn fetch(&self, context: &FetchContext) -> FetchResult<Vec<u8>> {
+        let mut urls = self.candidates(&context.url);
+        let url = urls.pop().expect("The URL itself is always a candidate");
+
+        for mirror in urls {
+            match self.fetch_from(&context.at(&mirror)).await {
+                Ok(data) => return Ok(data),
+                Err(e) => self
+                    .reporter
+                    .warn(warning::MIRROR_FAILED, format!("Mirror {mirror} failed: {e}")),
+            }
+        }
+        self.fetch_from(&context.at(&url)).await
+    }
+
+    /// Opens content from any supported source as a stream, trying the
+    /// mirrors of the URL first. Only failures to open fall back to the
+    /// next mirror, failures while reading fail the stream.
+    pub async fn open(&self, context: &FetchContext) -> FetchResult<FetchStream> {
+        let mut urls = self.candidates(&context.url);
+        let url = urls.pop().expect("The URL itself is always a candidate");
+
+        for mirror in urls {
+            match self.open_from(&context.at(&mirror)).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => self
+                    .reporter
+                    .warn(warning::MIRROR_FAILED, format!("Mirror {mirror} failed: {e}")),
+            }
+        }
+        self.open_from(&context.at(&url)).await
+    }
+
+    /// Opens content from the URL of the context as a stream
+    async fn open_from(&self, context: &FetchContext) -> FetchResult<FetchStream> {
+        let scheme = self.scheme(&context.url)?;
+        let fetcher =
+            self.fetchers.get(&scheme).ok_or_else(|| FetchError::UnsupportedScheme(scheme))?;
+
+        let stream = fetcher.open(context).await?;
+        Ok(stream.report_to(self.reporter.clone(), &context.url))
+    }
+
+    /// Returns the URLs content is fetched from, the mirrors first and the
+    /// URL itself last.
+    fn candidates(&self, url: &str) -> Vec<String> {
+        let mut urls: Vec<String> = Vec::new();
+        for (prefix, mirrors) in &self.mirrors {
+            let Some(rest) = url.strip_prefix(prefix.as_str()) else {
+                continue;
+            };
+            for mirror in mirrors {
+                let candidate = format!("{mirror}{rest}");
+                if !urls.contains(&candidate) {
+                    urls.push(candidate);
+                }
+            }
+        }
+        urls.retain(|candidate| candidate != url);
+        urls.push(url.to_string());
+        urls
+    }
+
+    /// Returns the size of the content in bytes without fetching it, from
+    /// the first of the mirrors and the URL itself that reports one
+    pub async fn size(&self, context: &FetchContext) -> Option<u64> {
+        for url in self.candidates(&context.url) {
+            let Ok(scheme) = self.scheme(&url) else { continue };
+            let Some(fetcher) = self.fetchers.get(&scheme) else { continue };
+            if let Ok(Some(size)) = fetcher.size(&context.at(&url)).await {
+                return Some(size);
+            }
+        }
+        None
+    }
+
+    /// Fetches content from the URL of the context
+    async fn fetch_from(&self, context: &FetchContext) -> FetchResult<Vec<u8>> {
         let scheme = self.scheme(&context.url)?;
 
         let fetcher =
             self.fetchers.get(&scheme).ok_or_else(|| FetchError::UnsupportedScheme(scheme))?;
 
-        fetcher.fetch(context).await
+        let data = fetcher.fetch(context).await?;
+        self.reporter.report(Event::Fetched { url: context.url.clone(), size: data.len() });
+
+        Ok(data)
     }
 
     /// Parse url and return scheme
@@ -62,13 +183,7 @@ impl Fetcher {
 impl Default for Fetcher {
     /// Holds the default fetcher instance.
     fn default() -> Self {
-        let mut fetcher = Self::new();
-
-        // Register default fetchers
-        fetcher.register(Arc::new(RemoteFetcher::new()));
-        fetcher.register(Arc::new(LocalFetcher));
-
-        fetcher
+        Self::with_remote(RemoteFetcher::new())
     }
 }
 
@@ -105,6 +220,32 @@ mod tests {
         assert_eq!(result.unwrap(), vec![1, 2, 3, 4]);
     }
 
+    #[tokio::test]
+    async fn test_fetcher_mirrors() {
+        let mut mirrors = BTreeMap::new();
+        mirrors.insert(
+            "https://github.com/".to_string(),
+            vec!["ftp://broken/".to_string(), "mock://mirror/github/".to_string()],
+        );
+
+        let mut fetcher = Fetcher::new().with_mirrors(mirrors);
+        fetcher.register(Arc::new(MockFetcher { schemes: vec!["mock"] }));
+
+        assert_eq!(
+            fetcher.candidates("https://github.com/hummanta/a.tar.gz"),
+            [
+                "ftp://broken/hummanta/a.tar.gz",
+                "mock://mirror/github/hummanta/a.tar.gz",
+                "https://github.com/hummanta/a.tar.gz",
+            ]
+        );
+        assert_eq!(fetcher.candidates("https://example.com/a"), ["https://example.com/a"]);
+
+        // The unsupported mirror is skipped, the next one serves the content
+        let context = FetchContext::new("https://github.com/hummanta/a.tar.gz");
+        assert_eq!(fetcher.fetch(&context).await.unwrap(), vec![1, 2, 3, 4]);
+    }
+
     #[tokio::test]
     async fn test_fetcher_invalid_url() {
         let fetcher = Fetcher::new();
@@ -17,8 +17,13 @@ pub mod errors;
 pub mod fetcher;
 pub mod local;
 pub mod remote;
+pub mod stream;
 pub mod traits;
+pub mod webhook;
 
 // Re-exports
 pub use context::FetchContext;
 pub use fetcher::Fetcher;
+pub use remote::RemoteFetcher;
+pub use stream::FetchStream;
+pub use webhook::Webhook;
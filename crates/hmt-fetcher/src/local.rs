@@ -14,11 +14,12 @@
 
 use async_trait::async_trait;
 use hmt_utils::checksum;
-use tokio::fs;
+use tokio::{fs, io::AsyncReadExt};
 
 use crate::{
     context::FetchContext,
     errors::{FetchError, FetchResult},
+    stream::FetchStream,
     traits::Fetcher,
 };
 
@@ -29,6 +30,14 @@ impl LocalFetcher {
     pub async fn read(&self, url: &str) -> FetchResult<Vec<u8>> {
         Ok(fs::read(url.trim_start_matches("file://")).await?)
     }
+
+    /// Resolves the hash the content is expected to have, if any.
+    async fn expected_hash(&self, context: &FetchContext) -> FetchResult<Option<String>> {
+        Ok(match &context.checksum_url {
+            Some(url) => Some(String::from_utf8_lossy(&self.read(url).await?).into_owned()),
+            None => context.checksum.clone(),
+        })
+    }
 }
 
 #[async_trait]
@@ -38,18 +47,41 @@ impl Fetcher for LocalFetcher {
         let data = self.read(&context.url).await?;
 
         // Resolve checksum and verify checksum if provided
-        if let Some(checksum) = match &context.checksum_url {
-            Some(url) => Some(self.read(url).await?),
-            None => context.checksum.as_ref().map(|s| s.as_bytes().to_vec()),
-        } {
-            let expected_hash = std::str::from_utf8(&checksum).unwrap();
-            checksum::verify(&data, expected_hash)
-                .map_err(|_| FetchError::HashMismatch(expected_hash.to_string()))?;
+        if let Some(expected_hash) = self.expected_hash(context).await? {
+            checksum::verify(&data, &expected_hash)
+                .map_err(|_| FetchError::HashMismatch(expected_hash))?;
         }
 
         Ok(data)
     }
 
+    async fn open(&self, context: &FetchContext) -> FetchResult<FetchStream> {
+        let expected = self.expected_hash(context).await?;
+        let mut file = fs::File::open(context.url.trim_start_matches("file://")).await?;
+
+        let (sender, stream) = FetchStream::channel(expected);
+        tokio::spawn(async move {
+            loop {
+                let mut chunk = vec![0; checksum::BUFFER_SIZE];
+                let chunk = match file.read(&mut chunk).await {
+                    Ok(0) => break,
+                    Ok(read) => Ok(chunk[..read].to_vec()),
+                    Err(e) => Err(e),
+                };
+                let failed = chunk.is_err();
+                if sender.send(chunk).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(stream)
+    }
+
+    async fn size(&self, context: &FetchContext) -> FetchResult<Option<u64>> {
+        Ok(Some(fs::metadata(context.url.trim_start_matches("file://")).await?.len()))
+    }
+
     fn supported_schemes(&self) -> Vec<&'static str> {
         vec!["file"]
     }
@@ -78,6 +110,16 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_local_fetcher_size() {
+        let temp_file = NamedTempFile::new().unwrap();
+        write(temp_file.path(), b"test data").await.unwrap();
+
+        let context = FetchContext::new(&format!("file://{}", temp_file.path().display()));
+        assert_eq!(LocalFetcher.size(&context).await.unwrap(), Some(9));
+        assert!(LocalFetcher.size(&FetchContext::new("file://dummy_path")).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_local_fetcher_hash_mismatch() {
         let context = FetchContext::new("file://dummy_path").checksum("incorrect_hash");
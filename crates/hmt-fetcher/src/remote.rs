@@ -12,35 +12,154 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::io::{self, Read};
+
 use async_trait::async_trait;
+use flate2::read::GzDecoder;
 use hmt_utils::checksum;
-use reqwest::Client;
+use reqwest::{
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, USER_AGENT},
+    Client, Response,
+};
 
 use crate::{
     context::FetchContext,
     errors::{FetchError, FetchResult},
+    stream::FetchStream,
     traits::Fetcher,
 };
 
+/// The content codings requested for metadata and decoded transparently.
+const ENCODINGS: &str = "gzip";
+
+/// The largest decoded metadata response accepted, bounding the memory a
+/// compressed response can expand to.
+pub const MAX_DECODED_SIZE: u64 = 64 * 1024 * 1024;
+
 /// Fetcher implementation for HTTP/HTTPS resources
+///
+/// Registry metadata is requested gzip-compressed, which large manifests
+/// shrink well under, and decoded before checksums are verified. Other
+/// resources, such as artifacts, are requested without content coding, so
+/// an already compressed archive is hashed exactly as it was published.
 pub struct RemoteFetcher {
     client: Client,
+    /// Extra headers attached to every outgoing request.
+    headers: Vec<(String, String)>,
 }
 
 impl RemoteFetcher {
     /// Creates a new RemoteFetcher with default client
     pub fn new() -> Self {
-        Self { client: Client::new() }
+        Self { client: Client::new(), headers: Vec::new() }
+    }
+
+    /// Sets the `User-Agent` header sent with every request.
+    pub fn user_agent(self, user_agent: &str) -> Self {
+        self.header(USER_AGENT.as_str(), user_agent)
     }
 
+    /// Adds a header sent with every request.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Fetches a resource as it is stored.
     pub async fn get(&self, url: &str) -> FetchResult<Vec<u8>> {
-        let response = self.client.get(url).send().await?;
+        self.request(url, false).await
+    }
+
+    /// Fetches a resource, requesting it compressed and decoding it.
+    pub async fn get_compressed(&self, url: &str) -> FetchResult<Vec<u8>> {
+        self.request(url, true).await
+    }
+
+    async fn request(&self, url: &str, compressed: bool) -> FetchResult<Vec<u8>> {
+        let response = self.send(url, compressed).await?;
+
+        // Only a requested coding is decoded, an unsolicited one is part of
+        // the resource
+        let encoding = response
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+            .filter(|_| compressed);
+        decode(encoding.as_deref(), response.bytes().await?.to_vec())
+    }
+
+    /// Sends a GET request, failing unless it succeeds.
+    async fn send(&self, url: &str, compressed: bool) -> FetchResult<Response> {
+        let mut request = self.client.get(url);
+        if compressed {
+            request = request.header(ACCEPT_ENCODING, ENCODINGS);
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let response = request.send().await?;
 
         if !response.status().is_success() {
             return Err(FetchError::NetworkError(response.error_for_status().unwrap_err()));
         }
 
-        Ok(response.bytes().await?.to_vec())
+        Ok(response)
+    }
+
+    /// Resolves the hash the content is expected to have, if any.
+    async fn expected_hash(&self, context: &FetchContext) -> FetchResult<Option<String>> {
+        Ok(match &context.checksum_url {
+            Some(url) => Some(String::from_utf8_lossy(&self.get(url).await?).into_owned()),
+            None => context.checksum.clone(),
+        })
+    }
+
+    /// Sends a HEAD request, failing unless the resource is reachable.
+    /// Returns the `Content-Length` of the resource, if reported.
+    pub async fn head(&self, url: &str) -> FetchResult<Option<u64>> {
+        let mut request = self.client.head(url);
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let response = request.send().await?.error_for_status()?;
+        Ok(response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok()))
+    }
+
+    /// Sends a POST request with the given body and extra headers.
+    pub async fn post(
+        &self,
+        url: &str,
+        body: Vec<u8>,
+        headers: &[(&str, &str)],
+    ) -> FetchResult<()> {
+        let mut request = self.client.post(url).body(body);
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Sends a PUT request storing the given body at `url`.
+    pub async fn put(&self, url: &str, body: Vec<u8>) -> FetchResult<()> {
+        let mut request = self.client.put(url).body(body);
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        request.send().await?.error_for_status()?;
+        Ok(())
     }
 }
 
@@ -48,26 +167,83 @@ impl RemoteFetcher {
 impl Fetcher for RemoteFetcher {
     async fn fetch(&self, context: &FetchContext) -> FetchResult<Vec<u8>> {
         // Download main content
-        let data = self.get(&context.url).await?;
+        let data = match context.metadata {
+            true => self.get_compressed(&context.url).await?,
+            false => self.get(&context.url).await?,
+        };
 
         // Resolve checksum and verify checksum if provided
-        if let Some(checksum) = match &context.checksum_url {
-            Some(url) => Some(self.get(url).await?),
-            None => context.checksum.as_ref().map(|s| s.as_bytes().to_vec()),
-        } {
-            let expected_hash = std::str::from_utf8(&checksum).unwrap();
-            checksum::verify(&data, expected_hash)
-                .map_err(|_| FetchError::HashMismatch(expected_hash.to_string()))?;
+        if let Some(expected_hash) = self.expected_hash(context).await? {
+            checksum::verify(&data, &expected_hash)
+                .map_err(|_| FetchError::HashMismatch(expected_hash))?;
         }
 
         Ok(data)
     }
 
+    /// Streams artifacts chunk by chunk as they arrive. Metadata is decoded
+    /// and fetched as a whole, as it is small.
+    async fn open(&self, context: &FetchContext) -> FetchResult<FetchStream> {
+        if context.metadata {
+            return Ok(FetchStream::from_bytes(self.fetch(context).await?));
+        }
+
+        let expected = self.expected_hash(context).await?;
+        let mut response = self.send(&context.url, false).await?;
+
+        let (sender, stream) = FetchStream::channel(expected);
+        tokio::spawn(async move {
+            loop {
+                let chunk = match response.chunk().await {
+                    Ok(Some(chunk)) => Ok(chunk.to_vec()),
+                    Ok(None) => break,
+                    Err(e) => Err(io::Error::other(e)),
+                };
+                let failed = chunk.is_err();
+                if sender.send(chunk).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(stream)
+    }
+
+    async fn size(&self, context: &FetchContext) -> FetchResult<Option<u64>> {
+        self.head(&context.url).await
+    }
+
     fn supported_schemes(&self) -> Vec<&'static str> {
         vec!["http", "https"]
     }
 }
 
+/// Decodes a response body by its `Content-Encoding`, failing when it
+/// decodes to more than [`MAX_DECODED_SIZE`] bytes.
+fn decode(encoding: Option<&str>, body: Vec<u8>) -> FetchResult<Vec<u8>> {
+    decode_limited(encoding, body, MAX_DECODED_SIZE)
+}
+
+fn decode_limited(encoding: Option<&str>, body: Vec<u8>, limit: u64) -> FetchResult<Vec<u8>> {
+    match encoding.map(|e| e.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("") | Some("identity") => Ok(body),
+        Some("gzip") | Some("x-gzip") => {
+            let mut data = Vec::new();
+            GzDecoder::new(body.as_slice())
+                .take(limit + 1)
+                .read_to_end(&mut data)
+                .map_err(|e| FetchError::DecodeError(e.to_string()))?;
+            if data.len() as u64 > limit {
+                return Err(FetchError::DecodeError(format!(
+                    "response decodes to more than {limit} bytes"
+                )));
+            }
+            Ok(data)
+        }
+        Some(other) => Err(FetchError::DecodeError(format!("unsupported encoding: {other}"))),
+    }
+}
+
 impl Default for RemoteFetcher {
     fn default() -> Self {
         Self::new()
@@ -77,38 +253,15 @@ impl Default for RemoteFetcher {
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
-    use tokio::{
-        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-        net::TcpListener,
-    };
 
-    use super::*;
+    use hmt_e2e::{MockServer, Response};
 
-    async fn start_mock_server() -> String {
-        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
-        let addr = listener.local_addr().unwrap();
-        let url = format!("http://{addr}");
-
-        tokio::spawn(async move {
-            let (mut socket, _) = listener.accept().await.unwrap();
-            let mut reader = BufReader::new(&mut socket);
-            let mut request = String::new();
-            reader.read_line(&mut request).await.unwrap();
-
-            let response = "HTTP/1.1 200 OK\r\n\
-                          Content-Length: 9\r\n\
-                          \r\n\
-                          test data";
-            socket.write_all(response.as_bytes()).await.unwrap();
-        });
-
-        url
-    }
+    use super::*;
 
     #[tokio::test]
     async fn test_remote_fetcher_success() {
-        let url = start_mock_server().await;
-        let context = FetchContext::new(&url)
+        let server = MockServer::body("test data").await;
+        let context = FetchContext::new(&server.url("/"))
             .checksum("916f0027a575074ce72a331777c3478d6513f786a591bd892da1a577bf2335f9");
 
         let fetcher = Arc::new(RemoteFetcher::new());
@@ -116,6 +269,97 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_remote_fetcher_open() {
+        let server = MockServer::body("test data").await;
+        let context = FetchContext::new(&server.url("/"))
+            .checksum("916f0027a575074ce72a331777c3478d6513f786a591bd892da1a577bf2335f9");
+
+        let mut stream = RemoteFetcher::new().open(&context).await.unwrap();
+        let data = tokio::task::spawn_blocking(move || {
+            let mut data = Vec::new();
+            stream.read_to_end(&mut data).map(|_| data)
+        });
+        assert_eq!(data.await.unwrap().unwrap(), b"test data");
+    }
+
+    #[tokio::test]
+    async fn test_remote_fetcher_sends_headers() {
+        let server = MockServer::body("").await;
+
+        let fetcher = RemoteFetcher::new().user_agent("hummanta/test").header("x-trace-id", "abc");
+        fetcher.fetch(&FetchContext::new(&server.url("/"))).await.unwrap();
+
+        let request = &server.requests()[0].head;
+        assert!(request.contains("user-agent: hummanta/test"));
+        assert!(request.contains("x-trace-id: abc"));
+    }
+
+    #[tokio::test]
+    async fn test_remote_fetcher_decompresses() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"test data").unwrap();
+        let body = encoder.finish().unwrap();
+
+        let response = Response::ok(body).header("Content-Encoding", "gzip");
+        let server = MockServer::start(move |_| response.clone()).await;
+
+        let context = FetchContext::new(&server.url("/"))
+            .checksum("916f0027a575074ce72a331777c3478d6513f786a591bd892da1a577bf2335f9")
+            .metadata();
+        let data = RemoteFetcher::new().fetch(&context).await.unwrap();
+        assert_eq!(data, b"test data");
+
+        assert!(server.requests()[0].head.contains("accept-encoding: gzip"));
+    }
+
+    #[tokio::test]
+    async fn test_remote_fetcher_keeps_artifact_encoding() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        // An archive served with a gzip coding it was not asked for
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"archive").unwrap();
+        let body = encoder.finish().unwrap();
+        let hash = checksum::digest(&body);
+
+        let response = Response::ok(body).header("Content-Encoding", "gzip");
+        let server = MockServer::start(move |_| response.clone()).await;
+
+        let context = FetchContext::new(&server.url("/")).checksum(&hash);
+        RemoteFetcher::new().fetch(&context).await.unwrap();
+
+        assert!(!server.requests()[0].head.contains("accept-encoding"));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(None, b"data".to_vec()).unwrap(), b"data");
+        assert_eq!(decode(Some("identity"), b"data".to_vec()).unwrap(), b"data");
+        assert!(matches!(decode(Some("gzip"), b"data".to_vec()), Err(FetchError::DecodeError(_))));
+        assert!(matches!(decode(Some("br"), b"data".to_vec()), Err(FetchError::DecodeError(_))));
+    }
+
+    #[test]
+    fn test_decode_limited() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[0; 1024]).unwrap();
+        let body = encoder.finish().unwrap();
+
+        assert_eq!(decode_limited(Some("gzip"), body.clone(), 1024).unwrap().len(), 1024);
+        assert!(matches!(
+            decode_limited(Some("gzip"), body, 1023),
+            Err(FetchError::DecodeError(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_remote_fetcher_network_error() {
         let context = FetchContext::new("http://invalid-url").checksum("dummy_hash");
@@ -127,8 +371,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_remote_fetcher_hash_mismatch() {
-        let url = start_mock_server().await;
-        let context = FetchContext::new(&url).checksum("incorrect_hash");
+        let server = MockServer::body("test data").await;
+        let context = FetchContext::new(&server.url("/")).checksum("incorrect_hash");
 
         let fetcher = Arc::new(RemoteFetcher::new());
         let result = fetcher.fetch(&context).await;
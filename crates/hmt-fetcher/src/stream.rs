@@ -0,0 +1,181 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Content read while it is fetched.
+
+use std::{
+    io::{self, Read},
+    sync::Arc,
+};
+
+use hmt_utils::{
+    checksum::HashReader,
+    event::{Event, Reporter},
+};
+use tokio::sync::mpsc;
+
+use crate::errors::FetchError;
+
+/// The number of chunks fetched ahead of the reader, bounding the memory a
+/// stream holds however large the content is.
+const BUFFERED_CHUNKS: usize = 16;
+
+/// Sends the chunks of fetched content to its [`FetchStream`].
+pub type ChunkSender = mpsc::Sender<io::Result<Vec<u8>>>;
+
+/// The content of a fetch, read while it arrives.
+///
+/// Reads wait for the next chunk, so read it on a blocking thread, e.g.
+/// with `spawn_blocking`. The content is hashed as it is read, and reading
+/// its end fails with [`FetchError::HashMismatch`] if the hash differs from
+/// the expected one. Content read before the end is unverified, so only
+/// use it once the stream is read to its end.
+pub struct FetchStream {
+    reader: HashReader<Chunks>,
+    expected: Option<String>,
+    /// The number of bytes read so far.
+    size: usize,
+    /// Whether the end of the content was read.
+    ended: bool,
+    /// The reporter told about the fetch once the content is read, with the
+    /// URL it was fetched from.
+    reporter: Option<(Arc<dyn Reporter>, String)>,
+}
+
+impl FetchStream {
+    /// Creates a stream of the chunks sent through the returned sender,
+    /// verified against the `expected` hash if given. The content ends once
+    /// the sender is dropped, and fails with the first error sent.
+    pub fn channel(expected: Option<String>) -> (ChunkSender, Self) {
+        let (sender, receiver) = mpsc::channel(BUFFERED_CHUNKS);
+        let chunks = Chunks { receiver, chunk: Vec::new(), pos: 0 };
+        let stream = Self {
+            reader: HashReader::new(chunks),
+            expected,
+            size: 0,
+            ended: false,
+            reporter: None,
+        };
+        (sender, stream)
+    }
+
+    /// Creates a stream of content fetched and verified as a whole.
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        let (sender, stream) = Self::channel(None);
+        sender.try_send(Ok(data)).expect("A new channel has room for a chunk");
+        stream
+    }
+
+    /// Reports the fetch from `url` as [`Event::Fetched`] once the content
+    /// is read and verified.
+    pub fn report_to(mut self, reporter: Arc<dyn Reporter>, url: &str) -> Self {
+        self.reporter = Some((reporter, url.to_string()));
+        self
+    }
+
+    /// Verifies the hash of the content read, at its end.
+    fn verify(&mut self) -> io::Result<()> {
+        if let Some(expected) = &self.expected {
+            if self.reader.digest() != *expected {
+                let error = FetchError::HashMismatch(expected.clone());
+                return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+            }
+        }
+        if let Some((reporter, url)) = self.reporter.take() {
+            reporter.report(Event::Fetched { url, size: self.size });
+        }
+        Ok(())
+    }
+}
+
+impl Read for FetchStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.size += read;
+        if read == 0 && !buf.is_empty() && !self.ended {
+            self.ended = true;
+            self.verify()?;
+        }
+        Ok(read)
+    }
+}
+
+/// Returns the fetch error an error reading a [`FetchStream`] carries, from
+/// among `error` and its sources.
+pub fn stream_error<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a FetchError> {
+    std::iter::successors(Some(error), |error| error.source()).find_map(|cause| {
+        let io = cause.downcast_ref::<io::Error>()?;
+        io.get_ref()?.downcast_ref::<FetchError>()
+    })
+}
+
+/// The chunks received from the fetch, read in order.
+struct Chunks {
+    receiver: mpsc::Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for Chunks {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => (self.chunk, self.pos) = (chunk?, 0),
+                None => return Ok(0),
+            }
+        }
+
+        let read = buf.len().min(self.chunk.len() - self.pos);
+        buf[..read].copy_from_slice(&self.chunk[self.pos..self.pos + read]);
+        self.pos += read;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hmt_utils::checksum;
+
+    use super::*;
+
+    fn read(stream: FetchStream) -> io::Result<Vec<u8>> {
+        let mut stream = stream;
+        let mut data = Vec::new();
+        stream.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    #[test]
+    fn test_fetch_stream_verifies_at_end() {
+        let (sender, stream) = FetchStream::channel(Some(checksum::digest(b"test data")));
+        sender.try_send(Ok(b"test ".to_vec())).unwrap();
+        sender.try_send(Ok(b"data".to_vec())).unwrap();
+        drop(sender);
+        assert_eq!(read(stream).unwrap(), b"test data");
+
+        let (sender, stream) = FetchStream::channel(Some("incorrect_hash".into()));
+        sender.try_send(Ok(b"test data".to_vec())).unwrap();
+        drop(sender);
+        let error = read(stream).unwrap_err();
+        assert!(matches!(stream_error(&error), Some(FetchError::HashMismatch(_))));
+    }
+
+    #[test]
+    fn test_fetch_stream_fails_with_sent_error() {
+        let (sender, stream) = FetchStream::channel(None);
+        sender.try_send(Err(io::Error::other("connection reset"))).unwrap();
+        assert!(read(stream).is_err());
+        assert_eq!(read(FetchStream::from_bytes(b"data".to_vec())).unwrap(), b"data");
+    }
+}
@@ -14,7 +14,7 @@
 
 use async_trait::async_trait;
 
-use crate::{context::FetchContext, errors::FetchResult};
+use crate::{context::FetchContext, errors::FetchResult, stream::FetchStream};
 
 /// Defines the common interface for all fetchers
 #[async_trait]
@@ -22,6 +22,18 @@ pub trait Fetcher {
     /// Fetches content from source and verifies its hash
     async fn fetch(&self, context: &FetchContext) -> FetchResult<Vec<u8>>;
 
+    /// Opens the content as a stream, verifying its hash once read. By
+    /// default, the content is fetched as a whole first
+    async fn open(&self, context: &FetchContext) -> FetchResult<FetchStream> {
+        Ok(FetchStream::from_bytes(self.fetch(context).await?))
+    }
+
+    /// Returns the size of the content in bytes without fetching it, if the
+    /// source reports one
+    async fn size(&self, _context: &FetchContext) -> FetchResult<Option<u64>> {
+        Ok(None)
+    }
+
     /// Returns supported URL schemes (e.g., ["http", "https"])
     fn supported_schemes(&self) -> Vec<&'static str>;
 }
@@ -0,0 +1,126 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use crate::{errors::FetchError, remote::RemoteFetcher};
+
+/// The header carrying the hex-encoded signature of the payload.
+pub const SIGNATURE_HEADER: &str = "X-Hummanta-Signature";
+
+/// Delivers signed JSON payloads to a set of HTTP endpoints.
+pub struct Webhook {
+    fetcher: RemoteFetcher,
+    endpoints: Vec<String>,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Webhook {
+    /// Creates a webhook delivering to the given endpoints.
+    pub fn new(endpoints: Vec<String>) -> Self {
+        Self {
+            fetcher: RemoteFetcher::new(),
+            endpoints,
+            retries: 3,
+            backoff: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets the number of retries after a failed delivery.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets the delay before the first retry, doubled on every further one.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Posts the payload to every endpoint, returning those that still
+    /// failed after all retries.
+    pub async fn send(&self, payload: &[u8], signature: &str) -> Vec<(String, FetchError)> {
+        let mut failures = Vec::new();
+        for endpoint in &self.endpoints {
+            if let Err(e) = self.deliver(endpoint, payload, signature).await {
+                failures.push((endpoint.clone(), e));
+            }
+        }
+        failures
+    }
+
+    /// Posts the payload to a single endpoint, retrying with exponential backoff.
+    async fn deliver(
+        &self,
+        endpoint: &str,
+        payload: &[u8],
+        signature: &str,
+    ) -> Result<(), FetchError> {
+        let headers = [("Content-Type", "application/json"), (SIGNATURE_HEADER, signature)];
+
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+        loop {
+            match self.fetcher.post(endpoint, payload.to_vec(), &headers).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.retries => return Err(e),
+                Err(_) => {
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use hmt_e2e::{MockServer, Response};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_webhook_retries() {
+        // Fails the first request, accepts the second
+        let attempts = AtomicUsize::new(0);
+        let server = MockServer::start(move |_| match attempts.fetch_add(1, Ordering::SeqCst) {
+            0 => Response::status("500 Internal Server Error"),
+            _ => Response::ok(""),
+        })
+        .await;
+
+        let webhook = Webhook::new(vec![server.url("/")]).backoff(Duration::from_millis(1));
+        let failures = webhook.send(b"{}", "abc").await;
+        assert!(failures.is_empty());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].method, "POST");
+        assert!(requests[1].head.contains("x-hummanta-signature: abc"));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_gives_up() {
+        let webhook = Webhook::new(vec!["http://invalid-url".to_string()])
+            .retries(1)
+            .backoff(Duration::from_millis(1));
+        let failures = webhook.send(b"{}", "abc").await;
+        assert_eq!(failures.len(), 1);
+    }
+}
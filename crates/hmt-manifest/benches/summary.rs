@@ -0,0 +1,101 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares reading the latest version of a package manifest through the
+//! full `PackageManifest` with the borrowed `PackageSummary`, by wall time
+//! and by the allocations made while parsing.
+//!
+//! The number of releases in the manifest is set with
+//! `HUMMANTA_BENCH_RELEASES` (default 500).
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use hmt_manifest::{Package, PackageManifest, PackageSummary};
+use hmt_utils::bytes::FromSlice;
+
+/// The number of runs each parser is measured over.
+const RUNS: usize = 21;
+
+/// Counts the allocations and allocated bytes of the process.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+// SAFETY: every call is forwarded unchanged to the system allocator
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc`
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::dealloc`
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn main() {
+    let releases =
+        std::env::var("HUMMANTA_BENCH_RELEASES").ok().and_then(|v| v.parse().ok()).unwrap_or(500);
+
+    let package = Package { name: "solidity-compiler".into(), ..Default::default() };
+    let mut manifest = PackageManifest::new(package, format!("v1.{}.0", releases - 1));
+    for i in 0..releases {
+        manifest.add_release(format!("v1.{i}.0"), format!("release-v1.{i}.0.toml"));
+    }
+    let content = toml::to_string(&manifest).expect("Failed to serialize manifest");
+    println!("{releases} releases, {} KiB", content.len() >> 10);
+
+    measure("PackageManifest", || {
+        let manifest = PackageManifest::from_slice(content.as_bytes()).unwrap();
+        assert_eq!(manifest.latest, format!("v1.{}.0", releases - 1));
+    });
+    measure("PackageSummary", || {
+        let summary = PackageSummary::parse(content.as_bytes()).unwrap();
+        assert_eq!(summary.latest, format!("v1.{}.0", releases - 1));
+    });
+}
+
+/// Prints the median wall time of `f`, and the allocations of one run.
+fn measure(name: &str, mut f: impl FnMut()) {
+    let (allocations, bytes) = (ALLOCATIONS.load(Ordering::Relaxed), BYTES.load(Ordering::Relaxed));
+    f();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let bytes = BYTES.load(Ordering::Relaxed) - bytes;
+
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+
+    println!(
+        "{name:<16} {:>10.2?} {allocations:>8} allocations {:>8} KiB",
+        times[RUNS / 2],
+        bytes >> 10
+    );
+}
@@ -34,4 +34,28 @@ pub struct Args {
     /// Version to publish
     #[arg(long)]
     pub version: String,
+
+    /// Release channel pointed at the published version
+    #[arg(long, default_value = "nightly")]
+    pub channel: String,
+
+    /// Endpoint notified with a signed JSON payload once published, repeatable
+    #[arg(long = "webhook", value_name = "URL")]
+    pub webhooks: Vec<String>,
+
+    /// How often a failed webhook delivery is retried
+    #[arg(long, default_value_t = 3)]
+    pub webhook_retries: u32,
+
+    /// Path to the hex-encoded Ed25519 key signing the manifest and webhook payloads
+    #[arg(long, env = "HUMMANTA_SIGNING_KEY")]
+    pub signing_key: Option<PathBuf>,
+
+    /// Perform every step, but write into a staging directory and notify no webhook
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Staging directory of a dry run, by default in the temporary directory
+    #[arg(long, requires = "dry_run")]
+    pub stage_dir: Option<PathBuf>,
 }
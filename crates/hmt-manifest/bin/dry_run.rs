@@ -0,0 +1,90 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Publishing into a staging directory instead of the registry.
+//!
+//! A dry run performs every step of a publish, but writes the manifests
+//! into a staging directory seeded with the published package manifest,
+//! and notifies no webhook. The staged tree is exactly what a publish
+//! would write, and is linted like a registry submission.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+use hmt_manifest::{PackageManifest, Submission, Verdict, PACKAGE_MANIFEST};
+use hmt_utils::{
+    checksum,
+    signature::{self, SigningKey},
+};
+
+/// Prepares an empty staging directory holding a copy of the published
+/// package manifest, if any, so it is updated as a publish would.
+pub fn stage(output_dir: &Path, stage_dir: &Path) -> Result<()> {
+    if stage_dir.exists() {
+        fs::remove_dir_all(stage_dir)
+            .context(format!("Failed to clear {}", stage_dir.display()))?;
+    }
+    fs::create_dir_all(stage_dir)?;
+
+    let published = output_dir.join(PACKAGE_MANIFEST);
+    if published.exists() {
+        fs::copy(&published, stage_dir.join(PACKAGE_MANIFEST))?;
+    }
+
+    Ok(())
+}
+
+/// Lints the staged package manifest with every release it references,
+/// taken from the staging directory or else the published ones.
+pub fn validate(output_dir: &Path, stage_dir: &Path) -> Result<Verdict> {
+    let package = fs::read_to_string(stage_dir.join(PACKAGE_MANIFEST))?;
+    let mut submission = Submission { package, ..Default::default() };
+
+    let manifest: PackageManifest = submission.package.parse()?;
+    for file in manifest.releases.values() {
+        let path =
+            [stage_dir, output_dir].map(|dir| dir.join(file)).into_iter().find(|p| p.exists());
+        if let Some(path) = path {
+            submission.releases.insert(file.clone(), fs::read_to_string(path)?);
+        }
+    }
+
+    Ok(submission.lint())
+}
+
+/// Checks that webhook payloads signed with the key verify against its
+/// public key, as receivers authenticate them.
+pub fn check_auth(key: &SigningKey, payload: &[u8]) -> Result<()> {
+    signature::verify(&key.public_key(), payload, &key.sign(payload))
+        .context("Webhook payloads would fail verification")
+}
+
+/// Lists the staged files, relative to the staging directory, with their
+/// size and SHA-256 hash.
+pub fn tree(stage_dir: &Path) -> Result<Vec<(String, u64, String)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(stage_dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            bail!("Unexpected staged entry: {}", path.display());
+        }
+        let data = fs::read(&path)?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        files.push((name, data.len() as u64, checksum::digest(&data)));
+    }
+
+    files.sort();
+    Ok(files)
+}
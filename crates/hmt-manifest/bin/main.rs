@@ -13,15 +13,20 @@
 // limitations under the License.
 
 mod args;
+mod dry_run;
 mod package;
 mod release;
 
+use std::path::Path;
+
 use anyhow::{anyhow, Context, Result};
 use args::Args;
 use clap::Parser;
 
-use hmt_manifest::{ManifestFile, Package};
-use tracing::info;
+use hmt_fetcher::Webhook;
+use hmt_manifest::{ManifestFile, Package, ReleaseManifest, ReleaseNotification, Severity};
+use hmt_utils::signature::{SigningKey, SIGNATURE_FILE_SUFFIX};
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -29,28 +34,121 @@ async fn main() -> Result<()> {
     let version = &args.version;
 
     // load package configuration
-    let package = Package::load(&args.package)
+    let mut package = Package::load(&args.package)
         .context(format!("Failed to read package config from file: {}", args.package.display()))?;
 
+    // Check the signing key up front, so a missing key fails before publishing
+    let key = args.signing_key.as_deref().map(SigningKey::load).transpose()?;
+    if key.is_none() && !args.webhooks.is_empty() {
+        return Err(anyhow!("--signing-key is required to sign webhook payloads"));
+    }
+
+    let index_path = args.output_dir.join("index.toml");
+    package::verify_owner(&package, &index_path, key.as_ref())?;
+    if let Some(key) = &key {
+        package::add_public_key(&mut package, key);
+    }
+
     if !args.artifacts_dir.exists() {
         return Err(anyhow!("Artifacts dir does not exist: {}", args.artifacts_dir.display()));
     }
 
-    // Create output directory if it doesn't exist
-    std::fs::create_dir_all(&args.output_dir)?;
+    // Dry runs publish into a staging directory seeded with the published
+    // package manifest
+    let publish_dir = if args.dry_run {
+        let stage_dir = args.stage_dir.clone().unwrap_or_else(|| {
+            std::env::temp_dir().join(format!("hmt-publish-{}-{version}", package.name))
+        });
+        dry_run::stage(&args.output_dir, &stage_dir)?;
+        stage_dir
+    } else {
+        // Create output directory if it doesn't exist
+        std::fs::create_dir_all(&args.output_dir)?;
+        args.output_dir.clone()
+    };
+    let index_path = publish_dir.join("index.toml");
 
     // Generate release manifest and save to path
-    let release = release::generate(&package, &args.artifacts_dir, version)?;
-    release.save(args.output_dir.join(format!("release-{version}.toml")))?;
+    let release = release::generate(&package, &args.artifacts_dir, version, key.as_ref()).await?;
+    release.save(publish_dir.join(format!("release-{version}.toml")))?;
 
     // Update or create package manifest
-    let index_path = args.output_dir.join("index.toml");
     if index_path.exists() {
-        package::update(&package, &index_path, version)?;
+        package::update(&package, &index_path, version, &args.channel)?;
     } else {
-        package::create(&package, &index_path, version)?;
+        package::create(&package, &index_path, version, &args.channel)?;
+    }
+
+    // Sign the package manifest
+    if let Some(key) = &key {
+        let signature = key.sign(&std::fs::read(&index_path)?);
+        std::fs::write(
+            index_path.with_extension(format!("toml.{SIGNATURE_FILE_SUFFIX}")),
+            signature,
+        )?;
+    }
+
+    if args.dry_run {
+        return check(&args, &publish_dir, &package, &release, key.as_ref());
     }
 
     info!("Manifests generated successfully!");
+
+    // Notify downstream mirrors, the release is already written so failures only warn
+    if let Some(key) = key.filter(|_| !args.webhooks.is_empty()) {
+        let payload = ReleaseNotification::new("publish", &package.name, &args.channel, &release)
+            .to_json()?;
+        let webhook = Webhook::new(args.webhooks.clone()).retries(args.webhook_retries);
+        for (endpoint, e) in webhook.send(&payload, &key.sign(&payload)).await {
+            warn!("Failed to notify {endpoint}: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates the manifests a dry run staged and the webhook authentication,
+/// then shows what a publish would write and notify.
+fn check(
+    args: &Args,
+    stage_dir: &Path,
+    package: &Package,
+    release: &ReleaseManifest,
+    key: Option<&SigningKey>,
+) -> Result<()> {
+    let verdict = dry_run::validate(&args.output_dir, stage_dir)?;
+    for finding in &verdict.findings {
+        match finding.severity {
+            Severity::Error => println!("error[{}]: {}", finding.check, finding.message),
+            Severity::Warning => println!("warning[{}]: {}", finding.check, finding.message),
+        }
+    }
+    if !verdict.valid {
+        return Err(anyhow!("The staged manifests would be rejected by the registry"));
+    }
+
+    for (target, artifact) in &release.artifacts {
+        println!("Artifact for {target}: {}", artifact.url);
+    }
+
+    if let Some(key) = key.filter(|_| !args.webhooks.is_empty()) {
+        let payload =
+            ReleaseNotification::new("publish", &package.name, &args.channel, release).to_json()?;
+        dry_run::check_auth(key, &payload)?;
+        for endpoint in &args.webhooks {
+            println!("Would notify {endpoint}");
+        }
+    }
+
+    let files = dry_run::tree(stage_dir)?;
+    for (file, size, hash) in &files {
+        println!("Would publish {file} ({size} bytes, sha256 {hash})");
+    }
+    println!(
+        "Dry run staged {} files in {}, nothing was published",
+        files.len(),
+        stage_dir.display()
+    );
+
     Ok(())
 }
@@ -14,10 +14,11 @@
 
 use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use semver::Version;
 
 use hmt_manifest::{ManifestFile, Package, PackageManifest};
+use hmt_utils::signature::SigningKey;
 
 /// Creates a new package manifest file with the given configuration
 ///
@@ -25,9 +26,11 @@ use hmt_manifest::{ManifestFile, Package, PackageManifest};
 /// * `config` - Package configuration containing metadata and targets
 /// * `path` - Path where the manifest file should be created
 /// * `version` - Initial version of the package
-pub fn create(package: &Package, path: &Path, version: &str) -> Result<()> {
+/// * `channel` - Release channel to point at the version
+pub fn create(package: &Package, path: &Path, version: &str, channel: &str) -> Result<()> {
     let mut manifest = PackageManifest::new(package.clone(), version.to_string());
     manifest.add_release(version.to_string(), format!("release-{version}.toml"));
+    manifest.channels.insert(channel.to_string(), version.to_string());
 
     manifest.save(path)?;
     Ok(())
@@ -39,7 +42,8 @@ pub fn create(package: &Package, path: &Path, version: &str) -> Result<()> {
 /// * `config` - Updated package configuration
 /// * `path` - Path to the existing manifest file
 /// * `version` - New version to be added
-pub fn update(package: &Package, path: &Path, version: &str) -> Result<()> {
+/// * `channel` - Release channel to point at the version
+pub fn update(package: &Package, path: &Path, version: &str, channel: &str) -> Result<()> {
     // Read the existing manifest
     let mut manifest = PackageManifest::load(path)?;
 
@@ -64,7 +68,50 @@ pub fn update(package: &Package, path: &Path, version: &str) -> Result<()> {
     if !manifest.releases.contains_key(version) {
         manifest.add_release(version.to_string(), release);
     }
+    manifest.channels.insert(channel.to_string(), version.to_string());
 
     manifest.save(path)?;
     Ok(())
 }
+
+/// Records the public key of the signing key on the maintainer owning it,
+/// so installers can verify the artifact signatures
+pub fn add_public_key(package: &mut Package, key: &SigningKey) {
+    let fingerprint = key.fingerprint();
+    for maintainer in &mut package.maintainers {
+        if maintainer.fingerprint.eq_ignore_ascii_case(&fingerprint) {
+            maintainer.public_key = Some(key.public_key());
+        }
+    }
+}
+
+/// Verifies that the signing key belongs to a maintainer of the package
+///
+/// Ownership is taken from the published manifest at `path` if it exists,
+/// so a publisher cannot grant themselves access through the package config.
+/// Packages without maintainers may be published by anyone.
+///
+/// # Arguments
+/// * `package` - Package configuration being published
+/// * `path` - Path to the existing manifest file
+/// * `key` - The publisher's signing key
+pub fn verify_owner(package: &Package, path: &Path, key: Option<&SigningKey>) -> Result<()> {
+    let published = if path.exists() { Some(PackageManifest::load(path)?.package) } else { None };
+    let owners = published.as_ref().unwrap_or(package);
+    if owners.maintainers.is_empty() {
+        return Ok(());
+    }
+
+    let Some(key) = key else {
+        bail!("{} has maintainers, a signing key is required to publish it", package.name);
+    };
+    if owners.maintainer(&key.fingerprint()).is_none() {
+        bail!(
+            "Signing key {} does not belong to a maintainer of {}",
+            key.fingerprint(),
+            package.name
+        );
+    }
+
+    Ok(())
+}
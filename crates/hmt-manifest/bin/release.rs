@@ -12,26 +12,42 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashMap, path::Path};
+use std::{collections::HashMap, fs, path::Path};
 
 use anyhow::Result;
 
 use hmt_manifest::{Artifact, Package, Release, ReleaseManifest};
-use hmt_utils::checksum::{self, CHECKSUM_FILE_SUFFIX};
+use hmt_utils::{
+    checksum::{self, CHECKSUM_FILE_SUFFIX},
+    signature::SigningKey,
+};
 use tracing::warn;
 
+/// The suffix of in-toto provenance statements published next to artifacts.
+const PROVENANCE_FILE_SUFFIX: &str = "intoto.json";
+
 /// Generate a release manifest based on package configuration and artifacts
 ///
+/// Every artifact present is verified against its checksum file first.
+/// With a signing key, the hash of every artifact is signed.
+///
 /// # Arguments
 /// * `config` - Package configuration containing target information
 /// * `artifacts_dir` - Directory containing the release artifacts
 /// * `version` - Version string for the release
+/// * `key` - The publisher's signing key
 ///
 /// # Returns
 /// A Result containing the generated ReleaseManifest
-pub fn generate(package: &Package, artifacts_dir: &Path, version: &str) -> Result<ReleaseManifest> {
+pub async fn generate(
+    package: &Package,
+    artifacts_dir: &Path,
+    version: &str,
+    key: Option<&SigningKey>,
+) -> Result<ReleaseManifest> {
     let release = Release::new(version.to_string());
     let mut manifest = ReleaseManifest::new(release, HashMap::new());
+    let mut present = Vec::new();
 
     for target in &package.targets {
         let artifact_name = format!("{}-{}-{}.tar.gz", package.name, version, target);
@@ -48,9 +64,23 @@ pub fn generate(package: &Package, artifacts_dir: &Path, version: &str) -> Resul
 
         let hash = checksum::read(&checksum_path)?;
         let url = format!("{}/releases/download/{}/{}", package.repository, version, artifact_name);
+        let artifact_path = artifacts_dir.join(&artifact_name);
+        let size = fs::metadata(&artifact_path).ok().map(|m| m.len());
+        if size.is_some() {
+            present.push((artifact_path, hash.clone()));
+        }
 
-        manifest.add_artifact(target.clone(), Artifact { url, hash });
+        // Attestations are uploaded next to the artifact
+        let attestation = format!("{artifact_name}.{PROVENANCE_FILE_SUFFIX}");
+        let provenance = artifacts_dir.join(&attestation).exists().then(|| {
+            format!("{}/releases/download/{}/{}", package.repository, version, attestation)
+        });
+
+        let signature = key.map(|key| key.sign(hash.as_bytes()));
+        manifest.add_artifact(target.clone(), Artifact { url, hash, signature, size, provenance });
     }
 
+    checksum::verify_files(&present).await?;
+
     Ok(manifest)
 }
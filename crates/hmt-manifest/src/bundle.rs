@@ -0,0 +1,111 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use hmt_utils::bytes::FromSlice;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{untrusted, ManifestError, ManifestFile};
+
+/// `BundleManifest` names the packages a target platform is built and run
+/// with. Each role is the category of the package in the target domain
+/// index, and its value the name of the package.
+///
+/// The registry index lists the bundle of a target under `bundles`.
+///
+/// example:
+/// ```toml
+/// backend = "cranelift-backend"
+/// linker = "lld-linker"
+/// sysroot = "x86_64-linux-gnu-sysroot"
+/// runner = "native-runner"
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BundleManifest {
+    /// Compiles CLIF to objects for the target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+
+    /// Links objects into executables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linker: Option<String>,
+
+    /// The libraries and startup objects executables link against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sysroot: Option<String>,
+
+    /// Runs executables of the target, e.g. an emulator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runner: Option<String>,
+}
+
+impl BundleManifest {
+    /// The roles of a bundle, in installation order.
+    pub const ROLES: [&'static str; 4] = ["backend", "linker", "sysroot", "runner"];
+
+    /// The roles every target needs to build executables.
+    pub const REQUIRED: [&'static str; 2] = ["backend", "linker"];
+
+    /// Returns the package filling a role, if the bundle names one.
+    pub fn get(&self, role: &str) -> Option<&String> {
+        match role {
+            "backend" => self.backend.as_ref(),
+            "linker" => self.linker.as_ref(),
+            "sysroot" => self.sysroot.as_ref(),
+            "runner" => self.runner.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Implement load from file and save to file
+impl ManifestFile for BundleManifest {}
+
+impl FromStr for BundleManifest {
+    type Err = ManifestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(ManifestError::from)
+    }
+}
+
+impl FromSlice for BundleManifest {
+    type Err = ManifestError;
+
+    fn from_slice(v: &[u8]) -> Result<Self, Self::Err> {
+        untrusted::from_slice(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_roles() {
+        let bundle = BundleManifest::from_str(
+            r#"
+            backend = "cranelift-backend"
+            linker = "lld-linker"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(bundle.get("backend").unwrap(), "cranelift-backend");
+        assert_eq!(bundle.get("linker").unwrap(), "lld-linker");
+        assert!(bundle.get("sysroot").is_none());
+        assert!(bundle.get("frontend").is_none());
+        assert!(BundleManifest::from_str("backend = 1").is_err());
+    }
+}
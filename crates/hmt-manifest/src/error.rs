@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use hmt_error::{Category, Code, Diagnostic, Help};
 use thiserror::Error;
 
 pub type ManifestResult<T> = std::result::Result<T, ManifestError>;
@@ -24,15 +25,91 @@ pub enum ManifestError {
     #[error("Failed to serialize the manifest: {0}")]
     SerializeError(#[from] toml::ser::Error),
 
+    #[error("Failed to (de)serialize the JSON manifest: {0}")]
+    JsonError(#[from] serde_json::Error),
+
     #[error("Manifest file not found at path: {0}")]
     FileNotFound(String),
 
     #[error("Invalid manifest format: {0}")]
     InvalidFormat(String),
 
+    #[error("Provenance does not match: {0}")]
+    ProvenanceMismatch(String),
+
+    #[error("Provenance is not signed by a trusted builder: {0}")]
+    ProvenanceUnverified(String),
+
+    #[error("Manifest exceeds a parser limit: {0}")]
+    LimitExceeded(String),
+
+    #[error("Unknown feature: {0}")]
+    UnknownFeature(String),
+
+    #[error("Environment variable {0} expands {1}, which is not allowed")]
+    EnvNotAllowed(String, String),
+
     #[error("IO error occurred: {0}")]
     IoError(#[from] std::io::Error),
 
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
+
+impl Diagnostic for ManifestError {
+    fn code(&self) -> Code {
+        match self {
+            ManifestError::DeserializeError(_) => Code::new("manifest.parse", Category::Data),
+            ManifestError::SerializeError(_) => Code::new("manifest.serialize", Category::Internal),
+            ManifestError::JsonError(_) => Code::new("manifest.json", Category::Data),
+            ManifestError::FileNotFound(_) => Code::new("manifest.not-found", Category::NotFound),
+            ManifestError::InvalidFormat(_) => Code::new("manifest.invalid", Category::Data),
+            ManifestError::ProvenanceMismatch(_) => {
+                Code::new("manifest.provenance-mismatch", Category::Denied)
+            }
+            ManifestError::ProvenanceUnverified(_) => {
+                Code::new("manifest.provenance-unverified", Category::Denied)
+            }
+            ManifestError::LimitExceeded(_) => Code::new("manifest.limit-exceeded", Category::Data),
+            ManifestError::UnknownFeature(_) => {
+                Code::new("manifest.unknown-feature", Category::Data)
+            }
+            ManifestError::EnvNotAllowed(..) => {
+                Code::new("manifest.env-not-allowed", Category::Config)
+            }
+            ManifestError::IoError(_) => Code::new("manifest.io", Category::Io),
+            ManifestError::Unknown(_) => Code::new("manifest.unknown", Category::Internal),
+        }
+    }
+
+    fn help(&self) -> Option<Help> {
+        let help = match self {
+            ManifestError::FileNotFound(_) => Help::new("the manifest file does not exist")
+                .step("check that you are in the right directory")
+                .step("run `hummanta init` to create a project manifest")
+                .page("missing-manifest"),
+            ManifestError::DeserializeError(_) | ManifestError::InvalidFormat(_) => {
+                Help::new("the manifest is not valid TOML or misses required fields")
+                    .step("fix the location reported above")
+                    .page("manifest-format")
+            }
+            ManifestError::ProvenanceMismatch(_) => {
+                Help::new("the package was not built from the source it claims")
+                    .step("do not use this release; report it to the package maintainer")
+                    .page("provenance")
+            }
+            ManifestError::ProvenanceUnverified(_) => {
+                Help::new("provenance is only trusted when a known builder signed it")
+                    .step("add the builder's public key to `provenance-keys` under [policy]")
+                    .page("provenance")
+            }
+            ManifestError::EnvNotAllowed(..) => {
+                Help::new("variables are only expanded from an allow-list")
+                    .step("add the variable to `expand` under [env] in ~/.hummanta/config.toml")
+                    .page("project-env")
+            }
+            _ => return None,
+        };
+        Some(help)
+    }
+}
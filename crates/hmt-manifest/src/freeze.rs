@@ -0,0 +1,183 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Entry, InstalledManifest, ManifestError, ManifestFile, Stage};
+
+/// `FreezeManifest` describes the exact installed state of a machine, so it
+/// can be replayed elsewhere.
+///
+/// Packages are listed in installation order.
+///
+/// Example:
+/// ```toml
+/// [[package]]
+/// kind = "toolchains"
+/// domain = "solidity"
+/// category = "detector"
+/// name = "solidity-detector-foundry"
+/// version = "v1.2.0"
+/// url = "https://github.com/hummanta/solidity-detector-foundry/releases/download/v1.2.0/solidity-detector-foundry-v1.2.0-x86_64-unknown-linux-gnu.tar.gz"
+/// hash = "a80a0dd7425173064ce6d1a4ba04b18a967484d6f0d19080170843229065c006"
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FreezeManifest {
+    /// The installed packages, in installation order.
+    #[serde(default, rename = "package")]
+    pub packages: Vec<FrozenPackage>,
+}
+
+impl FreezeManifest {
+    /// Creates a new, empty `FreezeManifest`.
+    pub fn new() -> Self {
+        Self { packages: Vec::new() }
+    }
+
+    /// Freezes the installed packages, returning the names of those that
+    /// lack the artifact needed to reinstall them.
+    pub fn from_installed(installed: &InstalledManifest) -> (Self, Vec<String>) {
+        let mut entries: Vec<_> = installed.entries().collect();
+        entries.sort_by_key(|(.., entry)| entry.order);
+
+        let mut manifest = Self::new();
+        let mut missing = Vec::new();
+        for (kind, domain, category, name, entry) in entries {
+            match FrozenPackage::new(kind, domain, category, name, entry) {
+                Some(package) => manifest.packages.push(package),
+                None => missing.push(name.to_string()),
+            }
+        }
+
+        (manifest, missing)
+    }
+}
+
+/// Implement load from file and save to file
+impl ManifestFile for FreezeManifest {}
+
+impl FromStr for FreezeManifest {
+    type Err = ManifestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(ManifestError::from)
+    }
+}
+
+/// `FrozenPackage` pins a single installed package to its artifact.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrozenPackage {
+    /// The package kind, e.g. "toolchains" or "targets".
+    pub kind: String,
+
+    /// The domain the package was installed for.
+    pub domain: String,
+
+    /// The category of the package, e.g. "detector".
+    pub category: String,
+
+    /// The name of the package.
+    pub name: String,
+
+    /// The installed version.
+    pub version: String,
+
+    /// An optional description of the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The URL of the installed artifact.
+    pub url: String,
+
+    /// The SHA-256 hash of the installed artifact.
+    pub hash: String,
+
+    /// The compile pipeline stage the package provides.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage: Option<Stage>,
+}
+
+impl FrozenPackage {
+    /// Creates a frozen package from an installed entry, if the entry
+    /// records its artifact.
+    pub fn new(
+        kind: &str,
+        domain: &str,
+        category: &str,
+        name: &str,
+        entry: &Entry,
+    ) -> Option<Self> {
+        Some(Self {
+            kind: kind.to_string(),
+            domain: domain.to_string(),
+            category: category.to_string(),
+            name: name.to_string(),
+            version: entry.version.clone(),
+            description: entry.description.clone(),
+            url: entry.url.clone()?,
+            hash: entry.hash.clone()?,
+            stage: entry.stage.clone(),
+        })
+    }
+
+    /// Converts the package back into an installed entry, without a path.
+    pub fn to_entry(&self) -> Entry {
+        let mut entry =
+            Entry::new(self.version.clone(), self.description.clone(), Default::default())
+                .artifact(&self.url, &self.hash);
+        entry.stage = self.stage.clone();
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_installed_orders_packages() {
+        let mut installed = InstalledManifest::new();
+        let mut entry = Entry::new("v1.0.0".into(), None, "/tmp/b".into()).artifact("url-b", "b");
+        entry.order = 1;
+        installed.insert("targets", "evm", "backend", "b", entry);
+        let entry = Entry::new("v1.0.0".into(), None, "/tmp/a".into()).artifact("url-a", "a");
+        installed.insert("toolchains", "solidity", "frontend", "a", entry);
+        installed.insert(
+            "toolchains",
+            "solidity",
+            "detector",
+            "c",
+            Entry::new("v1".into(), None, "/tmp/c".into()),
+        );
+
+        let (manifest, missing) = FreezeManifest::from_installed(&installed);
+        let names: Vec<_> = manifest.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+        assert_eq!(missing, ["c"]);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let entry = Entry::new("v1.0.0".into(), None, "/tmp/a".into()).artifact("url", "abc");
+        let package =
+            FrozenPackage::new("toolchains", "solidity", "frontend", "a", &entry).unwrap();
+        let manifest = FreezeManifest { packages: vec![package] };
+
+        let content = toml::to_string(&manifest).unwrap();
+        assert_eq!(FreezeManifest::from_str(&content).unwrap(), manifest);
+        assert_eq!(manifest.packages[0].to_entry().hash.as_deref(), Some("abc"));
+    }
+}
@@ -16,7 +16,7 @@ use hmt_utils::bytes::FromSlice;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, str::FromStr};
 
-use crate::{ManifestError, ManifestFile};
+use crate::{untrusted, ManifestError, ManifestFile};
 
 /// `IndexManifest` is a struct used to represent an index manifest.
 ///
@@ -138,9 +138,7 @@ impl FromSlice for IndexManifest {
     type Err = ManifestError;
 
     fn from_slice(v: &[u8]) -> Result<Self, Self::Err> {
-        let s = std::str::from_utf8(v)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-        toml::from_str(s).map_err(ManifestError::from)
+        untrusted::from_slice(v)
     }
 }
 
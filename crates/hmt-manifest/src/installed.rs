@@ -14,9 +14,13 @@
 
 use hmt_utils::bytes::FromSlice;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    str::FromStr,
+};
 
-use crate::{ManifestError, ManifestFile};
+use crate::{ManifestError, ManifestFile, Stage};
 
 /// Represents a single installed package entry with version and optional description.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,12 +31,131 @@ pub struct Entry {
     pub description: Option<String>,
     /// The file path where the package is located.
     pub path: PathBuf,
+    /// The URL of the installed artifact.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// The hash of the installed artifact.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// The position of the package in installation order.
+    #[serde(default)]
+    pub order: u64,
+    /// The compile pipeline stage the package provides.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage: Option<Stage>,
+    /// The optional features the package supports.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
+    /// The components of the release installed, with the archive paths
+    /// they select. Empty if the whole artifact is installed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, Vec<String>>,
+    /// The files the package installed, relative to the installation path
+    /// of its domain. Empty for packages installed before files were
+    /// recorded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    /// How files clashing with files of other packages were installed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub conflicts: BTreeMap<String, Resolution>,
+    /// The shared runtime packages the package uses, with the requirement
+    /// on their version.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub runtimes: BTreeMap<String, String>,
+    /// Where the package was installed from.
+    #[serde(default, skip_serializing_if = "Source::is_registry")]
+    pub source: Source,
+    /// The published size of the artifact in bytes, checked against the
+    /// free disk space before downloading it. Not stored.
+    #[serde(skip)]
+    pub size: Option<u64>,
 }
 
 impl Entry {
     /// Create a new, empty Entry.
     pub fn new(version: String, description: Option<String>, path: PathBuf) -> Self {
-        Self { version, description, path }
+        Self {
+            version,
+            description,
+            path,
+            url: None,
+            hash: None,
+            order: 0,
+            stage: None,
+            capabilities: Vec::new(),
+            components: BTreeMap::new(),
+            files: Vec::new(),
+            conflicts: BTreeMap::new(),
+            runtimes: BTreeMap::new(),
+            source: Source::Registry,
+            size: None,
+        }
+    }
+
+    /// Records the artifact the package was installed from.
+    pub fn artifact(mut self, url: &str, hash: &str) -> Self {
+        self.url = Some(url.to_string());
+        self.hash = Some(hash.to_string());
+        self
+    }
+
+    /// Whether the package supports the given capability.
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+/// How a file clashing with a file of another package was installed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    /// The file was left out, the other package keeps its file.
+    Skip,
+    /// The file was installed under the given name instead.
+    Rename(String),
+    /// The file replaced the file of the other package.
+    Replace,
+}
+
+/// Where an installed package came from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// Resolved from the registry manifests.
+    #[default]
+    Registry,
+    /// Installed from a local archive, without any manifests.
+    Local,
+    /// Linked to a binary built outside Hummanta, used in place.
+    Linked,
+}
+
+impl Source {
+    /// Whether the package came from the registry.
+    pub fn is_registry(&self) -> bool {
+        *self == Source::Registry
+    }
+
+    /// Returns the name of the source, as written to `installed.toml`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Registry => "registry",
+            Source::Local => "local",
+            Source::Linked => "linked",
+        }
+    }
+}
+
+impl FromStr for Source {
+    type Err = ManifestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "registry" => Ok(Source::Registry),
+            "local" => Ok(Source::Local),
+            "linked" => Ok(Source::Linked),
+            _ => Err(ManifestError::InvalidFormat(format!("unknown package source: {s}"))),
+        }
     }
 }
 
@@ -143,6 +266,17 @@ impl InstalledManifest {
         self.0.get(kind)?.get(domain)?.get(cat)
     }
 
+    /// Get a mutable reference to the package map under a specific kind,
+    /// domain, and type
+    pub fn get_package_mut(
+        &mut self,
+        kind: &str,
+        domain: &str,
+        cat: &str,
+    ) -> Option<&mut PackageMap> {
+        self.0.get_mut(kind)?.get_mut(domain)?.get_mut(cat)
+    }
+
     /// Remove all packages under a specific kind and domain.
     pub fn remove_domain(&mut self, kind: &str, domain: &str) {
         if let Some(kind_map) = self.0.get_mut(kind) {
@@ -150,6 +284,34 @@ impl InstalledManifest {
         }
     }
 
+    /// Returns the packages of `self` overlaid with those of `overlay`,
+    /// whose entries take precedence over entries of the same package.
+    pub fn overlay(&self, overlay: &InstalledManifest) -> InstalledManifest {
+        let mut merged = self.clone();
+        for (kind, domain, category, name, entry) in overlay.entries() {
+            merged.insert(kind, domain, category, name, entry.clone());
+        }
+        merged
+    }
+
+    /// Returns the order for the next installed package.
+    pub fn next_order(&self) -> u64 {
+        self.entries().map(|(.., entry)| entry.order + 1).max().unwrap_or(0)
+    }
+
+    /// Iterates over all entries as `(kind, domain, category, name, entry)`.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str, &str, &str, &Entry)> {
+        self.0.iter().flat_map(|(kind, domains)| {
+            domains.iter().flat_map(move |(domain, categories)| {
+                categories.iter().flat_map(move |(category, packages)| {
+                    packages.iter().map(move |(name, entry)| {
+                        (kind.as_str(), domain.as_str(), category.as_str(), name.as_str(), entry)
+                    })
+                })
+            })
+        })
+    }
+
     /// Get all package maps under the given kind and category across all domains.
     pub fn by_category(&self, kind: &str, category: &str) -> Vec<&PackageMap> {
         self.get_domain(kind)
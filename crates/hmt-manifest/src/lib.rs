@@ -12,23 +12,40 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod bundle;
 mod error;
+mod freeze;
 mod index;
 mod installed;
+mod lint;
+mod lock;
+mod notification;
+mod outputs;
 mod package;
 mod project;
+mod provenance;
 mod release;
+mod status;
+pub mod untrusted;
 
 use serde::Serialize;
 use std::{io::Read, path::Path, str::FromStr};
 
 // Re-exports.
+pub use bundle::*;
 pub use error::*;
+pub use freeze::*;
 pub use index::*;
 pub use installed::*;
+pub use lint::*;
+pub use lock::*;
+pub use notification::*;
+pub use outputs::*;
 pub use package::*;
 pub use project::*;
+pub use provenance::*;
 pub use release::*;
+pub use status::*;
 
 /// `ManifestFile` trait provides common file operations for manifest files.
 pub trait ManifestFile: FromStr<Err = ManifestError> + Serialize {
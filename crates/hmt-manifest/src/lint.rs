@@ -0,0 +1,273 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PackageManifest, ReleaseManifest};
+
+/// The name of the package manifest in a submission.
+pub const PACKAGE_MANIFEST: &str = "index.toml";
+
+/// `Submission` holds the raw manifests of a package submitted to a registry:
+/// the package manifest and the release manifests it references, keyed by
+/// file name.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Submission {
+    /// The contents of `index.toml`.
+    pub package: String,
+
+    /// The contents of each release manifest, keyed by file name.
+    #[serde(default)]
+    pub releases: BTreeMap<String, String>,
+}
+
+/// How severe a lint finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The submission must not be accepted.
+    Error,
+    /// The submission is accepted, but should be fixed.
+    Warning,
+}
+
+/// A single problem found in a submission.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    /// How severe the problem is.
+    pub severity: Severity,
+
+    /// A stable identifier of the check, e.g. `checksum`.
+    pub check: String,
+
+    /// A human readable description.
+    pub message: String,
+}
+
+impl Finding {
+    /// Creates a finding that rejects the submission.
+    pub fn error(check: &str, message: String) -> Self {
+        Self { severity: Severity::Error, check: check.to_string(), message }
+    }
+
+    /// Creates a finding that does not reject the submission.
+    pub fn warning(check: &str, message: String) -> Self {
+        Self { severity: Severity::Warning, check: check.to_string(), message }
+    }
+}
+
+/// `Verdict` is the outcome of linting a submission.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Verdict {
+    /// Whether no finding is an error.
+    pub valid: bool,
+
+    /// Everything found, in check order.
+    pub findings: Vec<Finding>,
+}
+
+impl Verdict {
+    /// Adds a finding, updating the validity.
+    pub fn push(&mut self, finding: Finding) {
+        self.findings.push(finding);
+        self.valid = !self.findings.iter().any(|f| f.severity == Severity::Error);
+    }
+}
+
+impl Submission {
+    /// Runs every offline check on the submission: schema, release
+    /// references, checksum and URL format, target coverage and license.
+    pub fn lint(&self) -> Verdict {
+        let mut verdict = Verdict { valid: true, findings: Vec::new() };
+
+        let package = match PackageManifest::from_str(&self.package) {
+            Ok(package) => package,
+            Err(e) => {
+                verdict.push(Finding::error("schema", format!("{PACKAGE_MANIFEST}: {e}")));
+                return verdict;
+            }
+        };
+        let meta = &package.package;
+
+        if meta.license.as_deref().is_none_or(|license| license.trim().is_empty()) {
+            verdict.push(Finding::warning("license", format!("{} declares no license", meta.name)));
+        }
+
+        if !package.releases.contains_key(&package.latest) {
+            let message = format!("latest version {} has no release", package.latest);
+            verdict.push(Finding::error("latest", message));
+        }
+        for (channel, version) in &package.channels {
+            if !package.releases.contains_key(version) {
+                let message = format!("channel {channel} points at unreleased version {version}");
+                verdict.push(Finding::error("channel", message));
+            }
+        }
+
+        let mut versions: Vec<_> = package.releases.iter().collect();
+        versions.sort();
+        for (version, file) in versions {
+            let Some(contents) = self.releases.get(file) else {
+                let message = format!("release {version} references missing file {file}");
+                verdict.push(Finding::error("missing-release", message));
+                continue;
+            };
+            let release = match ReleaseManifest::from_str(contents) {
+                Ok(release) => release,
+                Err(e) => {
+                    verdict.push(Finding::error("schema", format!("{file}: {e}")));
+                    continue;
+                }
+            };
+
+            if release.release.version != *version {
+                let message =
+                    format!("{file} describes {} instead of {version}", release.release.version);
+                verdict.push(Finding::error("schema", message));
+            }
+
+            let mut targets: Vec<_> = release.artifacts.iter().collect();
+            targets.sort_by_key(|(target, _)| *target);
+            for (target, artifact) in targets {
+                if !is_checksum(&artifact.hash) {
+                    let message = format!(
+                        "{version} {target}: '{}' is not a SHA-256 hex digest",
+                        artifact.hash
+                    );
+                    verdict.push(Finding::error("checksum", message));
+                }
+                if !artifact.url.starts_with("https://") && !artifact.url.starts_with("http://") {
+                    let message =
+                        format!("{version} {target}: '{}' is not an HTTP URL", artifact.url);
+                    verdict.push(Finding::error("url", message));
+                }
+                if !meta.targets.contains(target) {
+                    let message = format!("{version} ships undeclared target {target}");
+                    verdict.push(Finding::warning("target-coverage", message));
+                }
+            }
+
+            // Only the latest release has to cover every declared target
+            if *version == package.latest {
+                for target in meta.targets.iter().filter(|t| !release.supports_target(t)) {
+                    let message = format!("{version} has no artifact for declared target {target}");
+                    verdict.push(Finding::warning("target-coverage", message));
+                }
+            }
+        }
+
+        verdict
+    }
+
+    /// Returns the artifact URLs of every release that parses, sorted.
+    pub fn artifact_urls(&self) -> Vec<String> {
+        let mut urls: Vec<String> = self
+            .releases
+            .values()
+            .filter_map(|contents| ReleaseManifest::from_str(contents).ok())
+            .flat_map(|release| release.artifacts.into_values().map(|a| a.url))
+            .collect();
+        urls.sort();
+        urls.dedup();
+        urls
+    }
+}
+
+/// Checks that a hash is a lowercase or uppercase SHA-256 hex digest.
+fn is_checksum(hash: &str) -> bool {
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE: &str = r#"
+        latest = "v1.0.0"
+        name = "foo"
+        homepage = "https://example.com/foo"
+        repository = "https://github.com/example/foo"
+        kind = "frontend"
+        targets = ["x86_64-unknown-linux-gnu", "aarch64-apple-darwin"]
+
+        [releases]
+        "v1.0.0" = "release-v1.0.0.toml"
+
+        [channels]
+        nightly = "v1.1.0"
+    "#;
+
+    const RELEASE: &str = r#"
+        version = "v1.0.0"
+
+        [artifacts.x86_64-unknown-linux-gnu]
+        url = "https://example.com/foo.tar.gz"
+        hash = "a80a0dd7425173064ce6d1a4ba04b18a967484d6f0d19080170843229065c006"
+
+        [artifacts.x86_64-apple-darwin]
+        url = "ftp://example.com/foo.tar.gz"
+        hash = "abc"
+    "#;
+
+    fn checks(verdict: &Verdict) -> Vec<(&str, Severity)> {
+        verdict.findings.iter().map(|f| (f.check.as_str(), f.severity)).collect()
+    }
+
+    #[test]
+    fn test_lint_findings() {
+        let submission = Submission {
+            package: PACKAGE.to_string(),
+            releases: BTreeMap::from([("release-v1.0.0.toml".to_string(), RELEASE.to_string())]),
+        };
+
+        let verdict = submission.lint();
+        assert!(!verdict.valid);
+        assert_eq!(
+            checks(&verdict),
+            [
+                ("license", Severity::Warning),
+                ("channel", Severity::Error),
+                ("checksum", Severity::Error),
+                ("url", Severity::Error),
+                ("target-coverage", Severity::Warning),
+                ("target-coverage", Severity::Warning),
+            ]
+        );
+        assert_eq!(
+            submission.artifact_urls(),
+            ["ftp://example.com/foo.tar.gz", "https://example.com/foo.tar.gz"]
+        );
+    }
+
+    #[test]
+    fn test_lint_schema_and_missing_release() {
+        let broken = Submission { package: "latest = 1".to_string(), ..Default::default() };
+        assert_eq!(checks(&broken.lint()), [("schema", Severity::Error)]);
+
+        let missing = Submission { package: PACKAGE.to_string(), ..Default::default() };
+        let verdict = missing.lint();
+        assert!(checks(&verdict).contains(&("missing-release", Severity::Error)));
+    }
+
+    #[test]
+    fn test_verdict_validity() {
+        let mut verdict = Verdict { valid: true, findings: Vec::new() };
+        verdict.push(Finding::warning("license", "no license".to_string()));
+        assert!(verdict.valid);
+        verdict.push(Finding::error("checksum", "bad".to_string()));
+        assert!(!verdict.valid);
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeMap, path::PathBuf, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{FrozenPackage, ManifestError, ManifestFile};
+
+/// `LockManifest` pins the exact versions of registry dependencies, and of
+/// the toolchain and target packages the project is built with.
+///
+/// Toolchain and target packages are pinned with the artifact hash of every
+/// target their release supports, so one lockfile serves every platform.
+///
+/// Example:
+/// ```toml
+/// [[package]]
+/// name = "math"
+/// version = "v1.2.0"
+/// source = "https://github.com/hummanta/math/releases/download/v1.2.0/math-v1.2.0-source.tar.gz"
+/// checksum = "a80a0dd7425173064ce6d1a4ba04b18a967484d6f0d19080170843229065c006"
+/// manifest = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+/// path = "vendor/math"
+///
+/// [[tool]]
+/// kind = "toolchains"
+/// domain = "solidity"
+/// category = "frontend"
+/// name = "solidity-frontend"
+/// version = "v0.3.0"
+///
+/// [tool.hashes]
+/// aarch64-apple-darwin = "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae"
+/// x86_64-unknown-linux-gnu = "5d41402abc4b2a76b9719d911017c592ae1e3e0a6c9e3d5e2c7e6b3a1f0c9d8e"
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockManifest {
+    /// The locked packages, sorted by name.
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+
+    /// The locked toolchain and target packages, sorted by kind, domain,
+    /// category and name.
+    #[serde(default, rename = "tool", skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<LockedTool>,
+}
+
+impl LockManifest {
+    /// Creates a new, empty `LockManifest`.
+    pub fn new() -> Self {
+        Self { packages: Vec::new(), tools: Vec::new() }
+    }
+
+    /// Get a locked package by name.
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|p| p.name == name)
+    }
+
+    /// Inserts a locked package, replacing any entry with the same name.
+    pub fn insert(&mut self, package: LockedPackage) {
+        self.packages.retain(|p| p.name != package.name);
+        self.packages.push(package);
+        self.packages.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    /// Replaces the locked toolchain and target packages.
+    pub fn set_tools(&mut self, mut tools: Vec<LockedTool>) {
+        tools.sort_by(|a, b| {
+            (&a.kind, &a.domain, &a.category, &a.name).cmp(&(
+                &b.kind,
+                &b.domain,
+                &b.category,
+                &b.name,
+            ))
+        });
+        self.tools = tools;
+    }
+}
+
+/// Implement load from file and save to file
+impl ManifestFile for LockManifest {}
+
+impl FromStr for LockManifest {
+    type Err = ManifestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(ManifestError::from)
+    }
+}
+
+/// `LockedPackage` records where an exact package version was downloaded from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    /// The name of the package.
+    pub name: String,
+
+    /// The exact version of the package.
+    pub version: String,
+
+    /// The URL of the package archive.
+    pub source: String,
+
+    /// The SHA-256 hash of the package archive.
+    pub checksum: String,
+
+    /// The SHA-256 hash of the release manifest the package was locked from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+
+    /// The vendored copy of the package, relative to the project root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl LockedPackage {
+    /// Creates a new locked package entry.
+    pub fn new(name: &str, version: &str, source: &str, checksum: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: source.to_string(),
+            checksum: checksum.to_string(),
+            manifest: None,
+            path: None,
+        }
+    }
+
+    /// Records the digest of the release manifest.
+    pub fn manifest(mut self, digest: &str) -> Self {
+        self.manifest = Some(digest.to_string());
+        self
+    }
+}
+
+/// `LockedTool` pins a toolchain or target package to an exact release.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedTool {
+    /// The package kind, e.g. "toolchains" or "targets".
+    pub kind: String,
+
+    /// The domain the package is installed for.
+    pub domain: String,
+
+    /// The category of the package, e.g. "frontend".
+    pub category: String,
+
+    /// The name of the package.
+    pub name: String,
+
+    /// The exact version of the package.
+    pub version: String,
+
+    /// The SHA-256 hash of the release artifact, keyed by target triple.
+    pub hashes: BTreeMap<String, String>,
+}
+
+impl LockedTool {
+    /// Pins the release of an installed package, with the artifact hashes
+    /// of its release.
+    pub fn new(package: &FrozenPackage, hashes: BTreeMap<String, String>) -> Self {
+        Self {
+            kind: package.kind.clone(),
+            domain: package.domain.clone(),
+            category: package.category.clone(),
+            name: package.name.clone(),
+            version: package.version.clone(),
+            hashes,
+        }
+    }
+
+    /// Returns the hash of the artifact for a target.
+    pub fn hash(&self, target: &str) -> Option<&str> {
+        self.hashes.get(target).map(String::as_str)
+    }
+
+    /// Whether this pins the release an installed package was installed
+    /// from on `target`.
+    pub fn pins(&self, package: &FrozenPackage, target: &str) -> bool {
+        (&self.kind, &self.domain, &self.category, &self.name, &self.version) ==
+            (&package.kind, &package.domain, &package.category, &package.name, &package.version) &&
+            self.hash(target) == Some(&package.hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Entry;
+
+    use super::*;
+
+    #[test]
+    fn test_insert_replaces_and_sorts() {
+        let mut lock = LockManifest::new();
+        lock.insert(LockedPackage::new("math", "v1.0.0", "url", "abc"));
+        lock.insert(LockedPackage::new("core", "v0.1.0", "url", "def"));
+        lock.insert(LockedPackage::new("math", "v1.1.0", "url", "123"));
+
+        assert_eq!(lock.packages.len(), 2);
+        assert_eq!(lock.packages[0].name, "core");
+        assert_eq!(lock.get("math").unwrap().version, "v1.1.0");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut lock = LockManifest::new();
+        lock.insert(LockedPackage::new("math", "v1.0.0", "url", "abc").manifest("def"));
+
+        let entry = Entry::new("v0.3.0".into(), None, "/tmp/frontend".into()).artifact("url", "a");
+        let frontend = FrozenPackage::new("toolchains", "solidity", "frontend", "solc", &entry);
+        let backend = FrozenPackage::new("targets", "evm", "backend", "evm", &entry);
+        let hashes = BTreeMap::from([("linux".to_string(), "a".to_string())]);
+        lock.set_tools(vec![
+            LockedTool::new(&frontend.unwrap(), hashes.clone()),
+            LockedTool::new(&backend.unwrap(), hashes),
+        ]);
+        assert_eq!(lock.tools[0].kind, "targets");
+
+        let content = toml::to_string_pretty(&lock).unwrap();
+        assert_eq!(LockManifest::from_str(&content).unwrap(), lock);
+    }
+
+    #[test]
+    fn test_locked_tool_pins() {
+        let entry = Entry::new("v0.3.0".into(), None, "/tmp/frontend".into()).artifact("url", "a");
+        let package =
+            FrozenPackage::new("toolchains", "solidity", "frontend", "solc", &entry).unwrap();
+        let hashes = BTreeMap::from([
+            ("linux".to_string(), "a".to_string()),
+            ("macos".to_string(), "b".to_string()),
+        ]);
+        let tool = LockedTool::new(&package, hashes);
+
+        assert!(tool.pins(&package, "linux"));
+        assert!(!tool.pins(&package, "macos"));
+        assert!(!tool.pins(&package, "windows"));
+        let other = FrozenPackage { version: "v0.4.0".into(), ..package };
+        assert!(!tool.pins(&other, "linux"));
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ManifestResult, ReleaseManifest};
+
+/// `ReleaseNotification` is the JSON payload sent to webhooks when a
+/// release is published or promoted.
+///
+/// Example:
+/// ```json
+/// {
+///   "event": "promote",
+///   "package": "solidity-detector-foundry",
+///   "version": "v1.2.0",
+///   "channel": "stable",
+///   "artifacts": {
+///     "x86_64-unknown-linux-gnu": "a80a0dd7425173064ce6d1a4ba04b18a967484d6f0d19080170843229065c006"
+///   }
+/// }
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReleaseNotification {
+    /// What happened, either "publish" or "promote".
+    pub event: String,
+
+    /// The name of the package.
+    pub package: String,
+
+    /// The released version.
+    pub version: String,
+
+    /// The channel now pointing at the version.
+    pub channel: String,
+
+    /// The artifact hashes of the release, by target platform.
+    pub artifacts: BTreeMap<String, String>,
+}
+
+impl ReleaseNotification {
+    /// Creates a notification describing the given release.
+    pub fn new(event: &str, package: &str, channel: &str, release: &ReleaseManifest) -> Self {
+        Self {
+            event: event.to_string(),
+            package: package.to_string(),
+            version: release.release.version.clone(),
+            channel: channel.to_string(),
+            artifacts: release.artifacts.iter().map(|(t, a)| (t.clone(), a.hash.clone())).collect(),
+        }
+    }
+
+    /// Serializes the notification to JSON.
+    pub fn to_json(&self) -> ManifestResult<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{Artifact, Release};
+
+    #[test]
+    fn test_notification_from_release() {
+        let mut release = ReleaseManifest::new(Release::new("v1.0.0".to_string()), HashMap::new());
+        release.add_artifact(
+            "x86_64-unknown-linux-gnu".to_string(),
+            Artifact {
+                url: "https://example.com/a".to_string(),
+                hash: "abc".to_string(),
+                signature: None,
+                size: None,
+                provenance: None,
+            },
+        );
+
+        let notification = ReleaseNotification::new("publish", "foo", "nightly", &release);
+        let json = String::from_utf8(notification.to_json().unwrap()).unwrap();
+        assert!(json.contains(r#""version":"v1.0.0""#));
+        assert!(json.contains(r#""artifacts":{"x86_64-unknown-linux-gnu":"abc"}"#));
+    }
+}
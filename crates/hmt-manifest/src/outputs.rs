@@ -0,0 +1,228 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ManifestResult;
+
+/// `OutputManifest` lists every artifact emitted by a build.
+///
+/// It is written to `target/<triple>/outputs.json` for downstream packagers.
+///
+/// Example:
+/// ```json
+/// {
+///   "target": "x86_64-unknown-linux-gnu",
+///   "features": ["net"],
+///   "outputs": [
+///     {
+///       "kind": "object",
+///       "path": "target/x86_64-unknown-linux-gnu/main.o",
+///       "source": "target/x86_64-unknown-linux-gnu/main.clif",
+///       "hash": "a80a0dd7425173064ce6d1a4ba04b18a967484d6f0d19080170843229065c006",
+///       "tool": "/home/user/.hummanta/targets/x86_64-unknown-linux-gnu/backend",
+///       "package": "cranelift-backend@v0.3.0",
+///       "fingerprint": "0f8e4a6c1d2b3e5f7a9c0b1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0e1f"
+///     }
+///   ],
+///   "steps": {
+///     "clif-opt": "5d41402abc4b2a76b9719d911017c592ae3f0e7d8a6b4c1f2e9d3a7b6c5d4e3f"
+///   }
+/// }
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OutputManifest {
+    /// The target platform the outputs were built for.
+    pub target: String,
+
+    /// The features enabled for the build.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+
+    /// The emitted artifacts, in build order.
+    pub outputs: Vec<Output>,
+
+    /// The fingerprints of the build steps that ran, by step name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub steps: BTreeMap<String, String>,
+}
+
+impl OutputManifest {
+    /// Creates a new, empty manifest for the given target.
+    pub fn new(target: &str) -> Self {
+        Self {
+            target: target.to_string(),
+            features: Vec::new(),
+            outputs: Vec::new(),
+            steps: BTreeMap::new(),
+        }
+    }
+
+    /// Records an emitted artifact.
+    pub fn push(&mut self, output: Output) {
+        self.outputs.push(output);
+    }
+
+    /// Returns the executable with the given name, if one was linked.
+    pub fn executable(&self, name: &str) -> Option<&Output> {
+        self.by_kind(OutputKind::Executable)
+            .find(|o| o.path.file_name().is_some_and(|file| file == name))
+    }
+
+    /// Returns all outputs of the given kind.
+    pub fn by_kind(&self, kind: OutputKind) -> impl Iterator<Item = &Output> {
+        self.outputs.iter().filter(move |o| o.kind == kind)
+    }
+
+    /// Load the manifest from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> ManifestResult<Self> {
+        let contents = fs::read(path)?;
+        Ok(serde_json::from_slice(&contents)?)
+    }
+
+    /// Save the manifest to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ManifestResult<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)?;
+
+        Ok(())
+    }
+}
+
+/// The kind of an emitted artifact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputKind {
+    /// Source produced by a pipeline stage running before the frontend.
+    Intermediate,
+    /// Intermediate representation produced by a frontend.
+    Ir,
+    /// Machine code produced by a backend.
+    Object,
+    /// Executable produced by a linker.
+    Executable,
+}
+
+/// A single artifact emitted by a build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Output {
+    /// The kind of the artifact.
+    pub kind: OutputKind,
+
+    /// The path of the artifact.
+    pub path: PathBuf,
+
+    /// The input the artifact was produced from.
+    pub source: PathBuf,
+
+    /// The SHA-256 hash of the artifact contents.
+    pub hash: String,
+
+    /// Inputs besides `source`, e.g. the other objects linked into an executable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<PathBuf>,
+
+    /// The tool that produced the artifact.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<PathBuf>,
+
+    /// The package providing the tool, as `<name>@<version>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+
+    /// The fingerprint of the tool invocation, covering the tool package
+    /// and binary, the inputs and the arguments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+}
+
+impl Output {
+    /// Creates a new output entry.
+    pub fn new(kind: OutputKind, path: PathBuf, source: PathBuf, hash: String) -> Self {
+        Self {
+            kind,
+            path,
+            source,
+            hash,
+            inputs: Vec::new(),
+            tool: None,
+            package: None,
+            fingerprint: None,
+        }
+    }
+
+    /// Sets the additional inputs of the artifact.
+    pub fn inputs(mut self, inputs: Vec<PathBuf>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    /// Sets the tool that produced the artifact.
+    pub fn tool(mut self, tool: PathBuf) -> Self {
+        self.tool = Some(tool);
+        self
+    }
+
+    /// Sets the package providing the tool, as `<name>@<version>`.
+    pub fn package(mut self, package: String) -> Self {
+        self.package = Some(package);
+        self
+    }
+
+    /// Sets the fingerprint of the tool invocation.
+    pub fn fingerprint(mut self, fingerprint: String) -> Self {
+        self.fingerprint = Some(fingerprint);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_by_kind() {
+        let mut manifest = OutputManifest::new("x86_64-unknown-linux-gnu");
+        manifest.push(Output::new(
+            OutputKind::Ir,
+            "main.clif".into(),
+            "main.sol".into(),
+            "abc".to_string(),
+        ));
+        manifest.push(Output::new(
+            OutputKind::Object,
+            "main.o".into(),
+            "main.clif".into(),
+            "def".to_string(),
+        ));
+
+        let objects: Vec<_> = manifest.by_kind(OutputKind::Object).collect();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].path, PathBuf::from("main.o"));
+    }
+
+    #[test]
+    fn test_serialize_kind() {
+        let output =
+            Output::new(OutputKind::Object, "main.o".into(), "main.clif".into(), "abc".into());
+        let json = serde_json::to_string(&output).unwrap();
+        assert!(json.contains(r#""kind":"object""#));
+    }
+}
@@ -12,12 +12,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
 
 use hmt_utils::bytes::FromSlice;
 use serde::{Deserialize, Serialize};
 
-use crate::{ManifestError, ManifestFile};
+use crate::{untrusted, ManifestError, ManifestFile, ManifestResult};
+
+/// The channel whose version is published as `latest`.
+pub const STABLE_CHANNEL: &str = "stable";
+
+/// The category of shared runtime packages in a domain index. Runtimes are
+/// installed once for all packages listing them in `runtimes`, instead of
+/// every package bundling the same files.
+pub const RUNTIME_CATEGORY: &str = "runtime";
+
+/// The capability of a backend instrumenting the code it emits for
+/// coverage, enabled by passing it `--coverage`.
+pub const COVERAGE_CAPABILITY: &str = "coverage";
 
 /// `PackageManifest` keeps track of all versions of a component package.
 ///
@@ -32,6 +48,7 @@ use crate::{ManifestError, ManifestFile};
 /// language = "solidity"
 /// kind = "detector"
 /// description = "Solidity detector for Foundry projects"
+/// license = "Apache-2.0"
 ///
 /// targets = [
 ///   "x86_64-apple-darwin",
@@ -41,9 +58,19 @@ use crate::{ManifestError, ManifestFile};
 ///
 /// latest = "v1.2.0"
 ///
+/// [[maintainers]]
+/// name = "Jane Doe"
+/// contact = "jane@example.com"
+/// fingerprint = "5d41402abc4b2a76b9719d911017c592..."
+/// public_key = "d75a980182b10ab7d54bfed3c964073a..."
+///
 /// [releases]
 /// "v1.2.0" = "release-v1.2.0.toml"
 /// "v1.1.0" = "release-v1.1.0.toml"
+///
+/// [channels]
+/// nightly = "v1.2.0"
+/// stable = "v1.1.0"
 /// ```
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct PackageManifest {
@@ -56,12 +83,16 @@ pub struct PackageManifest {
 
     /// A mapping of version to their corresponding release file.
     pub releases: HashMap<String, String>,
+
+    /// A mapping of release channel (e.g. "nightly", "stable") to version.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub channels: BTreeMap<String, String>,
 }
 
 impl PackageManifest {
     /// Create a new PackageManifest instance.
     pub fn new(package: Package, latest: String) -> Self {
-        PackageManifest { package, latest, releases: HashMap::new() }
+        PackageManifest { package, latest, releases: HashMap::new(), channels: BTreeMap::new() }
     }
 
     /// Add a release to the PackageManifest.
@@ -80,6 +111,27 @@ impl PackageManifest {
     pub fn get_releases(&self) -> &HashMap<String, String> {
         &self.releases
     }
+
+    /// Points the `to` channel at a version the `from` channel points at.
+    ///
+    /// Promoting to the `stable` channel also makes the version `latest`.
+    pub fn promote(&mut self, version: &str, from: &str, to: &str) -> ManifestResult<()> {
+        if !self.releases.contains_key(version) {
+            return Err(ManifestError::InvalidFormat(format!("release {version} not found")));
+        }
+        if self.channels.get(from).map(String::as_str) != Some(version) {
+            return Err(ManifestError::InvalidFormat(format!(
+                "{version} is not on the {from} channel"
+            )));
+        }
+
+        self.channels.insert(to.to_string(), version.to_string());
+        if to == STABLE_CHANNEL {
+            self.latest = version.to_string();
+        }
+
+        Ok(())
+    }
 }
 
 /// Implement load from file and save to file
@@ -97,9 +149,32 @@ impl FromSlice for PackageManifest {
     type Err = ManifestError;
 
     fn from_slice(v: &[u8]) -> Result<Self, Self::Err> {
-        let s = std::str::from_utf8(v)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-        toml::from_str(s).map_err(ManifestError::from)
+        untrusted::from_slice(v)
+    }
+}
+
+/// `PackageSummary` is a borrowed view of a package manifest, for callers
+/// that only need to know the latest version.
+///
+/// The release and channel tables are skipped instead of collected, and
+/// strings borrow from the input unless they contain escapes.
+/// On a manifest of 500 releases this makes 70 allocations instead of
+/// 1088, see `benches/summary.rs`.
+#[derive(Debug, Deserialize)]
+pub struct PackageSummary<'a> {
+    /// The name of the package.
+    #[serde(borrow)]
+    pub name: Cow<'a, str>,
+
+    /// The latest version of the package.
+    #[serde(borrow)]
+    pub latest: Cow<'a, str>,
+}
+
+impl<'a> PackageSummary<'a> {
+    /// Parses the summary of a package manifest from bytes of text.
+    pub fn parse(v: &'a [u8]) -> ManifestResult<Self> {
+        untrusted::from_slice(v)
     }
 }
 
@@ -126,8 +201,79 @@ pub struct Package {
     /// A description of the package (optional).
     pub description: Option<String>,
 
+    /// The SPDX license expression of the package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+
     /// A list of supported platform targets (e.g., "x86_64-apple-darwin").
     pub targets: Vec<String>,
+
+    /// The people allowed to publish the package.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintainers: Vec<Maintainer>,
+
+    /// The place of the package in its toolchain's compile pipeline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage: Option<Stage>,
+
+    /// The optional features the package supports, e.g. `coverage`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
+
+    /// The shared runtime packages the package needs, listed under the
+    /// `runtime` category of the domain index, with the semver requirement
+    /// on their version, e.g. `runtimes = { llvm-runtime = "^17" }`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub runtimes: BTreeMap<String, String>,
+}
+
+impl Package {
+    /// Returns the maintainer owning the signing key with the given fingerprint.
+    pub fn maintainer(&self, fingerprint: &str) -> Option<&Maintainer> {
+        self.maintainers.iter().find(|m| m.fingerprint.eq_ignore_ascii_case(fingerprint))
+    }
+}
+
+/// `Stage` declares a step of a toolchain's compile pipeline, which turns
+/// source files into CLIF.
+///
+/// Stages run by ascending order, each reading the files the previous one
+/// wrote. Example of a preprocessor running before the frontend:
+/// ```toml
+/// [stage]
+/// order = 0
+/// input = "sol"
+/// output = "isol"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stage {
+    /// The position of the stage, lower runs first.
+    pub order: u32,
+
+    /// The extension of the files the stage reads.
+    pub input: String,
+
+    /// The extension of the files the stage writes.
+    pub output: String,
+}
+
+/// `Maintainer` identifies a person allowed to publish a package.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Maintainer {
+    /// The name of the maintainer.
+    pub name: String,
+
+    /// How to reach the maintainer, e.g. an email address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+
+    /// The fingerprint of the maintainer's signing key.
+    pub fingerprint: String,
+
+    /// The hex-encoded public key of the signing key, which artifact
+    /// signatures are verified against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
 }
 
 /// Implement load from file and save to file
@@ -153,10 +299,20 @@ mod tests {
             language: Some(String::from("Rust")),
             kind: String::from("detector"),
             description: Some(String::from("A test package")),
+            license: Some(String::from("Apache-2.0")),
             targets: vec![
                 String::from("x86_64-apple-darwin"),
                 String::from("aarch64-apple-darwin"),
             ],
+            maintainers: vec![Maintainer {
+                name: String::from("Jane Doe"),
+                contact: None,
+                fingerprint: String::from("abc123"),
+                public_key: None,
+            }],
+            stage: None,
+            capabilities: vec![String::from(COVERAGE_CAPABILITY)],
+            runtimes: BTreeMap::from([(String::from("llvm-runtime"), String::from("^17"))]),
         }
     }
 
@@ -199,4 +355,51 @@ mod tests {
         assert_eq!(releases.get("v1.1.0"), Some(&String::from("release-v1.1.0.toml")));
         assert_eq!(releases.get("v1.2.0"), Some(&String::from("release-v1.2.0.toml")));
     }
+
+    #[test]
+    fn test_maintainer() {
+        let package = create_test_package();
+        assert_eq!(package.maintainer("ABC123").map(|m| m.name.as_str()), Some("Jane Doe"));
+        assert!(package.maintainer("def456").is_none());
+    }
+
+    #[test]
+    fn test_maintainers_roundtrip() {
+        let manifest = PackageManifest::new(create_test_package(), String::from("v1.0.0"));
+        let content = toml::to_string(&manifest).unwrap();
+        let parsed = PackageManifest::from_str(&content).unwrap();
+        assert_eq!(parsed.package.maintainers, manifest.package.maintainers);
+        assert_eq!(parsed.package.capabilities, [COVERAGE_CAPABILITY]);
+        assert_eq!(parsed.package.runtimes["llvm-runtime"], "^17");
+    }
+
+    #[test]
+    fn test_promote() {
+        let mut manifest = PackageManifest::new(create_test_package(), String::from("v1.0.0"));
+        manifest.add_release(String::from("v1.0.0"), String::from("release-v1.0.0.toml"));
+        manifest.add_release(String::from("v1.1.0"), String::from("release-v1.1.0.toml"));
+        manifest.channels.insert(String::from("nightly"), String::from("v1.1.0"));
+
+        assert!(manifest.promote("v1.0.0", "nightly", "beta").is_err());
+        assert!(manifest.promote("v2.0.0", "nightly", "beta").is_err());
+
+        manifest.promote("v1.1.0", "nightly", "beta").unwrap();
+        assert_eq!(manifest.channels["beta"], "v1.1.0");
+        assert_eq!(manifest.latest, "v1.0.0");
+
+        manifest.promote("v1.1.0", "beta", "stable").unwrap();
+        assert_eq!(manifest.channels["stable"], "v1.1.0");
+        assert_eq!(manifest.latest, "v1.1.0");
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut manifest = PackageManifest::new(create_test_package(), String::from("v1.1.0"));
+        manifest.add_release(String::from("v1.1.0"), String::from("release-v1.1.0.toml"));
+        let content = toml::to_string(&manifest).unwrap();
+
+        let summary = PackageSummary::parse(content.as_bytes()).unwrap();
+        assert_eq!(summary.name, "test-package");
+        assert_eq!(summary.latest, "v1.1.0");
+    }
 }
@@ -12,6 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
 use serde::{Deserialize, Serialize};
 
 use crate::{error::ManifestResult, ManifestError, ManifestFile};
@@ -21,18 +26,188 @@ use crate::{error::ManifestResult, ManifestError, ManifestFile};
 /// Example:
 /// ```toml
 /// language = "Solidity"
+///
+/// [[bin]]
+/// name = "server"
+/// main = "src/server.sol"
+///
+/// [[bin]]
+/// name = "server-tests"
+/// main = "tests/server.sol"
+/// test = true
+///
+/// [dependencies]
+/// common = { path = "../common" }
+/// math = { version = "^1.2" }
+///
+/// [features]
+/// default = ["net"]
+/// net = []
+/// tls = ["net"]
+///
+/// [env]
+/// SOLC_OPTIMIZE = "1"
+/// SOLC_CACHE = { value = "${HOME}/.cache/solc", expand = true }
 /// ```
+///
+/// A manifest may extend a shared base manifest, by path relative to the
+/// manifest or by URL pinned to its SHA-256:
+///
+/// ```toml
+/// extends = { url = "https://example.com/hummanta-base.toml", sha256 = "..." }
+/// language = "Solidity"
+/// ```
+///
+/// The manifest is merged over its base, see [`ProjectManifest::with_base`].
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ProjectManifest {
+    /// The base manifest this manifest is merged over.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<Extends>,
+
     /// Metadata for the project, such as language and build.
     #[serde(flatten)]
     pub project: Project,
+
+    /// The executables built from this project, one per entry point.
+    #[serde(default, rename = "bin", skip_serializing_if = "Vec::is_empty")]
+    pub bins: Vec<Binary>,
+
+    /// Other Hummanta projects this project depends on, keyed by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, Dependency>,
+
+    /// Optional features, each mapped to the features it enables in turn.
+    /// The `default` feature lists the features enabled unless opted out.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub features: BTreeMap<String, Vec<String>>,
+
+    /// Environment variables set for every toolchain process of the project.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, EnvValue>,
 }
 
 impl ProjectManifest {
     /// Creates a new instance with the specified language.
     pub fn new(project: Project) -> Self {
-        ProjectManifest { project }
+        ProjectManifest {
+            extends: None,
+            project,
+            bins: Vec::new(),
+            dependencies: BTreeMap::new(),
+            features: BTreeMap::new(),
+            env: BTreeMap::new(),
+        }
+    }
+
+    /// Resolves the set of enabled features.
+    ///
+    /// Starts from `requested`, plus the `default` feature when `default` is
+    /// true, and transitively adds every feature they enable.
+    pub fn resolve_features(
+        &self,
+        requested: &[String],
+        default: bool,
+    ) -> ManifestResult<BTreeSet<String>> {
+        let mut pending: Vec<&str> = requested.iter().map(String::as_str).collect();
+        if default && self.features.contains_key("default") {
+            pending.push("default");
+        }
+
+        let mut enabled = BTreeSet::new();
+        while let Some(feature) = pending.pop() {
+            let implied = self
+                .features
+                .get(feature)
+                .ok_or_else(|| ManifestError::UnknownFeature(feature.to_string()))?;
+
+            if enabled.insert(feature.to_string()) {
+                pending.extend(implied.iter().map(String::as_str));
+            }
+        }
+
+        // `default` only groups other features, it is not a feature itself
+        enabled.remove("default");
+        Ok(enabled)
+    }
+
+    /// Resolves the environment variables of toolchain processes.
+    ///
+    /// Values are taken literally, unless they opt into expansion: then
+    /// `${NAME}` is replaced by the host variable `NAME`, which must be in
+    /// `allowed` so projects cannot read secrets from the environment, and
+    /// `$$` stands for a literal `$`. Unset host variables expand to nothing.
+    pub fn resolve_env(
+        &self,
+        allowed: &[String],
+        host: impl Fn(&str) -> Option<String>,
+    ) -> ManifestResult<Vec<(String, String)>> {
+        self.env
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    EnvValue::Detailed { value, expand: true } => expand(value, |var| {
+                        if !allowed.iter().any(|allowed| allowed == var) {
+                            return Err(ManifestError::EnvNotAllowed(
+                                name.clone(),
+                                var.to_string(),
+                            ));
+                        }
+                        Ok(host(var).unwrap_or_default())
+                    })?,
+                    EnvValue::Plain(value) | EnvValue::Detailed { value, .. } => value.clone(),
+                };
+                Ok((name.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Get a binary by name.
+    pub fn get_bin(&self, name: &str) -> Option<&Binary> {
+        self.bins.iter().find(|bin| bin.name == name)
+    }
+}
+
+impl ProjectManifest {
+    /// Returns the base a manifest source extends, without requiring the
+    /// fields the base may provide.
+    pub fn extends_of(s: &str) -> ManifestResult<Option<Extends>> {
+        let mut table: toml::Table = toml::from_str(s)?;
+        match table.remove("extends") {
+            Some(extends) => Ok(Some(extends.try_into()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses a manifest source merged over the source of its base.
+    ///
+    /// Values of the manifest take precedence over those of the base.
+    /// Tables, such as `[dependencies]`, `[features]` and `[env]`, are
+    /// merged key by key, recursively. Arrays, such as `[[bin]]`, are
+    /// replaced as a whole. Bases cannot extend other bases, and paths in
+    /// the base are relative to the extending manifest.
+    pub fn with_base(s: &str, base: &str) -> ManifestResult<Self> {
+        let mut merged: toml::Table = toml::from_str(base)?;
+        if merged.contains_key("extends") {
+            return Err(ManifestError::InvalidFormat(
+                "a base manifest cannot extend another manifest".to_string(),
+            ));
+        }
+
+        merge(&mut merged, toml::from_str(s)?);
+        Ok(merged.try_into()?)
+    }
+}
+
+/// Merges `overlay` into `base`, recursing into tables present in both.
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
     }
 }
 
@@ -65,3 +240,314 @@ impl Project {
         Self { language: language.to_string(), extension: extension.to_string(), target: None }
     }
 }
+
+/// `EnvValue` is the value of a project environment variable, either a
+/// literal string or a table opting into the expansion of host variables.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EnvValue {
+    /// A literal value.
+    Plain(String),
+
+    /// A value that may reference host variables as `${NAME}`.
+    Detailed {
+        /// The value of the variable.
+        value: String,
+
+        /// Whether `${NAME}` references are expanded.
+        #[serde(default)]
+        expand: bool,
+    },
+}
+
+/// Replaces the `${NAME}` references of `value` with the result of `lookup`,
+/// and `$$` with `$`.
+fn expand(value: &str, lookup: impl Fn(&str) -> ManifestResult<String>) -> ManifestResult<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(index) = rest.find('$') {
+        expanded.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+        } else if let Some(reference) = rest.strip_prefix('{') {
+            let end = reference.find('}').ok_or_else(|| {
+                ManifestError::InvalidFormat(format!("unterminated `${{` in `{value}`"))
+            })?;
+            expanded.push_str(&lookup(&reference[..end])?);
+            rest = &reference[end + 1..];
+        } else {
+            expanded.push('$');
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// `Extends` names the base manifest of a project.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Extends {
+    /// A path relative to the extending manifest.
+    Plain(String),
+
+    /// A URL, pinned to the SHA-256 of the base.
+    Pinned {
+        /// The URL of the base.
+        url: String,
+
+        /// The hex-encoded SHA-256 hash of the base.
+        sha256: String,
+    },
+}
+
+/// `Binary` describes an executable built from a single entry point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Binary {
+    /// The name of the executable.
+    pub name: String,
+
+    /// The source file containing the entry point, relative to the project root.
+    pub main: PathBuf,
+
+    /// Extra flags passed to the linker for this executable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+
+    /// Whether the executable is a test, run by `hummanta test`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub test: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// `Dependency` points at another Hummanta project, either on disk or
+/// published to the registry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dependency {
+    /// The directory of the dependency, relative to the depending project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+
+    /// The semver requirement of a library published to the registry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_bins() {
+        let manifest = ProjectManifest::from_str(
+            r#"
+            language = "solidity"
+            extension = "sol"
+
+            [[bin]]
+            name = "server"
+            main = "src/server.sol"
+            flags = ["--strip"]
+
+            [[bin]]
+            name = "client"
+            main = "src/client.sol"
+            test = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.bins.len(), 2);
+        assert_eq!(manifest.get_bin("server").unwrap().flags, vec!["--strip"]);
+        assert_eq!(manifest.get_bin("client").unwrap().main, PathBuf::from("src/client.sol"));
+        assert!(manifest.get_bin("client").unwrap().test);
+        assert!(!manifest.get_bin("server").unwrap().test);
+        assert!(manifest.get_bin("missing").is_none());
+    }
+
+    #[test]
+    fn test_resolve_env() {
+        let manifest = ProjectManifest::from_str(
+            r#"
+            language = "solidity"
+            extension = "sol"
+
+            [env]
+            SOLC_OPTIMIZE = "1"
+            SOLC_LITERAL = "${HOME}"
+            SOLC_CACHE = { value = "${HOME}/.cache/$$solc", expand = true }
+            "#,
+        )
+        .unwrap();
+
+        let host = |var: &str| (var == "HOME").then(|| "/home/dev".to_string());
+        let env = manifest.resolve_env(&["HOME".into()], host).unwrap();
+        assert_eq!(
+            env,
+            [
+                ("SOLC_CACHE".into(), "/home/dev/.cache/$solc".into()),
+                ("SOLC_LITERAL".into(), "${HOME}".into()),
+                ("SOLC_OPTIMIZE".into(), "1".into()),
+            ]
+        );
+
+        // Host variables are only expanded when allowed
+        let error = manifest.resolve_env(&[], host).unwrap_err();
+        assert!(matches!(error, ManifestError::EnvNotAllowed(name, var)
+            if name == "SOLC_CACHE" && var == "HOME"));
+    }
+
+    #[test]
+    fn test_expand() {
+        let lookup = |var: &str| Ok(var.to_lowercase());
+        assert_eq!(expand("${A}-${B}", lookup).unwrap(), "a-b");
+        assert_eq!(expand("$$A $ $", lookup).unwrap(), "$A $ $");
+        assert!(expand("${A", lookup).is_err());
+    }
+
+    #[test]
+    fn test_parse_without_bins() {
+        let manifest = ProjectManifest::from_str(
+            r#"
+            language = "solidity"
+            extension = "sol"
+            "#,
+        )
+        .unwrap();
+
+        assert!(manifest.bins.is_empty());
+        assert!(manifest.dependencies.is_empty());
+        assert!(manifest.features.is_empty());
+    }
+
+    fn features_manifest() -> ProjectManifest {
+        ProjectManifest::from_str(
+            r#"
+            language = "solidity"
+            extension = "sol"
+
+            [features]
+            default = ["net"]
+            net = []
+            tls = ["net"]
+            debug = []
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_default_features() {
+        let manifest = features_manifest();
+        let enabled = manifest.resolve_features(&[], true).unwrap();
+        assert_eq!(enabled.into_iter().collect::<Vec<_>>(), vec!["net"]);
+    }
+
+    #[test]
+    fn test_resolve_implied_features() {
+        let manifest = features_manifest();
+        let enabled = manifest.resolve_features(&["tls".to_string()], false).unwrap();
+        assert_eq!(enabled.into_iter().collect::<Vec<_>>(), vec!["net", "tls"]);
+    }
+
+    #[test]
+    fn test_resolve_no_default_features() {
+        let manifest = features_manifest();
+        assert!(manifest.resolve_features(&[], false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_unknown_feature() {
+        let manifest = features_manifest();
+        let result = manifest.resolve_features(&["gpu".to_string()], true);
+        assert!(matches!(result, Err(ManifestError::UnknownFeature(f)) if f == "gpu"));
+    }
+
+    #[test]
+    fn test_parse_dependencies() {
+        let manifest = ProjectManifest::from_str(
+            r#"
+            language = "solidity"
+            extension = "sol"
+
+            [dependencies]
+            common = { path = "../common" }
+            math = { version = "^1.2" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.dependencies["common"].path, Some(PathBuf::from("../common")));
+        assert_eq!(manifest.dependencies["math"].version.as_deref(), Some("^1.2"));
+    }
+
+    #[test]
+    fn test_with_base() {
+        let base = r#"
+            language = "solidity"
+            extension = "sol"
+
+            [[bin]]
+            name = "base"
+            main = "src/base.sol"
+
+            [dependencies]
+            common = { path = "../common" }
+
+            [env]
+            SOLC_OPTIMIZE = "1"
+            SOLC_CACHE = "/tmp"
+        "#;
+        let local = r#"
+            extends = "../base.toml"
+            extension = "yul"
+
+            [[bin]]
+            name = "app"
+            main = "src/app.sol"
+
+            [env]
+            SOLC_OPTIMIZE = "0"
+        "#;
+
+        assert_eq!(
+            ProjectManifest::extends_of(local).unwrap(),
+            Some(Extends::Plain("../base.toml".to_string()))
+        );
+
+        let manifest = ProjectManifest::with_base(local, base).unwrap();
+        assert_eq!(manifest.project.language, "solidity");
+        assert_eq!(manifest.project.extension, "yul");
+        assert_eq!(manifest.bins.len(), 1);
+        assert!(manifest.get_bin("app").is_some());
+        assert!(manifest.dependencies.contains_key("common"));
+        assert_eq!(manifest.env["SOLC_OPTIMIZE"], EnvValue::Plain("0".to_string()));
+        assert_eq!(manifest.env["SOLC_CACHE"], EnvValue::Plain("/tmp".to_string()));
+
+        let nested = "extends = \"other.toml\"\nlanguage = \"solidity\"";
+        assert!(ProjectManifest::with_base(local, nested).is_err());
+    }
+
+    #[test]
+    fn test_extends_pinned() {
+        let extends = ProjectManifest::extends_of(
+            r#"extends = { url = "https://example.com/base.toml", sha256 = "abc" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            extends,
+            Some(Extends::Pinned {
+                url: "https://example.com/base.toml".to_string(),
+                sha256: "abc".to_string()
+            })
+        );
+        assert_eq!(ProjectManifest::extends_of("language = \"solidity\"").unwrap(), None);
+    }
+}
@@ -0,0 +1,325 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmt_utils::{bytes::FromSlice, signature};
+use serde_json::Value;
+
+use crate::{ManifestError, ManifestResult};
+
+/// The type prefix of the in-toto statements accepted as provenance.
+pub const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/";
+
+/// `Provenance` is an in-toto statement attesting how artifacts were built.
+///
+/// Statements are read from DSSE envelopes, bare or inside a Sigstore
+/// bundle, and are only trusted when an envelope signature verifies against
+/// the Ed25519 key of a trusted builder. Keyless Sigstore bundles, whose
+/// signatures are backed by a certificate instead of a key, are rejected.
+///
+/// The source is read from SLSA v1 predicates, either the GitHub workflow
+/// parameters:
+/// ```json
+/// {
+///   "_type": "https://in-toto.io/Statement/v1",
+///   "subject": [{ "name": "foo.tar.gz", "digest": { "sha256": "a80a0dd7..." } }],
+///   "predicateType": "https://slsa.dev/provenance/v1",
+///   "predicate": {
+///     "buildDefinition": {
+///       "externalParameters": {
+///         "workflow": { "repository": "https://github.com/hummanta/foo", "ref": "refs/tags/v1.0.0" }
+///       }
+///     }
+///   }
+/// }
+/// ```
+/// or the first `git+<repository>@<ref>` URI of the resolved dependencies,
+/// and from the config source of SLSA v0.2 predicates.
+#[derive(Debug)]
+pub struct Provenance {
+    statement: Value,
+    /// The envelope the statement was read from, if any.
+    envelope: Option<Envelope>,
+}
+
+/// A DSSE envelope: a payload and signatures over its pre-authentication
+/// encoding.
+#[derive(Debug)]
+struct Envelope {
+    payload_type: String,
+    payload: Vec<u8>,
+    signatures: Vec<Vec<u8>>,
+}
+
+impl Envelope {
+    /// Returns the pre-authentication encoding the signatures cover.
+    fn pae(&self) -> Vec<u8> {
+        let (kind, payload) = (&self.payload_type, &self.payload);
+        let mut pae = format!("DSSEv1 {} {kind} {} ", kind.len(), payload.len()).into_bytes();
+        pae.extend_from_slice(payload);
+        pae
+    }
+}
+
+impl Provenance {
+    /// Checks that a builder holding one of the hex-encoded `keys` signed
+    /// the statement, and that it covers an artifact with the given SHA-256
+    /// hash, built from `repository` at the tag named `version`.
+    pub fn verify(
+        &self,
+        keys: &[String],
+        hash: &str,
+        repository: &str,
+        version: &str,
+    ) -> ManifestResult<()> {
+        self.verify_signature(keys)?;
+
+        let subjects = self.statement["subject"].as_array().map(Vec::as_slice).unwrap_or_default();
+        let covered = subjects.iter().any(|subject| {
+            subject["digest"]["sha256"].as_str().is_some_and(|d| d.eq_ignore_ascii_case(hash))
+        });
+        if !covered {
+            return Err(mismatch(format!("no subject has the digest {hash}")));
+        }
+
+        // The source the build was started from must be the package repository,
+        // other resolved dependencies do not count
+        let tag = format!("refs/tags/{version}");
+        let Some((repo, r)) = self.source() else {
+            return Err(mismatch("the predicate names no source".to_string()));
+        };
+        if !same_repository(&repo, repository) {
+            return Err(mismatch(format!("built from {repo}, not {repository}")));
+        }
+        if r != tag {
+            return Err(mismatch(format!("built from {r}, not {tag} of {repository}")));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that an envelope signature verifies against one of the keys.
+    fn verify_signature(&self, keys: &[String]) -> ManifestResult<()> {
+        let Some(envelope) = &self.envelope else {
+            return Err(unverified("the statement is not in a signed envelope"));
+        };
+        if keys.is_empty() {
+            return Err(unverified("no builder keys are trusted"));
+        }
+
+        let pae = envelope.pae();
+        let signed = envelope
+            .signatures
+            .iter()
+            .any(|sig| keys.iter().any(|key| signature::verify_bytes(key, &pae, sig).is_ok()));
+        if !signed {
+            return Err(unverified("no signature verifies against a trusted builder key"));
+        }
+        Ok(())
+    }
+
+    /// Returns the repository and ref the build was started from.
+    fn source(&self) -> Option<(String, String)> {
+        let predicate = &self.statement["predicate"];
+
+        let workflow = &predicate["buildDefinition"]["externalParameters"]["workflow"];
+        if let (Some(repo), Some(r)) = (workflow["repository"].as_str(), workflow["ref"].as_str()) {
+            return Some((repo.to_string(), r.to_string()));
+        }
+
+        let uri = predicate["buildDefinition"]["resolvedDependencies"][0]["uri"]
+            .as_str()
+            .or_else(|| predicate["invocation"]["configSource"]["uri"].as_str())?;
+        let (repo, r) = uri.strip_prefix("git+")?.rsplit_once('@')?;
+        Some((repo.to_string(), r.to_string()))
+    }
+}
+
+impl FromSlice for Provenance {
+    type Err = ManifestError;
+
+    fn from_slice(v: &[u8]) -> Result<Self, Self::Err> {
+        let mut value: Value = serde_json::from_slice(v)?;
+
+        // Unwrap a Sigstore bundle, then a DSSE envelope
+        if let Some(envelope) = value.get_mut("dsseEnvelope") {
+            value = envelope.take();
+        }
+        let mut envelope = None;
+        if let Some(payload) = value.get("payload").and_then(Value::as_str) {
+            let payload = decode(payload, "payload")?;
+            let signatures = value["signatures"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|signature| signature["sig"].as_str())
+                .map(|sig| decode(sig, "signature"))
+                .collect::<ManifestResult<_>>()?;
+            let payload_type = value["payloadType"].as_str().unwrap_or_default().to_string();
+
+            value = serde_json::from_slice(&payload)?;
+            envelope = Some(Envelope { payload_type, payload, signatures });
+        }
+
+        let kind = value["_type"].as_str().unwrap_or_default();
+        if !kind.starts_with(STATEMENT_TYPE) {
+            return Err(ManifestError::InvalidFormat(format!(
+                "'{kind}' is not an in-toto statement"
+            )));
+        }
+
+        Ok(Self { statement: value, envelope })
+    }
+}
+
+/// Decodes a base64 field of a DSSE envelope.
+fn decode(value: &str, field: &str) -> ManifestResult<Vec<u8>> {
+    STANDARD.decode(value).map_err(|e| ManifestError::InvalidFormat(format!("DSSE {field}: {e}")))
+}
+
+fn mismatch(message: String) -> ManifestError {
+    ManifestError::ProvenanceMismatch(message)
+}
+
+fn unverified(message: &str) -> ManifestError {
+    ManifestError::ProvenanceUnverified(message.to_string())
+}
+
+/// Compares repository URLs, ignoring case, a `.git` suffix and trailing slashes.
+fn same_repository(a: &str, b: &str) -> bool {
+    let normalize = |url: &str| {
+        let url = url.trim_end_matches('/');
+        url.strip_suffix(".git").unwrap_or(url).to_ascii_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use hmt_utils::signature::SigningKey;
+
+    use super::*;
+
+    const HASH: &str = "a80a0dd7425173064ce6d1a4ba04b18a967484d6f0d19080170843229065c006";
+    const REPOSITORY: &str = "https://github.com/hummanta/foo";
+    const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+    fn statement() -> Value {
+        serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{ "name": "foo.tar.gz", "digest": { "sha256": HASH } }],
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "predicate": {
+                "buildDefinition": {
+                    "externalParameters": {
+                        "workflow": { "repository": REPOSITORY, "ref": "refs/tags/v1.0.0" }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Wraps a statement in a DSSE envelope signed by `key`.
+    fn envelope(statement: &Value, key: &SigningKey) -> Value {
+        let payload = statement.to_string().into_bytes();
+        let unsigned = Envelope {
+            payload_type: PAYLOAD_TYPE.to_string(),
+            payload: payload.clone(),
+            signatures: Vec::new(),
+        };
+        let hex = key.sign(&unsigned.pae());
+        let sig: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+
+        serde_json::json!({
+            "payloadType": PAYLOAD_TYPE,
+            "payload": STANDARD.encode(payload),
+            "signatures": [{ "keyid": "", "sig": STANDARD.encode(sig) }]
+        })
+    }
+
+    fn parse(value: &Value) -> Provenance {
+        Provenance::from_slice(value.to_string().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_verify_statement() {
+        let key = SigningKey::from_seed([7; 32]);
+        let keys = [key.public_key()];
+        let provenance = parse(&envelope(&statement(), &key));
+        assert!(provenance.verify(&keys, HASH, REPOSITORY, "v1.0.0").is_ok());
+        let repository = "https://github.com/hummanta/foo.git/";
+        assert!(provenance.verify(&keys, HASH, repository, "v1.0.0").is_ok());
+
+        assert!(provenance.verify(&keys, &"0".repeat(64), REPOSITORY, "v1.0.0").is_err());
+        assert!(provenance.verify(&keys, HASH, "https://github.com/evil/foo", "v1.0.0").is_err());
+        assert!(provenance.verify(&keys, HASH, REPOSITORY, "v2.0.0").is_err());
+    }
+
+    #[test]
+    fn test_rejects_untrusted_signatures() {
+        let key = SigningKey::from_seed([7; 32]);
+        let keys = [key.public_key()];
+
+        let unsigned = parse(&statement());
+        let err = unsigned.verify(&keys, HASH, REPOSITORY, "v1.0.0").unwrap_err();
+        assert!(matches!(err, ManifestError::ProvenanceUnverified(_)));
+
+        let other = parse(&envelope(&statement(), &SigningKey::from_seed([8; 32])));
+        assert!(other.verify(&keys, HASH, REPOSITORY, "v1.0.0").is_err());
+
+        let signed = parse(&envelope(&statement(), &key));
+        assert!(signed.verify(&[], HASH, REPOSITORY, "v1.0.0").is_err());
+
+        // A payload swapped after signing
+        let mut tampered = envelope(&statement(), &key);
+        let mut forged = statement();
+        forged["subject"][0]["digest"]["sha256"] = "0".repeat(64).into();
+        tampered["payload"] = STANDARD.encode(forged.to_string()).into();
+        let tampered = parse(&tampered);
+        assert!(tampered.verify(&keys, &"0".repeat(64), REPOSITORY, "v1.0.0").is_err());
+    }
+
+    #[test]
+    fn test_verify_bundle_with_resolved_dependencies() {
+        let key = SigningKey::from_seed([7; 32]);
+        let keys = [key.public_key()];
+
+        let mut statement = statement();
+        statement["predicate"] = serde_json::json!({
+            "buildDefinition": {
+                "resolvedDependencies": [{ "uri": "git+https://github.com/hummanta/foo@refs/tags/v1.0.0" }]
+            }
+        });
+        let bundle = serde_json::json!({ "dsseEnvelope": envelope(&statement, &key) });
+        assert!(parse(&bundle).verify(&keys, HASH, REPOSITORY, "v1.0.0").is_ok());
+
+        // Only the first dependency is the source of the build
+        statement["predicate"]["buildDefinition"]["resolvedDependencies"] = serde_json::json!([
+            { "uri": "git+https://github.com/evil/foo@refs/tags/v1.0.0" },
+            { "uri": "git+https://github.com/hummanta/foo@refs/tags/v1.0.0" }
+        ]);
+        let bundle = serde_json::json!({ "dsseEnvelope": envelope(&statement, &key) });
+        let err = parse(&bundle).verify(&keys, HASH, REPOSITORY, "v1.0.0").unwrap_err();
+        assert!(matches!(err, ManifestError::ProvenanceMismatch(_)));
+    }
+
+    #[test]
+    fn test_rejects_non_statements() {
+        assert!(Provenance::from_slice(br#"{"_type": "other"}"#).is_err());
+        assert!(Provenance::from_slice(b"not json").is_err());
+    }
+}
@@ -12,12 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    str::FromStr,
+};
 
 use hmt_utils::bytes::FromSlice;
 use serde::{Deserialize, Serialize};
 
-use crate::{ManifestError, ManifestFile};
+use crate::{untrusted, ManifestError, ManifestFile};
 
 /// `ReleaseManifest` describes a specific released version of a package.
 ///
@@ -27,6 +30,11 @@ use crate::{ManifestError, ManifestFile};
 /// Example:
 /// ```toml
 /// version = "v1.2.0"
+/// changelog = "Support Solidity 0.8.30."
+///
+/// [components]
+/// core = ["bin/solidity-detector-foundry"]
+/// docs = ["share/doc"]
 ///
 /// [artifacts.x86_64-apple-darwin]
 /// url = "https://github.com/hummanta/solidity-detector-foundry/releases/download/v1.2.0/solidity-detector-foundry-x86_64-apple-darwin.tar.gz"
@@ -86,6 +94,79 @@ impl ReleaseManifest {
     pub fn supports_target(&self, target: &str) -> bool {
         self.artifacts.contains_key(target)
     }
+
+    /// Returns the artifact of the first target this release was built for,
+    /// e.g. of the targets running on the host, most preferred first.
+    pub fn find_artifact<'a>(&'a self, targets: &'a [String]) -> Option<(&'a str, &'a Artifact)> {
+        targets.iter().find_map(|target| Some((target.as_str(), self.artifacts.get(target)?)))
+    }
+
+    /// Resolves named components to the archive paths they select, skipping
+    /// the names this release does not declare. No names select the whole
+    /// artifact, as do releases declaring no components.
+    pub fn select(&self, names: &[String]) -> Result<BTreeMap<String, Vec<String>>, ManifestError> {
+        let components = &self.release.components;
+        if components.is_empty() || names.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+
+        let selected: BTreeMap<_, _> = names
+            .iter()
+            .filter_map(|name| Some((name.clone(), components.get(name)?.clone())))
+            .collect();
+        if selected.is_empty() {
+            return Err(ManifestError::InvalidFormat(format!(
+                "release {} has none of the components {}, expected one of: {}",
+                self.release.version,
+                names.join(", "),
+                components.keys().cloned().collect::<Vec<_>>().join(", ")
+            )));
+        }
+        Ok(selected)
+    }
+
+    /// Describes the changes from an older release to this one, as seen
+    /// from the `target` platform. `old` is `None` for a first install.
+    pub fn diff(&self, old: Option<&ReleaseManifest>, target: &str) -> ReleaseDiff {
+        let targets = |release: Option<&ReleaseManifest>| -> BTreeSet<String> {
+            release.map(|r| r.artifacts.keys().cloned().collect()).unwrap_or_default()
+        };
+        let (before, after) = (targets(old), targets(Some(self)));
+
+        // The delta is only known when every involved size was published
+        let size = |release: &ReleaseManifest| release.get_artifact(target).and_then(|a| a.size);
+        let size_delta = match old {
+            Some(old) => size(old).zip(size(self)).map(|(old, new)| new as i64 - old as i64),
+            None => size(self).map(|new| new as i64),
+        };
+
+        ReleaseDiff {
+            from: old.map(|r| r.release.version.clone()),
+            to: self.release.version.clone(),
+            size_delta,
+            added_targets: after.difference(&before).cloned().collect(),
+            removed_targets: before.difference(&after).cloned().collect(),
+        }
+    }
+}
+
+/// `ReleaseDiff` summarizes what changes when moving between two releases.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReleaseDiff {
+    /// The version being replaced, `None` for a first install.
+    pub from: Option<String>,
+
+    /// The version being installed.
+    pub to: String,
+
+    /// The change of the download size in bytes, if both sizes are known.
+    pub size_delta: Option<i64>,
+
+    /// Target platforms supported only by the new release.
+    pub added_targets: Vec<String>,
+
+    /// Target platforms no longer supported by the new release.
+    pub removed_targets: Vec<String>,
 }
 
 /// Implement load from file and save to file
@@ -103,9 +184,7 @@ impl FromSlice for ReleaseManifest {
     type Err = ManifestError;
 
     fn from_slice(v: &[u8]) -> Result<Self, Self::Err> {
-        let s = std::str::from_utf8(v)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-        toml::from_str(s).map_err(ManifestError::from)
+        untrusted::from_slice(v)
     }
 }
 
@@ -114,11 +193,24 @@ impl FromSlice for ReleaseManifest {
 pub struct Release {
     /// The version of the release.
     pub version: String,
+
+    /// The release notes, as Markdown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+
+    /// The URL of the release notes, for releases publishing them elsewhere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog_url: Option<String>,
+
+    /// Named subsets of the artifact files, as paths of files or
+    /// directories relative to the archive root.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, Vec<String>>,
 }
 
 impl Release {
     pub fn new(version: String) -> Self {
-        Self { version }
+        Self { version, changelog: None, changelog_url: None, components: BTreeMap::new() }
     }
 }
 
@@ -130,6 +222,18 @@ pub struct Artifact {
 
     /// The hash of the artifact file, used for integrity checking.
     pub hash: String,
+
+    /// The detached signature of the artifact, if the publisher signed it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+
+    /// The size of the artifact file in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+
+    /// The URL of an in-toto provenance statement covering the artifact.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<String>,
 }
 
 #[cfg(test)]
@@ -141,6 +245,9 @@ mod tests {
         let artifact = Artifact {
             url: String::from("https://example.com/artifact"),
             hash: String::from("abc123"),
+            signature: None,
+            size: None,
+            provenance: None,
         };
 
         assert_eq!(artifact.url, "https://example.com/artifact");
@@ -163,6 +270,9 @@ mod tests {
         let artifact = Artifact {
             url: String::from("https://example.com/artifact"),
             hash: String::from("abc123"),
+            signature: None,
+            size: None,
+            provenance: None,
         };
 
         manifest.add_artifact(String::from("x86_64-unknown-linux-gnu"), artifact);
@@ -177,6 +287,9 @@ mod tests {
         let artifact = Artifact {
             url: String::from("https://example.com/artifact"),
             hash: String::from("abc123"),
+            signature: None,
+            size: None,
+            provenance: None,
         };
 
         manifest.add_artifact(String::from("x86_64-unknown-linux-gnu"), artifact);
@@ -194,6 +307,9 @@ mod tests {
         let artifact = Artifact {
             url: String::from("https://example.com/artifact"),
             hash: String::from("abc123"),
+            signature: None,
+            size: None,
+            provenance: None,
         };
 
         manifest.add_artifact(String::from("x86_64-unknown-linux-gnu"), artifact);
@@ -201,4 +317,78 @@ mod tests {
         assert!(manifest.supports_target("x86_64-unknown-linux-gnu"));
         assert!(!manifest.supports_target("aarch64-unknown-linux-gnu"));
     }
+
+    #[test]
+    fn test_find_artifact() {
+        let manifest = release("v1.0.0", &[("x86_64-unknown-linux-musl", None)]);
+        let targets =
+            ["x86_64-unknown-linux-gnu".to_string(), "x86_64-unknown-linux-musl".to_string()];
+
+        let (target, _) = manifest.find_artifact(&targets).unwrap();
+        assert_eq!(target, "x86_64-unknown-linux-musl");
+        assert!(manifest.find_artifact(&targets[..1]).is_none());
+    }
+
+    fn release(version: &str, targets: &[(&str, Option<u64>)]) -> ReleaseManifest {
+        let mut manifest = ReleaseManifest::new(Release::new(version.to_string()), HashMap::new());
+        for (target, size) in targets {
+            let artifact = Artifact {
+                url: String::from("https://example.com/artifact"),
+                hash: String::from("abc123"),
+                signature: None,
+                size: *size,
+                provenance: None,
+            };
+            manifest.add_artifact(target.to_string(), artifact);
+        }
+        manifest
+    }
+
+    #[test]
+    fn test_select() {
+        let mut manifest: ReleaseManifest = r#"
+            version = "v1.0.0"
+
+            [components]
+            core = ["bin/compiler"]
+            docs = ["share/doc", "README.md"]
+
+            [artifacts]
+        "#
+        .parse()
+        .unwrap();
+
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let selected = manifest.select(&names(&["docs"])).unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected["docs"], ["share/doc", "README.md"]);
+        assert!(manifest.select(&[]).unwrap().is_empty());
+
+        let selected = manifest.select(&names(&["core", "tests"])).unwrap();
+        assert_eq!(selected.keys().collect::<Vec<_>>(), ["core"]);
+        let err = manifest.select(&names(&["tests"])).unwrap_err();
+        assert!(err.to_string().contains("expected one of: core, docs"));
+
+        // Releases without components are installed whole
+        manifest.release.components.clear();
+        assert!(manifest.select(&names(&["core"])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let linux = "x86_64-unknown-linux-gnu";
+        let old = release("v1.0.0", &[(linux, Some(300)), ("x86_64-apple-darwin", None)]);
+        let new = release("v1.1.0", &[(linux, Some(200)), ("aarch64-apple-darwin", None)]);
+
+        let diff = new.diff(Some(&old), linux);
+        assert_eq!(diff.from.as_deref(), Some("v1.0.0"));
+        assert_eq!(diff.to, "v1.1.0");
+        assert_eq!(diff.size_delta, Some(-100));
+        assert_eq!(diff.added_targets, ["aarch64-apple-darwin"]);
+        assert_eq!(diff.removed_targets, ["x86_64-apple-darwin"]);
+
+        // Unknown sizes leave the delta unknown
+        assert_eq!(new.diff(Some(&old), "x86_64-apple-darwin").size_delta, None);
+        assert_eq!(new.diff(None, linux).size_delta, Some(200));
+    }
 }
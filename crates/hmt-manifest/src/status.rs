@@ -0,0 +1,222 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{FrozenPackage, ManifestResult, OutputKind, OutputManifest};
+
+/// The version of the build status format, raised on incompatible changes.
+pub const BUILD_STATUS_VERSION: u32 = 1;
+
+/// `BuildStatus` describes the last build of a project for status reporters.
+///
+/// It is written to `target/<triple>/build-status.json` after every build,
+/// whether it succeeded or not. Fields are only ever added within a
+/// [`BUILD_STATUS_VERSION`].
+///
+/// Example:
+/// ```json
+/// {
+///   "version": 1,
+///   "status": "success",
+///   "target": "x86_64-unknown-linux-gnu",
+///   "finished_at": 1760000000,
+///   "durations": { "total_ms": 1840, "compile_ms": 920, "emit_ms": 610, "link_ms": 140 },
+///   "warnings": 2,
+///   "artifacts": [
+///     {
+///       "kind": "executable",
+///       "path": "target/x86_64-unknown-linux-gnu/main",
+///       "hash": "a80a0dd7425173064ce6d1a4ba04b18a967484d6f0d19080170843229065c006"
+///     }
+///   ],
+///   "tools": [
+///     { "kind": "toolchains", "domain": "solidity", "category": "frontend", "name": "solidity-frontend", "version": "v1.2.0" }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildStatus {
+    /// The version of the format, see [`BUILD_STATUS_VERSION`].
+    pub version: u32,
+
+    /// Whether the build succeeded.
+    pub status: Status,
+
+    /// The error the build failed with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// The target platform of the build.
+    pub target: String,
+
+    /// When the build finished, in seconds since the Unix epoch.
+    pub finished_at: u64,
+
+    /// How long the build and its phases took.
+    pub durations: Durations,
+
+    /// The number of warnings reported during the build.
+    pub warnings: usize,
+
+    /// The artifacts of the project, without those of its dependencies.
+    pub artifacts: Vec<StatusArtifact>,
+
+    /// The installed packages the build used.
+    pub tools: Vec<StatusTool>,
+}
+
+impl BuildStatus {
+    /// Creates the status of a build that has not finished yet.
+    pub fn new(target: &str) -> Self {
+        Self {
+            version: BUILD_STATUS_VERSION,
+            status: Status::Failure,
+            error: None,
+            target: target.to_string(),
+            finished_at: 0,
+            durations: Durations::default(),
+            warnings: 0,
+            artifacts: Vec::new(),
+            tools: Vec::new(),
+        }
+    }
+
+    /// Records the artifacts of a build.
+    pub fn artifacts(&mut self, outputs: &OutputManifest) {
+        self.artifacts = outputs
+            .outputs
+            .iter()
+            .map(|output| StatusArtifact {
+                kind: output.kind,
+                path: output.path.display().to_string(),
+                hash: output.hash.clone(),
+            })
+            .collect();
+    }
+
+    /// Records the installed packages of a build.
+    pub fn tools(&mut self, packages: &[FrozenPackage]) {
+        self.tools = packages
+            .iter()
+            .map(|package| StatusTool {
+                kind: package.kind.clone(),
+                domain: package.domain.clone(),
+                category: package.category.clone(),
+                name: package.name.clone(),
+                version: package.version.clone(),
+            })
+            .collect();
+    }
+
+    /// Load the status from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> ManifestResult<Self> {
+        let contents = fs::read(path)?;
+        Ok(serde_json::from_slice(&contents)?)
+    }
+
+    /// Save the status to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ManifestResult<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)?;
+
+        Ok(())
+    }
+}
+
+/// The outcome of a build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// The build succeeded.
+    Success,
+    /// The build failed, see [`BuildStatus::error`].
+    Failure,
+}
+
+/// How long a build took, in milliseconds. Phases sum over the project and
+/// its dependencies.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Durations {
+    /// The whole build.
+    pub total_ms: u64,
+    /// Compiling sources to intermediate representation.
+    pub compile_ms: u64,
+    /// Emitting machine code.
+    pub emit_ms: u64,
+    /// Linking executables.
+    pub link_ms: u64,
+}
+
+/// An artifact of a build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusArtifact {
+    /// The kind of the artifact.
+    pub kind: OutputKind,
+    /// The path of the artifact.
+    pub path: String,
+    /// The SHA-256 hash of the artifact contents.
+    pub hash: String,
+}
+
+/// An installed package used by a build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusTool {
+    /// The package kind, e.g. "toolchains" or "targets".
+    pub kind: String,
+    /// The domain the package was installed for.
+    pub domain: String,
+    /// The category of the package, e.g. "frontend".
+    pub category: String,
+    /// The name of the package.
+    pub name: String,
+    /// The installed version.
+    pub version: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::Output;
+
+    #[test]
+    fn test_build_status_roundtrip() {
+        let mut outputs = OutputManifest::new("x86_64-unknown-linux-gnu");
+        outputs.push(Output::new(
+            OutputKind::Executable,
+            PathBuf::from("target/main"),
+            PathBuf::from("target/main.o"),
+            "abc123".to_string(),
+        ));
+
+        let mut status = BuildStatus::new("x86_64-unknown-linux-gnu");
+        status.status = Status::Success;
+        status.durations.total_ms = 1200;
+        status.artifacts(&outputs);
+
+        let json = serde_json::to_string(&status).unwrap();
+        assert!(json.contains(r#""status":"success""#));
+        assert!(!json.contains("error"));
+
+        let loaded: BuildStatus = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.version, BUILD_STATUS_VERSION);
+        assert_eq!(loaded.durations.total_ms, 1200);
+        assert_eq!(loaded.artifacts[0].kind, OutputKind::Executable);
+        assert_eq!(loaded.artifacts[0].hash, "abc123");
+    }
+}
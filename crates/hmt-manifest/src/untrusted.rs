@@ -0,0 +1,225 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hardened parsing of manifests received from the network.
+//!
+//! Input is checked against [`Limits`] by a single linear scan before it
+//! reaches the TOML parser, so oversized documents, deeply nested arrays and
+//! inline tables, and huge string literals are rejected without allocating
+//! for them. Dotted keys are bounded by the TOML parser's own recursion limit.
+
+use serde::Deserialize;
+
+use crate::{ManifestError, ManifestResult};
+
+/// Bounds on manifest input from untrusted sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum size of the document in bytes.
+    pub max_size: usize,
+    /// The maximum nesting of arrays and inline tables.
+    pub max_depth: usize,
+    /// The maximum length of a single string literal in bytes.
+    pub max_string: usize,
+}
+
+impl Limits {
+    /// Limits well above any manifest a registry publishes.
+    pub const DEFAULT: Limits =
+        Limits { max_size: 16 * 1024 * 1024, max_depth: 32, max_string: 64 * 1024 };
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits::DEFAULT
+    }
+}
+
+/// Parses a TOML manifest from untrusted bytes with the default limits.
+pub fn from_slice<'de, T: Deserialize<'de>>(v: &'de [u8]) -> ManifestResult<T> {
+    from_slice_with(v, &Limits::DEFAULT)
+}
+
+/// Parses a TOML manifest from untrusted bytes, rejecting input beyond
+/// `limits` before deserializing it.
+pub fn from_slice_with<'de, T: Deserialize<'de>>(
+    v: &'de [u8],
+    limits: &Limits,
+) -> ManifestResult<T> {
+    if v.len() > limits.max_size {
+        return Err(exceeded(format!("{} bytes, at most {} allowed", v.len(), limits.max_size)));
+    }
+
+    let s = std::str::from_utf8(v)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    check(s, limits)?;
+    toml::from_str(s).map_err(ManifestError::from)
+}
+
+/// The lexical context of the scanner.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Code,
+    Comment,
+    Basic,
+    Literal,
+    MultiBasic,
+    MultiLiteral,
+}
+
+/// Scans TOML text for nesting and string literals beyond `limits`.
+///
+/// The scan only tracks strings and comments, so brackets inside them are
+/// not counted. Malformed input passes when it is within limits, the TOML
+/// parser reports it afterwards.
+pub fn check(s: &str, limits: &Limits) -> ManifestResult<()> {
+    let bytes = s.as_bytes();
+    let mut state = State::Code;
+    let mut depth = 0usize;
+    let mut start = 0usize;
+    let mut i = 0usize;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        match state {
+            State::Code => match byte {
+                b'#' => state = State::Comment,
+                b'[' | b'{' => {
+                    depth += 1;
+                    if depth > limits.max_depth {
+                        return Err(exceeded(format!(
+                            "nesting deeper than {} at byte {i}",
+                            limits.max_depth
+                        )));
+                    }
+                }
+                b']' | b'}' => depth = depth.saturating_sub(1),
+                b'"' | b'\'' => {
+                    let multi =
+                        bytes[i..].starts_with(if byte == b'"' { b"\"\"\"" } else { b"'''" });
+                    state = match (byte, multi) {
+                        (b'"', false) => State::Basic,
+                        (b'"', true) => State::MultiBasic,
+                        (_, false) => State::Literal,
+                        (_, true) => State::MultiLiteral,
+                    };
+                    if multi {
+                        i += 2;
+                    }
+                    start = i + 1;
+                }
+                _ => {}
+            },
+            State::Comment => {
+                if byte == b'\n' {
+                    state = State::Code;
+                }
+            }
+            State::Basic | State::Literal | State::MultiBasic | State::MultiLiteral => {
+                let quote =
+                    if matches!(state, State::Basic | State::MultiBasic) { b'"' } else { b'\'' };
+                let multi = matches!(state, State::MultiBasic | State::MultiLiteral);
+
+                if byte == b'\\' && quote == b'"' {
+                    // Skip the escaped character, it never ends the string
+                    i += 1;
+                } else if byte == quote && (!multi || bytes[i..].starts_with(&[quote; 3])) {
+                    if multi {
+                        // Up to two quotes may precede the closing delimiter
+                        i += 2;
+                        for _ in 0..2 {
+                            if bytes.get(i + 1) != Some(&quote) {
+                                break;
+                            }
+                            i += 1;
+                        }
+                    }
+                    state = State::Code;
+                } else if byte == b'\n' && !multi {
+                    // Unterminated, the TOML parser reports it
+                    state = State::Code;
+                }
+
+                if state != State::Code && i - start >= limits.max_string {
+                    return Err(exceeded(format!(
+                        "string of more than {} bytes",
+                        limits.max_string
+                    )));
+                }
+            }
+        }
+        i += 1;
+    }
+
+    Ok(())
+}
+
+fn exceeded(reason: String) -> ManifestError {
+    ManifestError::LimitExceeded(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IndexManifest;
+
+    const LIMITS: Limits = Limits { max_size: 1024, max_depth: 4, max_string: 16 };
+
+    #[test]
+    fn test_accepts_manifest() {
+        let manifest: IndexManifest = from_slice(
+            br#"
+            # a comment with [[[[[ brackets
+            [solidity]
+            detector = "https://example.com/[[[[[.toml"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifest.get("solidity", "detector").map(String::as_str),
+            Some("https://example.com/[[[[[.toml")
+        );
+    }
+
+    #[test]
+    fn test_rejects_size() {
+        let err = from_slice_with::<IndexManifest>(&[b' '; 2048], &LIMITS).unwrap_err();
+        assert!(matches!(err, ManifestError::LimitExceeded(_)));
+    }
+
+    #[test]
+    fn test_rejects_depth() {
+        assert!(check("a = [[[[1]]]]", &LIMITS).is_ok());
+        assert!(check("a = [[[[[1]]]]]", &LIMITS).is_err());
+        assert!(check("a = {b = {c = {d = {e = {}}}}}", &LIMITS).is_err());
+        assert!(check("a = '[[[[[[[['", &LIMITS).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_long_strings() {
+        assert!(check(r#"a = "0123456789abcdef""#, &LIMITS).is_ok());
+        assert!(check(r#"a = "0123456789abcdefg""#, &LIMITS).is_err());
+        assert!(check("a = '0123456789abcdefg'", &LIMITS).is_err());
+        assert!(check("a = \"\"\"\n0123456789abcdefg\"\"\"", &LIMITS).is_err());
+        // Unterminated strings are bounded too
+        assert!(check("a = '''0123456789abcdefg", &LIMITS).is_err());
+    }
+
+    #[test]
+    fn test_escapes_and_closing_quotes() {
+        assert!(check(r#"a = "\"[[[[[[\"""#, &LIMITS).is_ok());
+        assert!(check("a = '''x''''' \nb = [[[[1]]]]", &LIMITS).is_ok());
+        assert!(check("a = \"\"\"x\"\"\"\"\" \nb = [[[[[1]]]]]", &LIMITS).is_err());
+    }
+}
@@ -17,9 +17,29 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, arg_required_else_help = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub package: Option<Arguments>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Report files duplicated across package directories or archives
+    DedupReport {
+        /// The package directories or `.tar.gz` archives to compare
+        #[arg(required = true, value_name = "PACKAGE")]
+        packages: Vec<PathBuf>,
+    },
+}
+
+#[derive(Debug, Args)]
 pub struct Arguments {
     /// The profile to build with (e.g., release)
     #[arg(long = "profile")]
@@ -32,6 +52,10 @@ pub struct Arguments {
     /// The version of the package (e.g., v0.1.1)
     #[arg(long = "version")]
     version: String,
+
+    /// Require every executable to be statically linked (e.g., for musl targets)
+    #[arg(long = "static")]
+    pub static_link: bool,
 }
 
 impl Arguments {
@@ -95,14 +119,19 @@ mod tests {
             target: "x86_64-unknown-linux-gnu".to_string(),
             version: "".to_string(),
             profile: "".to_string(),
+            static_link: false,
         };
         assert_eq!(args.target(), "x86_64-unknown-linux-gnu");
     }
 
     #[test]
     fn test_target_without_value() {
-        let args =
-            Arguments { target: "".to_string(), version: "".to_string(), profile: "".to_string() };
+        let args = Arguments {
+            target: "".to_string(),
+            version: "".to_string(),
+            profile: "".to_string(),
+            static_link: false,
+        };
         assert_eq!(args.target(), target_triple::TARGET.to_string());
     }
 
@@ -112,14 +141,19 @@ mod tests {
             target: "".to_string(),
             version: "v1.0.0".to_string(),
             profile: "".to_string(),
+            static_link: false,
         };
         assert_eq!(args.version(), "v1.0.0");
     }
 
     #[test]
     fn test_version_without_value() {
-        let args =
-            Arguments { target: "".to_string(), version: "".to_string(), profile: "".to_string() };
+        let args = Arguments {
+            target: "".to_string(),
+            version: "".to_string(),
+            profile: "".to_string(),
+            static_link: false,
+        };
         assert_eq!(args.version(), format!("v{}", env!("CARGO_PKG_VERSION")));
     }
 
@@ -129,14 +163,19 @@ mod tests {
             target: "".to_string(),
             version: "".to_string(),
             profile: "release".to_string(),
+            static_link: false,
         };
         assert_eq!(args.profile(), "release");
     }
 
     #[test]
     fn test_profile_without_value() {
-        let args =
-            Arguments { target: "".to_string(), version: "".to_string(), profile: "".to_string() };
+        let args = Arguments {
+            target: "".to_string(),
+            version: "".to_string(),
+            profile: "".to_string(),
+            static_link: false,
+        };
         assert_eq!(args.profile(), "debug");
     }
 
@@ -146,6 +185,7 @@ mod tests {
             target: "x86_64-unknown-linux-gnu".to_string(),
             version: "".to_string(),
             profile: "release".to_string(),
+            static_link: false,
         };
         assert_eq!(
             args.target_dir(),
@@ -159,6 +199,7 @@ mod tests {
             target: "".to_string(),
             version: "".to_string(),
             profile: "debug".to_string(),
+            static_link: false,
         };
         assert_eq!(args.target_dir(), Path::new("target").join("debug"));
     }
@@ -0,0 +1,181 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of files duplicated across packages.
+//!
+//! Packages that bundle the same large runtime files make every toolchain
+//! install download and store them again. The report lists every file
+//! content, by hash, found in more than one package, so that it can move
+//! into a shared package of the `runtime` category, which the packages
+//! then list in `runtimes` instead.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use walkdir::WalkDir;
+
+use hmt_utils::{archive, checksum, temp::TempDir};
+
+/// A file content found in more than one package.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Duplicate {
+    /// The SHA-256 hash of the content.
+    pub hash: String,
+    /// The size of the content in bytes.
+    pub size: u64,
+    /// The package and relative path of every copy.
+    pub files: Vec<(String, String)>,
+}
+
+impl Duplicate {
+    /// Returns the bytes a shared runtime would save, keeping one copy.
+    pub fn wasted(&self) -> u64 {
+        let packages: BTreeSet<&str> = self.files.iter().map(|(p, _)| p.as_str()).collect();
+        self.size * (packages.len() as u64 - 1)
+    }
+}
+
+/// Finds the file contents shared by several packages, given as package
+/// directories or `.tar.gz` archives, most wasted bytes first. Empty files
+/// are ignored.
+pub async fn find(packages: &[PathBuf]) -> Result<Vec<Duplicate>> {
+    let mut files = Vec::new();
+    let mut unpacked = Vec::new();
+    for package in packages {
+        let name = package.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let root = if package.is_dir() {
+            package.clone()
+        } else {
+            let file = File::open(package).context(format!("Failed to read {:?}", package))?;
+            let dir = TempDir::new_in(&env::temp_dir())?;
+            archive::unpack(file, dir.path()).context(format!("Failed to unpack {:?}", package))?;
+            let root = dir.path().to_path_buf();
+            unpacked.push(dir);
+            root
+        };
+
+        for entry in WalkDir::new(&root).into_iter().filter_map(Result::ok) {
+            let size = entry.metadata()?.len();
+            if !entry.file_type().is_file() || size == 0 {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(&root)?.to_string_lossy().to_string();
+            files.push((name.clone(), relative, entry.into_path(), size));
+        }
+    }
+
+    let paths: Vec<&Path> = files.iter().map(|(_, _, path, _)| path.as_path()).collect();
+    let mut by_hash: BTreeMap<String, Duplicate> = BTreeMap::new();
+    for ((package, relative, path, size), hash) in
+        files.iter().zip(checksum::digest_files(&paths).await)
+    {
+        let hash = hash.context(format!("Failed to hash {:?}", path))?;
+        by_hash
+            .entry(hash.clone())
+            .or_insert_with(|| Duplicate { hash, size: *size, files: Vec::new() })
+            .files
+            .push((package.clone(), relative.clone()));
+    }
+
+    let mut duplicates: Vec<Duplicate> =
+        by_hash.into_values().filter(|duplicate| duplicate.wasted() > 0).collect();
+    duplicates.sort_by_key(|duplicate| std::cmp::Reverse(duplicate.wasted()));
+    Ok(duplicates)
+}
+
+/// Renders the duplicates as a human-readable report.
+pub fn report(duplicates: &[Duplicate]) -> String {
+    if duplicates.is_empty() {
+        return "No files are duplicated across packages.\n".to_string();
+    }
+
+    let mut out = String::new();
+    for duplicate in duplicates {
+        out.push_str(&format!(
+            "{} ({} bytes, {} bytes duplicated):\n",
+            duplicate.hash,
+            duplicate.size,
+            duplicate.wasted()
+        ));
+        for (package, path) in &duplicate.files {
+            out.push_str(&format!("  {package}: {path}\n"));
+        }
+    }
+
+    let total: u64 = duplicates.iter().map(Duplicate::wasted).sum();
+    out.push_str(&format!(
+        "\n{total} bytes are duplicated across packages. Consider moving these files into a \
+         package of the `runtime` category and listing it in the `runtimes` of each package.\n"
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn write(root: &Path, path: &str, data: &[u8]) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        write(&a, "bin/a", b"frontend a");
+        write(&a, "lib/runtime.so", &[7; 64]);
+        write(&a, "lib/copy.so", &[7; 64]);
+        write(&a, "empty", b"");
+        write(&b, "bin/b", b"frontend b");
+        write(&b, "runtime.so", &[7; 64]);
+        write(&b, "small", b"x");
+        write(&a, "small", b"x");
+        write(&b, "empty", b"");
+
+        // Archives are compared like directories
+        let archived = dir.path().join("b.tar.gz");
+        archive::archive_dir(&b, &archived).await.unwrap();
+        assert_eq!(find(&[a.clone(), archived]).await.unwrap().len(), 2);
+
+        let duplicates = find(&[a, b]).await.unwrap();
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(duplicates[0].size, 64);
+        assert_eq!(duplicates[0].wasted(), 64);
+        assert_eq!(duplicates[0].files.len(), 3);
+        assert_eq!(duplicates[1].wasted(), 1);
+
+        let report = report(&duplicates);
+        assert!(report.contains("  b: runtime.so\n"));
+        assert!(report.contains("65 bytes are duplicated"));
+    }
+
+    #[tokio::test]
+    async fn test_find_within_one_package() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a/one", b"same");
+        write(dir.path(), "a/two", b"same");
+
+        assert!(find(&[dir.path().join("a")]).await.unwrap().is_empty());
+        assert_eq!(report(&[]), "No files are duplicated across packages.\n");
+    }
+}
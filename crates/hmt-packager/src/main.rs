@@ -13,19 +13,41 @@
 // limitations under the License.
 
 mod args;
+mod dedup;
 mod package;
 mod utils;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use std::fs;
 use tracing::{error, info};
 
-use self::{args::Arguments, package::package};
+use self::{
+    args::{Cli, Command},
+    package::package,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Arguments::parse();
+    let cli = Cli::parse();
+
+    let args = match cli.command {
+        Some(Command::DedupReport { packages }) => {
+            match dedup::find(&packages).await {
+                Ok(duplicates) => print!("{}", dedup::report(&duplicates)),
+                Err(e) => {
+                    error!("Failed to find duplicate files: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return Ok(());
+        }
+        None => cli.package.unwrap_or_else(|| {
+            Cli::command()
+                .error(ErrorKind::MissingRequiredArgument, "Missing packaging flags")
+                .exit()
+        }),
+    };
 
     // prepare the bin directory
     let input_path = args.target_dir();
@@ -43,6 +65,13 @@ async fn main() -> Result<()> {
     let target = args.target();
     let version = args.version();
 
+    if args.static_link {
+        if let Err(e) = package::verify_static(&input_path) {
+            error!("Refusing to package a static distribution: {}", e);
+            std::process::exit(1);
+        }
+    }
+
     info!("Creating archives and checksums for executables in {:?}:\n", input_path);
 
     // Call the package function to handle processing
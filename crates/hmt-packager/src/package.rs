@@ -12,38 +12,95 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::{Path, PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use tracing::info;
 use walkdir::WalkDir;
 
 use hmt_utils::{
     archive::archive_file,
     checksum::{self, CHECKSUM_FILE_SUFFIX},
+    temp::TempFile,
 };
 
-use crate::utils::is_executable;
+use crate::utils::{is_executable, is_static};
+
+/// An archive written to a temporary file, persisted once its checksum is.
+struct Archive {
+    file: TempFile,
+    path: PathBuf,
+    checksum_path: PathBuf,
+}
 
 /// Package all executables in the output directory
+///
+/// The archives are hashed together once all are written, in parallel.
 pub async fn package(
     input_path: &Path,
     output_path: &Path,
     target: &str,
     version: &str,
 ) -> Result<()> {
-    for entry in WalkDir::new(input_path).max_depth(1).into_iter().filter_map(Result::ok) {
-        let path = entry.into_path();
-        if path.is_file() && is_executable(&path) {
-            process(path, output_path, target, version).await?;
+    let mut archives = Vec::new();
+    for path in executables(input_path) {
+        archives.push(process(path, output_path, target, version).await?);
+    }
+
+    let paths: Vec<PathBuf> = archives.iter().map(|a| a.file.path().to_path_buf()).collect();
+    let hashes = checksum::digest_files(&paths).await;
+
+    for (archive, hash) in archives.into_iter().zip(hashes) {
+        let hash = hash.context(format!("Failed to generate checksum for {:?}", archive.path))?;
+
+        let checksum = TempFile::new_in(output_path)?;
+        fs::write(checksum.path(), hash)
+            .context(format!("Failed to write checksum for {:?}", archive.path))?;
+
+        archive.file.persist(&archive.path)?;
+        checksum.persist(&archive.checksum_path)?;
+    }
+
+    Ok(())
+}
+
+/// Check that all executables in the output directory are statically
+/// linked, so they run on hosts without a matching C library
+pub fn verify_static(input_path: &Path) -> Result<()> {
+    let mut dynamic = Vec::new();
+    for path in executables(input_path) {
+        if !is_static(&path)? {
+            dynamic.push(path.display().to_string());
         }
     }
 
+    if !dynamic.is_empty() {
+        bail!("Executables are dynamically linked:\n  {}", dynamic.join("\n  "));
+    }
     Ok(())
 }
 
-/// Process a single executable by creating a tar.gz archive and checksum
-async fn process(path: PathBuf, output_path: &Path, target: &str, version: &str) -> Result<()> {
+/// List the executables directly inside a directory
+fn executables(input_path: &Path) -> Vec<PathBuf> {
+    WalkDir::new(input_path)
+        .max_depth(1)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.is_file() && is_executable(path))
+        .collect()
+}
+
+/// Process a single executable by creating a tar.gz archive
+async fn process(
+    path: PathBuf,
+    output_path: &Path,
+    target: &str,
+    version: &str,
+) -> Result<Archive> {
     let bin_name = path.file_stem().unwrap().to_string_lossy().to_string();
     let archive_name = format!("{bin_name}-{version}-{target}.tar.gz");
     let archive_path = output_path.join(&archive_name);
@@ -51,17 +108,14 @@ async fn process(path: PathBuf, output_path: &Path, target: &str, version: &str)
 
     info!("{}: \n  {}\n  {}\n", bin_name, archive_path.display(), checksum_path.display());
 
-    // Create a tar.gz archive for the executable
-    archive_file(&path, &archive_path)
+    // Create a tar.gz archive for the executable, so that an interrupted
+    // run never leaves a truncated archive in the output directory
+    let archive = TempFile::new_in(output_path)?;
+    archive_file(&path, archive.path())
         .await
         .context(format!("Failed to create archive for {path:?}"))?;
 
-    // Generate checksum for the archive
-    checksum::generate(&archive_path, &checksum_path)
-        .await
-        .context(format!("Failed to generate checksum for {archive_path:?}"))?;
-
-    Ok(())
+    Ok(Archive { file: archive, path: archive_path, checksum_path })
 }
 
 #[cfg(test)]
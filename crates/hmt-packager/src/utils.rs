@@ -12,7 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::Path;
+use std::{fs, path::Path};
+
+use anyhow::{bail, Result};
+
+/// The program header type of the interpreter of a dynamic executable.
+const PT_INTERP: u32 = 3;
 
 pub fn is_executable(path: &Path) -> bool {
     #[cfg(unix)]
@@ -27,6 +32,49 @@ pub fn is_executable(path: &Path) -> bool {
     }
 }
 
+/// Whether an ELF executable is statically linked, i.e. runs without a
+/// dynamic loader. Static PIE executables count as static.
+pub fn is_static(path: &Path) -> Result<bool> {
+    let data = fs::read(path)?;
+    match interpreter(&data) {
+        Some(interp) => Ok(!interp),
+        None => bail!("{} is not an ELF executable", path.display()),
+    }
+}
+
+/// Whether ELF data has an interpreter program header, or `None` if it is
+/// not ELF.
+fn interpreter(data: &[u8]) -> Option<bool> {
+    if data.get(..4)? != b"\x7fELF" {
+        return None;
+    }
+    let wide = *data.get(4)? == 2;
+    let little = *data.get(5)? == 1;
+
+    let read = |offset: usize, len: usize| -> Option<u64> {
+        let bytes = data.get(offset..offset + len)?;
+        let mut value = 0u64;
+        for i in 0..len {
+            let byte = if little { bytes[len - 1 - i] } else { bytes[i] };
+            value = value << 8 | byte as u64;
+        }
+        Some(value)
+    };
+
+    let (phoff, phentsize, phnum) = if wide {
+        (read(0x20, 8)?, read(0x36, 2)?, read(0x38, 2)?)
+    } else {
+        (read(0x1c, 4)?, read(0x2a, 2)?, read(0x2c, 2)?)
+    };
+    for i in 0..phnum {
+        let offset = usize::try_from(phoff + i * phentsize).ok()?;
+        if read(offset, 4)? == PT_INTERP as u64 {
+            return Some(true);
+        }
+    }
+    Some(false)
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(unix)]
@@ -96,4 +144,42 @@ mod tests {
         let nonexistent_path = PathBuf::from("nonexistent_file");
         assert!(!is_executable(&nonexistent_path));
     }
+
+    /// Builds a little-endian ELF64 header followed by program headers of
+    /// the given types.
+    fn elf(types: &[u32]) -> Vec<u8> {
+        let mut data = vec![0u8; 0x40];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[0x20..0x28].copy_from_slice(&0x40u64.to_le_bytes());
+        data[0x36..0x38].copy_from_slice(&0x38u16.to_le_bytes());
+        data[0x38..0x3a].copy_from_slice(&(types.len() as u16).to_le_bytes());
+        for kind in types {
+            let mut header = vec![0u8; 0x38];
+            header[..4].copy_from_slice(&kind.to_le_bytes());
+            data.extend(header);
+        }
+        data
+    }
+
+    #[test]
+    fn test_interpreter() {
+        assert_eq!(interpreter(&elf(&[6, PT_INTERP, 1])), Some(true));
+        assert_eq!(interpreter(&elf(&[1, 2])), Some(false));
+        assert_eq!(interpreter(b"#!/bin/sh"), None);
+
+        let mut truncated = elf(&[PT_INTERP]);
+        truncated.truncate(0x42);
+        assert_eq!(interpreter(&truncated), None);
+    }
+
+    #[test]
+    fn test_is_static_non_elf() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("script");
+        fs::write(&file_path, "#!/bin/sh").unwrap();
+
+        assert!(is_static(&file_path).is_err());
+    }
 }
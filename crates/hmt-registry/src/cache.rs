@@ -0,0 +1,250 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hmt_utils::{checksum::digest, temp::TempFile};
+use serde::{Deserialize, Serialize};
+
+/// The default time registry metadata is considered fresh.
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The default upper bound of the total cache size, in bytes.
+const DEFAULT_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+/// A cached metadata file, as found in the cache.
+pub enum Lookup {
+    /// The entry is younger than the TTL.
+    Fresh(Vec<u8>),
+    /// The entry has expired, but may be used when the registry is unreachable.
+    Stale(Vec<u8>),
+}
+
+/// Bookkeeping for a single cached response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// The URL the data was fetched from.
+    pub url: String,
+    /// When the data was fetched, in seconds since the Unix epoch.
+    pub fetched: u64,
+    /// When the data was last read, in seconds since the Unix epoch.
+    pub accessed: u64,
+    /// The size of the data, in bytes.
+    pub size: u64,
+}
+
+/// A size-bounded on-disk cache of registry metadata.
+///
+/// Every entry is stored as `<key>` with its bookkeeping in `<key>.json`,
+/// where the key is the SHA-256 of the URL. Entries are evicted least
+/// recently used first once the total size exceeds the bound.
+#[derive(Debug, Clone)]
+pub struct MetadataCache {
+    dir: PathBuf,
+    temp_dir: PathBuf,
+    ttl: Duration,
+    max_size: u64,
+}
+
+impl MetadataCache {
+    /// Creates a cache stored in the given directory.
+    pub fn new(dir: PathBuf) -> Self {
+        Self { temp_dir: dir.clone(), dir, ttl: DEFAULT_TTL, max_size: DEFAULT_MAX_SIZE }
+    }
+
+    /// Sets where entries are written before being moved into the cache,
+    /// which must be on the same filesystem. Defaults to the cache itself.
+    pub fn temp_dir(mut self, temp_dir: PathBuf) -> Self {
+        self.temp_dir = temp_dir;
+        self
+    }
+
+    /// Sets how long entries are considered fresh.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the upper bound of the total cache size, in bytes.
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Returns the directory the cache is stored in.
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /// Returns the configured upper bound of the total cache size.
+    pub fn limit(&self) -> u64 {
+        self.max_size
+    }
+
+    /// Looks up the data cached for a URL, marking it as recently used.
+    pub fn get(&self, url: &str) -> Option<Lookup> {
+        let (data_path, meta_path) = self.paths(url);
+        let mut entry = read_entry(&meta_path)?;
+        let data = fs::read(data_path).ok()?;
+
+        entry.accessed = now();
+        let _ = write_entry(&meta_path, &entry);
+
+        Some(if self.is_fresh(&entry) { Lookup::Fresh(data) } else { Lookup::Stale(data) })
+    }
+
+    /// Stores the data fetched from a URL, evicting old entries if needed.
+    pub fn put(&self, url: &str, data: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let (data_path, meta_path) = self.paths(url);
+        let now = now();
+        let entry = CacheEntry {
+            url: url.to_string(),
+            fetched: now,
+            accessed: now,
+            size: data.len() as u64,
+        };
+
+        // Write to a temporary file first, so an interrupted write never
+        // leaves truncated data in the cache
+        let file = TempFile::new_in(&self.temp_dir)?;
+        fs::write(file.path(), data)?;
+        file.persist(&data_path)?;
+        write_entry(&meta_path, &entry)?;
+
+        self.evict()
+    }
+
+    /// Returns all entries in the cache.
+    pub fn entries(&self) -> Vec<CacheEntry> {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        dir.filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|p| read_entry(&p))
+            .collect()
+    }
+
+    /// Whether an entry is younger than the TTL.
+    pub fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        now().saturating_sub(entry.fetched) < self.ttl.as_secs()
+    }
+
+    /// Returns the URLs of entries in the last fifth of their lifetime or
+    /// past it, least recently fetched first.
+    pub fn near_expiry(&self) -> Vec<String> {
+        let threshold = self.ttl.as_secs() * 4 / 5;
+        let mut entries: Vec<_> = self
+            .entries()
+            .into_iter()
+            .filter(|e| now().saturating_sub(e.fetched) >= threshold)
+            .collect();
+        entries.sort_by_key(|e| e.fetched);
+        entries.into_iter().map(|e| e.url).collect()
+    }
+
+    /// Removes least recently used entries until the cache fits its bound.
+    fn evict(&self) -> io::Result<()> {
+        let mut entries = self.entries();
+        let mut total: u64 = entries.iter().map(|e| e.size).sum();
+        entries.sort_by_key(|e| e.accessed);
+
+        for entry in entries {
+            if total <= self.max_size {
+                break;
+            }
+            let (data_path, meta_path) = self.paths(&entry.url);
+            fs::remove_file(meta_path)?;
+            let _ = fs::remove_file(data_path);
+            total -= entry.size;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the data and bookkeeping paths of a URL.
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = digest(url.as_bytes());
+        (self.dir.join(&key), self.dir.join(key).with_extension("json"))
+    }
+}
+
+fn read_entry(path: &Path) -> Option<CacheEntry> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+fn write_entry(path: &Path, entry: &CacheEntry) -> io::Result<()> {
+    fs::write(path, serde_json::to_vec(entry)?)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_and_put() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = MetadataCache::new(dir.path().to_path_buf());
+        assert!(cache.get("https://example.com/index.toml").is_none());
+
+        cache.put("https://example.com/index.toml", b"data").unwrap();
+        assert!(matches!(
+            cache.get("https://example.com/index.toml"),
+            Some(Lookup::Fresh(data)) if data == b"data"
+        ));
+        assert_eq!(cache.entries().len(), 1);
+    }
+
+    #[test]
+    fn test_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = MetadataCache::new(dir.path().to_path_buf()).ttl(Duration::ZERO);
+
+        cache.put("https://example.com/index.toml", b"data").unwrap();
+        assert!(matches!(cache.get("https://example.com/index.toml"), Some(Lookup::Stale(_))));
+        assert_eq!(cache.near_expiry(), ["https://example.com/index.toml"]);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = MetadataCache::new(dir.path().to_path_buf()).max_size(8);
+
+        cache.put("a", b"1234").unwrap();
+        cache.put("b", b"1234").unwrap();
+
+        // Make "b" the least recently used entry
+        let (_, meta) = cache.paths("b");
+        let mut entry = read_entry(&meta).unwrap();
+        entry.accessed = 0;
+        write_entry(&meta, &entry).unwrap();
+
+        cache.put("c", b"1234").unwrap();
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    }
+}
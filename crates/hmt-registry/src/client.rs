@@ -12,22 +12,49 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use hmt_fetcher::{FetchContext, Fetcher};
+use std::sync::Arc;
+
+use hmt_fetcher::{FetchContext, FetchStream, Fetcher};
 use hmt_manifest::IndexManifest;
-use hmt_utils::bytes::FromSlice;
+use hmt_utils::{
+    bytes::FromSlice,
+    event::{warning, Reporter},
+};
+use tracing::debug;
 
-use crate::error::{RegistryError, Result};
+use crate::{
+    cache::{Lookup, MetadataCache},
+    error::{RegistryError, Result},
+};
 
 /// A client for interacting with Hummanta Registry.
 pub struct RegistryClient {
     fetcher: Fetcher,
     base_url: String,
+    cache: Option<MetadataCache>,
 }
 
 impl RegistryClient {
     /// Creates a new instance.
     pub fn new(url: &str) -> Self {
-        Self { fetcher: Fetcher::default(), base_url: url.trim_end_matches('/').to_string() }
+        Self::with_fetcher(url, Fetcher::default())
+    }
+
+    /// Creates a new instance using the given fetcher, e.g. one configured
+    /// with a custom user agent or extra request headers.
+    pub fn with_fetcher(url: &str, fetcher: Fetcher) -> Self {
+        Self { fetcher, base_url: url.trim_end_matches('/').to_string(), cache: None }
+    }
+
+    /// Caches metadata fetched through [`RegistryClient::fetch_metadata`].
+    pub fn with_cache(mut self, cache: MetadataCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Returns the reporter receiving the events of registry operations.
+    pub fn reporter(&self) -> &Arc<dyn Reporter> {
+        self.fetcher.reporter()
     }
 
     #[inline]
@@ -36,10 +63,87 @@ impl RegistryClient {
         self.fetcher.fetch(&self.rewrite_context(context)).await.map_err(RegistryError::from)
     }
 
+    /// Opens data from the registry as a stream, verified once read to its
+    /// end.
+    pub async fn open(&self, context: &FetchContext) -> Result<FetchStream> {
+        self.fetcher.open(&self.rewrite_context(context)).await.map_err(RegistryError::from)
+    }
+
+    /// Returns the size of the data in bytes without fetching it, if the
+    /// registry or a mirror reports one.
+    pub async fn size(&self, context: &FetchContext) -> Option<u64> {
+        self.fetcher.size(&self.rewrite_context(context)).await
+    }
+
+    /// Fetches registry metadata, serving fresh copies from the cache.
+    ///
+    /// Expired copies are only used when the registry cannot be reached.
+    pub async fn fetch_metadata(&self, context: &FetchContext) -> Result<Vec<u8>> {
+        let context = self.rewrite_context(context).metadata();
+        let Some(cache) = &self.cache else {
+            return self.fetcher.fetch(&context).await.map_err(RegistryError::from);
+        };
+
+        let stale = match cache.get(&context.url) {
+            Some(Lookup::Fresh(data)) => return Ok(data),
+            Some(Lookup::Stale(data)) => Some(data),
+            None => None,
+        };
+
+        match self.fetcher.fetch(&context).await {
+            Ok(data) => {
+                if let Err(e) = cache.put(&context.url, &data) {
+                    self.reporter().warn(
+                        warning::CACHE_WRITE,
+                        format!("Failed to cache {}: {e}", context.url),
+                    );
+                }
+                Ok(data)
+            }
+            Err(e) => match stale {
+                Some(data) => {
+                    self.reporter().warn(
+                        warning::STALE_METADATA,
+                        format!("Using expired metadata for {}: {e}", context.url),
+                    );
+                    Ok(data)
+                }
+                None => Err(e.into()),
+            },
+        }
+    }
+
+    /// Returns cached metadata, fresh or expired, without accessing the
+    /// registry.
+    pub fn cached_metadata(&self, context: &FetchContext) -> Option<Vec<u8>> {
+        let context = self.rewrite_context(context);
+        match self.cache.as_ref()?.get(&context.url)? {
+            Lookup::Fresh(data) | Lookup::Stale(data) => Some(data),
+        }
+    }
+
+    /// Re-fetches cached metadata that is about to expire.
+    ///
+    /// This is best-effort, failures keep the existing entry.
+    pub async fn refresh_metadata(&self) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+
+        for url in cache.near_expiry() {
+            match self.fetcher.fetch(&FetchContext::new(&url).metadata()).await {
+                Ok(data) => {
+                    let _ = cache.put(&url, &data);
+                }
+                Err(e) => debug!("Failed to refresh {url}: {e}"),
+            }
+        }
+    }
+
     /// Fetches and parses the index manifest from the registry.
     pub async fn index(&self) -> Result<IndexManifest> {
         let context = FetchContext::new("index.toml");
-        let bytes = self.fetch(&context).await?;
+        let bytes = self.fetch_metadata(&context).await?;
         let manifest = IndexManifest::from_slice(&bytes)?;
 
         Ok(manifest)
@@ -58,6 +162,29 @@ impl RegistryClient {
             url: absolute_url,
             checksum: context.checksum.clone(),
             checksum_url: context.checksum_url.clone(),
+            metadata: context.metadata,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = MetadataCache::new(dir.path().to_path_buf());
+        cache.put("https://registry.example.com/index.toml", b"index").unwrap();
+
+        let client = RegistryClient::new("https://registry.example.com/").with_cache(cache);
+        let cached = |url: &str| client.cached_metadata(&FetchContext::new(url));
+        assert_eq!(cached("index.toml").as_deref(), Some(&b"index"[..]));
+        assert_eq!(cached("toolchains/solidity.toml"), None);
+        assert_eq!(
+            RegistryClient::new("https://registry.example.com")
+                .cached_metadata(&FetchContext::new("index.toml")),
+            None
+        );
+    }
+}
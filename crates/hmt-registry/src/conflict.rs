@@ -0,0 +1,124 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Files shipped by more than one package of a domain.
+//!
+//! Packages of a domain are unpacked into the same directory, so two
+//! frontends shipping a `fmt` binary would overwrite each other. Each clash
+//! is resolved by the configured [`OnConflict`] strategy, or by asking a
+//! [`ConflictResolver`], and the decision is recorded in the installed
+//! entry so reinstalls and updates repeat it.
+
+use std::path::Path;
+
+use async_trait::async_trait;
+use hmt_manifest::Resolution;
+use serde::{Deserialize, Serialize};
+
+/// A file of a package being installed that another package installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// The domain both packages belong to.
+    pub domain: String,
+    /// The file, relative to the installation path of the domain.
+    pub file: String,
+    /// The package being installed.
+    pub package: String,
+    /// The package that installed the file.
+    pub owner: String,
+}
+
+impl Conflict {
+    /// The name the file is installed under when renamed, e.g. `fmt-foo`
+    /// for the `fmt` file of the `foo` package.
+    pub fn renamed(&self) -> String {
+        let path = Path::new(&self.file);
+        let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+        let name = match path.extension() {
+            Some(ext) => format!("{stem}-{}.{}", self.package, ext.to_string_lossy()),
+            None => format!("{stem}-{}", self.package),
+        };
+
+        match self.file.rsplit_once('/') {
+            Some((dir, _)) => format!("{dir}/{name}"),
+            None => name,
+        }
+    }
+}
+
+/// Decides how clashing files are installed.
+#[async_trait]
+pub trait ConflictResolver: Send + Sync {
+    /// Resolves a clash of a file.
+    async fn resolve(&self, conflict: &Conflict) -> Resolution;
+}
+
+/// How files clashing with files of other packages are installed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnConflict {
+    /// Ask the resolver of the manager, which skips the file unless the
+    /// application provides one.
+    #[default]
+    Ask,
+    /// Leave the file out, keeping the file of the other package.
+    Skip,
+    /// Install the file under a name suffixed with the package name.
+    Rename,
+    /// Replace the file of the other package.
+    Replace,
+}
+
+#[async_trait]
+impl ConflictResolver for OnConflict {
+    async fn resolve(&self, conflict: &Conflict) -> Resolution {
+        match self {
+            OnConflict::Ask | OnConflict::Skip => Resolution::Skip,
+            OnConflict::Rename => Resolution::Rename(conflict.renamed()),
+            OnConflict::Replace => Resolution::Replace,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conflict(file: &str) -> Conflict {
+        Conflict {
+            domain: "solidity".to_string(),
+            file: file.to_string(),
+            package: "foo".to_string(),
+            owner: "bar".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_renamed() {
+        assert_eq!(conflict("fmt").renamed(), "fmt-foo");
+        assert_eq!(conflict("fmt.exe").renamed(), "fmt-foo.exe");
+        assert_eq!(conflict("bin/fmt").renamed(), "bin/fmt-foo");
+    }
+
+    #[tokio::test]
+    async fn test_strategies() {
+        let fmt = conflict("fmt");
+        assert_eq!(OnConflict::Ask.resolve(&fmt).await, Resolution::Skip);
+        assert_eq!(
+            OnConflict::Rename.resolve(&fmt).await,
+            Resolution::Rename("fmt-foo".to_string())
+        );
+        assert_eq!(OnConflict::Replace.resolve(&fmt).await, Resolution::Replace);
+    }
+}
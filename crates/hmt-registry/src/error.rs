@@ -16,8 +16,10 @@ use std::io;
 
 use thiserror::Error;
 
+use hmt_error::{Category, Code, Diagnostic, Help};
 use hmt_fetcher::errors::FetchError;
 use hmt_manifest::ManifestError;
+use hmt_utils::disk::InsufficientSpace;
 
 pub type Result<T> = std::result::Result<T, RegistryError>;
 
@@ -56,9 +58,108 @@ pub enum RegistryError {
     #[error("Failed to unpack archive: {0}")]
     UnpackError(String),
 
+    #[error(transparent)]
+    InsufficientSpace(#[from] InsufficientSpace),
+
     #[error("Failed to remove installation directory for '{0}")]
     RemoveError(String),
 
+    #[error("'{0}' is installed system-wide and cannot be changed")]
+    ReadOnly(String),
+
+    #[cfg(feature = "sqlite")]
+    #[error("database error: {0}")]
+    DatabaseError(#[from] rusqlite::Error),
+
+    #[error("release manifest of {0} {1} changed since it was locked")]
+    ManifestChanged(String, String),
+
+    #[error("policy violation: {0}")]
+    PolicyViolation(String),
+
     #[error("other error: {0}")]
     Other(String),
 }
+
+impl Diagnostic for RegistryError {
+    fn code(&self) -> Code {
+        match self {
+            RegistryError::FetchError(e) => e.code(),
+            RegistryError::ManifestError(e) => e.code(),
+            RegistryError::InsufficientSpace(e) => e.code(),
+            RegistryError::IoError(_) => Code::new("registry.io", Category::Io),
+            RegistryError::TomlError(_) => Code::new("registry.parse", Category::Data),
+            RegistryError::ManifestNotFound(_) => {
+                Code::new("registry.manifest-not-found", Category::NotFound)
+            }
+            RegistryError::InvalidPath(_) => Code::new("registry.invalid-path", Category::Config),
+            RegistryError::UnsupportedProtocol(_) => {
+                Code::new("registry.unsupported-protocol", Category::Config)
+            }
+            RegistryError::DomainNotFound(_) => {
+                Code::new("registry.domain-not-found", Category::NotFound)
+            }
+            RegistryError::PackageNotFound(_) => {
+                Code::new("registry.package-not-found", Category::NotFound)
+            }
+            RegistryError::ReleaseNotFound(..) => {
+                Code::new("registry.release-not-found", Category::NotFound)
+            }
+            RegistryError::UnpackError(_) => Code::new("registry.unpack", Category::Io),
+            RegistryError::RemoveError(_) => Code::new("registry.remove", Category::Io),
+            RegistryError::ReadOnly(_) => Code::new("registry.read-only", Category::Denied),
+            #[cfg(feature = "sqlite")]
+            RegistryError::DatabaseError(_) => Code::new("registry.database", Category::Io),
+            RegistryError::ManifestChanged(..) => {
+                Code::new("registry.manifest-changed", Category::Integrity)
+            }
+            RegistryError::PolicyViolation(_) => {
+                Code::new("registry.policy-violation", Category::Denied)
+            }
+            RegistryError::Other(_) => Code::new("registry.other", Category::Internal),
+        }
+    }
+
+    fn help(&self) -> Option<Help> {
+        let help =
+            match self {
+                RegistryError::FetchError(e) => return e.help(),
+                RegistryError::ManifestError(e) => return e.help(),
+                RegistryError::InsufficientSpace(e) => return e.help(),
+                RegistryError::PackageNotFound(_) | RegistryError::DomainNotFound(_) => {
+                    Help::new("the registry does not serve this package")
+                        .step("check the spelling of the language, domain and package names")
+                        .step("check the registry URL in ~/.hummanta/config.toml or --registry")
+                        .page("package-not-found")
+                }
+                RegistryError::ReleaseNotFound(..) => {
+                    Help::new("the registry no longer serves this version")
+                        .step("update the lockfile by running the command without --locked")
+                        .page("release-not-found")
+                }
+                RegistryError::ManifestChanged(..) => Help::new(
+                    "the registry serves a different release than the one locked",
+                )
+                .step("review the change, then update hummanta.lock by building without --locked")
+                .page("manifest-changed"),
+                RegistryError::UnsupportedProtocol(_) => {
+                    Help::new("registries are served over http, https or from a local directory")
+                        .step("check the registry URL in ~/.hummanta/config.toml or --registry")
+                        .page("registry-protocol")
+                }
+                RegistryError::PolicyViolation(_) => {
+                    Help::new("the install policy in ~/.hummanta/config.toml rejects this package")
+                        .step("review the [policy] table of the configuration")
+                        .step("trust a maintainer by adding their fingerprint to `trusted-keys`")
+                        .page("policy")
+                }
+                RegistryError::ReadOnly(_) => {
+                    Help::new("the system-wide installation is shared by all users")
+                        .step("ask an administrator to change it, or install into your own home")
+                        .page("system-install")
+                }
+                _ => return None,
+            };
+        Some(help)
+    }
+}
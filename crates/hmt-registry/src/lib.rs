@@ -12,10 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod cache;
 pub mod client;
+pub mod conflict;
 pub mod error;
 pub mod manager;
+pub mod policy;
+pub mod storage;
 pub mod traits;
 
 // Re-exports
 pub use client::RegistryClient;
+pub use policy::Policy;
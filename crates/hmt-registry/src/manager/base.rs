@@ -12,31 +12,84 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{marker::PhantomData, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use hmt_fetcher::FetchContext;
+use hmt_fetcher::{errors::FetchError, stream::stream_error, FetchContext};
 use hmt_manifest::{
-    CategoryMap, DomainMap, Entry, IndexManifest, InstalledManifest, ManifestFile, PackageEntry,
-    PackageManifest, ReleaseManifest,
+    Artifact, CategoryMap, DomainMap, Entry, FrozenPackage, IndexManifest, InstalledManifest,
+    LockedTool, PackageEntry, PackageManifest, PackageSummary, Provenance, ReleaseDiff,
+    ReleaseManifest, Resolution, Source, RUNTIME_CATEGORY,
+};
+use hmt_utils::{
+    archive,
+    bytes::FromSlice,
+    checksum, disk,
+    event::{warning, Event, Reporter},
+    path,
+    temp::{TempDir, TempFile},
 };
-use hmt_utils::{archive, bytes::FromSlice};
-use tracing::{error, warn};
+use serde::Serialize;
+use tracing::error;
 
 use crate::{
+    conflict::{Conflict, ConflictResolver, OnConflict},
     error::{RegistryError, Result},
+    policy::Policy,
+    storage::{Storage, TomlStorage, TOML_FILE},
     traits::{PackageKind, PackageManager, Query, RemoteMetadata},
     RegistryClient,
 };
 
+/// A pending change of a package, as planned by [`Manager::updates`].
+#[derive(Debug, Serialize)]
+pub struct Update {
+    /// The category of the package.
+    pub category: String,
+    /// The name of the package.
+    pub name: String,
+    /// What changes between the installed and the latest release.
+    #[serde(flatten)]
+    pub diff: ReleaseDiff,
+    /// The entry installed when the update is applied.
+    #[serde(skip)]
+    pub entry: Entry,
+}
+
+/// A file taken from another package, as the category and name of the
+/// package and the file.
+type Replaced = (String, String, String);
+
 /// A generic manager for handling package operations,
 /// with a registry client, cache, and installation root.
 pub struct Manager<T: PackageKind> {
     /// The registry client used for interacting with the registry.
-    registry: RegistryClient,
+    pub(super) registry: RegistryClient,
     /// The cache of installed manifests.
-    cache: InstalledManifest,
+    pub(super) cache: InstalledManifest,
+    /// The backend the cache is persisted to.
+    pub(super) storage: Box<dyn Storage>,
     /// The root path where packages are installed.
-    install_root: PathBuf,
+    pub(super) install_root: PathBuf,
+    /// The root of the read-only system-wide installation, if any.
+    pub(super) system_root: Option<PathBuf>,
+    /// The packages of the system-wide installation.
+    system: InstalledManifest,
+    /// The system packages overlaid with the cache, which take precedence.
+    /// Lookups go through this view, while changes only touch the cache.
+    pub(super) installed: InstalledManifest,
+    /// The installation policy enforced when adding packages.
+    pub(super) policy: Policy,
+    /// The reporter receiving warnings about skipped packages.
+    pub(super) reporter: Arc<dyn Reporter>,
+    /// Resolves clashing files when the policy asks to.
+    resolver: Arc<dyn ConflictResolver>,
+    /// The cache of downloaded artifacts, keyed by their hash, if any.
+    downloads: Option<PathBuf>,
     /// A marker type used to specify the package kind.
     _marker: PhantomData<T>,
 }
@@ -45,78 +98,882 @@ impl<T: PackageKind> Manager<T> {
     /// Creates a new package manager with the given registry client
     /// and install root, loading or initializing the cache.
     pub fn new(registry: RegistryClient, install_root: PathBuf) -> Self {
-        let path = install_root.join("installed.toml");
-        let cache = match InstalledManifest::load(path) {
-            Ok(manifest) => manifest,
-            Err(_) => InstalledManifest::new(),
+        let storage = TomlStorage::new(install_root.join(TOML_FILE));
+        let cache = storage.load().unwrap_or_default();
+
+        Self {
+            reporter: registry.reporter().clone(),
+            registry,
+            installed: cache.clone(),
+            cache,
+            storage: Box::new(storage),
+            install_root,
+            system_root: None,
+            system: InstalledManifest::new(),
+            policy: Policy::default(),
+            resolver: Arc::new(OnConflict::Ask),
+            downloads: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Replaces the cache storage backend, reloading the cache from it.
+    pub fn with_storage(mut self, storage: Box<dyn Storage>) -> Result<Self> {
+        self.cache = storage.load()?;
+        self.storage = storage;
+        self.installed = self.system.overlay(&self.cache);
+        Ok(self)
+    }
+
+    /// Adds a read-only system-wide installation under `root`, whose
+    /// packages are found unless the install root has the same package.
+    /// Packages are still only installed into and removed from the
+    /// install root.
+    pub fn with_system(mut self, root: PathBuf, storage: Box<dyn Storage>) -> Result<Self> {
+        self.system = storage.load()?;
+        self.system_root = Some(root);
+        self.installed = self.system.overlay(&self.cache);
+        Ok(self)
+    }
+
+    /// Sets the installation policy enforced when adding packages.
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the reporter receiving warnings, by default the reporter of the
+    /// registry client.
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Sets the resolver asked about clashing files under the default
+    /// [`OnConflict::Ask`] policy, e.g. one prompting the user.
+    pub fn with_resolver(mut self, resolver: Arc<dyn ConflictResolver>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Sets the cache of downloaded artifacts, keyed by their hash. Artifacts
+    /// found there are installed without fetching them again.
+    pub fn with_downloads(mut self, dir: PathBuf) -> Self {
+        self.downloads = Some(dir);
+        self
+    }
+
+    /// Fetches, verifies and unpacks the artifact of an entry into the
+    /// domain's installation path, then records it in the cache.
+    ///
+    /// Files another package of the domain installed are resolved by the
+    /// policy before anything is moved into the installation path. The shared
+    /// runtimes the package uses are installed first, unless already present.
+    ///
+    /// The signature and provenance of the artifact are checked by the
+    /// callers, so other crates install through the public entry points.
+    pub(super) async fn install(
+        &mut self,
+        domain: &str,
+        category: &str,
+        name: &str,
+        mut entry: Entry,
+    ) -> Result<()> {
+        self.policy.check_domain(domain)?;
+        self.policy.check_category(category)?;
+
+        let (Some(url), Some(hash)) = (&entry.url, &entry.hash) else {
+            return Err(RegistryError::Other(format!("{name} has no artifact to install")));
         };
 
-        Self { registry, cache, install_root, _marker: PhantomData }
+        if !entry.runtimes.is_empty() {
+            let index = self.fetch_index(domain).await?;
+            self.install_runtimes(&index, domain, &entry.runtimes).await?;
+        }
+
+        // Fetch and verify the checksum
+        let install_path = self.install_path(domain);
+        let context = self.artifact_context(url, hash)?;
+        self.check_download(&context, entry.size, &install_path).await?;
+        let stream = self.registry.open(&context).await?;
+
+        // Unpack into a staging directory first, so an interrupted unpack or
+        // an artifact failing its checksum at the end of the download never
+        // leaves a partial package in the installation path
+        let staging = TempDir::new_in(&self.temp_dir())?;
+        let staging_path = staging.path().to_path_buf();
+        let unpacked = if entry.components.is_empty() {
+            archive::unpack_async(stream, staging_path).await
+        } else {
+            let paths = entry.components.values().flatten().map(PathBuf::from).collect();
+            archive::unpack_paths_async(stream, staging_path, paths).await
+        };
+        unpacked.map_err(|e| unpack_error(name, &*e))?;
+
+        // Reload the cache, since managers of other kinds share the same
+        // storage, and resolve clashes with the files of other packages
+        self.cache = self.storage.load()?;
+        let (files, replaced) =
+            self.resolve_conflicts(domain, name, staging.path(), &mut entry).await?;
+        staging.merge(&install_path)?;
+
+        // Now, update cache to reflect the new installation.
+        for (owner_category, owner, file) in replaced {
+            let packages = self.cache.get_package_mut(T::kind(), domain, &owner_category);
+            if let Some(owner) = packages.and_then(|packages| packages.get_mut(&owner)) {
+                owner.files.retain(|f| *f != file);
+            }
+        }
+        entry.path = match entry.conflicts.get(name) {
+            Some(Resolution::Rename(to)) => install_path.join(to),
+            _ => install_path.join(name),
+        };
+        entry.files = files;
+        entry.order = self.cache.next_order();
+        self.cache.insert(T::kind(), domain, category, name, entry);
+        self.save()?;
+
+        Ok(())
     }
 
-    /// Returns the installation path for a package with the given domain.
-    fn install_path(&self, domain: &str) -> PathBuf {
-        self.install_root.join(T::kind()).join(domain)
+    /// Resolves the staged files of a package that other packages of the
+    /// domain installed, renaming or removing them in the staging directory.
+    /// Returns the files left to install, and the category, package and file
+    /// of every file replaced.
+    ///
+    /// Decisions recorded in the entry are repeated, and new ones recorded.
+    /// The main binary of the package is never skipped, but renamed, since
+    /// the package would be unusable without it.
+    async fn resolve_conflicts(
+        &self,
+        domain: &str,
+        name: &str,
+        staging: &Path,
+        entry: &mut Entry,
+    ) -> Result<(Vec<String>, Vec<Replaced>)> {
+        let resolver: &dyn ConflictResolver = match self.policy.on_conflict {
+            OnConflict::Ask => self.resolver.as_ref(),
+            ref strategy => strategy,
+        };
+
+        let previous = std::mem::take(&mut entry.conflicts);
+        let mut files = Vec::new();
+        let mut replaced = Vec::new();
+        for file in staged_files(staging)? {
+            let Some((category, owner)) = self.owner(domain, name, &file) else {
+                files.push(file);
+                continue;
+            };
+
+            let conflict = Conflict {
+                domain: domain.to_string(),
+                file: file.clone(),
+                package: name.to_string(),
+                owner: owner.clone(),
+            };
+            let resolution = match previous.get(&file) {
+                Some(resolution) => resolution.clone(),
+                None => resolver.resolve(&conflict).await,
+            };
+            let resolution = match resolution {
+                Resolution::Skip if file == name => Resolution::Rename(conflict.renamed()),
+                resolution => resolution,
+            };
+            match &resolution {
+                Resolution::Skip => {
+                    self.reporter.warn(
+                        warning::FILE_CONFLICT,
+                        format!("{name}: skipped {file}, installed by {owner}"),
+                    );
+                    std::fs::remove_file(staging.join(&file))?;
+                }
+                Resolution::Rename(to) => {
+                    if staging.join(to).exists() || self.owner(domain, name, to).is_some() {
+                        return Err(RegistryError::Other(format!(
+                            "{name}: cannot rename {file} to {to}, which is taken"
+                        )));
+                    }
+                    self.reporter
+                        .warn(warning::FILE_CONFLICT, format!("{name}: installed {file} as {to}"));
+                    std::fs::rename(staging.join(&file), staging.join(to))?;
+                    files.push(to.clone());
+                }
+                Resolution::Replace => {
+                    self.reporter.warn(
+                        warning::FILE_CONFLICT,
+                        format!("{name}: replaced {file} of {owner}"),
+                    );
+                    files.push(file.clone());
+                    replaced.push((category, owner, file.clone()));
+                }
+            }
+            entry.conflicts.insert(file, resolution);
+        }
+
+        Ok((files, replaced))
     }
 
-    /// Returns the path to the installed manifest cache file.
-    fn cache_path(&self) -> PathBuf {
-        self.install_root.join("installed.toml")
+    /// Returns the category and name of the package other than `except`
+    /// that installed a file into a domain. Packages installed before files
+    /// were recorded own their binary only.
+    fn owner(&self, domain: &str, except: &str, file: &str) -> Option<(String, String)> {
+        let categories = self.cache.get_category(T::kind(), domain)?;
+        owner(categories, &self.install_path(domain), except, file)
     }
-}
 
-// impl<T: PackageKind> ManagerTrait for Manager<T> {}
+    /// Returns the package that installed a file into a domain, as its
+    /// category and name, with the path of the file. The install root is
+    /// searched before the system-wide installation.
+    pub fn which(&self, domain: &str, file: &str) -> Option<(String, String, PathBuf)> {
+        if let Some((category, name)) = self.owner(domain, "", file) {
+            return Some((category, name, self.install_path(domain).join(file)));
+        }
+
+        let system_path = self.system_root.as_ref()?.join(T::kind()).join(domain);
+        let categories = self.system.get_category(T::kind(), domain)?;
+        let (category, name) = owner(categories, &system_path, "", file)?;
+        Some((category, name, system_path.join(file)))
+    }
+
+    /// Installs a package from a local archive, without any manifests,
+    /// returning its name. The name and version are read from the archive
+    /// file name, `<name>-v<version>[-<target>].tar.gz`.
+    ///
+    /// Local archives have no signature or provenance, so they are refused
+    /// when the policy requires either, unless `allow_unverified` is set.
+    pub async fn install_local(
+        &mut self,
+        domain: &str,
+        category: &str,
+        archive: &Path,
+        allow_unverified: bool,
+    ) -> Result<String> {
+        let archive = archive.canonicalize()?;
+        let (name, version) = archive_name(&archive).ok_or_else(|| {
+            RegistryError::Other(format!(
+                "Cannot read the package name and version from {}, expected \
+                 <name>-v<version>.tar.gz",
+                archive.display()
+            ))
+        })?;
+        self.policy.check_unverified(&name, allow_unverified)?;
+
+        let hash = checksum::digest_file(&archive)?;
+        let url = path::utf8(&archive).map_err(|e| RegistryError::InvalidPath(e.to_string()))?;
+        let entry =
+            Entry::new(version, None, PathBuf::new()).artifact(&format!("file://{url}"), &hash);
+        let entry = Entry { source: Source::Local, ..entry };
+        self.install(domain, category, &name, entry).await?;
+
+        Ok(name)
+    }
+
+    /// Links a package to a binary built outside Hummanta, e.g. in a cargo
+    /// workspace. The binary is used in place, so rebuilding it updates the
+    /// package without reinstalling.
+    ///
+    /// Like local archives, linked binaries are refused when the policy
+    /// requires signatures or provenance, unless `allow_unverified` is set.
+    pub fn link(
+        &mut self,
+        domain: &str,
+        category: &str,
+        name: &str,
+        binary: &Path,
+        allow_unverified: bool,
+    ) -> Result<()> {
+        self.policy.check_domain(domain)?;
+        self.policy.check_category(category)?;
+        self.policy.check_unverified(name, allow_unverified)?;
+
+        let binary = binary.canonicalize()?;
+        if !path::is_executable(&binary) {
+            return Err(RegistryError::InvalidPath(format!(
+                "{} is not an executable file",
+                binary.display()
+            )));
+        }
+
+        let mut entry = Entry::new("dev".to_string(), None, binary);
+        entry.source = Source::Linked;
+
+        self.cache = self.storage.load()?;
+        entry.order = self.cache.next_order();
+        self.cache.insert(T::kind(), domain, category, name, entry);
+        self.save()?;
+
+        Ok(())
+    }
+
+    /// Compares the installed packages of a domain with their latest
+    /// releases, returning the packages that are outdated or missing.
+    pub async fn updates(&self, domain: &str) -> Result<Vec<Update>> {
+        self.policy.check_domain(domain)?;
 
-impl<T: PackageKind> PackageManager for Manager<T> {
-    /// Add a package to the system and update the cache.
-    async fn add(&mut self, domain: &str) -> Result<()> {
         let index = self.fetch_index(domain).await?;
         let install_path = self.install_path(domain);
+        let installed = self.installed.get_category(T::kind(), domain);
 
-        // Iterate over the index entries to fetch and install packages
+        let mut updates = Vec::new();
         for (category, name) in index.entries() {
-            // let package = self.fetch_package(&index, category, name).await?;
-            let Ok(package) = self.fetch_package(&index, category, name).await else {
-                warn!("{name} failed to fetch, skipping");
+            if category == RUNTIME_CATEGORY || self.policy.check_category(category).is_err() {
+                continue;
+            }
+
+            let Ok(bytes) = self.fetch_package_bytes(&index, category, name).await else {
+                self.reporter
+                    .warn(warning::FETCH_FAILED, format!("{name} failed to fetch, skipping"));
                 continue;
             };
 
-            // Fetch the release manifest by latest version.
-            let release = self.fetch_release(&package, &package.latest).await?;
-            if !release.supports_target(target_triple::TARGET) {
-                warn!("{name} does not support current target platform, skipping.");
+            // Most packages are up to date, so only their latest version is
+            // parsed before the full manifest is needed
+            let current = installed.and_then(|c| c.get(category)).and_then(|p| p.get(name));
+            if current.is_some_and(|entry| !entry.source.is_registry()) {
                 continue;
             }
+            let latest = PackageSummary::parse(&bytes)?.latest;
+            if current.is_some_and(|entry| entry.version == latest) {
+                continue;
+            }
+            let package = PackageManifest::from_slice(&bytes)?;
 
-            // Get the appropriate artifact for the target platform
-            let artifact = release
-                .get_artifact(target_triple::TARGET)
-                .expect("Artifact should exist if platform is supported");
+            let release = self.fetch_release(&package, &package.latest).await?;
+            let Some(artifact) = release.get_artifact(target_triple::TARGET) else {
+                self.reporter.warn(
+                    warning::UNSUPPORTED_TARGET,
+                    format!("{name} does not support current target platform, skipping."),
+                );
+                continue;
+            };
+            self.policy.check_signature(&package.package, artifact)?;
+            self.verify_provenance(&package, &package.latest, artifact).await?;
 
-            // Fetch and verify the checksum
-            let context = FetchContext::new(&artifact.url).checksum(&artifact.hash);
-            let data = self.registry.fetch(&context).await?;
+            // Keep the installed components, as far as the release still has them
+            let names: Vec<String> =
+                current.map(|entry| entry.components.keys().cloned().collect()).unwrap_or_default();
+            let components = match release.select(&names) {
+                Ok(components) => components,
+                Err(e) => {
+                    self.reporter.warn(warning::POLICY_SKIPPED, format!("{name} skipped: {e}"));
+                    continue;
+                }
+            };
 
-            // Unpack the file and extract its contents to the target directory
-            archive::unpack(&data, &install_path).map_err(|e| {
-                error!("{}", e);
-                RegistryError::UnpackError(name.to_string())
-            })?;
+            let old = match current {
+                Some(entry) => self.fetch_release(&package, &entry.version).await.ok(),
+                None => None,
+            };
+            let mut diff = release.diff(old.as_ref(), target_triple::TARGET);
+            if let (Some(entry), None) = (current, &old) {
+                // The installed release is no longer published, so only its
+                // version is known
+                diff.from = Some(entry.version.clone());
+                diff.size_delta = None;
+            }
 
-            // Now, update cache to reflect the new installation
             let entry = Entry::new(
                 package.latest.to_string(),
                 package.package.description.clone(),
                 install_path.join(name),
+            )
+            .artifact(&artifact.url, &artifact.hash);
+            let entry = Entry {
+                stage: package.package.stage.clone(),
+                capabilities: package.package.capabilities.clone(),
+                runtimes: package.package.runtimes.clone(),
+                components,
+                size: artifact.size,
+                ..entry
+            };
+
+            updates.push(Update { category: category.clone(), name: name.clone(), diff, entry });
+        }
+        updates.extend(self.runtime_updates(&index, domain).await?);
+
+        Ok(updates)
+    }
+
+    /// Installs the latest releases of the outdated or missing packages of
+    /// a domain, returning the applied updates.
+    pub async fn update(&mut self, domain: &str) -> Result<Vec<Update>> {
+        let updates = self.updates(domain).await?;
+        self.apply(domain, updates).await
+    }
+
+    /// Installs the given updates of a domain in order, returning those
+    /// applied. Runtime updates no longer satisfying every package using
+    /// the runtime are skipped.
+    async fn apply(&mut self, domain: &str, updates: Vec<Update>) -> Result<Vec<Update>> {
+        let mut applied = Vec::new();
+        for update in updates {
+            if update.category == RUNTIME_CATEGORY {
+                // Updated packages may have installed a runtime release
+                // themselves, or now require another one
+                let reqs = self.runtime_requirements(domain, &update.name);
+                let version = &update.entry.version;
+                if !reqs.iter().all(|req| super::matches(version, req).unwrap_or(false)) {
+                    continue;
+                }
+                self.install_runtime(domain, &update.name, update.entry.clone()).await?;
+            } else {
+                self.install(domain, &update.category, &update.name, update.entry.clone()).await?;
+            }
+            applied.push(update);
+        }
+
+        Ok(applied)
+    }
+
+    /// Returns the latest versions of the packages of a domain, keyed by
+    /// category and name, as far as registry metadata is cached.
+    ///
+    /// Never accesses the registry, so packages whose manifests have not
+    /// been fetched yet are missing.
+    pub fn cached_latest(&self, domain: &str) -> BTreeMap<(String, String), String> {
+        let cached = |url: &str| self.registry.cached_metadata(&FetchContext::new(url));
+        let index = cached("index.toml")
+            .and_then(|bytes| IndexManifest::from_slice(&bytes).ok())
+            .and_then(|index| cached(index.get(T::kind(), domain)?))
+            .and_then(|bytes| IndexManifest::from_slice(&bytes).ok());
+        let Some(index) = index else {
+            return BTreeMap::new();
+        };
+
+        index
+            .entries()
+            .filter_map(|(category, name)| {
+                let bytes = cached(&package_url(&index, category, name).ok()?)?;
+                let latest = PackageSummary::parse(&bytes).ok()?.latest;
+                Some(((category.clone(), name.clone()), latest.into_owned()))
+            })
+            .collect()
+    }
+
+    /// Returns the category and name of every installed package of a domain
+    /// whose binary is missing or not executable.
+    pub fn broken(&self, domain: &str) -> Vec<(String, String)> {
+        let Some(categories) = self.installed.get_category(T::kind(), domain) else {
+            return Vec::new();
+        };
+
+        categories
+            .iter()
+            .flat_map(|(category, packages)| packages.iter().map(move |p| (category, p)))
+            .filter(|(_, (_, entry))| !path::is_executable(&entry.path))
+            .map(|(category, (name, _))| (category.clone(), name.clone()))
+            .collect()
+    }
+
+    /// Reinstalls a package from the artifact it was installed from.
+    pub async fn repair(&mut self, domain: &str, category: &str, name: &str) -> Result<()> {
+        let entry = self
+            .cache
+            .get_package(T::kind(), domain, category)
+            .and_then(|packages| packages.get(name))
+            .cloned()
+            .ok_or_else(|| RegistryError::PackageNotFound(name.to_string()))?;
+        if entry.source == Source::Linked {
+            return Err(RegistryError::Other(format!(
+                "{name} is linked to {}, rebuild it or link it again",
+                entry.path.display()
+            )));
+        }
+
+        self.install(domain, category, name, entry).await
+    }
+
+    /// Installs the exact release a freeze manifest pins, returning whether
+    /// it was installed. Packages already installed from the same artifact
+    /// are kept, and the install fails unless the registry still serves the
+    /// release with the frozen artifact for the current platform.
+    pub async fn install_locked(&mut self, package: &FrozenPackage) -> Result<bool> {
+        let FrozenPackage { domain, category, name, version, .. } = package;
+        self.install_pinned(domain, category, name, version, &package.hash, Some(&package.url))
+            .await
+    }
+
+    /// Installs the exact release a lockfile pins, selecting the artifact
+    /// of the current platform, and returns whether it was installed.
+    pub async fn install_locked_tool(&mut self, tool: &LockedTool) -> Result<bool> {
+        let LockedTool { domain, category, name, version, .. } = tool;
+        let hash = tool.hash(target_triple::TARGET).ok_or_else(|| {
+            RegistryError::Other(format!(
+                "The lockfile pins no artifact of {name} {version} for {}",
+                target_triple::TARGET
+            ))
+        })?;
+        self.install_pinned(domain, category, name, version, hash, None).await
+    }
+
+    /// Returns the artifact hashes of a release of a package, keyed by
+    /// target triple.
+    pub async fn release_hashes(
+        &self,
+        domain: &str,
+        category: &str,
+        name: &str,
+        version: &str,
+    ) -> Result<BTreeMap<String, String>> {
+        let index = self.fetch_index(domain).await?;
+        let manifest = self.fetch_package(&index, category, name).await?;
+        let release = self.fetch_release(&manifest, version).await?;
+
+        Ok(release
+            .artifacts
+            .iter()
+            .map(|(target, artifact)| (target.clone(), artifact.hash.clone()))
+            .collect())
+    }
+
+    /// Installs a release whose artifact for the current platform has the
+    /// given hash and, if given, URL. Packages already installed from that
+    /// artifact are kept.
+    async fn install_pinned(
+        &mut self,
+        domain: &str,
+        category: &str,
+        name: &str,
+        version: &str,
+        hash: &str,
+        url: Option<&str>,
+    ) -> Result<bool> {
+        let current =
+            self.installed.get_package(T::kind(), domain, category).and_then(|p| p.get(name));
+        if current.is_some_and(|entry| entry.hash.as_deref() == Some(hash)) {
+            return Ok(false);
+        }
+
+        self.policy.check_domain(domain)?;
+        let index = self.fetch_index(domain).await?;
+        let manifest = self.fetch_package(&index, category, name).await?;
+        let release = self.fetch_release(&manifest, version).await?;
+
+        let artifact = release
+            .get_artifact(target_triple::TARGET)
+            .filter(|artifact| artifact.hash == hash && url.is_none_or(|url| artifact.url == url))
+            .ok_or_else(|| RegistryError::ManifestChanged(name.to_string(), version.to_string()))?;
+        self.policy.check_signature(&manifest.package, artifact)?;
+        self.verify_provenance(&manifest, version, artifact).await?;
+
+        let entry = Entry::new(
+            version.to_string(),
+            manifest.package.description.clone(),
+            Default::default(),
+        )
+        .artifact(&artifact.url, &artifact.hash);
+        let entry = Entry {
+            stage: manifest.package.stage.clone(),
+            capabilities: manifest.package.capabilities.clone(),
+            runtimes: manifest.package.runtimes.clone(),
+            size: artifact.size,
+            ..entry
+        };
+        self.install(domain, category, name, entry).await?;
+        Ok(true)
+    }
+
+    /// Verifies that an artifact was built from the tagged source of the
+    /// package repository, when the policy requires provenance. The statement
+    /// must be signed by a builder the policy trusts, since its URL comes
+    /// from the publisher.
+    pub(super) async fn verify_provenance(
+        &self,
+        package: &PackageManifest,
+        version: &str,
+        artifact: &Artifact,
+    ) -> Result<()> {
+        let name = &package.package.name;
+        self.policy.check_provenance(name, artifact.provenance.as_deref())?;
+
+        let Some(url) = artifact.provenance.as_deref().filter(|_| self.policy.require_provenance)
+        else {
+            return Ok(());
+        };
+        let data = self.registry.fetch(&FetchContext::new(url)).await?;
+        Provenance::from_slice(&data)?
+            .verify(
+                &self.policy.provenance_keys,
+                &artifact.hash,
+                &package.package.repository,
+                version,
+            )
+            .map_err(|e| RegistryError::PolicyViolation(format!("{name} {version}: {e}")))
+    }
+
+    /// Fetches the raw package manifest for the given category and package name.
+    pub(super) async fn fetch_package_bytes(
+        &self,
+        index: &IndexManifest,
+        category: &str,
+        name: &str,
+    ) -> Result<Vec<u8>> {
+        let url = package_url(index, category, name)?;
+        self.registry.fetch_metadata(&FetchContext::new(&url)).await
+    }
+
+    /// Returns the URL of the release manifest for the specified version.
+    pub(super) fn release_url(&self, package: &PackageManifest, version: &str) -> Result<String> {
+        let name = &package.package.name;
+        let path = package
+            .get_releases()
+            .get(version)
+            .ok_or_else(|| RegistryError::ReleaseNotFound(name.to_string(), version.to_string()))?;
+
+        Ok(format!("{}/manifests/{}", package.package.homepage.trim_end_matches('/'), path))
+    }
+
+    /// Resolves the latest release of a package to an entry installable on
+    /// the current platform, or `None` if the release has no artifact for it.
+    /// Releases declaring components install only the named ones.
+    pub(super) async fn latest_entry(
+        &self,
+        domain: &str,
+        name: &str,
+        package: &PackageManifest,
+        components: &[String],
+    ) -> Result<Option<Entry>> {
+        // Fetch the release manifest by latest version.
+        let release = self.fetch_release(package, &package.latest).await?;
+        if !release.supports_target(target_triple::TARGET) {
+            return Ok(None);
+        }
+
+        // Get the appropriate artifact for the target platform
+        let artifact = release
+            .get_artifact(target_triple::TARGET)
+            .expect("Artifact should exist if platform is supported");
+        let components = release.select(components)?;
+        self.policy.check_signature(&package.package, artifact)?;
+        self.verify_provenance(package, &package.latest, artifact).await?;
+
+        let entry = Entry::new(
+            package.latest.to_string(),
+            package.package.description.clone(),
+            self.install_path(domain).join(name),
+        )
+        .artifact(&artifact.url, &artifact.hash);
+        Ok(Some(Entry {
+            stage: package.package.stage.clone(),
+            capabilities: package.package.capabilities.clone(),
+            runtimes: package.package.runtimes.clone(),
+            components,
+            size: artifact.size,
+            ..entry
+        }))
+    }
+
+    /// Installs the packages of a domain like [`PackageManager::add`],
+    /// selecting the named components of releases that declare components.
+    /// Releases without components are installed whole. Every package
+    /// handled is reported as [`Event::Progress`].
+    pub async fn add_components(&mut self, domain: &str, components: &[String]) -> Result<()> {
+        self.policy.check_domain(domain)?;
+
+        let index = self.fetch_index(domain).await?;
+
+        // Leave shared runtimes to the packages using them
+        let packages: Vec<_> =
+            index.entries().filter(|(category, _)| *category != RUNTIME_CATEGORY).collect();
+        let label = format!("Installing {domain}");
+        let total = packages.len();
+
+        let mut result = Ok(());
+        for (done, (category, name)) in packages.into_iter().enumerate() {
+            result = self.add_package(&index, domain, category, name, components).await;
+            if result.is_err() {
+                break;
+            }
+            self.reporter.report(Event::Progress {
+                label: label.clone(),
+                item: name.clone(),
+                done: done + 1,
+                total,
+            });
+        }
+        if total > 0 {
+            self.reporter.report(Event::Finished { label });
+        }
+
+        result
+    }
+
+    /// Downloads and verifies the artifacts of the latest releases of a
+    /// domain's packages into the download cache, without installing them,
+    /// so a later install needs no network access. Returns how many artifacts
+    /// were downloaded, skipping those already cached.
+    ///
+    /// Nothing under the install root is touched, so this is safe to run
+    /// alongside other managers installing packages.
+    pub async fn prefetch(&self, domain: &str) -> Result<usize> {
+        self.policy.check_domain(domain)?;
+
+        let index = self.fetch_index(domain).await?;
+        let mut downloaded = 0;
+        for (category, name) in index.entries() {
+            if category == RUNTIME_CATEGORY || self.policy.check_category(category).is_err() {
+                continue;
+            }
+            let Ok(package) = self.fetch_package(&index, category, name).await else {
+                self.reporter
+                    .warn(warning::FETCH_FAILED, format!("{name} failed to fetch, skipping"));
+                continue;
+            };
+            if let Some(entry) = self.latest_entry(domain, name, &package, &[]).await? {
+                downloaded += self.prefetch_runtimes(&index, domain, &entry.runtimes).await?;
+                downloaded += usize::from(self.download(&entry).await?);
+            }
+        }
+
+        Ok(downloaded)
+    }
+
+    /// Downloads and verifies the artifact of an entry into the download
+    /// cache, returning whether it was downloaded rather than already cached.
+    pub(super) async fn download(&self, entry: &Entry) -> Result<bool> {
+        let (Some(dir), Some(url), Some(hash)) = (&self.downloads, &entry.url, &entry.hash) else {
+            return Ok(false);
+        };
+        let path = dir.join(hash);
+        if path.exists() {
+            return Ok(false);
+        }
+
+        std::fs::create_dir_all(dir)?;
+        let context = FetchContext::new(url).checksum(hash);
+        let size = match entry.size {
+            Some(size) => Some(size),
+            None => self.registry.size(&context).await,
+        };
+        if let Some(size) = size {
+            disk::check(dir, size)?;
+        }
+
+        // Written under a temporary name first, so a concurrent install never
+        // reads a partial artifact from the cache
+        let stream = self.registry.open(&context).await?;
+        let file = TempFile::new_in(dir)?;
+        file.write_async(stream).await.map_err(|e| match stream_error(&e) {
+            Some(FetchError::HashMismatch(expected)) => {
+                FetchError::HashMismatch(expected.clone()).into()
+            }
+            _ => RegistryError::from(e),
+        })?;
+        file.persist(&path)?;
+        Ok(true)
+    }
+
+    /// Returns the context to fetch an artifact with, reading it from the
+    /// download cache if it was downloaded there. The hash is verified
+    /// either way.
+    pub(super) fn artifact_context(&self, url: &str, hash: &str) -> Result<FetchContext> {
+        let cached = self.downloads.as_ref().map(|dir| dir.join(hash)).filter(|p| p.is_file());
+        let Some(cached) = cached else {
+            return Ok(FetchContext::new(url).checksum(hash));
+        };
+        let path = path::utf8(&cached).map_err(|e| RegistryError::InvalidPath(e.to_string()))?;
+        Ok(FetchContext::new(&format!("file://{path}")).checksum(hash))
+    }
+
+    /// Fetches and installs one package of an index, skipping it with a
+    /// warning if the policy forbids it or it cannot be installed here.
+    async fn add_package(
+        &mut self,
+        index: &IndexManifest,
+        domain: &str,
+        category: &str,
+        name: &str,
+        components: &[String],
+    ) -> Result<()> {
+        if let Err(e) = self.policy.check_category(category) {
+            self.reporter.warn(warning::POLICY_SKIPPED, format!("{name} skipped: {e}"));
+            return Ok(());
+        }
+
+        let Ok(package) = self.fetch_package(index, category, name).await else {
+            self.reporter.warn(warning::FETCH_FAILED, format!("{name} failed to fetch, skipping"));
+            return Ok(());
+        };
+
+        let Some(entry) = self.latest_entry(domain, name, &package, components).await? else {
+            self.reporter.warn(
+                warning::UNSUPPORTED_TARGET,
+                format!("{name} does not support current target platform, skipping."),
             );
-            self.cache.insert(T::kind(), domain, category, name, entry);
-            self.cache.save(self.cache_path())?;
+            return Ok(());
+        };
+        self.install(domain, category, name, entry).await
+    }
+
+    /// Saves the cache to storage, and refreshes the view of all installed
+    /// packages.
+    pub(super) fn save(&mut self) -> Result<()> {
+        self.storage.save(&self.cache)?;
+        self.installed = self.system.overlay(&self.cache);
+        Ok(())
+    }
+
+    /// Returns the kind of packages this manager handles, e.g. "toolchains".
+    pub fn kind(&self) -> &'static str {
+        T::kind()
+    }
+
+    /// Returns the installation path for a package with the given domain.
+    fn install_path(&self, domain: &str) -> PathBuf {
+        self.install_root.join(T::kind()).join(domain)
+    }
+
+    /// Returns the directory packages are staged in before installation.
+    pub(super) fn temp_dir(&self) -> PathBuf {
+        self.install_root.join("tmp")
+    }
+
+    /// Checks that there is room to unpack an artifact into `path` before
+    /// downloading it, by its published size or else the size the registry
+    /// reports. Artifacts of unknown size are not checked.
+    pub(super) async fn check_download(
+        &self,
+        context: &FetchContext,
+        size: Option<u64>,
+        path: &Path,
+    ) -> Result<()> {
+        let size = match size {
+            Some(size) => Some(size),
+            None => self.registry.size(context).await,
+        };
+        match size {
+            Some(size) => self.check_space(size, path),
+            None => Ok(()),
         }
+    }
 
+    /// Checks that both the staging and the destination filesystem have
+    /// room to unpack an archive of `size` bytes.
+    pub(super) fn check_space(&self, size: u64, path: &Path) -> Result<()> {
+        let required = size.saturating_mul(disk::UNPACK_RATIO);
+        disk::check(&self.temp_dir(), required)?;
+        disk::check(path, required)?;
         Ok(())
     }
+}
+
+// impl<T: PackageKind> ManagerTrait for Manager<T> {}
+
+impl<T: PackageKind> PackageManager for Manager<T> {
+    /// Add a package to the system and update the cache.
+    async fn add(&mut self, domain: &str) -> Result<()> {
+        self.add_components(domain, &[]).await
+    }
 
     fn remove(&mut self, domain: &str) -> Result<()> {
+        // Packages of the system-wide installation are read-only.
+        self.cache = self.storage.load()?;
+        if self.cache.get_category(T::kind(), domain).is_none() &&
+            self.system.get_category(T::kind(), domain).is_some()
+        {
+            return Err(RegistryError::ReadOnly(domain.to_string()));
+        }
+
         // Determine the installation path for the given domain.
         let install_path = self.install_path(domain);
 
@@ -128,17 +985,20 @@ impl<T: PackageKind> PackageManager for Manager<T> {
             })?;
         }
 
-        // Remove all cached entries under the given domain,
-        // and save the updated cache back to disk.
+        // Remove all cached entries under the given domain, and the shared
+        // runtimes no other package uses, then save the cache back to disk.
         self.cache.remove_domain(T::kind(), domain);
-        self.cache.save(self.cache_path())?;
+        for (_, runtime) in self.remove_unused_runtimes()? {
+            self.reporter.info(format!("Removed unused runtime {runtime}"));
+        }
+        self.save()?;
 
         Ok(())
     }
 
     /// Return all installed packages under the current kind.
     fn list(&self) -> Option<&DomainMap> {
-        self.cache.get_domain(T::kind())
+        self.installed.get_domain(T::kind())
     }
 }
 
@@ -153,7 +1013,7 @@ impl<T: PackageKind> RemoteMetadata for Manager<T> {
             .ok_or_else(|| RegistryError::DomainNotFound(domain.to_string()))?;
 
         let context = FetchContext::new(path);
-        let bytes = self.registry.fetch(&context).await?;
+        let bytes = self.registry.fetch_metadata(&context).await?;
         let manifest = IndexManifest::from_slice(&bytes)?;
 
         Ok(manifest)
@@ -167,14 +1027,7 @@ impl<T: PackageKind> RemoteMetadata for Manager<T> {
         category: &str,
         name: &str,
     ) -> Result<PackageManifest> {
-        let registry = index
-            .get(category, name)
-            .ok_or_else(|| RegistryError::PackageNotFound(name.to_string()))?
-            .trim_end_matches('/');
-        let url = format!("{registry}/manifests/index.toml");
-
-        let context = FetchContext::new(&url);
-        let bytes = self.registry.fetch(&context).await?;
+        let bytes = self.fetch_package_bytes(index, category, name).await?;
         let manifest = PackageManifest::from_slice(&bytes)?;
 
         Ok(manifest)
@@ -187,15 +1040,10 @@ impl<T: PackageKind> RemoteMetadata for Manager<T> {
         package: &PackageManifest,
         version: &str,
     ) -> Result<ReleaseManifest> {
-        let name = &package.package.name;
-        let path = package
-            .get_releases()
-            .get(version)
-            .ok_or_else(|| RegistryError::ReleaseNotFound(name.to_string(), version.to_string()))?;
-        let url = format!("{}/manifests/{}", package.package.homepage.trim_end_matches('/'), path);
+        let url = self.release_url(package, version)?;
 
         let context = FetchContext::new(&url);
-        let bytes = self.registry.fetch(&context).await?;
+        let bytes = self.registry.fetch_metadata(&context).await?;
         let manifest = ReleaseManifest::from_slice(&bytes)?;
 
         Ok(manifest)
@@ -204,7 +1052,7 @@ impl<T: PackageKind> RemoteMetadata for Manager<T> {
 
 impl<T: PackageKind> Query for Manager<T> {
     fn by_category(&self, category: &str) -> Vec<PackageEntry> {
-        self.cache
+        self.installed
             .by_category(T::kind(), category)
             .iter()
             .flat_map(|pkg| pkg.iter().map(From::from))
@@ -212,13 +1060,419 @@ impl<T: PackageKind> Query for Manager<T> {
     }
 
     fn get_category(&self, domain: &str) -> Option<&CategoryMap> {
-        self.cache.get_category(T::kind(), domain)
+        self.installed.get_category(T::kind(), domain)
     }
 
     fn get_package(&self, domain: &str, cat: &str) -> Vec<PackageEntry> {
-        self.cache
+        self.installed
             .get_package(T::kind(), &domain.to_lowercase(), cat)
             .map(|pkg| pkg.iter().map(From::from).collect())
             .unwrap_or_default()
     }
 }
+
+/// Returns the category and name of the package other than `except` that
+/// installed a file into `install_path`, among the categories of a domain.
+fn owner(
+    categories: &CategoryMap,
+    install_path: &Path,
+    except: &str,
+    file: &str,
+) -> Option<(String, String)> {
+    categories.iter().find_map(|(category, packages)| {
+        packages
+            .iter()
+            .filter(|(name, _)| *name != except)
+            .find(|(_, entry)| match entry.files.is_empty() {
+                true => entry.path == install_path.join(file),
+                false => entry.files.iter().any(|f| f == file),
+            })
+            .map(|(name, _)| (category.clone(), name.clone()))
+    })
+}
+
+/// Checks that a name read from a manifest or lockfile is a single path
+/// component before it is joined into an installation path.
+pub(super) fn check_file_name(what: &str, name: &str) -> Result<()> {
+    if path::is_file_name(Path::new(name)) {
+        return Ok(());
+    }
+    Err(RegistryError::InvalidPath(format!("{what} '{name}' is not a valid file name")))
+}
+
+/// Maps an error unpacking a fetched artifact, reporting an artifact that
+/// failed its checksum as such rather than as unpackable.
+pub(super) fn unpack_error(name: &str, e: &(dyn std::error::Error + 'static)) -> RegistryError {
+    match stream_error(e) {
+        Some(FetchError::HashMismatch(expected)) => {
+            FetchError::HashMismatch(expected.clone()).into()
+        }
+        _ => {
+            error!("{}", e);
+            RegistryError::UnpackError(name.to_string())
+        }
+    }
+}
+
+/// Returns the URL of the package manifest listed in a domain index.
+fn package_url(index: &IndexManifest, category: &str, name: &str) -> Result<String> {
+    let registry = index
+        .get(category, name)
+        .ok_or_else(|| RegistryError::PackageNotFound(name.to_string()))?
+        .trim_end_matches('/');
+    Ok(format!("{registry}/manifests/index.toml"))
+}
+
+/// Lists the files below a staging directory, relative to it and with `/`
+/// separators, in sorted order.
+fn staged_files(staging: &Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![staging.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() && !path.is_symlink() {
+                dirs.push(path);
+            } else if let Ok(relative) = path.strip_prefix(staging) {
+                let parts: Vec<_> = relative.iter().map(|part| part.to_string_lossy()).collect();
+                files.push(parts.join("/"));
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Splits the file name of a package archive, `<name>-v<version>.tar.gz`
+/// with an optional target suffix, into the name and version.
+fn archive_name(archive: &Path) -> Option<(String, String)> {
+    let file_name = archive.file_name()?.to_str()?;
+    let stem = file_name.strip_suffix(".tar.gz").or_else(|| file_name.strip_suffix(".tgz"))?;
+    let stem = stem.strip_suffix(&format!("-{}", target_triple::TARGET)).unwrap_or(stem);
+
+    // The version starts at the first `-v` followed by a digit
+    let (index, _) = stem
+        .match_indices("-v")
+        .find(|(i, _)| stem[i + 2..].starts_with(|c: char| c.is_ascii_digit()))?;
+    let (name, version) = (&stem[..index], &stem[index + 1..]);
+    (!name.is_empty()).then(|| (name.to_string(), version.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::manager::Toolchain;
+
+    /// A manager with `bar` installed, owning `bar` and `fmt`.
+    fn installed(root: &Path, on_conflict: OnConflict) -> Manager<Toolchain> {
+        let registry = RegistryClient::new("file:///registry");
+        let mut manager = Manager::<Toolchain>::new(registry, root.to_path_buf())
+            .with_policy(Policy { on_conflict, ..Default::default() });
+
+        let mut entry = Entry::new("v1.0.0".into(), None, manager.install_path("solidity"));
+        entry.files = vec!["bar".to_string(), "fmt".to_string()];
+        manager.cache.insert("toolchains", "solidity", "frontend", "bar", entry);
+        manager
+    }
+
+    /// A staging directory holding the files of `foo`.
+    fn stage(root: &Path, dir: &str) -> PathBuf {
+        let staging = root.join(dir);
+        fs::create_dir_all(&staging).unwrap();
+        fs::write(staging.join("foo"), "foo").unwrap();
+        fs::write(staging.join("fmt"), "fmt of foo").unwrap();
+        staging
+    }
+
+    #[tokio::test]
+    async fn test_resolve_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut entry = Entry::new("v1.0.0".into(), None, PathBuf::new());
+
+        // Skipped files stay with their owner
+        let manager = installed(dir.path(), OnConflict::Ask);
+        let staging = stage(dir.path(), "skip");
+        let (files, replaced) =
+            manager.resolve_conflicts("solidity", "foo", &staging, &mut entry).await.unwrap();
+        assert_eq!(files, ["foo"]);
+        assert!(replaced.is_empty() && !staging.join("fmt").exists());
+        assert_eq!(entry.conflicts["fmt"], Resolution::Skip);
+
+        // Renamed files move aside, and recorded decisions are repeated
+        let manager = installed(dir.path(), OnConflict::Replace);
+        let staging = stage(dir.path(), "rename");
+        entry.conflicts.insert("fmt".into(), Resolution::Rename("fmt-foo".into()));
+        let (files, _) =
+            manager.resolve_conflicts("solidity", "foo", &staging, &mut entry).await.unwrap();
+        assert_eq!(files, ["fmt-foo", "foo"]);
+        assert_eq!(fs::read_to_string(staging.join("fmt-foo")).unwrap(), "fmt of foo");
+
+        // Replaced files are taken from their owner
+        entry.conflicts.clear();
+        let staging = stage(dir.path(), "replace");
+        let (files, replaced) =
+            manager.resolve_conflicts("solidity", "foo", &staging, &mut entry).await.unwrap();
+        assert_eq!(files, ["fmt", "foo"]);
+        assert_eq!(replaced, [("frontend".into(), "bar".into(), "fmt".into())]);
+
+        let (category, name, path) = manager.which("solidity", "fmt").unwrap();
+        assert_eq!((category.as_str(), name.as_str()), ("frontend", "bar"));
+        assert!(path.ends_with("toolchains/solidity/fmt"));
+        assert!(manager.which("solidity", "foo").is_none());
+
+        // The main binary of a package is renamed rather than skipped
+        let manager = installed(dir.path(), OnConflict::Skip);
+        let staging = stage(dir.path(), "main");
+        entry.conflicts.clear();
+        let (files, _) =
+            manager.resolve_conflicts("solidity", "fmt", &staging, &mut entry).await.unwrap();
+        assert_eq!(files, ["fmt-fmt", "foo"]);
+        assert_eq!(entry.conflicts["fmt"], Resolution::Rename("fmt-fmt".into()));
+    }
+
+    #[tokio::test]
+    async fn test_install_shared_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = RegistryClient::new("file:///registry");
+        let mut manager = Manager::<Toolchain>::new(registry, dir.path().join("home"));
+
+        // Both packages ship a `lib` directory with a file of their own
+        for name in ["foo", "bar"] {
+            let package = dir.path().join(name);
+            fs::create_dir_all(package.join("lib")).unwrap();
+            fs::write(package.join(name), name).unwrap();
+            fs::write(package.join("lib").join(format!("lib{name}.so")), name).unwrap();
+
+            let archive = dir.path().join(format!("{name}-v1.0.0.tar.gz"));
+            archive::archive_dir(&package, &archive).await.unwrap();
+            manager.install_local("solidity", "frontend", &archive, false).await.unwrap();
+        }
+
+        let lib = manager.install_path("solidity").join("lib");
+        assert_eq!(fs::read_to_string(lib.join("libfoo.so")).unwrap(), "foo");
+        assert_eq!(fs::read_to_string(lib.join("libbar.so")).unwrap(), "bar");
+        let (_, owner, _) = manager.which("solidity", "lib/libfoo.so").unwrap();
+        assert_eq!(owner, "foo");
+    }
+
+    #[tokio::test]
+    async fn test_install_local_requires_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = RegistryClient::new("file:///registry");
+        let policy = Policy { require_signatures: true, ..Default::default() };
+        let mut manager =
+            Manager::<Toolchain>::new(registry, dir.path().join("home")).with_policy(policy);
+
+        let package = dir.path().join("foo");
+        fs::create_dir_all(&package).unwrap();
+        fs::write(package.join("foo"), "foo").unwrap();
+        let archive = dir.path().join("foo-v1.0.0.tar.gz");
+        archive::archive_dir(&package, &archive).await.unwrap();
+
+        // Unsigned local archives are refused unless explicitly allowed
+        let result = manager.install_local("solidity", "frontend", &archive, false).await;
+        assert!(matches!(result, Err(RegistryError::PolicyViolation(_))));
+        assert!(!manager.install_path("solidity").join("foo").exists());
+
+        manager.install_local("solidity", "frontend", &archive, true).await.unwrap();
+        assert!(manager.install_path("solidity").join("foo").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_requires_override() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let registry = RegistryClient::new("file:///registry");
+        let policy = Policy { require_provenance: true, ..Default::default() };
+        let mut manager =
+            Manager::<Toolchain>::new(registry, dir.path().join("home")).with_policy(policy);
+
+        fs::create_dir_all(dir.path().join("home")).unwrap();
+        let binary = dir.path().join("foo");
+        fs::write(&binary, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let result = manager.link("solidity", "frontend", "foo", &binary, false);
+        assert!(matches!(result, Err(RegistryError::PolicyViolation(_))));
+        assert!(manager.which("solidity", "foo").is_none());
+
+        manager.link("solidity", "frontend", "foo", &binary, true).unwrap();
+        let packages = manager.installed.get_package(Toolchain::kind(), "solidity", "frontend");
+        assert!(packages.is_some_and(|packages| packages.contains_key("foo")));
+    }
+
+    #[tokio::test]
+    async fn test_apply_skips_unsatisfied_runtimes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = installed(dir.path(), OnConflict::Ask);
+        let mut entry = Entry::new("v1.0.0".into(), None, PathBuf::new());
+        entry.runtimes = BTreeMap::from([("llvm".to_string(), "^17".to_string())]);
+        manager.cache.insert("toolchains", "solidity", "frontend", "foo", entry);
+
+        // The runtime release no longer satisfies `foo`, so it is neither
+        // installed nor reported as applied
+        let update = Update {
+            category: RUNTIME_CATEGORY.to_string(),
+            name: "llvm".to_string(),
+            diff: ReleaseDiff {
+                from: Some("v17.0.0".into()),
+                to: "v18.0.0".into(),
+                size_delta: None,
+                added_targets: Vec::new(),
+                removed_targets: Vec::new(),
+            },
+            entry: Entry::new("v18.0.0".into(), None, PathBuf::new())
+                .artifact("file:///registry/llvm.tar.gz", "00"),
+        };
+        let applied = manager.apply("solidity", vec![update]).await.unwrap();
+        assert!(applied.is_empty());
+        assert!(!manager.runtime_path("solidity", "llvm").unwrap().exists());
+    }
+
+    #[tokio::test]
+    async fn test_install_verifies_streamed_artifact() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = RegistryClient::new("file:///registry");
+        let mut manager = Manager::<Toolchain>::new(registry, dir.path().join("home"));
+
+        let package = dir.path().join("foo");
+        fs::create_dir_all(&package).unwrap();
+        fs::write(package.join("foo"), "foo").unwrap();
+        let archive = dir.path().join("foo-v1.0.0.tar.gz");
+        archive::archive_dir(&package, &archive).await.unwrap();
+
+        // The artifact is unpacked while read, but never installed unless
+        // its checksum matches at the end
+        let url = format!("file://{}", archive.display());
+        let entry = Entry::new("v1.0.0".into(), None, PathBuf::new()).artifact(&url, "bad");
+        let result = manager.install("solidity", "frontend", "foo", entry).await;
+        assert!(matches!(result, Err(RegistryError::FetchError(FetchError::HashMismatch(_)))));
+        assert!(!manager.install_path("solidity").join("foo").exists());
+        assert_eq!(fs::read_dir(manager.temp_dir()).unwrap().count(), 0);
+
+        let hash = checksum::digest_file(&archive).unwrap();
+        let entry = Entry::new("v1.0.0".into(), None, PathBuf::new()).artifact(&url, &hash);
+        manager.install("solidity", "frontend", "foo", entry).await.unwrap();
+        assert!(manager.install_path("solidity").join("foo").exists());
+    }
+
+    #[tokio::test]
+    async fn test_install_from_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = RegistryClient::new("file:///registry");
+        let downloads = dir.path().join("downloads");
+        let mut manager = Manager::<Toolchain>::new(registry, dir.path().join("home"))
+            .with_downloads(downloads.clone());
+
+        let package = dir.path().join("foo");
+        fs::create_dir_all(&package).unwrap();
+        fs::write(package.join("foo"), "foo").unwrap();
+        let archive = dir.path().join("foo-v1.0.0.tar.gz");
+        archive::archive_dir(&package, &archive).await.unwrap();
+
+        // Downloading only fills the cache, and only once
+        let hash = checksum::digest_file(&archive).unwrap();
+        let url = format!("file://{}", archive.display());
+        let entry = Entry::new("v1.0.0".into(), None, PathBuf::new()).artifact(&url, &hash);
+        assert!(manager.download(&entry).await.unwrap());
+        assert!(!manager.download(&entry).await.unwrap());
+        assert!(downloads.join(&hash).is_file());
+        assert!(!manager.install_path("solidity").exists());
+
+        // Artifacts failing their checksum are never cached
+        let bad = Entry::new("v1.0.0".into(), None, PathBuf::new()).artifact(&url, "bad");
+        let result = manager.download(&bad).await;
+        assert!(matches!(result, Err(RegistryError::FetchError(FetchError::HashMismatch(_)))));
+        assert!(!downloads.join("bad").exists());
+
+        // The cached artifact is installed, without fetching it again
+        fs::remove_file(&archive).unwrap();
+        manager.install("solidity", "frontend", "foo", entry).await.unwrap();
+        assert!(manager.install_path("solidity").join("foo").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_check_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = installed(dir.path(), OnConflict::Ask);
+        let archive = dir.path().join("foo-v1.0.0.tar.gz");
+        fs::write(&archive, "archive").unwrap();
+        let context = FetchContext::new(&format!("file://{}", archive.display()));
+
+        // The reported size is used when none is published
+        assert!(manager.check_download(&context, None, dir.path()).await.is_ok());
+        let result = manager.check_download(&context, Some(u64::MAX), dir.path()).await;
+        assert!(matches!(result, Err(RegistryError::InsufficientSpace(_))));
+
+        let missing = FetchContext::new("file:///missing/foo-v1.0.0.tar.gz");
+        assert!(manager.check_download(&missing, None, dir.path()).await.is_ok());
+    }
+
+    #[test]
+    fn test_system_overlay() {
+        let dir = tempfile::tempdir().unwrap();
+        let system_root = dir.path().join("system");
+        let system_path = system_root.join("toolchains").join("solidity");
+
+        let mut system = InstalledManifest::new();
+        for (domain, category, name) in [
+            ("solidity", "frontend", "bar"),
+            ("solidity", "backend", "baz"),
+            ("move", "frontend", "mv"),
+        ] {
+            let entry = Entry::new("v0.9.0".into(), None, system_path.join(name));
+            system.insert("toolchains", domain, category, name, entry);
+        }
+        fs::create_dir_all(&system_path).unwrap();
+        fs::create_dir_all(dir.path().join("user")).unwrap();
+        let storage = TomlStorage::new(system_root.join(TOML_FILE));
+        storage.save(&system).unwrap();
+
+        let mut manager = installed(&dir.path().join("user"), OnConflict::Ask)
+            .with_system(system_root, Box::new(storage))
+            .unwrap();
+        manager.save().unwrap();
+
+        // The user's packages take precedence over the system's
+        let frontends = manager.get_package("solidity", "frontend");
+        assert_eq!(frontends.len(), 1);
+        assert_eq!(frontends[0].entry.version, "v1.0.0");
+        assert_eq!(manager.get_package("solidity", "backend")[0].name, "baz");
+        assert_eq!(manager.list().unwrap().len(), 2);
+
+        let (_, name, path) = manager.which("solidity", "bar").unwrap();
+        assert_eq!((name.as_str(), path), ("bar", manager.install_path("solidity").join("bar")));
+        let (_, name, path) = manager.which("solidity", "baz").unwrap();
+        assert_eq!((name.as_str(), path), ("baz", system_path.join("baz")));
+
+        // Only the user's packages can be removed, uncovering the system's
+        assert!(matches!(manager.remove("move"), Err(RegistryError::ReadOnly(_))));
+        manager.remove("solidity").unwrap();
+        assert_eq!(manager.get_package("solidity", "frontend")[0].entry.version, "v0.9.0");
+        assert!(matches!(manager.remove("solidity"), Err(RegistryError::ReadOnly(_))));
+    }
+
+    #[test]
+    fn test_archive_name() {
+        let name = |file: &str| archive_name(Path::new(file));
+
+        assert_eq!(
+            name("./solidity-frontend-v0.0.0-dev.tar.gz"),
+            Some(("solidity-frontend".into(), "v0.0.0-dev".into()))
+        );
+        assert_eq!(
+            name(&format!("/tmp/evm-v1.2.0-{}.tar.gz", target_triple::TARGET)),
+            Some(("evm".into(), "v1.2.0".into()))
+        );
+        assert_eq!(name("solidity-vm-v2.tgz"), Some(("solidity-vm".into(), "v2".into())));
+        assert_eq!(name("frontend.tar.gz"), None);
+        assert_eq!(name("frontend-v1.0.0.zip"), None);
+        assert_eq!(name("-v1.0.0.tar.gz"), None);
+    }
+}
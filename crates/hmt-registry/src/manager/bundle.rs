@@ -0,0 +1,158 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use hmt_fetcher::FetchContext;
+use hmt_manifest::{BundleManifest, IndexManifest};
+use hmt_utils::bytes::FromSlice;
+use serde::Serialize;
+
+use crate::{
+    error::Result,
+    manager::{target::Target, Manager},
+    traits::RemoteMetadata,
+};
+
+/// The section of the registry index listing the bundle of each target.
+const BUNDLES: &str = "bundles";
+
+/// The outcome of installing the bundle of a target platform.
+#[derive(Debug, Default, Serialize)]
+pub struct BundleReport {
+    /// The installed packages, in role order.
+    pub installed: Vec<BundlePackage>,
+    /// The roles the registry provides no installable package for.
+    pub missing: Vec<MissingRole>,
+}
+
+/// A package installed for a role of a bundle.
+#[derive(Debug, Serialize)]
+pub struct BundlePackage {
+    /// The role of the package, e.g. `backend`.
+    pub role: String,
+    /// The name of the package.
+    pub name: String,
+    /// The installed version.
+    pub version: String,
+}
+
+/// A role of a bundle left unfilled.
+#[derive(Debug, Serialize)]
+pub struct MissingRole {
+    /// The role, e.g. `sysroot`.
+    pub role: String,
+    /// Why no package was installed for it.
+    pub reason: String,
+}
+
+impl BundleReport {
+    fn missing(&mut self, role: &str, reason: String) {
+        self.missing.push(MissingRole { role: role.to_string(), reason });
+    }
+}
+
+impl Manager<Target> {
+    /// Installs the backend, linker, sysroot and runner of a target
+    /// platform, reporting the roles the registry lacks a package for.
+    ///
+    /// The packages are named by the bundle manifest of the target. Targets
+    /// without one fill each role with a package of the same category in
+    /// their index.
+    pub async fn add_bundle(&mut self, triple: &str) -> Result<BundleReport> {
+        self.policy.check_domain(triple)?;
+
+        let index = self.fetch_index(triple).await?;
+        let bundle = self.fetch_bundle(triple, &index).await?;
+
+        let mut report = BundleReport::default();
+        for role in BundleManifest::ROLES {
+            let Some(name) = bundle.get(role) else {
+                if BundleManifest::REQUIRED.contains(&role) {
+                    report.missing(role, format!("no {role} is published for {triple}"));
+                }
+                continue;
+            };
+            if !index.contains_key(role, name) {
+                report.missing(role, format!("{name} is not listed in the {triple} index"));
+                continue;
+            }
+            if let Err(e) = self.policy.check_category(role) {
+                report.missing(role, e.to_string());
+                continue;
+            }
+
+            let Ok(package) = self.fetch_package(&index, role, name).await else {
+                report.missing(role, format!("{name} failed to fetch"));
+                continue;
+            };
+            let Some(entry) = self.latest_entry(triple, name, &package, &[]).await? else {
+                let reason = format!("{name} has no release for {}", target_triple::TARGET);
+                report.missing(role, reason);
+                continue;
+            };
+
+            let version = entry.version.clone();
+            self.install(triple, role, name, entry).await?;
+            report.installed.push(BundlePackage {
+                role: role.to_string(),
+                name: name.clone(),
+                version,
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Fetches the bundle manifest of a target, inferring it from the target
+    /// index if the registry publishes none.
+    async fn fetch_bundle(&self, triple: &str, index: &IndexManifest) -> Result<BundleManifest> {
+        let registry = self.registry.index().await?;
+        let Some(url) = registry.get(BUNDLES, triple) else {
+            return Ok(infer(index));
+        };
+
+        let bytes = self.registry.fetch_metadata(&FetchContext::new(url)).await?;
+        Ok(BundleManifest::from_slice(&bytes)?)
+    }
+}
+
+/// Fills each role of a bundle with the first package, by name, of the same
+/// category in a target index.
+fn infer(index: &IndexManifest) -> BundleManifest {
+    let first = |role: &str| index.keys(role).map(|(name, _)| name).min().cloned();
+    BundleManifest {
+        backend: first("backend"),
+        linker: first("linker"),
+        sysroot: first("sysroot"),
+        runner: first("runner"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer() {
+        let mut index = IndexManifest::new();
+        index.insert("backend".into(), "llvm-backend".into(), "https://a".into());
+        index.insert("backend".into(), "cranelift-backend".into(), "https://b".into());
+        index.insert("linker".into(), "lld-linker".into(), "https://c".into());
+        index.insert("debugger".into(), "gdb".into(), "https://d".into());
+
+        let bundle = infer(&index);
+        assert_eq!(bundle.backend.as_deref(), Some("cranelift-backend"));
+        assert_eq!(bundle.linker.as_deref(), Some("lld-linker"));
+        assert!(bundle.sysroot.is_none() && bundle.runner.is_none());
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use hmt_manifest::{PackageManifest, PackageSummary};
+use hmt_utils::{bytes::FromSlice, event::warning};
+use semver::Version;
+use serde::Serialize;
+
+use crate::{
+    error::Result,
+    manager::{library::parse, Manager},
+    traits::{PackageKind, Query, RemoteMetadata},
+};
+
+/// The release notes of an outdated package, from the installed version to
+/// the latest.
+#[derive(Debug, Serialize)]
+pub struct Changelog {
+    /// The category of the package.
+    pub category: String,
+    /// The name of the package.
+    pub name: String,
+    /// The installed version.
+    pub from: String,
+    /// The latest version.
+    pub to: String,
+    /// The notes of every release after the installed one, newest first.
+    pub releases: Vec<ReleaseNotes>,
+}
+
+/// The notes of a single release.
+#[derive(Debug, Serialize)]
+pub struct ReleaseNotes {
+    /// The version of the release.
+    pub version: String,
+    /// The release notes, if published in the manifest.
+    pub changelog: Option<String>,
+    /// The URL of the release notes, if published elsewhere.
+    pub changelog_url: Option<String>,
+}
+
+impl<T: PackageKind> Manager<T> {
+    /// Collects the release notes of the outdated packages of a domain,
+    /// covering every release after the installed version up to the
+    /// latest.
+    pub async fn changelog(&self, domain: &str) -> Result<Vec<Changelog>> {
+        self.policy.check_domain(domain)?;
+
+        let Some(installed) = self.get_category(domain) else {
+            return Ok(Vec::new());
+        };
+        let index = self.fetch_index(domain).await?;
+
+        let mut changelogs = Vec::new();
+        for (category, name) in index.entries() {
+            if self.policy.check_category(category).is_err() {
+                continue;
+            }
+            let current = installed.get(category).and_then(|packages| packages.get(name));
+            let Some(current) = current.filter(|entry| entry.source.is_registry()) else {
+                continue;
+            };
+
+            let Ok(bytes) = self.fetch_package_bytes(&index, category, name).await else {
+                self.reporter
+                    .warn(warning::FETCH_FAILED, format!("{name} failed to fetch, skipping"));
+                continue;
+            };
+            if PackageSummary::parse(&bytes)?.latest == current.version {
+                continue;
+            }
+            let package = PackageManifest::from_slice(&bytes)?;
+
+            let mut releases = Vec::new();
+            for version in between(&package, &current.version, &package.latest) {
+                let release = self.fetch_release(&package, &version).await?.release;
+                releases.push(ReleaseNotes {
+                    version,
+                    changelog: release.changelog,
+                    changelog_url: release.changelog_url,
+                });
+            }
+
+            changelogs.push(Changelog {
+                category: category.clone(),
+                name: name.clone(),
+                from: current.version.clone(),
+                to: package.latest.clone(),
+                releases,
+            });
+        }
+
+        Ok(changelogs)
+    }
+}
+
+/// Returns the published versions after `from` up to and including `to`,
+/// newest first. Versions that are not semver are skipped.
+fn between(package: &PackageManifest, from: &str, to: &str) -> Vec<String> {
+    let (Ok(from), Ok(to)) = (parse(from), parse(to)) else {
+        return vec![to.to_string()];
+    };
+
+    let mut versions: Vec<(Version, &String)> = package
+        .get_releases()
+        .keys()
+        .filter_map(|v| parse(v).ok().map(|parsed| (parsed, v)))
+        .filter(|(v, _)| *v > from && *v <= to)
+        .collect();
+    versions.sort_by(|a, b| b.0.cmp(&a.0));
+    versions.into_iter().map(|(_, v)| v.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use hmt_manifest::Package;
+
+    use super::*;
+
+    #[test]
+    fn test_between() {
+        let mut package = PackageManifest::new(Package::default(), "v1.2.0".into());
+        for version in ["v1.0.0", "v1.1.0", "v1.1.1", "v1.2.0", "v2.0.0-rc.1", "nightly"] {
+            package.add_release(version.into(), format!("release-{version}.toml"));
+        }
+
+        assert_eq!(between(&package, "v1.0.0", "v1.2.0"), ["v1.2.0", "v1.1.1", "v1.1.0"]);
+        assert!(between(&package, "v1.2.0", "v1.2.0").is_empty());
+        // The latest release is kept when versions cannot be ordered
+        assert_eq!(between(&package, "nightly", "v1.2.0"), ["v1.2.0"]);
+    }
+}
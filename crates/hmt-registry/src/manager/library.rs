@@ -0,0 +1,192 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, path::PathBuf};
+
+use hmt_fetcher::FetchContext;
+use hmt_manifest::{LockedPackage, ReleaseManifest};
+use hmt_utils::{archive, bytes::FromSlice, checksum, temp::TempDir};
+use semver::{Version, VersionReq};
+
+use super::{
+    base::{check_file_name, unpack_error},
+    Manager,
+};
+
+use crate::{
+    error::{RegistryError, Result},
+    traits::{PackageKind, RemoteMetadata},
+};
+
+/// The category and artifact key under which source packages are
+/// published, since they do not depend on the target platform.
+pub const SOURCE_ARTIFACT: &str = "source";
+
+pub type LibraryManager = Manager<Library>;
+pub struct Library;
+
+impl PackageKind for Library {
+    fn kind() -> &'static str {
+        "libraries"
+    }
+}
+
+impl Manager<Library> {
+    /// Resolves the newest published release of a library matching the
+    /// version requirement.
+    pub async fn resolve(&self, name: &str, req: &str) -> Result<LockedPackage> {
+        let index = self.fetch_index(name).await?;
+        let package = self.fetch_package(&index, SOURCE_ARTIFACT, name).await?;
+
+        let version = package
+            .get_releases()
+            .keys()
+            .filter(|version| matches(version, req).unwrap_or(false))
+            .max_by_key(|version| parse(version).ok())
+            .ok_or_else(|| RegistryError::ReleaseNotFound(name.to_string(), req.to_string()))?;
+
+        // Keep the raw manifest, so its digest can be pinned in the lockfile
+        let url = self.release_url(&package, version)?;
+        let bytes = self.registry.fetch_metadata(&FetchContext::new(&url)).await?;
+        let release = ReleaseManifest::from_slice(&bytes)?;
+        let artifact = release
+            .get_artifact(SOURCE_ARTIFACT)
+            .ok_or_else(|| RegistryError::PackageNotFound(format!("{name} {version} source")))?;
+        self.policy.check_signature(&package.package, artifact)?;
+        self.verify_provenance(&package, version, artifact).await?;
+
+        Ok(LockedPackage::new(name, version, &artifact.url, &artifact.hash)
+            .manifest(&checksum::digest(&bytes)))
+    }
+
+    /// Checks that the registry still serves the release manifest a library
+    /// was locked from. The metadata cache is bypassed, and packages locked
+    /// without a manifest digest are accepted.
+    pub async fn verify(&self, package: &LockedPackage) -> Result<()> {
+        let Some(expected) = &package.manifest else {
+            return Ok(());
+        };
+
+        let index = self.fetch_index(&package.name).await?;
+        let manifest = self.fetch_package(&index, SOURCE_ARTIFACT, &package.name).await?;
+        let url = self.release_url(&manifest, &package.version)?;
+        let bytes = self.registry.fetch(&FetchContext::new(&url).metadata()).await?;
+
+        if checksum::digest(&bytes) != *expected {
+            return Err(RegistryError::ManifestChanged(
+                package.name.clone(),
+                package.version.clone(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the directory of a locked library if it is already cached,
+    /// in the install root or else the system-wide installation.
+    pub fn cached_source(&self, package: &LockedPackage) -> Result<Option<PathBuf>> {
+        check_package(package)?;
+        let system = self.system_root.as_ref().map(|root| root.join(Library::kind()));
+        Ok(std::iter::once(self.install_root.join(Library::kind()))
+            .chain(system)
+            .map(|dir| dir.join(&package.name).join(&package.version))
+            .find(|path| path.exists()))
+    }
+
+    /// Downloads and unpacks a locked library into the cache, returning its
+    /// directory. Cached libraries are returned without fetching.
+    pub async fn fetch_source(&self, package: &LockedPackage) -> Result<PathBuf> {
+        if let Some(path) = self.cached_source(package)? {
+            return Ok(path);
+        }
+
+        let dir = self.source_dir(package)?;
+        let path = dir.join(&package.version);
+
+        let context = FetchContext::new(&package.source).checksum(&package.checksum);
+        self.check_download(&context, None, &dir).await?;
+        let stream = self.registry.open(&context).await?;
+
+        // Unpack into a staging directory first, so an interrupted
+        // download never leaves a partial library in the cache.
+        let staging = TempDir::new_in(&self.temp_dir())?;
+        archive::unpack_async(stream, staging.path().to_path_buf())
+            .await
+            .map_err(|e| unpack_error(&package.name, &*e))?;
+        fs::create_dir_all(&dir)?;
+        staging.persist(&path)?;
+
+        Ok(path)
+    }
+
+    /// Returns the cache directory holding all versions of a library.
+    fn source_dir(&self, package: &LockedPackage) -> Result<PathBuf> {
+        check_package(package)?;
+        Ok(self.install_root.join(Library::kind()).join(&package.name))
+    }
+}
+
+/// Checks that the name and version of a locked library are single path
+/// components, as the lockfile may have been edited by hand.
+fn check_package(package: &LockedPackage) -> Result<()> {
+    check_file_name("library", &package.name)?;
+    check_file_name("library version", &package.version)
+}
+
+/// Checks whether a version, optionally prefixed with `v`, satisfies a
+/// semver requirement.
+pub fn matches(version: &str, req: &str) -> Result<bool> {
+    let req = VersionReq::parse(req)
+        .map_err(|e| RegistryError::Other(format!("invalid version requirement '{req}': {e}")))?;
+
+    Ok(req.matches(&parse(version)?))
+}
+
+/// Parses a version, optionally prefixed with `v`.
+pub(super) fn parse(version: &str) -> Result<Version> {
+    Version::parse(version.trim_start_matches('v'))
+        .map_err(|e| RegistryError::Other(format!("invalid version '{version}': {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RegistryClient;
+
+    #[tokio::test]
+    async fn test_fetch_source_rejects_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = RegistryClient::new("file:///registry");
+        let manager = LibraryManager::new(registry, dir.path().join("home"));
+
+        for (name, version) in [("../escape", "v1.0.0"), ("foo", "../../escape"), ("foo", "")] {
+            let package = LockedPackage::new(name, version, "file:///foo.tar.gz", "00");
+            let result = manager.fetch_source(&package).await;
+            assert!(matches!(result, Err(RegistryError::InvalidPath(_))), "{name} {version}");
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("v1.2.3", "^1.2").unwrap());
+        assert!(matches("1.2.3", "=1.2.3").unwrap());
+        assert!(!matches("v2.0.0", "^1.2").unwrap());
+    }
+
+    #[test]
+    fn test_matches_invalid() {
+        assert!(matches("latest", "^1.2").is_err());
+        assert!(matches("v1.0.0", "not a requirement").is_err());
+    }
+}
@@ -13,10 +13,18 @@
 // limitations under the License.
 
 mod base;
+mod bundle;
+mod changelog;
+mod library;
+mod runtime;
 mod target;
 mod toolchain;
 
 // Re-exports
-pub use base::Manager;
-pub use target::TargetManager;
-pub use toolchain::ToolchainManager;
+pub use base::{Manager, Update};
+pub use bundle::{BundlePackage, BundleReport, MissingRole};
+pub use changelog::{Changelog, ReleaseNotes};
+pub use library::{matches, Library, LibraryManager, SOURCE_ARTIFACT};
+pub use runtime::RUNTIMES;
+pub use target::{Target, TargetManager};
+pub use toolchain::{Toolchain, ToolchainManager};
@@ -0,0 +1,350 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shared runtime packages.
+//!
+//! Packages declaring `runtimes` leave large common files out of their own
+//! archives. Each runtime is installed once per domain into
+//! `<root>/runtimes/<domain>/<name>`, for every package of the domain using
+//! it, and recorded in the cache under the `runtimes` kind. The release
+//! installed is the newest satisfying the version requirements of all those
+//! packages, and a runtime is removed with the last package using it.
+
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use hmt_manifest::{
+    Entry, IndexManifest, PackageEntry, PackageManifest, ReleaseManifest, RUNTIME_CATEGORY,
+};
+use hmt_utils::{archive, temp::TempDir};
+use tracing::error;
+
+use crate::{
+    error::{RegistryError, Result},
+    manager::{
+        base::{check_file_name, unpack_error},
+        library::{matches, parse},
+        Manager, Update,
+    },
+    traits::{PackageKind, RemoteMetadata},
+};
+
+/// The kind shared runtimes are installed and recorded under.
+pub const RUNTIMES: &str = "runtimes";
+
+impl<T: PackageKind> Manager<T> {
+    /// Installs the runtimes a package of a domain uses, from the `runtime`
+    /// category of the domain index, unless the installed release already
+    /// satisfies the requirement of the package.
+    pub(super) async fn install_runtimes(
+        &mut self,
+        index: &IndexManifest,
+        domain: &str,
+        runtimes: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        for (name, req) in runtimes {
+            let installed = self.runtime(domain, name);
+            if installed.is_some_and(|entry| matches(&entry.version, req).unwrap_or(false)) {
+                continue;
+            }
+
+            let mut reqs = self.runtime_requirements(domain, name);
+            reqs.push(req.clone());
+            let (package, release) = self.resolve_runtime(index, name, &reqs).await?;
+            let entry = self.runtime_entry(domain, &package, &release).await?;
+            self.install_runtime(domain, name, entry).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Downloads the runtimes a package of a domain uses into the download
+    /// cache, like [`Manager::install_runtimes`] would install them. Returns
+    /// how many artifacts were downloaded.
+    pub(super) async fn prefetch_runtimes(
+        &self,
+        index: &IndexManifest,
+        domain: &str,
+        runtimes: &BTreeMap<String, String>,
+    ) -> Result<usize> {
+        let mut downloaded = 0;
+        for (name, req) in runtimes {
+            let installed = self.runtime(domain, name);
+            if installed.is_some_and(|entry| matches(&entry.version, req).unwrap_or(false)) {
+                continue;
+            }
+
+            let mut reqs = self.runtime_requirements(domain, name);
+            reqs.push(req.clone());
+            let (package, release) = self.resolve_runtime(index, name, &reqs).await?;
+            let entry = self.runtime_entry(domain, &package, &release).await?;
+            downloaded += usize::from(self.download(&entry).await?);
+        }
+
+        Ok(downloaded)
+    }
+
+    /// Returns the installed runtimes of a domain that have a newer release
+    /// satisfying the requirements of every package using them.
+    pub(super) async fn runtime_updates(
+        &self,
+        index: &IndexManifest,
+        domain: &str,
+    ) -> Result<Vec<Update>> {
+        let Some(installed) = self
+            .cache
+            .get_package(RUNTIMES, domain, RUNTIME_CATEGORY)
+            .filter(|runtimes| !runtimes.is_empty())
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut updates = Vec::new();
+        for (name, current) in installed {
+            let reqs = self.runtime_requirements(domain, name);
+            let (package, release) = self.resolve_runtime(index, name, &reqs).await?;
+            if parse(&release.release.version)? <= parse(&current.version)? {
+                continue;
+            }
+
+            let old = self.fetch_release(&package, &current.version).await.ok();
+            let mut diff = release.diff(old.as_ref(), target_triple::TARGET);
+            if old.is_none() {
+                diff.from = Some(current.version.clone());
+                diff.size_delta = None;
+            }
+            let entry = self.runtime_entry(domain, &package, &release).await?;
+            updates.push(Update {
+                category: RUNTIME_CATEGORY.to_string(),
+                name: name.clone(),
+                diff,
+                entry,
+            });
+        }
+
+        Ok(updates)
+    }
+
+    /// Resolves the newest release of a runtime satisfying every
+    /// requirement.
+    async fn resolve_runtime(
+        &self,
+        index: &IndexManifest,
+        name: &str,
+        reqs: &[String],
+    ) -> Result<(PackageManifest, ReleaseManifest)> {
+        let package = self.fetch_package(index, RUNTIME_CATEGORY, name).await?;
+        let version = package
+            .get_releases()
+            .keys()
+            .filter(|version| reqs.iter().all(|req| matches(version, req).unwrap_or(false)))
+            .max_by_key(|version| parse(version).ok())
+            .ok_or_else(|| RegistryError::ReleaseNotFound(name.to_string(), reqs.join(", ")))?;
+        let release = self.fetch_release(&package, version).await?;
+
+        Ok((package, release))
+    }
+
+    /// Resolves a release of a runtime to an entry installable on the
+    /// current platform. The signature and provenance of the artifact are
+    /// checked like those of packages.
+    async fn runtime_entry(
+        &self,
+        domain: &str,
+        package: &PackageManifest,
+        release: &ReleaseManifest,
+    ) -> Result<Entry> {
+        let (name, version) = (&package.package.name, &release.release.version);
+        let artifact = release.get_artifact(target_triple::TARGET).ok_or_else(|| {
+            RegistryError::Other(format!(
+                "Runtime {name} {version} does not support the current target platform"
+            ))
+        })?;
+        self.policy.check_signature(&package.package, artifact)?;
+        self.verify_provenance(package, version, artifact).await?;
+
+        let entry = Entry::new(
+            version.clone(),
+            package.package.description.clone(),
+            self.runtime_path(domain, name)?,
+        )
+        .artifact(&artifact.url, &artifact.hash);
+        Ok(Entry { size: artifact.size, ..entry })
+    }
+
+    /// Fetches and unpacks a release of a runtime into its installation
+    /// path, replacing the release installed before, and records it.
+    pub(super) async fn install_runtime(
+        &mut self,
+        domain: &str,
+        name: &str,
+        mut entry: Entry,
+    ) -> Result<()> {
+        let (Some(url), Some(hash)) = (&entry.url, &entry.hash) else {
+            return Err(RegistryError::Other(format!("{name} has no artifact to install")));
+        };
+
+        let path = self.runtime_path(domain, name)?;
+        let context = self.artifact_context(url, hash)?;
+        self.check_download(&context, entry.size, &path).await?;
+        let stream = self.registry.open(&context).await?;
+
+        let staging = TempDir::new_in(&self.temp_dir())?;
+        archive::unpack_async(stream, staging.path().to_path_buf())
+            .await
+            .map_err(|e| unpack_error(name, &*e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        staging.persist(&path)?;
+
+        self.cache = self.storage.load()?;
+        entry.path = path;
+        entry.order = self.cache.next_order();
+        self.cache.insert(RUNTIMES, domain, RUNTIME_CATEGORY, name, entry);
+        self.save()
+    }
+
+    /// Returns the installed runtime of a domain, from the install root or
+    /// else the system-wide installation.
+    pub fn runtime(&self, domain: &str, name: &str) -> Option<&Entry> {
+        self.installed.get_package(RUNTIMES, domain, RUNTIME_CATEGORY)?.get(name)
+    }
+
+    /// Returns the installed runtimes of a domain among `names`, the
+    /// runtimes a package uses. Tools find them through the environment.
+    pub fn package_runtimes<'a>(
+        &self,
+        domain: &str,
+        names: impl IntoIterator<Item = &'a String>,
+    ) -> Vec<PackageEntry> {
+        let domain = domain.to_lowercase();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                Some(PackageEntry::new(name.clone(), self.runtime(&domain, name)?.clone()))
+            })
+            .collect()
+    }
+
+    /// Returns the version requirements the installed packages of a domain,
+    /// of any kind, place on a runtime, in sorted order.
+    pub fn runtime_requirements(&self, domain: &str, name: &str) -> Vec<String> {
+        let mut reqs: Vec<String> = self
+            .cache
+            .entries()
+            .filter(|(kind, package_domain, ..)| *kind != RUNTIMES && *package_domain == domain)
+            .filter_map(|(.., entry)| entry.runtimes.get(name).cloned())
+            .collect();
+        reqs.sort();
+        reqs
+    }
+
+    /// Returns the number of installed packages of a domain, of any kind,
+    /// using a runtime.
+    pub fn runtime_references(&self, domain: &str, name: &str) -> usize {
+        self.runtime_requirements(domain, name).len()
+    }
+
+    /// Removes the installed runtimes no installed package uses anymore,
+    /// returning their domains and names. The cache is left for the caller
+    /// to save.
+    pub(super) fn remove_unused_runtimes(&mut self) -> Result<Vec<(String, String)>> {
+        let mut unused: Vec<(String, String)> = self
+            .cache
+            .entries()
+            .filter(|(kind, ..)| *kind == RUNTIMES)
+            .filter(|(_, domain, _, name, _)| self.runtime_references(domain, name) == 0)
+            .map(|(_, domain, _, name, _)| (domain.to_string(), name.to_string()))
+            .collect();
+        unused.sort();
+
+        for (domain, name) in &unused {
+            let path = self.runtime_path(domain, name)?;
+            if path.exists() {
+                fs::remove_dir_all(&path).map_err(|e| {
+                    error!("Failed to remove runtime '{name}': {e}");
+                    RegistryError::RemoveError(name.to_string())
+                })?;
+            }
+            self.cache.remove(RUNTIMES, domain, RUNTIME_CATEGORY, name);
+        }
+
+        Ok(unused)
+    }
+
+    /// Returns the installation path of a shared runtime of a domain. The
+    /// name comes from package manifests, so it must be a single path
+    /// component.
+    pub fn runtime_path(&self, domain: &str, name: &str) -> Result<PathBuf> {
+        check_file_name("runtime", name)?;
+        Ok(self.install_root.join(RUNTIMES).join(domain).join(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hmt_manifest::Entry;
+
+    use super::*;
+    use crate::{manager::Toolchain, RegistryClient};
+
+    #[test]
+    fn test_runtime_path_rejects_paths() {
+        let registry = RegistryClient::new("file:///registry");
+        let manager = Manager::<Toolchain>::new(registry, PathBuf::from("home"));
+
+        assert!(manager.runtime_path("solidity", "llvm").is_ok());
+        for name in ["..", "../../bin", "llvm/lib", ""] {
+            let result = manager.runtime_path("solidity", name);
+            assert!(matches!(result, Err(RegistryError::InvalidPath(_))), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_remove_unused_runtimes() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = RegistryClient::new("file:///registry");
+        let mut manager = Manager::<Toolchain>::new(registry, dir.path().to_path_buf());
+
+        for (domain, name) in [("solidity", "llvm"), ("solidity", "wasm"), ("move", "llvm")] {
+            let path = manager.runtime_path(domain, name).unwrap();
+            fs::create_dir_all(&path).unwrap();
+            let entry = Entry::new("v17.0.0".into(), None, path);
+            manager.cache.insert(RUNTIMES, domain, RUNTIME_CATEGORY, name, entry);
+        }
+        for (kind, req) in [("toolchains", "^17"), ("targets", ">=17.0.0")] {
+            let mut entry = Entry::new("v1.0.0".into(), None, PathBuf::new());
+            entry.runtimes = BTreeMap::from([("llvm".to_string(), req.to_string())]);
+            manager.cache.insert(kind, "solidity", "frontend", "foo", entry);
+        }
+        assert_eq!(manager.runtime_requirements("solidity", "llvm"), [">=17.0.0", "^17"]);
+        assert_eq!(manager.runtime_references("solidity", "wasm"), 0);
+        assert_eq!(manager.runtime_references("move", "llvm"), 0);
+
+        // Runtimes are kept per domain until their last user is removed
+        let unused = |domain: &str, name: &str| (domain.to_string(), name.to_string());
+        assert_eq!(
+            manager.remove_unused_runtimes().unwrap(),
+            [unused("move", "llvm"), unused("solidity", "wasm")]
+        );
+        assert!(!manager.runtime_path("solidity", "wasm").unwrap().exists());
+        assert!(manager.runtime_path("solidity", "llvm").unwrap().exists());
+        manager.cache.remove_domain("toolchains", "solidity");
+        assert!(manager.remove_unused_runtimes().unwrap().is_empty());
+        manager.cache.remove_domain("targets", "solidity");
+        assert_eq!(manager.remove_unused_runtimes().unwrap(), [unused("solidity", "llvm")]);
+        assert!(!manager.runtime_path("solidity", "llvm").unwrap().exists());
+        assert!(manager.cache.entries().all(|(kind, ..)| kind != RUNTIMES));
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use hmt_manifest::{Artifact, Package};
+use hmt_utils::signature;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conflict::OnConflict,
+    error::{RegistryError, Result},
+};
+
+/// Installation policy enforced by package managers.
+///
+/// Example:
+/// ```toml
+/// [policy]
+/// allowed-domains = ["solidity", "move"]
+/// blocked-categories = ["detector"]
+/// require-signatures = true
+/// trusted-keys = ["9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"]
+/// require-provenance = true
+/// provenance-keys = ["d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"]
+/// on-conflict = "rename"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Policy {
+    /// Domains that may be installed. All domains are allowed when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_domains: Option<Vec<String>>,
+
+    /// Package categories that must never be installed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocked_categories: Vec<String>,
+
+    /// Refuse artifacts that are not signed by a trusted maintainer of the
+    /// package.
+    pub require_signatures: bool,
+
+    /// The fingerprints or hex-encoded Ed25519 public keys of the maintainers
+    /// trusted to sign artifacts. Without any, required signatures always
+    /// fail, since the keys in package manifests come from the registry.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trusted_keys: Vec<String>,
+
+    /// Refuse artifacts without a provenance statement proving they were
+    /// built from the tagged source of the package repository.
+    pub require_provenance: bool,
+
+    /// The hex-encoded Ed25519 public keys of the builders trusted to sign
+    /// provenance statements. Without any, required provenance always fails.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub provenance_keys: Vec<String>,
+
+    /// What to do when a package ships a file another package of the same
+    /// domain already installed.
+    pub on_conflict: OnConflict,
+}
+
+impl Policy {
+    /// Checks that the given domain may be installed.
+    pub fn check_domain(&self, domain: &str) -> Result<()> {
+        match &self.allowed_domains {
+            Some(allowed) if !allowed.iter().any(|d| d.eq_ignore_ascii_case(domain)) => {
+                Err(RegistryError::PolicyViolation(format!(
+                    "domain '{domain}' is not in the allowed domains"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that packages of the given category may be installed.
+    pub fn check_category(&self, category: &str) -> Result<()> {
+        if self.blocked_categories.iter().any(|c| c.eq_ignore_ascii_case(category)) {
+            return Err(RegistryError::PolicyViolation(format!("category '{category}' is blocked")));
+        }
+        Ok(())
+    }
+
+    /// Checks that an artifact satisfies the signature requirement: its
+    /// signature of the artifact hash must verify against the public key of
+    /// a maintainer of the package that the policy trusts. Keys not matching
+    /// the fingerprint of their maintainer are ignored.
+    pub fn check_signature(&self, package: &Package, artifact: &Artifact) -> Result<()> {
+        if !self.require_signatures {
+            return Ok(());
+        }
+
+        let name = &package.name;
+        let Some(sig) = artifact.signature.as_deref().filter(|sig| !sig.is_empty()) else {
+            return Err(RegistryError::PolicyViolation(format!(
+                "{name} is not signed, but signatures are required"
+            )));
+        };
+
+        let signed = package
+            .maintainers
+            .iter()
+            .filter_map(|maintainer| {
+                let key = maintainer.public_key.as_deref()?;
+                let fingerprint = signature::fingerprint_hex(key).ok()?;
+                let trusted = self.trusted_keys.iter().any(|trusted| {
+                    trusted.eq_ignore_ascii_case(key) || trusted.eq_ignore_ascii_case(&fingerprint)
+                });
+                (trusted && fingerprint.eq_ignore_ascii_case(&maintainer.fingerprint))
+                    .then_some(key)
+            })
+            .any(|key| signature::verify(key, artifact.hash.as_bytes(), sig).is_ok());
+        if !signed {
+            return Err(RegistryError::PolicyViolation(format!(
+                "the signature of {name} does not verify against the key of any trusted maintainer"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that an artifact satisfies the provenance requirement.
+    pub fn check_provenance(&self, name: &str, provenance: Option<&str>) -> Result<()> {
+        if self.require_provenance && matches!(provenance, None | Some("")) {
+            return Err(RegistryError::PolicyViolation(format!(
+                "{name} has no provenance, but provenance is required"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that a package from outside the registry, with no signature
+    /// or provenance to verify, may be installed. Such packages are refused
+    /// when either is required, unless `allow_unverified` overrides it.
+    pub fn check_unverified(&self, name: &str, allow_unverified: bool) -> Result<()> {
+        if allow_unverified || !(self.require_signatures || self.require_provenance) {
+            return Ok(());
+        }
+        Err(RegistryError::PolicyViolation(format!(
+            "{name} is not from the registry and cannot be verified, but signatures or \
+             provenance are required; pass --allow-unverified to install it anyway"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hmt_manifest::Maintainer;
+    use hmt_utils::signature::SigningKey;
+
+    use super::*;
+
+    fn artifact(signature: Option<String>) -> Artifact {
+        Artifact {
+            url: "https://example.com/foo.tar.gz".into(),
+            hash: "abc123".into(),
+            signature,
+            size: None,
+            provenance: None,
+        }
+    }
+
+    fn package(key: &SigningKey) -> Package {
+        Package {
+            name: "foo".into(),
+            maintainers: vec![Maintainer {
+                name: "Jane Doe".into(),
+                contact: None,
+                fingerprint: key.fingerprint(),
+                public_key: Some(key.public_key()),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_allows_everything() {
+        let policy = Policy::default();
+        assert!(policy.check_domain("solidity").is_ok());
+        assert!(policy.check_category("detector").is_ok());
+        let key = SigningKey::from_seed([7; 32]);
+        assert!(policy.check_signature(&package(&key), &artifact(None)).is_ok());
+    }
+
+    #[test]
+    fn test_allowed_domains() {
+        let policy =
+            Policy { allowed_domains: Some(vec!["solidity".to_string()]), ..Default::default() };
+        assert!(policy.check_domain("Solidity").is_ok());
+        assert!(matches!(policy.check_domain("move"), Err(RegistryError::PolicyViolation(_))));
+    }
+
+    #[test]
+    fn test_blocked_categories() {
+        let policy =
+            Policy { blocked_categories: vec!["detector".to_string()], ..Default::default() };
+        assert!(policy.check_category("compiler").is_ok());
+        assert!(policy.check_category("detector").is_err());
+    }
+
+    #[test]
+    fn test_require_signatures() {
+        let key = SigningKey::from_seed([7; 32]);
+        let policy = Policy {
+            require_signatures: true,
+            trusted_keys: vec![key.fingerprint()],
+            ..Default::default()
+        };
+        let package = package(&key);
+        assert!(policy.check_signature(&package, &artifact(Some(key.sign(b"abc123")))).is_ok());
+        assert!(policy.check_signature(&package, &artifact(Some("sig".into()))).is_err());
+        assert!(policy.check_signature(&package, &artifact(Some(String::new()))).is_err());
+        assert!(policy.check_signature(&package, &artifact(None)).is_err());
+
+        // Signed by someone else
+        let other = SigningKey::from_seed([8; 32]);
+        assert!(policy.check_signature(&package, &artifact(Some(other.sign(b"abc123")))).is_err());
+
+        // A key that does not match the maintainer's fingerprint is ignored
+        let mut forged = package.clone();
+        forged.maintainers[0].public_key = Some(other.public_key());
+        assert!(policy.check_signature(&forged, &artifact(Some(other.sign(b"abc123")))).is_err());
+
+        // Packages without maintainer keys fail closed
+        let mut unkeyed = package;
+        unkeyed.maintainers.clear();
+        assert!(policy.check_signature(&unkeyed, &artifact(Some(key.sign(b"abc123")))).is_err());
+    }
+
+    #[test]
+    fn test_require_trusted_keys() {
+        let key = SigningKey::from_seed([7; 32]);
+        let signed = artifact(Some(key.sign(b"abc123")));
+
+        // A manifest consistent with its own key is not enough
+        let policy = Policy { require_signatures: true, ..Default::default() };
+        assert!(policy.check_signature(&package(&key), &signed).is_err());
+        let other = SigningKey::from_seed([8; 32]);
+        let policy = Policy { trusted_keys: vec![other.fingerprint()], ..policy };
+        assert!(policy.check_signature(&package(&key), &signed).is_err());
+
+        // Maintainers are trusted by fingerprint or by key
+        let policy = Policy { trusted_keys: vec![key.public_key().to_uppercase()], ..policy };
+        assert!(policy.check_signature(&package(&key), &signed).is_ok());
+    }
+
+    #[test]
+    fn test_require_provenance() {
+        let policy = Policy { require_provenance: true, ..Default::default() };
+        assert!(policy
+            .check_provenance("foo", Some("https://example.com/foo.intoto.json"))
+            .is_ok());
+        assert!(policy.check_provenance("foo", None).is_err());
+        assert!(Policy::default().check_provenance("foo", None).is_ok());
+    }
+
+    #[test]
+    fn test_check_unverified() {
+        assert!(Policy::default().check_unverified("foo", false).is_ok());
+        for policy in [
+            Policy { require_signatures: true, ..Default::default() },
+            Policy { require_provenance: true, ..Default::default() },
+        ] {
+            let result = policy.check_unverified("foo", false);
+            assert!(matches!(result, Err(RegistryError::PolicyViolation(_))));
+            assert!(policy.check_unverified("foo", true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy: Policy = toml::from_str(
+            r#"
+            allowed-domains = ["solidity"]
+            blocked-categories = ["detector"]
+            require-signatures = true
+            trusted-keys = ["abc123"]
+            on-conflict = "skip"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(policy.trusted_keys, ["abc123"]);
+        assert_eq!(policy.allowed_domains, Some(vec!["solidity".to_string()]));
+        assert_eq!(policy.blocked_categories, vec!["detector".to_string()]);
+        assert!(policy.require_signatures);
+        assert_eq!(policy.on_conflict, OnConflict::Skip);
+        assert_eq!(Policy::default().on_conflict, OnConflict::Ask);
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+
+use hmt_manifest::{InstalledManifest, ManifestFile};
+
+use super::Storage;
+use crate::error::Result;
+
+/// Stores installed packages in a single TOML file.
+pub struct TomlStorage {
+    path: PathBuf,
+}
+
+impl TomlStorage {
+    /// Creates a storage backed by the TOML file at the given path.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl Storage for TomlStorage {
+    fn load(&self) -> Result<InstalledManifest> {
+        if !self.path.exists() {
+            return Ok(InstalledManifest::new());
+        }
+        Ok(InstalledManifest::load(&self.path)?)
+    }
+
+    fn save(&self, manifest: &InstalledManifest) -> Result<()> {
+        Ok(manifest.save(&self.path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmt_manifest::Entry;
+
+    #[test]
+    fn test_toml_storage_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = TomlStorage::new(dir.path().join("installed.toml"));
+        assert!(storage.load().unwrap().as_map().is_empty());
+
+        let mut manifest = InstalledManifest::new();
+        let entry = Entry::new("v1.0.0".to_string(), None, PathBuf::from("/tmp/foo"));
+        manifest.insert("toolchains", "solidity", "compiler", "foo", entry);
+        storage.save(&manifest).unwrap();
+
+        let loaded = storage.load().unwrap();
+        assert!(loaded.contains("toolchains", "solidity", "compiler", "foo"));
+    }
+}
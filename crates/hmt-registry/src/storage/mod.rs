@@ -0,0 +1,94 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage backends for the installed package cache.
+
+mod file;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use std::path::Path;
+
+use hmt_manifest::InstalledManifest;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+pub use file::TomlStorage;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+/// The file name of the TOML cache.
+pub const TOML_FILE: &str = "installed.toml";
+
+/// The file name of the SQLite cache.
+pub const SQLITE_FILE: &str = "installed.db";
+
+/// Persists the set of installed packages.
+pub trait Storage: Send + Sync {
+    /// Loads the installed packages, returning an empty manifest if
+    /// nothing has been stored yet.
+    fn load(&self) -> Result<InstalledManifest>;
+
+    /// Replaces the stored packages with the given manifest.
+    fn save(&self, manifest: &InstalledManifest) -> Result<()>;
+}
+
+/// The available storage backends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    /// A single `installed.toml` file.
+    #[default]
+    Toml,
+    /// A single `installed.db` SQLite database, requires the `sqlite` feature.
+    Sqlite,
+}
+
+/// Opens the storage backend of the given kind under the install root.
+pub fn open(kind: StorageKind, install_root: &Path) -> Result<Box<dyn Storage>> {
+    match kind {
+        StorageKind::Toml => Ok(Box::new(TomlStorage::new(install_root.join(TOML_FILE)))),
+        #[cfg(feature = "sqlite")]
+        StorageKind::Sqlite => Ok(Box::new(SqliteStorage::open(
+            install_root.join(SQLITE_FILE),
+            install_root.join(TOML_FILE),
+        )?)),
+        #[cfg(not(feature = "sqlite"))]
+        StorageKind::Sqlite => Err(crate::error::RegistryError::Other(
+            "the sqlite storage backend is not enabled in this build".to_string(),
+        )),
+    }
+}
+
+/// Opens the storage backend of the given kind under an install root that
+/// is only read, such as a system-wide installation. Nothing is created,
+/// migrated or written.
+pub fn open_read_only(kind: StorageKind, install_root: &Path) -> Result<Box<dyn Storage>> {
+    #[cfg(feature = "sqlite")]
+    if kind == StorageKind::Sqlite {
+        return Ok(Box::new(SqliteStorage::open_read_only(install_root.join(SQLITE_FILE))));
+    }
+    open(kind, install_root)
+}
+
+/// Returns the kind of the storage found under an install root: the
+/// database if one exists, and the TOML file otherwise. Used for roots that
+/// are only read, such as a system-wide installation.
+pub fn detect(install_root: &Path) -> StorageKind {
+    match install_root.join(SQLITE_FILE).exists() {
+        true => StorageKind::Sqlite,
+        false => StorageKind::Toml,
+    }
+}
@@ -0,0 +1,387 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use hmt_manifest::{Entry, InstalledManifest, Stage};
+use hmt_utils::{path, temp::TempFile};
+use rusqlite::{params, Connection, OpenFlags};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::info;
+
+use super::{Storage, TomlStorage};
+use crate::error::{RegistryError, Result};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS installed (
+    kind        TEXT NOT NULL,
+    domain      TEXT NOT NULL,
+    category    TEXT NOT NULL,
+    name        TEXT NOT NULL,
+    version     TEXT NOT NULL,
+    description TEXT,
+    path        TEXT NOT NULL,
+    url         TEXT,
+    hash        TEXT,
+    seq         INTEGER NOT NULL DEFAULT 0,
+    stage_order  INTEGER,
+    stage_input  TEXT,
+    stage_output TEXT,
+    source      TEXT,
+    capabilities TEXT,
+    components  TEXT,
+    files       TEXT,
+    conflicts   TEXT,
+    runtimes    TEXT,
+    PRIMARY KEY (kind, domain, category, name)
+);
+DROP INDEX IF EXISTS installed_kind_category;
+DROP INDEX IF EXISTS installed_name_version;
+";
+
+/// The columns loaded, in the order they are read.
+const COLUMNS: [&str; 19] = [
+    "kind",
+    "domain",
+    "category",
+    "name",
+    "version",
+    "description",
+    "path",
+    "url",
+    "hash",
+    "seq",
+    "stage_order",
+    "stage_input",
+    "stage_output",
+    "source",
+    "capabilities",
+    "components",
+    "files",
+    "conflicts",
+    "runtimes",
+];
+
+/// Columns added after the initial schema, with their definitions.
+const ADDED_COLUMNS: [(&str, &str); 12] = [
+    ("url", "TEXT"),
+    ("hash", "TEXT"),
+    ("seq", "INTEGER NOT NULL DEFAULT 0"),
+    ("stage_order", "INTEGER"),
+    ("stage_input", "TEXT"),
+    ("stage_output", "TEXT"),
+    ("source", "TEXT"),
+    ("capabilities", "TEXT"),
+    ("components", "TEXT"),
+    ("files", "TEXT"),
+    ("conflicts", "TEXT"),
+    ("runtimes", "TEXT"),
+];
+
+/// Stores installed packages in a SQLite database, one row per package
+/// keyed by its kind, domain, category and name.
+///
+/// The whole cache is loaded and saved at once, like the TOML file, so
+/// there are no further indexes to maintain.
+///
+/// A connection is opened per operation, so the storage can be shared
+/// between threads.
+pub struct SqliteStorage {
+    path: PathBuf,
+    read_only: bool,
+}
+
+impl SqliteStorage {
+    /// Opens the database at `path`, creating the schema if needed.
+    ///
+    /// When the database does not exist yet and a legacy TOML cache is
+    /// found at `legacy`, its entries are imported and the file is kept
+    /// as `<legacy>.bak`. The import is written to a temporary database
+    /// renamed into place once complete, so a failed import is retried
+    /// rather than leaving an empty database behind.
+    pub fn open(path: PathBuf, legacy: PathBuf) -> Result<Self> {
+        if !path.exists() && legacy.exists() {
+            let manifest = TomlStorage::new(legacy.clone()).load()?;
+            let parent = path.parent().unwrap_or(Path::new("."));
+            let staging = TempFile::new_in(parent)?;
+            Self { path: staging.path().to_path_buf(), read_only: false }.save(&manifest)?;
+            staging.persist(&path)?;
+            fs::rename(&legacy, legacy.with_extension("toml.bak"))?;
+            info!("Migrated {} to {}", legacy.display(), path.display());
+        }
+
+        let storage = Self { path, read_only: false };
+        storage.connect()?;
+        Ok(storage)
+    }
+
+    /// Opens the database at `path` for reading only, as for a system-wide
+    /// installation of another user. Nothing is created or migrated, and
+    /// columns missing from an older schema are read as empty.
+    pub fn open_read_only(path: PathBuf) -> Self {
+        Self { path, read_only: true }
+    }
+
+    /// Opens a connection and ensures the schema is up to date, unless the
+    /// storage is read only.
+    fn connect(&self) -> Result<Connection> {
+        if self.read_only {
+            let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+            return Ok(Connection::open_with_flags(&self.path, flags)?);
+        }
+
+        let conn = Connection::open(&self.path)?;
+        conn.execute_batch(SCHEMA)?;
+
+        let columns = columns(&conn)?;
+        for (column, definition) in ADDED_COLUMNS {
+            if !columns.iter().any(|c| c == column) {
+                conn.execute(
+                    &format!("ALTER TABLE installed ADD COLUMN {column} {definition}"),
+                    [],
+                )?;
+            }
+        }
+
+        Ok(conn)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&self) -> Result<InstalledManifest> {
+        let conn = self.connect()?;
+        // Columns an older schema lacks are selected as NULL, since read
+        // only databases are not migrated
+        let existing = columns(&conn)?;
+        let select = COLUMNS
+            .map(|column| match existing.iter().any(|c| c == column) {
+                true => column.to_string(),
+                false => format!("NULL AS {column}"),
+            })
+            .join(", ");
+        let mut stmt = conn.prepare(&format!("SELECT {select} FROM installed"))?;
+        let mut rows = stmt.query([])?;
+
+        let mut manifest = InstalledManifest::new();
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get(0)?;
+            let domain: String = row.get(1)?;
+            let category: String = row.get(2)?;
+            let name: String = row.get(3)?;
+            let path: String = row.get(6)?;
+            let mut entry = Entry::new(row.get(4)?, row.get(5)?, PathBuf::from(path));
+            entry.url = row.get(7)?;
+            entry.hash = row.get(8)?;
+            entry.order = row.get::<_, Option<i64>>(9)?.unwrap_or_default() as u64;
+            if let (Some(order), Some(input), Some(output)) =
+                (row.get::<_, Option<i64>>(10)?, row.get(11)?, row.get(12)?)
+            {
+                entry.stage = Some(Stage { order: order as u32, input, output });
+            }
+            if let Some(source) = row.get::<_, Option<String>>(13)? {
+                entry.source = source.parse()?;
+            }
+            if let Some(capabilities) = row.get::<_, Option<String>>(14)? {
+                entry.capabilities = capabilities.split(',').map(str::to_string).collect();
+            }
+            if let Some(components) = row.get::<_, Option<String>>(15)? {
+                entry.components = from_json(&components)?;
+            }
+            if let Some(files) = row.get::<_, Option<String>>(16)? {
+                entry.files = from_json(&files)?;
+            }
+            if let Some(conflicts) = row.get::<_, Option<String>>(17)? {
+                entry.conflicts = from_json(&conflicts)?;
+            }
+            if let Some(runtimes) = row.get::<_, Option<String>>(18)? {
+                entry.runtimes = from_json(&runtimes)?;
+            }
+            manifest.insert(&kind, &domain, &category, &name, entry);
+        }
+
+        Ok(manifest)
+    }
+
+    fn save(&self, manifest: &InstalledManifest) -> Result<()> {
+        if self.read_only {
+            return Err(RegistryError::ReadOnly(self.path.display().to_string()));
+        }
+        let mut conn = self.connect()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM installed", [])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO installed
+                 (kind, domain, category, name, version, description, path, url, hash, seq,
+                  stage_order, stage_input, stage_output, source, capabilities,
+                  components, files, conflicts, runtimes)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                         ?17, ?18, ?19)",
+            )?;
+            for (kind, domain, category, name, entry) in manifest.entries() {
+                stmt.execute(params![
+                    kind,
+                    domain,
+                    category,
+                    name,
+                    entry.version,
+                    entry.description,
+                    path::utf8(&entry.path)
+                        .map_err(|e| RegistryError::InvalidPath(e.to_string()))?,
+                    entry.url,
+                    entry.hash,
+                    entry.order as i64,
+                    entry.stage.as_ref().map(|stage| stage.order as i64),
+                    entry.stage.as_ref().map(|stage| &stage.input),
+                    entry.stage.as_ref().map(|stage| &stage.output),
+                    (!entry.source.is_registry()).then(|| entry.source.as_str()),
+                    (!entry.capabilities.is_empty()).then(|| entry.capabilities.join(",")),
+                    to_json(&entry.components, entry.components.is_empty())?,
+                    to_json(&entry.files, entry.files.is_empty())?,
+                    to_json(&entry.conflicts, entry.conflicts.is_empty())?,
+                    to_json(&entry.runtimes, entry.runtimes.is_empty())?,
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+}
+
+/// Returns the names of the columns of the table.
+fn columns(conn: &Connection) -> Result<Vec<String>> {
+    Ok(conn
+        .prepare("SELECT name FROM pragma_table_info('installed')")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?)
+}
+
+/// Encodes a column stored as JSON, or `NULL` if the value is empty.
+fn to_json<T: Serialize>(value: &T, empty: bool) -> Result<Option<String>> {
+    if empty {
+        return Ok(None);
+    }
+    let json = serde_json::to_string(value).map_err(|e| RegistryError::Other(e.to_string()))?;
+    Ok(Some(json))
+}
+
+/// Decodes a column stored as JSON.
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    serde_json::from_str(json).map_err(|e| RegistryError::Other(format!("Invalid column: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmt_manifest::{ManifestFile, Resolution, Source};
+
+    fn manifest() -> InstalledManifest {
+        let mut manifest = InstalledManifest::new();
+        let entry = Entry::new("v1.0.0".to_string(), Some("foo".to_string()), "/tmp/foo".into());
+        manifest.insert("toolchains", "solidity", "compiler", "foo", entry);
+        let mut entry = Entry::new("v0.1.0-dev".to_string(), None, "/tmp/bar".into());
+        entry.source = Source::Local;
+        entry.capabilities = vec!["coverage".to_string(), "debug".to_string()];
+        entry.components.insert("core".to_string(), vec!["bin/bar".to_string()]);
+        entry.files = vec!["bin/bar".to_string(), "bin/fmt-bar".to_string()];
+        entry.conflicts.insert("bin/fmt".to_string(), Resolution::Rename("bin/fmt-bar".into()));
+        entry.runtimes.insert("llvm-runtime".to_string(), "^17".to_string());
+        manifest.insert("toolchains", "solidity", "frontend", "bar", entry);
+        manifest
+    }
+
+    #[test]
+    fn test_sqlite_storage_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage =
+            SqliteStorage::open(dir.path().join("installed.db"), dir.path().join("installed.toml"))
+                .unwrap();
+        assert!(storage.load().unwrap().as_map().is_empty());
+
+        storage.save(&manifest()).unwrap();
+        let loaded = storage.load().unwrap();
+        let entry = &loaded.get_package("toolchains", "solidity", "compiler").unwrap()["foo"];
+        assert_eq!(entry.version, "v1.0.0");
+        assert_eq!(entry.description.as_deref(), Some("foo"));
+        assert_eq!(entry.source, Source::Registry);
+        assert!(entry.capabilities.is_empty());
+        let entry = &loaded.get_package("toolchains", "solidity", "frontend").unwrap()["bar"];
+        assert_eq!(entry.source, Source::Local);
+        assert!(entry.supports("coverage") && !entry.supports("profile"));
+        assert_eq!(entry.components["core"], ["bin/bar"]);
+        assert_eq!(entry.files.len(), 2);
+        assert_eq!(entry.conflicts["bin/fmt"], Resolution::Rename("bin/fmt-bar".into()));
+        assert_eq!(entry.runtimes["llvm-runtime"], "^17");
+    }
+
+    #[test]
+    fn test_sqlite_storage_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("installed.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE installed (
+                kind TEXT NOT NULL, domain TEXT NOT NULL, category TEXT NOT NULL,
+                name TEXT NOT NULL, version TEXT NOT NULL, description TEXT, path TEXT NOT NULL
+            );
+            INSERT INTO installed VALUES
+                ('toolchains', 'solidity', 'compiler', 'foo', 'v1.0.0', NULL, '/opt/foo');",
+        )
+        .unwrap();
+        drop(conn);
+
+        // An older schema is read as it is, and never migrated
+        let storage = SqliteStorage::open_read_only(path.clone());
+        let loaded = storage.load().unwrap();
+        let entry = &loaded.get_package("toolchains", "solidity", "compiler").unwrap()["foo"];
+        assert_eq!(entry.version, "v1.0.0");
+        assert!(entry.url.is_none() && entry.runtimes.is_empty());
+        assert!(matches!(storage.save(&loaded), Err(RegistryError::ReadOnly(_))));
+
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(columns(&conn).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn test_sqlite_storage_migrates_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("installed.toml");
+        manifest().save(&legacy).unwrap();
+
+        let storage = SqliteStorage::open(dir.path().join("installed.db"), legacy.clone()).unwrap();
+        assert!(storage.load().unwrap().contains("toolchains", "solidity", "compiler", "foo"));
+        assert!(!legacy.exists());
+        assert!(dir.path().join("installed.toml.bak").exists());
+    }
+
+    #[test]
+    fn test_sqlite_storage_retries_failed_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("installed.toml");
+        let path = dir.path().join("installed.db");
+        fs::write(&legacy, "not a manifest [").unwrap();
+
+        // A failed import leaves no database behind, so it is retried
+        assert!(SqliteStorage::open(path.clone(), legacy.clone()).is_err());
+        assert!(!path.exists() && legacy.exists());
+
+        manifest().save(&legacy).unwrap();
+        let storage = SqliteStorage::open(path, legacy).unwrap();
+        assert!(storage.load().unwrap().contains("toolchains", "solidity", "compiler", "foo"));
+    }
+}
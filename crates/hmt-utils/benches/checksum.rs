@@ -0,0 +1,105 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares hashing a set of release-sized files one at a time through a
+//! 4KB buffer, as checksums used to be generated, with the parallel
+//! batch API.
+//!
+//! The file count and size are set with `HUMMANTA_BENCH_FILES` (default 16)
+//! and `HUMMANTA_BENCH_MB` (default 32).
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use base16ct::lower;
+use hmt_utils::checksum::{digest_file, digest_files};
+use sha2::{Digest, Sha256};
+
+/// The number of runs each strategy is measured over.
+const RUNS: usize = 5;
+
+fn main() {
+    let files = var("HUMMANTA_BENCH_FILES", 16);
+    let size = var("HUMMANTA_BENCH_MB", 32) << 20;
+
+    let dir = tempfile::tempdir().expect("Failed to create directory");
+    let paths: Vec<PathBuf> = (0..files)
+        .map(|i| {
+            let path = dir.path().join(format!("artifact-{i}.tar.gz"));
+            let data: Vec<u8> = (0..size).map(|j| (j * 31 + i) as u8).collect();
+            fs::write(&path, data).expect("Failed to write file");
+            path
+        })
+        .collect();
+
+    let expected: Vec<String> = paths.iter().map(|path| sequential(path)).collect();
+    let baseline = median(|| {
+        let hashes: Vec<String> = paths.iter().map(|path| sequential(path)).collect();
+        assert_eq!(hashes, expected);
+    });
+    println!("{files} files of {} MiB", size >> 20);
+    println!("{:<24} {:>10.2?}", "sequential, 4KB buffer", baseline);
+
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start runtime");
+    let time = median(|| {
+        let hashes: Vec<String> =
+            runtime.block_on(digest_files(&paths)).into_iter().map(|hash| hash.unwrap()).collect();
+        assert_eq!(hashes, expected);
+    });
+    let speedup = baseline.as_secs_f64() / time.as_secs_f64();
+    println!("{:<24} {time:>10.2?} {speedup:>6.2}x", "parallel, 1MB buffer");
+
+    let single = median(|| {
+        digest_file(&paths[0]).unwrap();
+    });
+    println!("{:<24} {:>10.2?}", "single file, 1MB buffer", single);
+}
+
+/// Hashes a file through a 4KB buffer.
+fn sequential(path: &Path) -> String {
+    let mut file = File::open(path).expect("Failed to open file");
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 4096];
+    loop {
+        let read = file.read(&mut buffer).expect("Failed to read file");
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    lower::encode_string(&hasher.finalize())
+}
+
+/// Runs `f` and returns the median wall time.
+fn median(mut f: impl FnMut()) -> Duration {
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+
+    times.sort();
+    times[RUNS / 2]
+}
+
+/// Reads a numeric environment variable.
+fn var(name: &str, default: usize) -> usize {
+    std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
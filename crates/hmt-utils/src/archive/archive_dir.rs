@@ -18,6 +18,8 @@ use anyhow::{Context, Result};
 use flate2::{write::GzEncoder, Compression};
 use tar::Builder;
 
+use crate::path;
+
 /// Archive a directory into tar.gz
 pub async fn archive_dir(src: &Path, dest: &Path) -> Result<()> {
     if !src.exists() {
@@ -33,11 +35,12 @@ pub async fn archive_dir(src: &Path, dest: &Path) -> Result<()> {
             .context("Failed to create parent directories for destination")?;
     }
 
-    let file = fs::File::create(dest).context(format!("Failed to create archive: {dest:?}"))?;
+    let file = fs::File::create(path::long(dest))
+        .context(format!("Failed to create archive: {dest:?}"))?;
     let encoder = GzEncoder::new(file, Compression::default());
 
     let mut tar = Builder::new(encoder);
-    tar.append_dir_all("", src).context("Failed to add directory to archive")?;
+    tar.append_dir_all("", path::long(src)).context("Failed to add directory to archive")?;
     tar.finish().context("Failed to finish tar creation")?;
 
     Ok(())
@@ -18,6 +18,8 @@ use anyhow::{Context, Result};
 use flate2::{write::GzEncoder, Compression};
 use tar::Builder;
 
+use crate::path;
+
 /// Archive a single file into tar.gz
 pub async fn archive_file(src: &Path, dest: &Path) -> Result<()> {
     if !src.exists() {
@@ -32,16 +34,15 @@ pub async fn archive_file(src: &Path, dest: &Path) -> Result<()> {
             .context("Failed to create parent directories for destination")?;
     }
 
-    let file = fs::File::create(dest).context(format!("Failed to create archive: {dest:?}"))?;
+    let file = fs::File::create(path::long(dest))
+        .context(format!("Failed to create archive: {dest:?}"))?;
     let encoder = GzEncoder::new(file, Compression::default());
     let mut tar = Builder::new(encoder);
 
-    let file_name = src
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or_else(|| anyhow::anyhow!("Invalid UTF-8 in source file name"))?;
+    let file_name =
+        src.file_name().ok_or_else(|| anyhow::anyhow!("Source file has no name: {:?}", src))?;
 
-    tar.append_path_with_name(src, file_name).context("Failed to add file to tar")?;
+    tar.append_path_with_name(path::long(src), file_name).context("Failed to add file to tar")?;
     tar.finish().context("Failed to finish tar creation")?;
 
     Ok(())
@@ -19,4 +19,6 @@ mod unpack;
 // Re-exports
 pub use archive_dir::archive_dir;
 pub use archive_file::archive_file;
-pub use unpack::unpack;
+pub use unpack::{
+    unpack, unpack_async, unpack_limited, unpack_paths, unpack_paths_async, MAX_UNPACKED_SIZE,
+};
@@ -12,19 +12,378 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{io::Cursor, path::Path};
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use flate2::read::GzDecoder;
-use tar::Archive;
+use tar::{Archive, EntryType};
 
-/// Unpack a `.tar.gz` archive from memory buffer into the target directory
-pub fn unpack(data: &[u8], target_dir: &Path) -> Result<()> {
-    let buffer = Cursor::new(data);
-    let decoder = GzDecoder::new(buffer);
-    let mut archive = Archive::new(decoder);
+use crate::path;
+
+/// The upper bound of threads writing files of a single archive.
+const MAX_WORKERS: usize = 8;
+
+/// The largest file handed to the writer threads. Larger files are copied
+/// to disk while reading the archive, so memory use stays bounded.
+const MAX_BUFFERED: u64 = 4 * 1024 * 1024;
+
+/// The largest decompressed tarball accepted by [`unpack`], so a small
+/// archive cannot expand to exhaust the disk.
+pub const MAX_UNPACKED_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// The paths of an archive to unpack, each selecting a file or everything
+/// below a directory. No paths select the whole archive.
+struct Filter {
+    paths: Vec<PathBuf>,
+    matched: Vec<bool>,
+}
+
+impl Filter {
+    fn new(paths: &[PathBuf]) -> Result<Self> {
+        let paths = paths.iter().map(|path| relative(path)).collect::<Result<Vec<_>>>()?;
+        Ok(Self { matched: vec![false; paths.len()], paths })
+    }
+
+    /// Whether an entry is selected, remembering which paths selected it.
+    fn select(&mut self, entry: &Path) -> bool {
+        if self.paths.is_empty() {
+            return true;
+        }
+
+        let mut selected = false;
+        for (path, matched) in self.paths.iter().zip(&mut self.matched) {
+            if entry.starts_with(path) {
+                *matched = true;
+                selected = true;
+            }
+        }
+        selected
+    }
+
+    /// Fails if a path selected nothing, as it most likely has a typo.
+    fn check(&self) -> Result<()> {
+        match self.paths.iter().zip(&self.matched).find(|(_, matched)| !**matched) {
+            Some((path, _)) => bail!("Archive has no entry at {}", path.display()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Fails reads once more than `max` bytes have been read.
+struct Limited<R> {
+    inner: R,
+    read: u64,
+    max: u64,
+}
+
+impl<R> Limited<R> {
+    fn exceeded(&self) -> bool {
+        self.read > self.max
+    }
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read as u64;
+        if self.exceeded() {
+            return Err(io::Error::other("archive exceeds the unpacked size limit"));
+        }
+        Ok(read)
+    }
+}
+
+/// Unpack a `.tar.gz` archive read from `reader` into the target directory
+///
+/// The archive is decompressed as it is read, so it is never held in memory
+/// as a whole. Regular files are written by
+/// a pool of threads meanwhile, while links and special files are unpacked
+/// once every file before them is written, so links never point at files
+/// still being written.
+pub fn unpack(reader: impl Read, target_dir: &Path) -> Result<()> {
+    unpack_limited(reader, target_dir, MAX_UNPACKED_SIZE)
+}
+
+/// Unpacks a `.tar.gz` archive like [`unpack`], failing if it decompresses
+/// to more than `max_size` bytes.
+pub fn unpack_limited(reader: impl Read, target_dir: &Path, max_size: u64) -> Result<()> {
+    unpack_filtered(reader, target_dir, max_size, Filter::new(&[])?)
+}
+
+/// Unpacks only the given paths of a `.tar.gz` archive, each a file or a
+/// directory relative to the archive root, failing if a path is missing.
+pub fn unpack_paths(reader: impl Read, target_dir: &Path, paths: &[PathBuf]) -> Result<()> {
+    unpack_filtered(reader, target_dir, MAX_UNPACKED_SIZE, Filter::new(paths)?)
+}
+
+/// Unpacks the selected entries of an archive into the target directory.
+fn unpack_filtered(
+    reader: impl Read,
+    target_dir: &Path,
+    max_size: u64,
+    mut filter: Filter,
+) -> Result<()> {
+    let target_dir = path::long(target_dir);
+    fs::create_dir_all(&target_dir)?;
+
+    let reader = Limited { inner: GzDecoder::new(reader), read: 0, max: max_size };
+    let mut archive = Archive::new(reader);
+    let result = extract(&mut archive, &target_dir, &mut filter);
+
+    // Read past the end of the tarball, so the gzip checksum is verified,
+    // and then to the end of the input, so a reader verifying its content
+    // once read sees all of it
+    let mut reader = archive.into_inner();
+    let result = result.and_then(|_| {
+        io::copy(&mut reader, &mut io::sink()).context("Failed to decompress archive")
+    });
+    if reader.exceeded() {
+        bail!("Archive decompresses to more than {max_size} bytes");
+    }
+    result?;
+    io::copy(&mut reader.inner.into_inner(), &mut io::sink()).context("Failed to read archive")?;
+
+    filter.check()
+}
+
+/// Unpacks an archive on the blocking thread pool, keeping the async
+/// runtime responsive while large archives are decompressed.
+pub async fn unpack_async(reader: impl Read + Send + 'static, target_dir: PathBuf) -> Result<()> {
+    tokio::task::spawn_blocking(move || unpack(reader, &target_dir))
+        .await
+        .context("Unpack task failed")?
+}
+
+/// Unpacks the given paths of an archive on the blocking thread pool, like
+/// [`unpack_paths`].
+pub async fn unpack_paths_async(
+    reader: impl Read + Send + 'static,
+    target_dir: PathBuf,
+    paths: Vec<PathBuf>,
+) -> Result<()> {
+    tokio::task::spawn_blocking(move || unpack_paths(reader, &target_dir, &paths))
+        .await
+        .context("Unpack task failed")?
+}
+
+/// Reads the entries of an archive, creating directories and handing small
+/// files to the writers as they come.
+fn extract<R: Read>(
+    archive: &mut Archive<R>,
+    target_dir: &Path,
+    filter: &mut Filter,
+) -> Result<()> {
+    let mut writers = Writers::new(target_dir);
+
+    for entry in archive.entries().context("Failed to read archive")? {
+        let mut entry = entry.context("Failed to read archive entry")?;
+        let path = relative(&entry.path()?)?;
+        if !filter.select(&path) {
+            continue;
+        }
+
+        let mode = entry.header().mode().unwrap_or(0o644);
+        match entry.header().entry_type() {
+            EntryType::Directory => create_dirs(target_dir, &path)?,
+            EntryType::Regular if entry.size() <= MAX_BUFFERED => {
+                let mut data = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut data).context("Failed to read archive entry")?;
+                writers.send(File { path, data, mode })?;
+            }
+            EntryType::Regular => {
+                writers.wait()?;
+                write(target_dir, &path, &mut entry, mode)?;
+            }
+            EntryType::XGlobalHeader => {}
+            _ => {
+                writers.wait()?;
+                entry.unpack_in(target_dir).context("Failed to unpack archive")?;
+            }
+        }
+    }
+
+    writers.wait()
+}
+
+/// A regular file read from an archive, relative to the target directory.
+struct File {
+    path: PathBuf,
+    data: Vec<u8>,
+    mode: u32,
+}
+
+/// The files being written, and the first error writing one.
+#[derive(Default)]
+struct Pending {
+    count: usize,
+    error: Option<anyhow::Error>,
+}
+
+/// A pool of threads writing files while the archive is read.
+struct Writers {
+    sender: Option<SyncSender<File>>,
+    handles: Vec<JoinHandle<()>>,
+    pending: Arc<(Mutex<Pending>, Condvar)>,
+    /// The paths sent since the last wait, as a later entry of the same
+    /// path must not be overtaken by an earlier one.
+    queued: HashSet<PathBuf>,
+}
+
+impl Writers {
+    fn new(target_dir: &Path) -> Self {
+        let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_WORKERS);
+        let (sender, receiver) = mpsc::sync_channel(workers * 2);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let pending = Arc::new((Mutex::new(Pending::default()), Condvar::new()));
+
+        let handles = (0..workers)
+            .map(|_| {
+                let (receiver, pending) = (receiver.clone(), pending.clone());
+                let target_dir = target_dir.to_path_buf();
+                thread::spawn(move || work(&receiver, &pending, &target_dir))
+            })
+            .collect();
+
+        Self { sender: Some(sender), handles, pending, queued: HashSet::new() }
+    }
+
+    /// Queues a file to be written.
+    fn send(&mut self, file: File) -> Result<()> {
+        if !self.queued.insert(file.path.clone()) {
+            self.wait()?;
+            self.queued.insert(file.path.clone());
+        }
+
+        self.pending.0.lock().expect("unpack state poisoned").count += 1;
+        let sender = self.sender.as_ref().expect("writers are running");
+        sender.send(file).map_err(|_| anyhow!("Unpack workers stopped"))
+    }
+
+    /// Waits until every queued file is written, returning the first error.
+    fn wait(&mut self) -> Result<()> {
+        let (lock, written) = &*self.pending;
+        let mut pending = lock.lock().expect("unpack state poisoned");
+        while pending.count > 0 {
+            pending = written.wait(pending).expect("unpack state poisoned");
+        }
+
+        self.queued.clear();
+        match pending.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Writers {
+    fn drop(&mut self) {
+        // Closing the channel stops the threads once the queue is drained
+        self.sender.take();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Writes the files received until the channel closes. Files received
+/// after an error are dropped.
+fn work(receiver: &Mutex<Receiver<File>>, pending: &(Mutex<Pending>, Condvar), target_dir: &Path) {
+    let (lock, written) = pending;
+    loop {
+        let next = receiver.lock().expect("unpack queue poisoned").recv();
+        let Ok(file) = next else { return };
+
+        let failed = lock.lock().expect("unpack state poisoned").error.is_some();
+        let result = match failed {
+            true => Ok(()),
+            false => write(target_dir, &file.path, &mut file.data.as_slice(), file.mode),
+        };
+
+        let mut pending = lock.lock().expect("unpack state poisoned");
+        pending.count -= 1;
+        if let Err(e) = result {
+            pending.error.get_or_insert(e);
+        }
+        written.notify_all();
+    }
+}
+
+/// Rejects entry paths escaping the target directory.
+fn relative(path: &Path) -> Result<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => bail!("Archive entry escapes the target directory: {}", path.display()),
+        }
+    }
+    Ok(relative)
+}
+
+/// Creates a directory and its parents below the target directory, failing
+/// rather than following a symbolic link, as an earlier entry may have
+/// placed one to redirect later entries out of the target directory.
+fn create_dirs(target_dir: &Path, dir: &Path) -> Result<()> {
+    let mut path = target_dir.to_path_buf();
+    for component in dir.components() {
+        path.push(component);
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => continue,
+            Ok(_) => bail!("Archive entry is below a non-directory: {}", path.display()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        match fs::create_dir(&path) {
+            // Another writer may have created it meanwhile
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && path.is_dir() => {}
+            result => result.context(format!("Failed to create {}", path.display()))?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes a single file below the target directory, keeping the permission
+/// bits recorded in the archive but never the setuid, setgid or sticky bits.
+/// A link at the path is replaced, never followed.
+fn write(target_dir: &Path, file: &Path, data: &mut impl Read, mode: u32) -> Result<()> {
+    if let Some(parent) = file.parent() {
+        create_dirs(target_dir, parent)?;
+    }
+    let path = target_dir.join(file);
+    if fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        fs::remove_file(&path)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    let mut out = options.open(&path).context(format!("Failed to write {}", path.display()))?;
+    io::copy(data, &mut out).context(format!("Failed to write {}", path.display()))?;
+    out.flush()?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o777))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
 
-    archive.unpack(target_dir).context("Failed to unpack archive")?;
     Ok(())
 }
 
@@ -54,7 +413,7 @@ mod tests {
 
         // Unpack the tar.gz file to the same temp directory
         let unpacked_dir = tempdir()?;
-        unpack(&fs::read(archive_path)?, unpacked_dir.path())?;
+        unpack(fs::File::open(archive_path)?, unpacked_dir.path())?;
 
         // Check if the file was unpacked correctly
         let unpacked_file = unpacked_dir.path().join("hello.txt");
@@ -65,4 +424,200 @@ mod tests {
 
         Ok(())
     }
+
+    /// Builds a `.tar.gz` archive in memory with the given entries.
+    fn tarball(build: impl FnOnce(&mut tar::Builder<Vec<u8>>)) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        build(&mut builder);
+        let tar = builder.into_inner().unwrap();
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(&tar).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn append(builder: &mut tar::Builder<Vec<u8>>, path: &str, data: &[u8], mode: u32) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(mode);
+        header.set_cksum();
+        builder.append_data(&mut header, path, data).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unpack_many_files() -> Result<()> {
+        let data = tarball(|builder| {
+            for i in 0..32 {
+                append(builder, &format!("bin/tool-{i}"), format!("tool {i}").as_bytes(), 0o755);
+            }
+            append(builder, "bin/tool-0", b"replaced", 0o644);
+        });
+
+        let dir = tempdir()?;
+        unpack_async(io::Cursor::new(data), dir.path().to_path_buf()).await?;
+
+        assert_eq!(fs::read_to_string(dir.path().join("bin/tool-31"))?, "tool 31");
+        assert_eq!(fs::read_to_string(dir.path().join("bin/tool-0"))?, "replaced");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.path().join("bin/tool-1"))?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unpack_drops_special_bits() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let data = tarball(|builder| {
+            append(builder, "bin/setuid", b"tool", 0o4755);
+            append(builder, "bin/sticky", b"tool", 0o1777);
+        });
+
+        let dir = tempdir()?;
+        unpack(data.as_slice(), dir.path())?;
+        let mode = |name: &str| fs::metadata(dir.path().join(name)).unwrap().permissions().mode();
+        assert_eq!(mode("bin/setuid") & 0o7777, 0o755);
+        assert_eq!(mode("bin/sticky") & 0o7777, 0o777);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unpack_links_sequentially() -> Result<()> {
+        let data = tarball(|builder| {
+            append(builder, "bin/tool", b"tool", 0o755);
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_cksum();
+            builder.append_link(&mut header, "bin/alias", "tool").unwrap();
+        });
+
+        let dir = tempdir()?;
+        unpack(data.as_slice(), dir.path())?;
+
+        assert_eq!(fs::read_to_string(dir.path().join("bin/alias"))?, "tool");
+        assert!(fs::symlink_metadata(dir.path().join("bin/alias"))?.file_type().is_symlink());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unpack_paths() -> Result<()> {
+        let data = tarball(|builder| {
+            append(builder, "bin/compiler", b"compiler", 0o755);
+            append(builder, "bin/formatter", b"formatter", 0o755);
+            append(builder, "lib/std/core.hmt", b"core", 0o644);
+            append(builder, "docs/README.md", b"docs", 0o644);
+        });
+
+        let dir = tempdir()?;
+        let paths = vec![PathBuf::from("bin/compiler"), PathBuf::from("./lib")];
+        unpack_paths_async(io::Cursor::new(data.clone()), dir.path().to_path_buf(), paths).await?;
+
+        assert_eq!(fs::read_to_string(dir.path().join("bin/compiler"))?, "compiler");
+        assert_eq!(fs::read_to_string(dir.path().join("lib/std/core.hmt"))?, "core");
+        assert!(!dir.path().join("bin/formatter").exists());
+        assert!(!dir.path().join("docs").exists());
+
+        // Paths are matched component-wise, and must exist
+        let err =
+            unpack_paths(data.as_slice(), dir.path(), &[PathBuf::from("bin/comp")]).unwrap_err();
+        assert!(err.to_string().contains("no entry at bin/comp"));
+        assert!(unpack_paths(data.as_slice(), dir.path(), &[PathBuf::from("../bin")]).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unpack_paths_sequentially() -> Result<()> {
+        let data = tarball(|builder| {
+            append(builder, "bin/tool", b"tool", 0o755);
+            append(builder, "extra/data", b"data", 0o644);
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_cksum();
+            builder.append_link(&mut header, "bin/alias", "tool").unwrap();
+        });
+
+        let dir = tempdir()?;
+        unpack_paths(data.as_slice(), dir.path(), &[PathBuf::from("bin")])?;
+
+        assert_eq!(fs::read_to_string(dir.path().join("bin/alias"))?, "tool");
+        assert!(!dir.path().join("extra").exists());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unpack_rejects_symlinked_parents() -> Result<()> {
+        let outside = tempdir()?;
+        let data = tarball(|builder| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_cksum();
+            builder.append_link(&mut header, "bin", outside.path()).unwrap();
+            append(builder, "bin/tool", b"tool", 0o755);
+        });
+
+        // Neither a link from the archive nor one already in the target
+        // directory is followed
+        let dir = tempdir()?;
+        assert!(unpack(data.as_slice(), dir.path()).is_err());
+        let data = tarball(|builder| append(builder, "lib/tool", b"tool", 0o755));
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("lib"))?;
+        assert!(unpack(data.as_slice(), dir.path()).is_err());
+        assert!(!outside.path().join("tool").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_large_files() -> Result<()> {
+        let large: Vec<u8> = (0..MAX_BUFFERED as usize + 1).map(|i| i as u8).collect();
+        let data = tarball(|builder| {
+            append(builder, "small", b"small", 0o644);
+            append(builder, "large", &large, 0o644);
+            append(builder, "small", b"replaced", 0o644);
+        });
+
+        let dir = tempdir()?;
+        unpack(data.as_slice(), dir.path())?;
+        assert_eq!(fs::read(dir.path().join("large"))?, large);
+        assert_eq!(fs::read_to_string(dir.path().join("small"))?, "replaced");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_rejects_escapes() {
+        assert_eq!(relative(Path::new("./bin/tool")).unwrap(), PathBuf::from("bin/tool"));
+        assert!(relative(Path::new("../tool")).is_err());
+        assert!(relative(Path::new("/etc/passwd")).is_err());
+    }
+
+    #[test]
+    fn test_unpack_limited_rejects_bombs() -> Result<()> {
+        let data = tarball(|builder| append(builder, "zeros", &[0; 64 * 1024], 0o644));
+
+        let dir = tempdir()?;
+        let err = unpack_limited(data.as_slice(), dir.path(), 1024).unwrap_err();
+        assert!(err.to_string().contains("more than 1024 bytes"));
+        assert!(!dir.path().join("zeros").exists());
+
+        unpack_limited(data.as_slice(), dir.path(), 128 * 1024)?;
+        assert!(dir.path().join("zeros").exists());
+        Ok(())
+    }
 }
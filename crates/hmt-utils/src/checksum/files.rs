@@ -0,0 +1,148 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hashing of files, one at a time or many in parallel.
+//!
+//! Files are read through a large buffer. Batches run on the blocking thread
+//! pool, with at most one file per CPU being hashed at a time.
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    panic,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use anyhow::{bail, Context, Result};
+use base16ct::lower;
+use sha2::{Digest, Sha256};
+use tokio::task::{JoinError, JoinSet};
+
+/// The size of the buffer files are read through.
+pub const BUFFER_SIZE: usize = 1 << 20;
+
+/// Computes the lowercase hex-encoded SHA-256 hash of a file.
+pub fn digest_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(lower::encode_string(&hasher.finalize()))
+}
+
+/// Computes the hashes of many files in parallel, in the order of `paths`.
+pub async fn digest_files<P: AsRef<Path>>(paths: &[P]) -> Vec<io::Result<String>> {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut results: Vec<Option<io::Result<String>>> = paths.iter().map(|_| None).collect();
+
+    let mut tasks = JoinSet::new();
+    for (index, path) in paths.iter().enumerate() {
+        if tasks.len() >= workers {
+            if let Some(joined) = tasks.join_next().await {
+                store(&mut results, joined);
+            }
+        }
+        let path = path.as_ref().to_path_buf();
+        tasks.spawn_blocking(move || (index, digest_file(&path)));
+    }
+    while let Some(joined) = tasks.join_next().await {
+        store(&mut results, joined);
+    }
+
+    results.into_iter().map(|result| result.expect("every file is hashed")).collect()
+}
+
+/// Records the hash of one file, passing on a panic of its task.
+fn store(
+    results: &mut [Option<io::Result<String>>],
+    joined: Result<(usize, io::Result<String>), JoinError>,
+) {
+    let (index, result) = joined.unwrap_or_else(|err| panic::resume_unwind(err.into_panic()));
+    results[index] = Some(result);
+}
+
+/// Verifies that every file has its expected hash, hashing them in
+/// parallel. All mismatches are reported together.
+pub async fn verify_files(files: &[(PathBuf, String)]) -> Result<()> {
+    let paths: Vec<&PathBuf> = files.iter().map(|(path, _)| path).collect();
+
+    let mut mismatches = Vec::new();
+    for ((path, expected), actual) in files.iter().zip(digest_files(&paths).await) {
+        let actual = actual.context(format!("Failed to hash {}", path.display()))?;
+        if actual != *expected {
+            mismatches.push(format!("{}: expected {expected}, actual {actual}", path.display()));
+        }
+    }
+
+    if !mismatches.is_empty() {
+        bail!("Hash mismatch:\n  {}", mismatches.join("\n  "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::checksum::digest;
+
+    #[tokio::test]
+    async fn test_digest_files() {
+        let dir = tempdir().unwrap();
+        let contents: Vec<Vec<u8>> =
+            (0..16).map(|i| vec![i as u8; i * BUFFER_SIZE / 8 + i]).collect();
+        let paths: Vec<PathBuf> = contents
+            .iter()
+            .enumerate()
+            .map(|(i, data)| {
+                let path = dir.path().join(format!("file-{i}"));
+                fs::write(&path, data).unwrap();
+                path
+            })
+            .collect();
+
+        let hashes = digest_files(&paths).await;
+        for (data, hash) in contents.iter().zip(hashes) {
+            assert_eq!(hash.unwrap(), digest(data));
+        }
+
+        let missing = digest_files(&[dir.path().join("missing")]).await;
+        assert!(missing[0].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file");
+        fs::write(&path, b"test data").unwrap();
+
+        let hash = digest(b"test data");
+        assert!(verify_files(&[(path.clone(), hash)]).await.is_ok());
+
+        let err = verify_files(&[(path, "wrong".to_string())]).await.unwrap_err();
+        assert!(err.to_string().contains("expected wrong"));
+    }
+}
@@ -15,34 +15,17 @@
 use std::path::Path;
 
 use anyhow::{Context, Result};
-use base16ct::lower;
-use sha2::{Digest, Sha256};
-use tokio::{
-    fs,
-    io::{AsyncReadExt, AsyncWriteExt, BufReader},
-};
+use tokio::{fs, io::AsyncWriteExt};
+
+use super::digest_file;
 
 /// Generate SHA256 checksum of a file and write it to an output file
 pub async fn generate(file: &Path, output_path: &Path) -> Result<()> {
-    // Open the file for reading
-    let mut hasher = Sha256::new();
-    let file = fs::File::open(file)
-        .await
+    // Hash the file off the async runtime
+    let path = file.to_path_buf();
+    let checksum = tokio::task::spawn_blocking(move || digest_file(&path))
+        .await?
         .context(format!("Failed to open file for checksum: {file:?}"))?;
-    let mut reader = BufReader::new(file);
-    let mut buffer = [0; 4096];
-
-    // Read the file in chunks and update the hash
-    while let Ok(bytes_read) = reader.read(&mut buffer).await {
-        if bytes_read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes_read]);
-    }
-
-    // Finalize the hash
-    let hash = hasher.finalize();
-    let checksum = lower::encode_string(&hash);
 
     // Create the checksum file
     let mut checksum_file = fs::File::create(output_path)
@@ -58,6 +41,8 @@ pub async fn generate(file: &Path, output_path: &Path) -> Result<()> {
 
 #[cfg(test)]
 mod tests {
+    use base16ct::lower;
+    use sha2::{Digest, Sha256};
     use std::{fs, io::Write};
     use tempfile::tempdir;
 
@@ -12,13 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod digest;
+mod files;
 mod generate;
 mod read;
+mod reader;
 mod verify;
 
 // Re-export
+pub use digest::digest;
+pub use files::{digest_file, digest_files, verify_files, BUFFER_SIZE};
 pub use generate::generate;
 pub use read::read;
+pub use reader::HashReader;
 pub use verify::verify;
 
 pub const CHECKSUM_FILE_SUFFIX: &str = "sha256";
@@ -0,0 +1,64 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hashing of data while it is read.
+
+use std::io::{self, Read};
+
+use base16ct::lower;
+use sha2::{Digest, Sha256};
+
+/// A reader hashing the data read through it.
+pub struct HashReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R> HashReader<R> {
+    /// Creates a reader hashing the data read from `inner`.
+    pub fn new(inner: R) -> Self {
+        Self { inner, hasher: Sha256::new() }
+    }
+
+    /// Returns the lowercase hex-encoded SHA-256 hash of the data read so
+    /// far.
+    pub fn digest(&self) -> String {
+        lower::encode_string(&self.hasher.clone().finalize())
+    }
+}
+
+impl<R: Read> Read for HashReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::digest;
+
+    #[test]
+    fn test_hash_reader() {
+        let mut reader = HashReader::new(&b"test data"[..]);
+        let mut head = [0; 4];
+        reader.read_exact(&mut head).unwrap();
+        assert_eq!(reader.digest(), digest(b"test"));
+
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert_eq!(reader.digest(), digest(b"test data"));
+    }
+}
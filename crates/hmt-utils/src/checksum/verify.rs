@@ -12,17 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use base16ct::lower;
-use sha2::{Digest, Sha256};
-
 use anyhow::Result;
 
+use super::digest;
+
 /// Verifies SHA-256 hash of the data
 pub fn verify(data: &[u8], expected_hash: &str) -> Result<()> {
-    let mut hasher = Sha256::new();
-    hasher.update(data);
-    let hash = hasher.finalize();
-    let actual_hash = lower::encode_string(&hash);
+    let actual_hash = digest(data);
 
     if actual_hash != expected_hash {
         anyhow::bail!("Hash mismatch: expected {}, actual {}", expected_hash, actual_hash);
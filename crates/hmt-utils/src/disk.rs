@@ -0,0 +1,120 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Free disk space checks, run before writing large amounts of data so an
+//! install fails up front rather than with `ENOSPC` halfway through.
+
+use std::path::{Path, PathBuf};
+
+use hmt_error::{Category, Code, Diagnostic, Help};
+use thiserror::Error;
+
+/// How much larger an unpacked archive is estimated to be than the archive.
+pub const UNPACK_RATIO: u64 = 4;
+
+/// A filesystem has less free space than an operation needs.
+#[derive(Error, Debug)]
+#[error(
+    "Not enough disk space in {}: {required} bytes required, {available} bytes available",
+    path.display()
+)]
+pub struct InsufficientSpace {
+    pub path: PathBuf,
+    pub required: u64,
+    pub available: u64,
+}
+
+impl Diagnostic for InsufficientSpace {
+    fn code(&self) -> Code {
+        Code::new("disk.insufficient-space", Category::Io)
+    }
+
+    fn help(&self) -> Option<Help> {
+        Some(
+            Help::new(format!("{} is running out of disk space", self.path.display()))
+                .step("remove unused packages with `hummanta toolchain remove`")
+                .step("remove cached downloads under `~/.hummanta/cache`")
+                .page("insufficient-space"),
+        )
+    }
+}
+
+/// Returns the bytes available to the current user on the filesystem of
+/// `path`, or `None` if it cannot be determined.
+///
+/// `path` need not exist yet, the nearest existing ancestor is queried.
+pub fn available(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+    statvfs(existing)
+}
+
+/// Checks that the filesystem of `path` has at least `required` bytes free.
+///
+/// Passes when the free space cannot be determined, the write itself then
+/// reports any failure.
+pub fn check(path: &Path, required: u64) -> Result<(), InsufficientSpace> {
+    match available(path) {
+        Some(available) if available < required => {
+            Err(InsufficientSpace { path: path.to_path_buf(), required, available })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn statvfs(path: &Path) -> Option<u64> {
+    use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is a valid C string and `stat` is written on success
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    // SAFETY: `statvfs` succeeded, so `stat` is initialized
+    let stat = unsafe { stat.assume_init() };
+
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn statvfs(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_available() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(available(dir.path()).is_some_and(|bytes| bytes > 0));
+        assert_eq!(available(&dir.path().join("missing/dir")), available(dir.path()));
+    }
+
+    #[test]
+    fn test_check() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(check(dir.path(), 0).is_ok());
+
+        if cfg!(unix) {
+            let err = check(dir.path(), u64::MAX).unwrap_err();
+            assert_eq!(err.required, u64::MAX);
+            assert!(err.to_string().contains("bytes available"));
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Events reported by long-running operations.
+//!
+//! Libraries never write to the terminal themselves. They report events to
+//! an injected [`Reporter`], and the application decides how to show them,
+//! e.g. as styled lines or as JSON. [`LogReporter`] is the default and
+//! forwards events to `tracing`.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+/// An event reported by an operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A message about normal operation.
+    Info { message: String },
+    /// A problem the operation recovered from, e.g. a skipped package.
+    /// The `id` is one of [`warning::ALL`], by which users can allow it.
+    Warning { id: String, message: String },
+    /// Item `done` of `total` of a task has finished.
+    Progress { label: String, item: String, done: usize, total: usize },
+    /// A task reported with [`Event::Progress`] has ended, completed or not.
+    Finished { label: String },
+    /// A resource has been fetched.
+    Fetched { url: String, size: usize },
+}
+
+/// Receives the events of operations.
+pub trait Reporter: Send + Sync {
+    /// Handles an event.
+    fn report(&self, event: Event);
+
+    /// Reports an [`Event::Info`].
+    fn info(&self, message: String) {
+        self.report(Event::Info { message });
+    }
+
+    /// Reports an [`Event::Warning`] with one of the [`warning`] ids.
+    fn warn(&self, id: &str, message: String) {
+        self.report(Event::Warning { id: id.to_string(), message });
+    }
+}
+
+/// The ids of warnings, stable across releases.
+pub mod warning {
+    /// Fetched registry metadata could not be cached.
+    pub const CACHE_WRITE: &str = "cache-write";
+    /// Expired registry metadata is used because the registry is unreachable.
+    pub const STALE_METADATA: &str = "stale-metadata";
+    /// A package could not be fetched and is skipped.
+    pub const FETCH_FAILED: &str = "fetch-failed";
+    /// A package has no release for the current target and is skipped.
+    pub const UNSUPPORTED_TARGET: &str = "unsupported-target";
+    /// A package is skipped by the install policy.
+    pub const POLICY_SKIPPED: &str = "policy-skipped";
+    /// A file of a package clashes with one of another package.
+    pub const FILE_CONFLICT: &str = "file-conflict";
+    /// A mirror failed and the next one is tried.
+    pub const MIRROR_FAILED: &str = "mirror-failed";
+    /// A locked package cannot be checked against the registry.
+    pub const UNVERIFIED_LOCK: &str = "unverified-lock";
+    /// An installed package is missing or not executable.
+    pub const BROKEN_PACKAGE: &str = "broken-package";
+    /// A target was added without some of its packages.
+    pub const INCOMPLETE_TARGET: &str = "incomplete-target";
+    /// An artifact could not be uploaded to the remote cache.
+    pub const REMOTE_CACHE: &str = "remote-cache";
+    /// The build status file could not be written.
+    pub const BUILD_STATUS: &str = "build-status";
+    /// A project could not be read and is skipped.
+    pub const PROJECT_SKIPPED: &str = "project-skipped";
+
+    /// Every warning id.
+    pub const ALL: &[&str] = &[
+        CACHE_WRITE,
+        STALE_METADATA,
+        FETCH_FAILED,
+        UNSUPPORTED_TARGET,
+        POLICY_SKIPPED,
+        FILE_CONFLICT,
+        MIRROR_FAILED,
+        UNVERIFIED_LOCK,
+        BROKEN_PACKAGE,
+        INCOMPLETE_TARGET,
+        REMOTE_CACHE,
+        BUILD_STATUS,
+        PROJECT_SKIPPED,
+    ];
+}
+
+/// Forwards events to `tracing`, with progress and fetches at debug level.
+#[derive(Debug, Default)]
+pub struct LogReporter;
+
+impl LogReporter {
+    /// Returns a shared instance, the default reporter of every library.
+    pub fn shared() -> Arc<dyn Reporter> {
+        Arc::new(LogReporter)
+    }
+}
+
+impl Reporter for LogReporter {
+    fn report(&self, event: Event) {
+        match event {
+            Event::Info { message } => info!("{message}"),
+            Event::Warning { id, message } => warn!("{message} [{id}]"),
+            Event::Progress { label, item, done, total } => {
+                debug!("{label} [{done}/{total}] {item}")
+            }
+            Event::Finished { label } => debug!("{label} finished"),
+            Event::Fetched { url, size } => debug!("Fetched {url} ({size} bytes)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Event>>);
+
+    impl Reporter for Recorder {
+        fn report(&self, event: Event) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    #[test]
+    fn test_helpers() {
+        let recorder = Recorder::default();
+        recorder.info("building".into());
+        recorder.warn(warning::FETCH_FAILED, "skipped".into());
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                Event::Info { message: "building".into() },
+                Event::Warning { id: "fetch-failed".into(), message: "skipped".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let event = Event::Fetched { url: "file:///index.toml".into(), size: 3 };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"fetched","url":"file:///index.toml","size":3}"#
+        );
+    }
+}
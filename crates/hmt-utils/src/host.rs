@@ -0,0 +1,125 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Capabilities of the host, deciding which release artifacts run on it.
+//!
+//! Linux hosts run binaries linked against their C library, and fully
+//! static binaries, which are built for the `musl` targets. Hosts without
+//! glibc, such as Alpine or distroless containers, only run the latter.
+
+use std::fs;
+
+/// The directories holding the dynamic loader of Linux hosts.
+const LOADER_DIRS: [&str; 3] = ["/lib", "/lib64", "/usr/lib"];
+
+/// The C library of a Linux host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Libc {
+    Gnu,
+    Musl,
+}
+
+/// Detects the C library of the host from its dynamic loaders, or returns
+/// `None` if it has none or is not Linux.
+pub fn libc() -> Option<Libc> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    let names = LOADER_DIRS
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned());
+    loader_libc(names)
+}
+
+/// Returns the targets of release artifacts that run on the host, most
+/// preferred first.
+pub fn targets() -> Vec<String> {
+    compatible(target_triple::TARGET, libc())
+}
+
+/// Picks the C library from the names of the dynamic loaders present.
+/// glibc wins, since musl's loader is often installed next to it.
+fn loader_libc(names: impl Iterator<Item = String>) -> Option<Libc> {
+    let mut libc = None;
+    for name in names {
+        if name.starts_with("ld-linux") {
+            return Some(Libc::Gnu);
+        }
+        if name.starts_with("ld-musl-") {
+            libc = Some(Libc::Musl);
+        }
+    }
+    libc
+}
+
+/// Returns the targets of artifacts running on a host with `libc`, for a
+/// binary built for `target`.
+///
+/// The variant of `target` comes first, so static installations stay
+/// static. Hosts without glibc only get the static variant.
+fn compatible(target: &str, libc: Option<Libc>) -> Vec<String> {
+    let Some((base, variant)) = target
+        .rsplit_once('-')
+        .filter(|(base, variant)| base.ends_with("-linux") && matches!(*variant, "gnu" | "musl"))
+    else {
+        return vec![target.to_string()];
+    };
+
+    let (gnu, musl) = (format!("{base}-gnu"), format!("{base}-musl"));
+    match (variant, libc) {
+        (_, Some(Libc::Musl)) => vec![musl],
+        ("musl", Some(Libc::Gnu)) => vec![musl, gnu],
+        ("musl", None) => vec![musl],
+        _ => vec![gnu, musl],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> std::vec::IntoIter<String> {
+        names.iter().map(|name| name.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn test_loader_libc() {
+        assert_eq!(loader_libc(names(&["ld-linux-x86-64.so.2", "libc.so.6"])), Some(Libc::Gnu));
+        assert_eq!(loader_libc(names(&["ld-musl-x86_64.so.1"])), Some(Libc::Musl));
+        assert_eq!(
+            loader_libc(names(&["ld-musl-x86_64.so.1", "ld-linux-x86-64.so.2"])),
+            Some(Libc::Gnu)
+        );
+        assert_eq!(loader_libc(names(&["libz.so.1"])), None);
+    }
+
+    #[test]
+    fn test_compatible() {
+        let gnu = "x86_64-unknown-linux-gnu";
+        let musl = "x86_64-unknown-linux-musl";
+
+        assert_eq!(compatible(gnu, Some(Libc::Gnu)), [gnu, musl]);
+        assert_eq!(compatible(gnu, Some(Libc::Musl)), [musl]);
+        assert_eq!(compatible(musl, Some(Libc::Gnu)), [musl, gnu]);
+        assert_eq!(compatible(musl, None), [musl]);
+        assert_eq!(compatible("aarch64-apple-darwin", None), ["aarch64-apple-darwin"]);
+        assert_eq!(
+            compatible("arm-unknown-linux-gnueabihf", None),
+            ["arm-unknown-linux-gnueabihf"]
+        );
+    }
+}
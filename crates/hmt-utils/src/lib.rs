@@ -15,3 +15,10 @@
 pub mod archive;
 pub mod bytes;
 pub mod checksum;
+pub mod disk;
+pub mod event;
+pub mod host;
+pub mod path;
+pub mod process;
+pub mod signature;
+pub mod temp;
@@ -0,0 +1,136 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Path helpers for paths that are not valid UTF-8 or exceed the Windows
+//! path length limit.
+//!
+//! Paths passed to tools should stay `OsStr` all the way to the argument
+//! list; [`utf8`] is for the few places that must store a path as text.
+
+use std::{
+    borrow::Cow,
+    path::{Component, Path},
+};
+
+use anyhow::{anyhow, Result};
+
+/// The longest path Windows accepts without the verbatim prefix.
+#[cfg_attr(not(windows), allow(dead_code))]
+const MAX_PATH: usize = 260;
+
+/// Returns the path as UTF-8, failing with the lossy path in the error.
+pub fn utf8(path: &Path) -> Result<&str> {
+    path.to_str().ok_or_else(|| anyhow!("Path is not valid UTF-8: {}", path.display()))
+}
+
+/// Whether the path is a single normal component, such as a file name,
+/// so joining it to a directory stays inside that directory.
+pub fn is_file_name(path: &Path) -> bool {
+    let mut components = path.components();
+    matches!((components.next(), components.next()), (Some(Component::Normal(_)), None))
+}
+
+/// Whether the path is a file the current user may execute.
+pub fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        path.metadata().is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(windows)]
+    {
+        path.is_file()
+    }
+}
+
+/// Returns a path usable beyond the Windows path length limit.
+///
+/// On Windows, long absolute paths are given the verbatim `\\?\` prefix.
+/// Other paths, and all paths on other platforms, are returned unchanged.
+pub fn long(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    {
+        let raw = path.as_os_str().to_string_lossy();
+        if path.is_absolute() && raw.len() >= MAX_PATH && !raw.starts_with(r"\\?\") {
+            return Cow::Owned(match raw.strip_prefix(r"\\") {
+                Some(unc) => format!(r"\\?\UNC\{unc}").into(),
+                None => format!(r"\\?\{raw}").into(),
+            });
+        }
+    }
+
+    Cow::Borrowed(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8() {
+        assert_eq!(utf8(Path::new("/tmp/über")).unwrap(), "/tmp/über");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_utf8_invalid() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let path = Path::new(OsStr::from_bytes(b"/tmp/\xff"));
+        assert!(utf8(path).is_err());
+    }
+
+    #[test]
+    fn test_is_file_name() {
+        assert!(is_file_name(Path::new("llvm")));
+        assert!(is_file_name(Path::new("v1.0.0")));
+        for path in ["", ".", "..", "a/b", "../a", "/a"] {
+            assert!(!is_file_name(Path::new(path)), "{path}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_executable() {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tool");
+        assert!(!is_executable(&path));
+
+        fs::write(&path, "").unwrap();
+        assert!(!is_executable(&path));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(is_executable(&path));
+        assert!(!is_executable(dir.path()));
+    }
+
+    #[test]
+    fn test_long_short_path() {
+        let path = Path::new("project/src");
+        assert!(matches!(long(path), Cow::Borrowed(p) if p == path));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_long_prefixed() {
+        let path = format!(r"C:\{}", "a".repeat(MAX_PATH));
+        assert!(long(Path::new(&path)).to_string_lossy().starts_with(r"\\?\C:\"));
+
+        let unc = format!(r"\\server\share\{}", "a".repeat(MAX_PATH));
+        assert!(long(Path::new(&unc)).to_string_lossy().starts_with(r"\\?\UNC\server"));
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Running external tools.
+//!
+//! Children are killed when the future waiting on them is dropped, so a
+//! cancelled command never leaves tools running in the background.
+
+use std::{
+    ffi::{OsStr, OsString},
+    future::Future,
+    io,
+    path::Path,
+    process::{ExitStatus, Output, Stdio},
+    time::Duration,
+};
+
+use thiserror::Error;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader},
+    process::{Child, Command},
+};
+use tracing::info;
+
+use crate::path;
+
+/// Errors raised while running a process.
+#[derive(Error, Debug)]
+pub enum ProcessError {
+    #[error("Failed to start {program}: {source}")]
+    Spawn { program: String, source: io::Error },
+
+    #[error("Failed to wait for {program}: {source}")]
+    Wait { program: String, source: io::Error },
+
+    #[error("{program} did not finish within {timeout:?}")]
+    Timeout { program: String, timeout: Duration },
+}
+
+/// A process to run, configured with a builder.
+pub struct Process {
+    command: Command,
+    program: OsString,
+    args: Vec<OsString>,
+    timeout: Option<Duration>,
+    stream: bool,
+}
+
+impl Process {
+    /// Creates a process running the given program.
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        let program = program.as_ref().to_os_string();
+        let mut command = Command::new(path::long(Path::new(&program)).as_ref());
+        command.kill_on_drop(true);
+
+        Self { command, program, args: Vec::new(), timeout: None, stream: false }
+    }
+
+    /// Adds an argument.
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    /// Adds arguments.
+    pub fn args<I, T>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<OsStr>,
+    {
+        self.args.extend(args.into_iter().map(|a| a.as_ref().to_os_string()));
+        self
+    }
+
+    /// Sets an environment variable.
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        self.command.env(key, value);
+        self
+    }
+
+    /// Sets environment variables.
+    pub fn envs<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.command.envs(vars);
+        self
+    }
+
+    /// Sets the working directory.
+    pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.command.current_dir(dir);
+        self
+    }
+
+    /// Kills the process if it runs longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Forwards the error output to this process's standard error as it is
+    /// written, in addition to capturing it.
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    /// Runs the process to completion, capturing its output.
+    pub async fn output(mut self) -> Result<Output, ProcessError> {
+        self.command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        let mut child = self.spawn()?;
+
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let stream = self.stream;
+        let read = async {
+            let mut out = Vec::new();
+            let (out_result, err_result) =
+                tokio::join!(stdout.read_to_end(&mut out), read_stderr(stderr, stream));
+            out_result?;
+            let err = err_result?;
+            let status = child.wait().await?;
+            Ok(Output { status, stdout: out, stderr: err })
+        };
+
+        self.wait(read).await
+    }
+
+    /// Runs the process to completion, sharing this process's standard
+    /// input and output, for interactive tools.
+    pub async fn status(mut self) -> Result<ExitStatus, ProcessError> {
+        let mut child = self.spawn()?;
+        self.wait(child.wait()).await
+    }
+
+    /// Starts the process detached from this one, without waiting for it.
+    pub fn spawn_detached(mut self) -> Result<(), ProcessError> {
+        self.command
+            .kill_on_drop(false)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        self.spawn().map(drop)
+    }
+
+    /// Returns the program and arguments as a single line for display.
+    pub fn display(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|s| s.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn spawn(&mut self) -> Result<Child, ProcessError> {
+        info!("Executing {}", self.display());
+        self.command
+            .args(&self.args)
+            .spawn()
+            .map_err(|source| ProcessError::Spawn { program: self.program(), source })
+    }
+
+    /// Waits for the future, failing once the timeout elapses. The child is
+    /// owned by the future and killed when it is dropped.
+    async fn wait<T>(
+        &self,
+        future: impl Future<Output = io::Result<T>>,
+    ) -> Result<T, ProcessError> {
+        let result = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .map_err(|_| ProcessError::Timeout { program: self.program(), timeout })?,
+            None => future.await,
+        };
+
+        result.map_err(|source| ProcessError::Wait { program: self.program(), source })
+    }
+
+    fn program(&self) -> String {
+        self.program.to_string_lossy().into_owned()
+    }
+}
+
+/// Reads the error output line by line, echoing it when streaming.
+async fn read_stderr(stderr: impl AsyncRead + Unpin, stream: bool) -> io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(stderr);
+    let mut data = Vec::new();
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line).await? > 0 {
+        if stream {
+            eprint!("{}", String::from_utf8_lossy(&line));
+        }
+        data.append(&mut line);
+    }
+
+    Ok(data)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_output() {
+        let output = Process::new("sh")
+            .args(["-c", "echo $GREETING; echo oops >&2; exit 3"])
+            .env("GREETING", "hello")
+            .output()
+            .await
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"hello\n");
+        assert_eq!(output.stderr, b"oops\n");
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let result =
+            Process::new("sleep").arg("5").timeout(Duration::from_millis(50)).output().await;
+        assert!(matches!(result, Err(ProcessError::Timeout { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_error() {
+        let result = Process::new("/nonexistent/tool").output().await;
+        assert!(matches!(result, Err(ProcessError::Spawn { .. })));
+    }
+
+    #[test]
+    fn test_display() {
+        let process = Process::new("tool").args(["--input", "a b.c"]);
+        assert_eq!(process.display(), "tool --input a b.c");
+    }
+}
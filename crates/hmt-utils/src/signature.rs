@@ -0,0 +1,122 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use base16ct::lower;
+use ed25519_dalek::{Signer, Verifier};
+
+use crate::checksum::digest;
+
+/// The file suffix of detached signatures.
+pub const SIGNATURE_FILE_SUFFIX: &str = "sig";
+
+/// An Ed25519 key used to sign manifests and payloads.
+pub struct SigningKey(ed25519_dalek::SigningKey);
+
+impl SigningKey {
+    /// Creates a key from its 32-byte seed.
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self(ed25519_dalek::SigningKey::from_bytes(&seed))
+    }
+
+    /// Loads a key from a file holding its hex-encoded seed.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read signing key: {}", path.display()))?;
+        let seed = decode(content.trim())?
+            .try_into()
+            .map_err(|_| anyhow!("Signing key must be 32 bytes: {}", path.display()))?;
+
+        Ok(Self::from_seed(seed))
+    }
+
+    /// Signs the data, returning the hex-encoded signature.
+    pub fn sign(&self, data: &[u8]) -> String {
+        lower::encode_string(&self.0.sign(data).to_bytes())
+    }
+
+    /// Returns the hex-encoded public key.
+    pub fn public_key(&self) -> String {
+        lower::encode_string(self.0.verifying_key().as_bytes())
+    }
+
+    /// Returns the fingerprint of the public key.
+    pub fn fingerprint(&self) -> String {
+        fingerprint(self.0.verifying_key().as_bytes())
+    }
+}
+
+/// Returns the fingerprint of a public key: the SHA-256 of its bytes.
+pub fn fingerprint(public_key: &[u8]) -> String {
+    digest(public_key)
+}
+
+/// Returns the fingerprint of a hex-encoded public key.
+pub fn fingerprint_hex(public_key: &str) -> Result<String> {
+    Ok(fingerprint(&decode(public_key)?))
+}
+
+/// Verifies a hex-encoded signature against a hex-encoded public key.
+pub fn verify(public_key: &str, data: &[u8], signature: &str) -> Result<()> {
+    verify_bytes(public_key, data, &decode(signature)?)
+}
+
+/// Verifies a raw signature against a hex-encoded public key.
+pub fn verify_bytes(public_key: &str, data: &[u8], signature: &[u8]) -> Result<()> {
+    let key: [u8; 32] =
+        decode(public_key)?.try_into().map_err(|_| anyhow!("Public key must be 32 bytes"))?;
+    let key = ed25519_dalek::VerifyingKey::from_bytes(&key).context("Invalid public key")?;
+
+    let signature: [u8; 64] =
+        signature.try_into().map_err(|_| anyhow!("Signature must be 64 bytes"))?;
+    let signature = ed25519_dalek::Signature::from_bytes(&signature);
+
+    key.verify(data, &signature).context("Signature verification failed")
+}
+
+/// Decodes a hex string.
+fn decode(hex: &str) -> Result<Vec<u8>> {
+    base16ct::mixed::decode_vec(hex).map_err(|e| anyhow!("Invalid hex encoding: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = SigningKey::from_seed([7; 32]);
+        let signature = key.sign(b"hello");
+
+        assert!(verify(&key.public_key(), b"hello", &signature).is_ok());
+        assert!(verify(&key.public_key(), b"world", &signature).is_err());
+        assert_eq!(fingerprint_hex(&key.public_key()).unwrap(), key.fingerprint());
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+        std::fs::write(&path, format!("{}\n", "07".repeat(32))).unwrap();
+
+        let key = SigningKey::load(&path).unwrap();
+        assert_eq!(key.public_key(), SigningKey::from_seed([7; 32]).public_key());
+        assert_eq!(key.fingerprint().len(), 64);
+
+        std::fs::write(&path, "0707").unwrap();
+        assert!(SigningKey::load(&path).is_err());
+    }
+}
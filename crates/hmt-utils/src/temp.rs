@@ -0,0 +1,376 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Scoped temporary files and directories.
+//!
+//! Both are removed when dropped, including when the future owning them is
+//! cancelled, and moved into place with [`TempFile::persist`] or
+//! [`TempDir::persist`] once complete. Anything left behind by a crashed
+//! process carries the [`PREFIX`] and is removed by [`sweep`].
+//!
+//! Moving into place falls back to copying when the temporary and final
+//! locations are on different filesystems, see [`rename`].
+
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// The name prefix of every temporary file and directory.
+pub const PREFIX: &str = ".hmt-tmp-";
+
+/// A temporary directory, removed on drop unless persisted.
+#[derive(Debug)]
+pub struct TempDir(tempfile::TempDir);
+
+impl TempDir {
+    /// Creates a temporary directory inside `root`, creating `root` if needed.
+    ///
+    /// Create it on the same filesystem as its final location, so that
+    /// persisting it is a rename.
+    pub fn new_in(root: &Path) -> io::Result<Self> {
+        fs::create_dir_all(root)?;
+        tempfile::Builder::new().prefix(PREFIX).tempdir_in(root).map(Self)
+    }
+
+    /// Returns the path of the directory.
+    pub fn path(&self) -> &Path {
+        self.0.path()
+    }
+
+    /// Moves the directory to `path`, replacing any directory already there.
+    ///
+    /// Temporary directories are private to the user, so the directory is
+    /// opened up to 0755 first, less the umask, like one made by `mkdir`.
+    pub fn persist(self, path: &Path) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = 0o755 & !umask(self.path())?;
+            fs::set_permissions(self.path(), fs::Permissions::from_mode(mode))?;
+        }
+
+        if path.exists() {
+            fs::remove_dir_all(path)?;
+        }
+        rename(self.0.path(), path)?;
+
+        // The directory is gone from its temporary path, so there is nothing
+        // left to remove
+        let _ = self.0.keep();
+        Ok(())
+    }
+
+    /// Moves the files of the directory into `path`, replacing files of the
+    /// same name and keeping all others. Directories already in `path` are
+    /// merged into rather than replaced, so packages can share them.
+    pub fn merge(self, path: &Path) -> io::Result<()> {
+        merge_dir(self.path(), path)
+    }
+}
+
+/// Moves the entries of `from` into `to` recursively, replacing files and
+/// merging directories.
+fn merge_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let is_dir = |path: &Path| path.is_dir() && !path.is_symlink();
+        match (entry.file_type()?.is_dir(), is_dir(&target)) {
+            (true, true) => merge_dir(&entry.path(), &target)?,
+            (false, true) => {
+                fs::remove_dir_all(&target)?;
+                rename(&entry.path(), &target)?;
+            }
+            (true, false) if target.symlink_metadata().is_ok() => {
+                fs::remove_file(&target)?;
+                rename(&entry.path(), &target)?;
+            }
+            _ => rename(&entry.path(), &target)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the umask of the process, as applied to a directory created in
+/// `dir`. Reading it with `umask(2)` would change it for other threads.
+#[cfg(unix)]
+fn umask(dir: &Path) -> io::Result<u32> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let probe = dir.join(format!("{PREFIX}umask"));
+    fs::DirBuilder::new().mode(0o777).create(&probe)?;
+    let mode = fs::metadata(&probe)?.permissions().mode();
+    fs::remove_dir(&probe)?;
+    Ok(!mode & 0o777)
+}
+
+/// A temporary file, removed on drop unless persisted.
+#[derive(Debug)]
+pub struct TempFile(tempfile::TempPath);
+
+impl TempFile {
+    /// Creates an empty temporary file inside `root`, creating `root` if
+    /// needed.
+    pub fn new_in(root: &Path) -> io::Result<Self> {
+        fs::create_dir_all(root)?;
+        let file = tempfile::Builder::new().prefix(PREFIX).tempfile_in(root)?;
+        Ok(Self(file.into_temp_path()))
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Copies everything `reader` yields into the file on the blocking
+    /// thread pool, returning the number of bytes written.
+    pub async fn write_async(&self, mut reader: impl Read + Send + 'static) -> io::Result<u64> {
+        let path = self.path().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut file = File::create(&path)?;
+            let written = io::copy(&mut reader, &mut file)?;
+            file.sync_all()?;
+            Ok(written)
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// Moves the file to `path`, replacing any file already there.
+    pub fn persist(self, path: &Path) -> io::Result<()> {
+        rename(&self.0, path)?;
+        let _ = self.0.keep();
+        Ok(())
+    }
+}
+
+/// Renames a file or directory, replacing a file at `to`.
+///
+/// When `from` and `to` are on different filesystems, `from` is copied to a
+/// temporary path next to `to`, synced to disk and renamed into place, so
+/// `to` is still never seen half-written. `from` is removed afterwards.
+pub fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => copy_rename(from, to),
+        result => result,
+    }
+}
+
+/// Moves `from` to `to` by copying, for moves across filesystems.
+fn copy_rename(from: &Path, to: &Path) -> io::Result<()> {
+    let parent = to.parent().unwrap_or(Path::new("."));
+    if from.is_dir() {
+        let staging = TempDir::new_in(parent)?;
+        copy_dir(from, staging.path())?;
+        fs::rename(staging.path(), to)?;
+        let _ = staging.0.keep();
+        fs::remove_dir_all(from)
+    } else {
+        let staging = TempFile::new_in(parent)?;
+        copy_file(from, staging.path())?;
+        staging.0.persist(to).map_err(|e| e.error)?;
+        fs::remove_file(from)
+    }
+}
+
+/// Copies a directory recursively, syncing every file to disk.
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let (source, target) = (entry.path(), to.join(entry.file_name()));
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&source, &target)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&source, &target)?;
+        } else {
+            copy_file(&source, &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies a file with its permissions and syncs it to disk.
+fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to)?;
+    File::open(to)?.sync_all()
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    copy_file(from, to)
+}
+
+/// Removes temporary files and directories in `root` older than `max_age`,
+/// returning how many were removed.
+///
+/// Entries younger than `max_age` may belong to a process still running,
+/// and are left alone.
+pub fn sweep(root: &Path, max_age: Duration) -> io::Result<usize> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in entries.filter_map(Result::ok) {
+        if !entry.file_name().to_string_lossy().starts_with(PREFIX) {
+            continue;
+        }
+
+        let metadata = entry.metadata()?;
+        let age = metadata.modified().ok().and_then(|m| now.duration_since(m).ok());
+        if age.is_none_or(|age| age < max_age) {
+            continue;
+        }
+
+        let path = entry.path();
+        if metadata.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+        removed += 1;
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_dir_removed_on_drop() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = TempDir::new_in(&root.path().join("tmp")).unwrap();
+        let path = dir.path().to_path_buf();
+        assert!(path.is_dir());
+
+        drop(dir);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_temp_dir_persist() {
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("target");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("old"), "old").unwrap();
+
+        let dir = TempDir::new_in(root.path()).unwrap();
+        fs::write(dir.path().join("new"), "new").unwrap();
+        dir.persist(&target).unwrap();
+
+        assert!(target.join("new").exists());
+        assert!(!target.join("old").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&target).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode, 0o755 & !umask(root.path()).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_temp_dir_merge() {
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("target");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("kept"), "kept").unwrap();
+        fs::write(target.join("replaced"), "old").unwrap();
+        fs::create_dir_all(target.join("lib")).unwrap();
+        fs::write(target.join("lib").join("a"), "a").unwrap();
+
+        let dir = TempDir::new_in(root.path()).unwrap();
+        let path = dir.path().to_path_buf();
+        fs::write(path.join("replaced"), "new").unwrap();
+        fs::create_dir_all(path.join("lib")).unwrap();
+        fs::write(path.join("lib").join("b"), "b").unwrap();
+        dir.merge(&target).unwrap();
+
+        assert_eq!(fs::read_to_string(target.join("kept")).unwrap(), "kept");
+        assert_eq!(fs::read_to_string(target.join("replaced")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(target.join("lib").join("a")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(target.join("lib").join("b")).unwrap(), "b");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_temp_file_persist() {
+        let root = tempfile::tempdir().unwrap();
+        let file = TempFile::new_in(root.path()).unwrap();
+        let path = file.path().to_path_buf();
+        fs::write(&path, "data").unwrap();
+
+        let target = root.path().join("data");
+        file.persist(&target).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "data");
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_temp_file_write_async() {
+        let root = tempfile::tempdir().unwrap();
+        let file = TempFile::new_in(root.path()).unwrap();
+        assert_eq!(file.write_async(&b"data"[..]).await.unwrap(), 4);
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "data");
+    }
+
+    #[test]
+    fn test_copy_rename() {
+        let root = tempfile::tempdir().unwrap();
+        let from = root.path().join("from");
+        fs::create_dir_all(from.join("bin")).unwrap();
+        fs::write(from.join("bin").join("tool"), "tool").unwrap();
+        fs::write(root.path().join("file"), "file").unwrap();
+
+        let to = root.path().join("to");
+        copy_rename(&from, &to).unwrap();
+        assert_eq!(fs::read_to_string(to.join("bin").join("tool")).unwrap(), "tool");
+        assert!(!from.exists());
+
+        copy_rename(&root.path().join("file"), &root.path().join("moved")).unwrap();
+        assert_eq!(fs::read_to_string(root.path().join("moved")).unwrap(), "file");
+        assert!(!root.path().join("file").exists());
+    }
+
+    #[test]
+    fn test_sweep() {
+        let root = tempfile::tempdir().unwrap();
+        let leftover = TempDir::new_in(root.path()).unwrap().0.keep();
+        fs::write(root.path().join("other"), "").unwrap();
+
+        assert_eq!(sweep(root.path(), Duration::from_secs(3600)).unwrap(), 0);
+        assert_eq!(sweep(root.path(), Duration::ZERO).unwrap(), 1);
+        assert!(!leftover.exists());
+        assert!(root.path().join("other").exists());
+        assert_eq!(sweep(&root.path().join("missing"), Duration::ZERO).unwrap(), 0);
+    }
+}
@@ -0,0 +1,24 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+
+use hmt_manifest::IndexManifest;
+use hmt_utils::bytes::FromSlice;
+use libfuzzer_sys::fuzz_target;
+
+// Registry manifests are fetched from the network, parsing must never panic
+fuzz_target!(|data: &[u8]| {
+    let _ = IndexManifest::from_slice(data);
+});
@@ -0,0 +1,33 @@
+// Copyright (c) The Hummanta Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![no_main]
+
+use hmt_utils::archive::unpack_limited;
+use libfuzzer_sys::fuzz_target;
+
+/// Keeps each run small, the default limit is sized for real toolchains.
+const MAX_SIZE: u64 = 16 * 1024 * 1024;
+
+// Archives are fetched from the network, unpacking must never panic or
+// write outside the target directory
+fuzz_target!(|data: &[u8]| {
+    let root = tempfile::tempdir().unwrap();
+    let target = root.path().join("target");
+    let _ = unpack_limited(data, &target, MAX_SIZE);
+
+    for entry in std::fs::read_dir(root.path()).unwrap() {
+        assert_eq!(entry.unwrap().file_name(), "target", "unpacked outside the target");
+    }
+});